@@ -0,0 +1,788 @@
+//! The boundary between the Rust library and its hosts, which the generators of both sides
+//! have to agree on.
+//!
+//! Every function callable from the host is an [`Export`], a C function named by its
+//! [`symbol`](Export::symbol) and a JNI method of [`JNI_CLASS`] named by the symbol without
+//! the `slime_` prefix. Values are passed as given by [`AbiType`]. A C function returns a
+//! `CallResult` whose value is the [`AbiType`] of the return type, a JNI function returns the
+//! runtime's `Outcome` record as `byte[]`.
+//!
+//! Async functions return a future handle, polled through the `_poll`, `_complete`, `_cancel`
+//! and `_free` functions suffixed to the symbol. Functions returning `Stream<T>` return a stream
+//! handle, polled through `_stream_poll`, `_stream_next`, `_stream_cancel` and `_stream_free`,
+//! where each item is an `Optional<T>` which is empty after the last one.
+//!
+//! Host objects implement the methods of an interface vtable with the signature
+//! `FfiBuffer (*)(void *object, FfiSlice args)`, or `byte[] name(byte[] args)` on the JVM. The
+//! arguments are encoded one after another and the result is an `Outcome` record.
+
+use std::collections::HashSet;
+
+use slime_ffi_ir::ast::{
+    Accessor, BuiltinType, ClassDecl, ClassMember, Decl, DeclKind, DeclRef, FieldDecl, FnDecl,
+    Function, Ident, InterfaceDecl, InterfaceMember, KeywordType, Module, Pat, PropertyDecl,
+    QualifiedName, RefType, Type, TypeKind, TypeParam, TypeWithArgs,
+};
+use slime_ffi_ir::resolved::ResolvedIr;
+
+use crate::CodegenError;
+
+/// The class declaring the JNI methods of the library, in the package of the JVM config.
+pub const JNI_CLASS: &str = "SlimeNative";
+
+/// How a value of an IDL type crosses the boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbiType {
+    /// Integers, floats and `bool` by value, also behind newtypes.
+    Scalar(KeywordType),
+    /// Unit enums as their repr integer.
+    Enum(KeywordType),
+    /// Models of scalar fields as `#[repr(C)]` struct by value. Only for C, JNI passes them
+    /// as [`AbiType::Buffer`].
+    Struct(DeclRef),
+    /// Classes and interfaces as `u64` handle.
+    Handle,
+    /// The handle of a `Stream` returned from Rust.
+    Stream,
+    /// Everything else, encoded in the wire format.
+    Buffer,
+}
+
+/// What an [`Export`] calls on the Rust side.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// A function of the module.
+    Function,
+    /// The constructor of `class` with the index among its constructors, returning the object.
+    Constructor { class: DeclRef, index: usize },
+    /// A static method of `class`.
+    Static(DeclRef),
+    /// A method or accessor of the class or interface whose handle is passed first.
+    Method(DeclRef),
+    /// Releases the handle passed in.
+    Free(DeclRef),
+    /// Registers the vtable of host implementations of the interface.
+    Register(DeclRef),
+}
+
+/// A function of the Rust library callable from the host.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// `slime_net_Client_send`.
+    pub symbol: String,
+    /// The module and `mod`s declaring the function or its class.
+    pub path: Vec<Ident>,
+    /// The function as the host sees it, without the receiver handle.
+    pub function: FnDecl,
+    pub target: Target,
+}
+
+impl Export {
+    /// The JNI method name of the symbol, also the name the JVM bindings declare.
+    pub fn jni_method(&self) -> &str {
+        self.symbol.trim_start_matches("slime_")
+    }
+
+    /// The class or interface whose handle is passed before the parameters.
+    pub fn receiver(&self) -> Option<&DeclRef> {
+        match &self.target {
+            Target::Method(decl) | Target::Free(decl) => Some(decl),
+            _ => None,
+        }
+    }
+
+    pub fn returns_stream(&self) -> bool {
+        self.function
+            .return_type
+            .as_ref()
+            .is_some_and(|ty| matches!(ty.kind, TypeKind::Builtin(BuiltinType::Stream(_))))
+    }
+}
+
+/// `Java_com_example_SlimeNative_net_1fetch`.
+pub fn jni_symbol(package: &str, method: &str) -> String {
+    let mut symbol = "Java_".to_string();
+    for (i, part) in package.split('.').chain([JNI_CLASS]).enumerate() {
+        if i > 0 {
+            symbol.push('_');
+        }
+        symbol.push_str(&jni_mangle(part));
+    }
+    symbol.push('_');
+    symbol.push_str(&jni_mangle(method));
+    symbol
+}
+
+fn jni_mangle(name: &str) -> String {
+    let mut mangled = String::new();
+    for c in name.chars() {
+        match c {
+            '_' => mangled.push_str("_1"),
+            ';' => mangled.push_str("_2"),
+            '[' => mangled.push_str("_3"),
+            c if c.is_ascii_alphanumeric() => mangled.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    mangled.push_str(&format!("_0{:04x}", unit));
+                }
+            }
+        }
+    }
+    mangled
+}
+
+pub struct Abi<'ir> {
+    pub ir: &'ir ResolvedIr,
+}
+
+impl<'ir> Abi<'ir> {
+    pub fn new(ir: &'ir ResolvedIr) -> Self {
+        Abi { ir }
+    }
+
+    fn decl(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.ir.get(decl)
+    }
+
+    /// The declaration `ty` names, through aliases but not newtypes.
+    pub fn resolve<'a>(&'a self, ty: &'a Type) -> Option<(&'a DeclRef, &'a Decl)> {
+        let ty = self.alias_target(ty);
+        match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => Some((target, self.decl(target)?)),
+            _ => None,
+        }
+    }
+
+    /// `ty` with non-distinct aliases replaced by their definition.
+    pub fn alias_target<'a>(&'a self, mut ty: &'a Type) -> &'a Type {
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            match self.decl(target) {
+                Some(Decl::Alias(alias)) if !alias.distinct => ty = &alias.ty,
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    /// The integer, float or `bool` a value of `ty` is, through aliases and newtypes.
+    pub fn scalar(&self, ty: &Type) -> Option<KeywordType> {
+        match &self.ir.underlying(ty).kind {
+            TypeKind::Keyword(KeywordType::String | KeywordType::Bytes) => None,
+            TypeKind::Keyword(keyword) => Some(*keyword),
+            _ => None,
+        }
+    }
+
+    pub fn c_type(&self, ty: &Type) -> AbiType {
+        if let Some(keyword) = self.scalar(ty) {
+            return AbiType::Scalar(keyword);
+        }
+        let ty = self.ir.underlying(ty);
+        if let TypeKind::Builtin(BuiltinType::Stream(_)) = ty.kind {
+            return AbiType::Stream;
+        }
+        let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind else {
+            return AbiType::Buffer;
+        };
+        match self.decl(target) {
+            Some(Decl::Enum(decl)) if decl.is_primitive() => AbiType::Enum(decl.repr_type()),
+            Some(Decl::Model(decl)) if self.is_plain(&decl.fields, &mut vec![]) => {
+                AbiType::Struct(target.clone())
+            }
+            Some(Decl::Class(_) | Decl::Interface(_)) => AbiType::Handle,
+            _ => AbiType::Buffer,
+        }
+    }
+
+    pub fn jni_type(&self, ty: &Type) -> AbiType {
+        match self.c_type(ty) {
+            AbiType::Struct(_) => AbiType::Buffer,
+            abi => abi,
+        }
+    }
+
+    /// Whether a model of these fields has a C layout: scalars and such models only.
+    pub fn is_plain_model(&self, fields: &[FieldDecl]) -> bool {
+        self.is_plain(fields, &mut vec![])
+    }
+
+    fn is_plain(&self, fields: &[FieldDecl], seen: &mut Vec<QualifiedName>) -> bool {
+        !fields.is_empty()
+            && fields.iter().all(|field| {
+                if self.scalar(&field.ty).is_some() {
+                    return true;
+                }
+                let ty = self.ir.underlying(&field.ty);
+                let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind else {
+                    return false;
+                };
+                match self.decl(target) {
+                    Some(Decl::Model(model)) if !seen.contains(&target.name) => {
+                        seen.push(target.name.clone());
+                        let plain = self.is_plain(&model.fields, seen);
+                        seen.pop();
+                        plain
+                    }
+                    _ => false,
+                }
+            })
+    }
+
+    /// Whether `ty` mentions a type parameter or a generic class or interface, which have no
+    /// single representation on the boundary.
+    pub fn is_generic(&self, ty: &Type) -> bool {
+        match &ty.kind {
+            TypeKind::Keyword(_) => false,
+            TypeKind::RefType(RefType::TypeParam(_)) => true,
+            TypeKind::RefType(RefType::Resolved(target)) => match self.decl(target) {
+                Some(Decl::Class(decl)) => decl.type_params.is_some(),
+                Some(Decl::Interface(decl)) => decl.type_params.is_some(),
+                Some(Decl::Alias(decl)) => self.is_generic(&decl.ty),
+                _ => false,
+            },
+            TypeKind::RefType(_) => false,
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty)
+                | BuiltinType::List(ty)
+                | BuiltinType::Set(ty)
+                | BuiltinType::Stream(ty) => self.is_generic(ty),
+                BuiltinType::Map(map) => self.is_generic(&map.key) || self.is_generic(&map.value),
+                BuiltinType::Tuple(types) => types.iter().any(|ty| self.is_generic(ty)),
+                BuiltinType::Array(array) => self.is_generic(&array.element),
+            },
+        }
+    }
+
+    /// Whether `function` can be exported: not generic itself, nor in the types it uses.
+    pub fn is_exported(&self, function: &Function) -> bool {
+        function.type_params.as_ref().is_none_or(Vec::is_empty)
+            && function
+                .params
+                .iter()
+                .map(|param| &param.ty)
+                .chain(function.return_type.as_deref())
+                .chain(function.error_type.as_deref())
+                .all(|ty| !self.is_generic(ty))
+    }
+
+    /// Whether the enum is the error of a function.
+    pub fn is_thrown(&self, decl: &QualifiedName) -> bool {
+        let mut thrown = false;
+        for module in &self.ir.modules {
+            visit_functions(module.decls.iter(), &mut |function| {
+                if let Some(error) = &function.error_type {
+                    if let Some((target, _)) = self.resolve(error) {
+                        thrown |= &target.name == decl;
+                    }
+                }
+            });
+        }
+        thrown
+    }
+
+    /// Functions of a class instance in slot order: its own members, the ones of its base
+    /// classes and then of the interfaces it implements, each name only once.
+    pub fn class_functions(&self, class: &ClassDecl) -> Vec<FnDecl> {
+        let mut functions = own_functions(class);
+        let mut names: HashSet<String> = functions
+            .iter()
+            .map(|function| function.name.as_str().to_string())
+            .collect();
+        for function in self.supertrait_functions(class) {
+            if names.insert(function.name.as_str().to_string()) {
+                functions.push(function)
+            }
+        }
+        functions
+    }
+
+    /// Functions a class instance gets from its base classes and interfaces.
+    pub fn supertrait_functions(&self, class: &ClassDecl) -> Vec<FnDecl> {
+        let mut functions = vec![];
+        if let Some(base) = &class.extend {
+            if let Some(Decl::Class(decl)) =
+                base.target.as_ref().and_then(|target| self.decl(target))
+            {
+                let substitute = Substitute::new(decl.type_params.as_deref(), base);
+                functions.extend(
+                    self.class_functions(decl)
+                        .into_iter()
+                        .map(|function| substitute.function(function)),
+                );
+            }
+        }
+        for interface in class.impls.iter().flatten() {
+            if let Some(Decl::Interface(decl)) = interface
+                .target
+                .as_ref()
+                .and_then(|target| self.decl(target))
+            {
+                let substitute = Substitute::new(decl.type_params.as_deref(), interface);
+                functions.extend(
+                    decl.vtable()
+                        .into_iter()
+                        .map(|function| substitute.function(function)),
+                );
+            }
+        }
+        functions
+    }
+
+    /// [`Abi::class_functions`] as members.
+    pub fn class_members(&self, class: &ClassDecl) -> Vec<Member> {
+        let mut members = own_members(class);
+        let mut names: HashSet<String> = members
+            .iter()
+            .map(|member| member.function().name.as_str().to_string())
+            .collect();
+        let mut inherited = vec![];
+        if let Some(base) = &class.extend {
+            if let Some(Decl::Class(decl)) =
+                base.target.as_ref().and_then(|target| self.decl(target))
+            {
+                let substitute = Substitute::new(decl.type_params.as_deref(), base);
+                inherited.extend(
+                    self.class_members(decl)
+                        .into_iter()
+                        .map(|member| member.substitute(&substitute)),
+                );
+            }
+        }
+        for interface in class.impls.iter().flatten() {
+            if let Some(Decl::Interface(decl)) = interface
+                .target
+                .as_ref()
+                .and_then(|target| self.decl(target))
+            {
+                let substitute = Substitute::new(decl.type_params.as_deref(), interface);
+                inherited.extend(
+                    interface_members(decl)
+                        .into_iter()
+                        .map(|member| member.substitute(&substitute)),
+                );
+            }
+        }
+        for member in inherited {
+            if names.insert(member.function().name.as_str().to_string()) {
+                members.push(member)
+            }
+        }
+        members
+    }
+
+    /// The interfaces a class and its base classes implement, with their type arguments.
+    pub fn class_interfaces(&self, class: &ClassDecl) -> Vec<TypeWithArgs> {
+        let mut interfaces = vec![];
+        if let Some(base) = &class.extend {
+            if let Some(Decl::Class(decl)) =
+                base.target.as_ref().and_then(|target| self.decl(target))
+            {
+                let substitute = Substitute::new(decl.type_params.as_deref(), base);
+                for mut interface in self.class_interfaces(decl) {
+                    for ty in interface.type_args.iter_mut().flatten() {
+                        substitute.ty(ty)
+                    }
+                    interfaces.push(interface);
+                }
+            }
+        }
+        interfaces.extend(
+            class
+                .impls
+                .iter()
+                .flatten()
+                .map(|with| with.as_ref().clone()),
+        );
+        interfaces
+    }
+
+    /// The exports of `module` and its `mod`s.
+    pub fn exports(&self, module: &Module) -> Result<Vec<Export>, CodegenError> {
+        let mut exports = vec![];
+        let path = vec![Ident::from(&module.name)];
+        self.exports_in(&path, module.decls.iter(), &mut exports);
+        let mut symbols = HashSet::new();
+        for export in &exports {
+            if !symbols.insert(export.symbol.as_str()) {
+                return Err(CodegenError::custom(format!(
+                    "`{}` is exported more than once, rename one of the functions",
+                    export.symbol
+                )));
+            }
+        }
+        Ok(exports)
+    }
+
+    fn exports_in<'a>(
+        &self,
+        path: &[Ident],
+        decls: impl Iterator<Item = &'a Decl>,
+        exports: &mut Vec<Export>,
+    ) {
+        let segments: Vec<&str> = path.iter().map(Ident::as_str).collect();
+        let prefix = format!("slime_{}", segments.join("_"));
+        let decl_ref = |kind, name: &Ident| DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(path.to_vec()),
+                name: name.clone(),
+            },
+        };
+        let export = |symbol: String, function: FnDecl, target| Export {
+            symbol,
+            path: path.to_vec(),
+            function,
+            target,
+        };
+        for decl in decls {
+            match decl {
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    self.exports_in(&path, decl.items.iter().map(Box::as_ref), exports)
+                }
+                Decl::Method(function) if self.is_exported(function) => exports.push(export(
+                    format!("{}_{}", prefix, function.name.as_str()),
+                    function.clone(),
+                    Target::Function,
+                )),
+                Decl::Class(class) if class.type_params.is_none() => {
+                    let class_ref = decl_ref(DeclKind::Class, &class.name);
+                    let prefix = format!("{}_{}", prefix, class.name.as_str());
+                    for (index, ctor) in constructors(class).enumerate() {
+                        let function = constructor_function(&class_ref, index, ctor);
+                        if self.is_exported(&function) {
+                            let target = Target::Constructor {
+                                class: class_ref.clone(),
+                                index,
+                            };
+                            let symbol = format!("{}_{}", prefix, function.name.as_str());
+                            exports.push(export(symbol, function, target))
+                        }
+                    }
+                    for member in &class.member {
+                        match member {
+                            ClassMember::StaticMethod(function) if self.is_exported(function) => {
+                                exports.push(export(
+                                    format!("{}_{}", prefix, function.name.as_str()),
+                                    function.clone(),
+                                    Target::Static(class_ref.clone()),
+                                ))
+                            }
+                            _ => {}
+                        }
+                    }
+                    for function in self.class_functions(class) {
+                        if self.is_exported(&function) {
+                            exports.push(export(
+                                format!("{}_{}", prefix, function.name.as_str()),
+                                function,
+                                Target::Method(class_ref.clone()),
+                            ))
+                        }
+                    }
+                    exports.push(export(
+                        format!("{}_free", prefix),
+                        bare_function("free", class.name.span),
+                        Target::Free(class_ref),
+                    ))
+                }
+                Decl::Interface(interface) if interface.type_params.is_none() => {
+                    let interface_ref = decl_ref(DeclKind::Interface, &interface.name);
+                    let prefix = format!("{}_{}", prefix, interface.name.as_str());
+                    for function in interface.vtable() {
+                        if self.is_exported(&function) {
+                            exports.push(export(
+                                format!("{}_{}", prefix, function.name.as_str()),
+                                function,
+                                Target::Method(interface_ref.clone()),
+                            ))
+                        }
+                    }
+                    exports.push(export(
+                        format!("{}_free", prefix),
+                        bare_function("free", interface.name.span),
+                        Target::Free(interface_ref.clone()),
+                    ));
+                    exports.push(export(
+                        format!("{}_register", prefix),
+                        bare_function("register", interface.name.span),
+                        Target::Register(interface_ref),
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The constructors of a class in declaration order.
+pub fn constructors(class: &ClassDecl) -> impl Iterator<Item = &Function> {
+    class.member.iter().filter_map(|member| match member {
+        ClassMember::Constructor(ctor) => Some(ctor.function.as_ref()),
+        _ => None,
+    })
+}
+
+/// `new` for the first constructor, `new_1` and so on for the others.
+pub fn constructor_name(index: usize) -> String {
+    match index {
+        0 => "new".to_string(),
+        index => format!("new_{}", index),
+    }
+}
+
+/// The constructor at `index` as a function named by [`constructor_name`] returning the class.
+pub fn constructor_function(class: &DeclRef, index: usize, ctor: &Function) -> FnDecl {
+    let span = class.name.name.span;
+    FnDecl {
+        name: Ident::new(constructor_name(index), span),
+        function: Box::new(Function {
+            return_type: Some(Box::new(Type::new(
+                TypeKind::RefType(RefType::Resolved(class.clone())),
+                span,
+            ))),
+            ..ctor.clone()
+        }),
+        is_async: false,
+        span,
+    }
+}
+
+/// Methods, accessors of properties and fields declared by the class itself.
+pub fn own_functions(class: &ClassDecl) -> Vec<FnDecl> {
+    class
+        .member
+        .iter()
+        .flat_map(|member| match member {
+            ClassMember::Method(method) => vec![method.clone()],
+            ClassMember::Property(property) => property
+                .getter()
+                .into_iter()
+                .chain(property.setter())
+                .collect(),
+            ClassMember::Field(field) => {
+                let property = PropertyDecl {
+                    name: field.name.clone(),
+                    ty: field.ty.clone(),
+                    accessors: vec![Accessor::Getter, Accessor::Setter],
+                    span: field.span,
+                };
+                property
+                    .getter()
+                    .into_iter()
+                    .chain(property.setter())
+                    .collect()
+            }
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// A function of a class or interface, by what the host sees of it.
+#[derive(Clone)]
+pub enum Member {
+    Method(FnDecl),
+    /// The getter of the property.
+    Get(Ident, FnDecl),
+    /// The setter of the property.
+    Set(Ident, FnDecl),
+}
+
+impl Member {
+    pub fn function(&self) -> &FnDecl {
+        match self {
+            Member::Method(function) | Member::Get(_, function) | Member::Set(_, function) => {
+                function
+            }
+        }
+    }
+
+    pub(crate) fn substitute(self, substitute: &Substitute) -> Self {
+        match self {
+            Member::Method(function) => Member::Method(substitute.function(function)),
+            Member::Get(name, function) => Member::Get(name, substitute.function(function)),
+            Member::Set(name, function) => Member::Set(name, substitute.function(function)),
+        }
+    }
+}
+
+fn property_members(property: &PropertyDecl) -> Vec<Member> {
+    let getter = property
+        .getter()
+        .map(|function| Member::Get(property.name.clone(), function));
+    let setter = property
+        .setter()
+        .map(|function| Member::Set(property.name.clone(), function));
+    getter.into_iter().chain(setter).collect()
+}
+
+/// The members an interface declares itself.
+pub fn own_interface_members(decl: &InterfaceDecl) -> Vec<Member> {
+    decl.member.iter().flat_map(interface_member).collect()
+}
+
+/// The members of an interface in slot order, like `InterfaceDecl::vtable`.
+pub fn interface_members(decl: &InterfaceDecl) -> Vec<Member> {
+    decl.inherited
+        .iter()
+        .map(|inherited| &inherited.member)
+        .chain(&decl.member)
+        .flat_map(interface_member)
+        .collect()
+}
+
+fn interface_member(member: &InterfaceMember) -> Vec<Member> {
+    match member {
+        InterfaceMember::Method(function) => vec![Member::Method(function.clone())],
+        InterfaceMember::Property(property) => property_members(property),
+    }
+}
+
+/// [`own_functions`] as members.
+fn own_members(class: &ClassDecl) -> Vec<Member> {
+    class
+        .member
+        .iter()
+        .flat_map(|member| match member {
+            ClassMember::Method(method) => vec![Member::Method(method.clone())],
+            ClassMember::Property(property) => property_members(property),
+            ClassMember::Field(field) => property_members(&PropertyDecl {
+                name: field.name.clone(),
+                ty: field.ty.clone(),
+                accessors: vec![Accessor::Getter, Accessor::Setter],
+                span: field.span,
+            }),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Whether `members` get the property.
+pub fn has_getter(members: &[Member], property: &Ident) -> bool {
+    members
+        .iter()
+        .any(|member| matches!(member, Member::Get(name, _) if name == property))
+}
+
+/// Whether `members` set the property.
+pub fn has_setter(members: &[Member], property: &Ident) -> bool {
+    members
+        .iter()
+        .any(|member| matches!(member, Member::Set(name, _) if name == property))
+}
+
+/// Fails for a method a host object cannot implement, which semantic analysis rejects.
+pub fn check_host_method(function: &FnDecl) -> Result<(), CodegenError> {
+    let kind = if function
+        .type_params
+        .as_ref()
+        .is_some_and(|params| !params.is_empty())
+    {
+        "generic"
+    } else if function.is_async {
+        "async"
+    } else if matches!(
+        function.return_type.as_deref().map(|ty| &ty.kind),
+        Some(TypeKind::Builtin(BuiltinType::Stream(_)))
+    ) {
+        "streaming"
+    } else {
+        return Ok(());
+    };
+    Err(CodegenError::custom(format!(
+        "host objects cannot implement the {} method `{}`",
+        kind,
+        function.name.as_str()
+    )))
+}
+
+/// Calls `f` with every function, method and constructor of `decls`.
+pub fn visit_functions<'a>(decls: impl Iterator<Item = &'a Decl>, f: &mut dyn FnMut(&Function)) {
+    for decl in decls {
+        match decl {
+            Decl::Mod(decl) => visit_functions(decl.items.iter().map(Box::as_ref), f),
+            Decl::Method(function) => f(function),
+            Decl::Interface(decl) => decl.vtable().iter().for_each(|function| f(function)),
+            Decl::Class(decl) => {
+                for member in &decl.member {
+                    match member {
+                        ClassMember::Method(function) | ClassMember::StaticMethod(function) => {
+                            f(function)
+                        }
+                        ClassMember::Constructor(ctor) => f(ctor),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn bare_function(name: &str, span: slime_ffi_ir::span::Span) -> FnDecl {
+    FnDecl {
+        name: Ident::new(name, span),
+        function: Box::new(Function {
+            params: vec![],
+            type_params: None,
+            return_type: None,
+            error_type: None,
+        }),
+        is_async: false,
+        span,
+    }
+}
+
+/// Replaces the type parameters of a generic class or interface by the type arguments it is
+/// extended or implemented with.
+pub(crate) struct Substitute<'a> {
+    params: &'a [TypeParam],
+    args: &'a [Box<Type>],
+}
+
+impl<'a> Substitute<'a> {
+    pub(crate) fn new(params: Option<&'a [TypeParam]>, with: &'a TypeWithArgs) -> Self {
+        Substitute {
+            params: params.unwrap_or_default(),
+            args: with.type_args.as_deref().unwrap_or_default(),
+        }
+    }
+
+    pub(crate) fn function(&self, mut function: FnDecl) -> FnDecl {
+        let inner = function.function.as_mut();
+        for param in &mut inner.params {
+            self.ty(&mut param.ty)
+        }
+        for ty in inner.return_type.iter_mut().chain(&mut inner.error_type) {
+            self.ty(ty)
+        }
+        function
+    }
+
+    pub(crate) fn ty(&self, ty: &mut Type) {
+        match &mut ty.kind {
+            TypeKind::RefType(RefType::TypeParam(name)) => {
+                let index = self.params.iter().position(|param| &param.name == name);
+                if let Some(arg) = index.and_then(|index| self.args.get(index)) {
+                    *ty = arg.as_ref().clone()
+                }
+            }
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty)
+                | BuiltinType::List(ty)
+                | BuiltinType::Set(ty)
+                | BuiltinType::Stream(ty) => self.ty(ty),
+                BuiltinType::Map(map) => {
+                    self.ty(&mut map.key);
+                    self.ty(&mut map.value)
+                }
+                BuiltinType::Tuple(types) => types.iter_mut().for_each(|ty| self.ty(ty)),
+                BuiltinType::Array(array) => self.ty(&mut array.element),
+            },
+            TypeKind::RefType(_) => ty.type_args.iter_mut().flatten().for_each(|ty| self.ty(ty)),
+            TypeKind::Keyword(_) => {}
+        }
+    }
+}
@@ -0,0 +1,956 @@
+//! A C header declaring the `extern "C"` functions of [`crate::abi`], with a pkg-config file and
+//! a CMake package config to link the library by.
+//!
+//! Scalars and unit enums are passed by value, the latter as a typedef of their repr with a
+//! constant per variant. Models of scalars and of such models are structs of the same layout as
+//! their `#[repr(C)]` Rust type. Classes and interfaces are `uint64_t` handles released
+//! by their `_free` function, host objects implementing an interface are passed as their pointer
+//! once the methods of the interface are registered. Everything else is a buffer in the wire
+//! format, the comment above each prototype is the IDL signature it follows. Other models have
+//! no struct and payload enums only a constant per discriminant.
+//!
+//! Every function returns a `SlimeResult_*`, the layout of the Rust `CallResult`. The runtime
+//! part of the header is shared between libraries and guarded on its own, so headers of two
+//! libraries can be included together.
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use slime_ffi_ir::ast::{
+    BuiltinType, ClassMember, ConstDecl, Decl, DeclKind, DeclRef, EnumDecl, FnDecl, Ident,
+    KeywordType, Lit, NumLit, Pat, QualifiedName, RefType, Type, TypeKind,
+};
+use slime_ffi_ir::config::CConfig;
+use slime_ffi_ir::resolved::ResolvedIr;
+use slime_ffi_ir::Lang;
+
+use crate::abi::{Abi, AbiType, Export, Target};
+use crate::lang::SourceFile;
+use crate::names::shouty_snake_case;
+use crate::writer::CodeWriter;
+use crate::{Codegen, CodegenError};
+
+const INDENT: &str = "    ";
+
+const RUNTIME: &str = include_str!("c/runtime.h");
+
+/// Keywords of C and C++, the header is included by both. Names among them get an underscore.
+pub(super) const KEYWORDS: &[&str] = &[
+    "alignas",
+    "alignof",
+    "and",
+    "and_eq",
+    "asm",
+    "auto",
+    "bitand",
+    "bitor",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "char16_t",
+    "char32_t",
+    "char8_t",
+    "class",
+    "co_await",
+    "co_return",
+    "co_yield",
+    "compl",
+    "concept",
+    "const",
+    "const_cast",
+    "consteval",
+    "constexpr",
+    "constinit",
+    "continue",
+    "decltype",
+    "default",
+    "delete",
+    "do",
+    "double",
+    "dynamic_cast",
+    "else",
+    "enum",
+    "explicit",
+    "export",
+    "extern",
+    "false",
+    "float",
+    "for",
+    "friend",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "mutable",
+    "namespace",
+    "new",
+    "noexcept",
+    "not",
+    "not_eq",
+    "nullptr",
+    "operator",
+    "or",
+    "or_eq",
+    "private",
+    "protected",
+    "public",
+    "register",
+    "reinterpret_cast",
+    "requires",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "static_assert",
+    "static_cast",
+    "struct",
+    "switch",
+    "template",
+    "this",
+    "thread_local",
+    "throw",
+    "true",
+    "try",
+    "typedef",
+    "typeid",
+    "typename",
+    "union",
+    "unsigned",
+    "using",
+    "virtual",
+    "void",
+    "volatile",
+    "wchar_t",
+    "while",
+    "xor",
+    "xor_eq",
+];
+
+/// `int` as `int_`, and `object`, the name of receivers.
+pub(super) fn ident(name: &str) -> String {
+    match KEYWORDS.contains(&name) || name == "object" {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+pub(super) fn keyword_ty(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 => "int8_t",
+        KeywordType::Int16 => "int16_t",
+        KeywordType::Int32 => "int32_t",
+        KeywordType::Int64 => "int64_t",
+        KeywordType::Uint8 => "uint8_t",
+        KeywordType::Uint16 => "uint16_t",
+        KeywordType::Uint32 => "uint32_t",
+        KeywordType::Uint64 => "uint64_t",
+        KeywordType::Float32 => "float",
+        KeywordType::Float64 => "double",
+        KeywordType::Bool => "bool",
+        KeywordType::String => "String",
+        KeywordType::Bytes => "Bytes",
+    }
+}
+
+/// The `SlimeResult_*` of the runtime for a scalar.
+fn scalar_result(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 => "SlimeResult_i8",
+        KeywordType::Int16 => "SlimeResult_i16",
+        KeywordType::Int32 => "SlimeResult_i32",
+        KeywordType::Int64 => "SlimeResult_i64",
+        KeywordType::Uint8 => "SlimeResult_u8",
+        KeywordType::Uint16 => "SlimeResult_u16",
+        KeywordType::Uint32 => "SlimeResult_u32",
+        KeywordType::Uint64 => "SlimeResult_u64",
+        KeywordType::Float32 => "SlimeResult_f32",
+        KeywordType::Float64 => "SlimeResult_f64",
+        KeywordType::Bool => "SlimeResult_bool",
+        KeywordType::String | KeywordType::Bytes => "SlimeResult_buffer",
+    }
+}
+
+fn float(value: f64, suffix: &str) -> String {
+    match value {
+        value if value.is_nan() => "NAN".to_string(),
+        value if value == f64::INFINITY => "INFINITY".to_string(),
+        value if value == f64::NEG_INFINITY => "(-INFINITY)".to_string(),
+        value => format!("{:?}{}", value, suffix),
+    }
+}
+
+/// An integer literal of the `stdint.h` macros, e.g. `UINT32_C(30)` or `INT64_MIN`.
+fn int(value: impl ToString, min: bool, signed: bool, bits: u8) -> String {
+    match (min, signed) {
+        (true, _) => format!("INT{}_MIN", bits),
+        (false, true) => format!("INT{}_C({})", bits, value.to_string()),
+        (false, false) => format!("UINT{}_C({})", bits, value.to_string()),
+    }
+}
+
+pub(super) fn num(value: &NumLit) -> String {
+    match value {
+        NumLit::I8(value) => int(value, *value == i8::MIN, true, 8),
+        NumLit::I16(value) => int(value, *value == i16::MIN, true, 16),
+        NumLit::I32(value) => int(value, *value == i32::MIN, true, 32),
+        NumLit::I64(value) => int(value, *value == i64::MIN, true, 64),
+        NumLit::U8(value) => int(value, false, false, 8),
+        NumLit::U16(value) => int(value, false, false, 16),
+        NumLit::U32(value) => int(value, false, false, 32),
+        NumLit::U64(value) => int(value, false, false, 64),
+        NumLit::F32(value) => float(*value as f64, "f"),
+        NumLit::F64(value) => float(*value, ""),
+    }
+}
+
+/// The implicit discriminant of the variant at `index`.
+pub(super) fn index(repr: KeywordType, index: usize) -> String {
+    match repr {
+        KeywordType::Int8 => num(&NumLit::I8(index as i8)),
+        KeywordType::Int16 => num(&NumLit::I16(index as i16)),
+        KeywordType::Int64 => num(&NumLit::I64(index as i64)),
+        KeywordType::Uint8 => num(&NumLit::U8(index as u8)),
+        KeywordType::Uint16 => num(&NumLit::U16(index as u16)),
+        KeywordType::Uint32 => num(&NumLit::U32(index as u32)),
+        KeywordType::Uint64 => num(&NumLit::U64(index as u64)),
+        _ => num(&NumLit::I32(index as i32)),
+    }
+}
+
+/// A string literal of ASCII, with the other bytes of the UTF-8 as octal escapes.
+pub(super) fn string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            b' '..=b'~' => out.push(byte as char),
+            byte => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The C name of a declaration, `app_sub_Inner`.
+pub(super) fn c_name(decl: &DeclRef) -> String {
+    let mut names: Vec<&str> = decl
+        .name
+        .path
+        .segments()
+        .iter()
+        .map(Ident::as_str)
+        .collect();
+    names.push(decl.name.name.as_str());
+    names.join("_")
+}
+
+pub struct CCodegen<'ir> {
+    pub ir: &'ir ResolvedIr,
+    pub config: &'ir CConfig,
+}
+
+impl<'ir> CCodegen<'ir> {
+    pub fn new(ir: &'ir ResolvedIr, config: &'ir CConfig) -> Self {
+        CCodegen { ir, config }
+    }
+
+    /// `app.pc`, relocatable by the directory it is installed to.
+    fn pkg_config(&self) -> String {
+        let name = &self.config.library_name;
+        format!(
+            "\
+# Generated by slime-ffi, do not edit.
+
+prefix=${{pcfiledir}}/../..
+libdir=${{prefix}}/lib
+includedir=${{prefix}}/include
+
+Name: {name}
+Description: C bindings of {name}, generated by slime-ffi
+Version: {version}
+Libs: -L${{libdir}} -l{name}
+Cflags: -I${{includedir}}
+",
+            name = name,
+            version = self.config.version
+        )
+    }
+
+    /// `app-config.cmake`, defining the imported target `app::app`.
+    fn cmake_config(&self) -> String {
+        let name = &self.config.library_name;
+        format!(
+            "\
+# Generated by slime-ffi, do not edit.
+
+get_filename_component(_{name}_prefix \"${{CMAKE_CURRENT_LIST_DIR}}/../../..\" ABSOLUTE)
+find_library({upper}_LIBRARY NAMES {name} PATHS \"${{_{name}_prefix}}/lib\" NO_DEFAULT_PATH)
+
+if(NOT TARGET {name}::{name})
+    add_library({name}::{name} UNKNOWN IMPORTED)
+    set_target_properties({name}::{name} PROPERTIES
+        IMPORTED_LOCATION \"${{{upper}_LIBRARY}}\"
+        INTERFACE_INCLUDE_DIRECTORIES \"${{_{name}_prefix}}/include\")
+endif()
+
+unset(_{name}_prefix)
+",
+            name = name,
+            upper = shouty_snake_case(name)
+        )
+    }
+}
+
+impl Codegen for CCodegen<'_> {
+    const LANG: Lang = Lang::C;
+
+    type Output = Vec<SourceFile>;
+
+    fn generate(&self) -> Result<Self::Output, CodegenError> {
+        let abi = Abi::new(self.ir);
+        let header = Header {
+            abi: &abi,
+            library: &self.config.library_name,
+            math: Cell::new(false),
+        };
+        let name = &self.config.library_name;
+        Ok(vec![
+            SourceFile {
+                path: PathBuf::from("include").join(format!("{}.h", name)),
+                source: header.header()?,
+            },
+            SourceFile {
+                path: ["lib", "pkgconfig", &format!("{}.pc", name)]
+                    .iter()
+                    .collect(),
+                source: self.pkg_config(),
+            },
+            SourceFile {
+                path: ["lib", "cmake", name, &format!("{}-config.cmake", name)]
+                    .iter()
+                    .collect(),
+                source: self.cmake_config(),
+            },
+        ])
+    }
+}
+
+struct Header<'a, 'ir> {
+    abi: &'a Abi<'ir>,
+    library: &'a str,
+    /// Whether a const needs the `NAN` or `INFINITY` of `math.h`.
+    math: Cell<bool>,
+}
+
+impl<'a, 'ir> Header<'a, 'ir> {
+    fn get(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.abi.ir.get(decl)
+    }
+
+    /// `APP_MODE_FAST` for the `Fast` of `app.Mode`.
+    fn macro_name(decl: &DeclRef, suffix: &str) -> String {
+        let mut names: Vec<String> = decl
+            .name
+            .path
+            .segments()
+            .iter()
+            .map(|segment| shouty_snake_case(segment.as_str()))
+            .collect();
+        names.push(shouty_snake_case(decl.name.name.as_str()));
+        if !suffix.is_empty() {
+            names.push(shouty_snake_case(suffix));
+        }
+        names.join("_")
+    }
+
+    fn decl_ref(path: &[Ident], kind: DeclKind, name: &Ident) -> DeclRef {
+        DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(path.to_vec()),
+                name: name.clone(),
+            },
+        }
+    }
+
+    /// The declaration a value of `ty` is, through aliases and newtypes.
+    fn target(&self, ty: &Type) -> Result<DeclRef, CodegenError> {
+        match &self.abi.ir.underlying(ty).kind {
+            TypeKind::RefType(RefType::Resolved(target)) => Ok(target.clone()),
+            _ => Err(CodegenError::custom(
+                "types are resolved by semantic analysis",
+            )),
+        }
+    }
+
+    /// The C type of a scalar, the typedef of a newtype declaring it.
+    fn scalar(&self, ty: &Type, keyword: KeywordType) -> String {
+        if let TypeKind::RefType(RefType::Resolved(target)) = &self.abi.alias_target(ty).kind {
+            if let Some(Decl::Alias(alias)) = self.get(target) {
+                if alias.distinct {
+                    return c_name(target);
+                }
+            }
+        }
+        keyword_ty(keyword).to_string()
+    }
+
+    /// The C type a parameter of type `ty` is passed as.
+    fn param_ty(&self, ty: &Type) -> Result<String, CodegenError> {
+        Ok(match self.abi.c_type(ty) {
+            AbiType::Scalar(keyword) => self.scalar(ty, keyword),
+            AbiType::Enum(_) | AbiType::Handle => c_name(&self.target(ty)?),
+            AbiType::Struct(decl) => c_name(&decl),
+            AbiType::Stream => "const void *".to_string(),
+            AbiType::Buffer => "SlimeSlice".to_string(),
+        })
+    }
+
+    /// The `SlimeResult_*` of a function returning `ty`.
+    fn result(&self, ty: Option<&Type>) -> Result<String, CodegenError> {
+        let Some(ty) = ty else {
+            return Ok("SlimeResult_void".to_string());
+        };
+        Ok(match self.abi.c_type(ty) {
+            AbiType::Scalar(keyword) | AbiType::Enum(keyword) => scalar_result(keyword).to_string(),
+            AbiType::Struct(decl) => format!("SlimeResult_{}", c_name(&decl)),
+            AbiType::Handle => "SlimeResult_u64".to_string(),
+            AbiType::Stream => "SlimeResult_ptr".to_string(),
+            AbiType::Buffer => "SlimeResult_buffer".to_string(),
+        })
+    }
+
+    /// `ty` as written in the IDL.
+    fn idl(ty: &Type) -> String {
+        match &ty.kind {
+            TypeKind::Keyword(keyword) => keyword.name().to_string(),
+            TypeKind::RefType(RefType::Resolved(target)) => target.name.name.as_str().to_string(),
+            TypeKind::RefType(RefType::TypeParam(name)) => name.as_str().to_string(),
+            TypeKind::RefType(_) => "?".to_string(),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => format!("Optional<{}>", Self::idl(ty)),
+                BuiltinType::List(ty) => format!("List<{}>", Self::idl(ty)),
+                BuiltinType::Set(ty) => format!("Set<{}>", Self::idl(ty)),
+                BuiltinType::Stream(ty) => format!("Stream<{}>", Self::idl(ty)),
+                BuiltinType::Map(map) => {
+                    format!("Map<{}, {}>", Self::idl(&map.key), Self::idl(&map.value))
+                }
+                BuiltinType::Array(array) => {
+                    format!("Array<{}, {}>", Self::idl(&array.element), array.len)
+                }
+                BuiltinType::Tuple(types) => {
+                    let types: Vec<String> = types.iter().map(Self::idl).collect();
+                    format!("({})", types.join(", "))
+                }
+            },
+        }
+    }
+
+    /// `async fn fetch(url: String) -> Bytes throws IoError`.
+    fn signature(function: &FnDecl) -> String {
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name.as_str(), Self::idl(&param.ty)))
+            .collect();
+        let mut signature = format!(
+            "{}fn {}({})",
+            if function.is_async { "async " } else { "" },
+            function.name.as_str(),
+            params.join(", ")
+        );
+        if let Some(ty) = &function.return_type {
+            signature.push_str(&format!(" -> {}", Self::idl(ty)));
+        }
+        if let Some(ty) = &function.error_type {
+            signature.push_str(&format!(" throws {}", Self::idl(ty)));
+        }
+        signature
+    }
+
+    fn literal(&self, value: &Lit) -> String {
+        match value {
+            Lit::Str(value) => string(value),
+            Lit::Bool(value) => value.to_string(),
+            Lit::Num(value) => {
+                let literal = num(value);
+                if literal.contains("NAN") || literal.contains("INFINITY") {
+                    self.math.set(true);
+                }
+                literal
+            }
+        }
+    }
+
+    fn header(&self) -> Result<String, CodegenError> {
+        let mut body = CodeWriter::new(INDENT);
+        for module in &self.abi.ir.modules {
+            let decls: Vec<&Decl> = module.decls.iter().collect();
+            self.types(&mut body, &[Ident::from(&module.name)], &decls);
+        }
+        let mut structs = vec![];
+        for module in &self.abi.ir.modules {
+            let decls: Vec<&Decl> = module.decls.iter().collect();
+            self.plain_models(&[Ident::from(&module.name)], &decls, &mut structs);
+        }
+        let mut emitted = HashSet::new();
+        for model in &structs {
+            self.model(&mut body, model, &mut emitted)?;
+        }
+        for module in &self.abi.ir.modules {
+            let exports = self.abi.exports(module)?;
+            if exports.is_empty() {
+                continue;
+            }
+            body.blank();
+            body.line(format!("/* The functions of `{}`. */", module.name));
+            for export in &exports {
+                self.export(&mut body, export)?;
+            }
+        }
+
+        let guard = format!("{}_H", shouty_snake_case(self.library));
+        let mut out = CodeWriter::new(INDENT);
+        out.line("// Generated by slime-ffi, do not edit.");
+        out.blank();
+        out.line(format!("#ifndef {}", guard));
+        out.line(format!("#define {}", guard));
+        out.blank();
+        if self.math.get() {
+            out.line("#include <math.h>");
+        }
+        out.line("#include <stdbool.h>");
+        out.line("#include <stdint.h>");
+        out.blank();
+        out.line("#ifdef __cplusplus");
+        out.line("extern \"C\" {");
+        out.line("#endif");
+        out.blank();
+        out.lines(RUNTIME.trim_end());
+        out.blank();
+        out.lines(body.finish().trim_end());
+        out.blank();
+        out.line("#ifdef __cplusplus");
+        out.line("}");
+        out.line("#endif");
+        out.blank();
+        out.line(format!("#endif /* {} */", guard));
+        Ok(out.finish())
+    }
+
+    /// Consts, typedefs and enum constants of a module and its `mod`s.
+    fn types(&self, out: &mut CodeWriter, path: &[Ident], decls: &[&Decl]) {
+        let mut types = CodeWriter::new(INDENT);
+        for decl in decls {
+            match decl {
+                Decl::Const(decl) => self.constant(&mut types, path, None, decl),
+                Decl::Alias(alias) if alias.distinct => {
+                    let this = Self::decl_ref(path, DeclKind::Alias, &alias.name);
+                    if let Some(keyword) = self.abi.scalar(&alias.ty) {
+                        types.blank();
+                        types.line(format!(
+                            "typedef {} {};",
+                            keyword_ty(keyword),
+                            c_name(&this)
+                        ));
+                    }
+                }
+                Decl::Enum(decl) => {
+                    let this = Self::decl_ref(path, DeclKind::Enum, &decl.name);
+                    self.enumeration(&mut types, decl, &this)
+                }
+                Decl::Class(class) if class.type_params.is_none() => {
+                    let this = Self::decl_ref(path, DeclKind::Class, &class.name);
+                    types.blank();
+                    types.line(format!(
+                        "/* A handle of `{}`, released with slime_{}_free. */",
+                        class.name.as_str(),
+                        c_name(&this)
+                    ));
+                    types.line(format!("typedef uint64_t {};", c_name(&this)));
+                    for member in &class.member {
+                        if let ClassMember::Const(decl) = member {
+                            self.constant(&mut types, path, Some(&this), decl);
+                        }
+                    }
+                }
+                Decl::Interface(interface) if interface.type_params.is_none() => {
+                    let this = Self::decl_ref(path, DeclKind::Interface, &interface.name);
+                    types.blank();
+                    types.line(format!(
+                        "/* A handle of `{}` from Rust, or the pointer of a host object. */",
+                        interface.name.as_str()
+                    ));
+                    types.line(format!("typedef uint64_t {};", c_name(&this)));
+                    types.line("/* The slots of the methods of host objects. */");
+                    let vtable = interface.vtable();
+                    for (slot, function) in vtable.iter().enumerate() {
+                        types.line(format!(
+                            "#define {} {}",
+                            Self::macro_name(&this, &format!("slot_{}", function.name.as_str())),
+                            slot
+                        ));
+                    }
+                    types.line(format!(
+                        "#define {} {}",
+                        Self::macro_name(&this, "slot_count"),
+                        vtable.len()
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let types = types.finish();
+        if !types.is_empty() {
+            let names: Vec<&str> = path.iter().map(Ident::as_str).collect();
+            out.blank();
+            out.line(format!("/* The types of `{}`. */", names.join(".")));
+            out.blank();
+            out.lines(types);
+        }
+        for decl in decls {
+            if let Decl::Mod(decl) = decl {
+                let mut path = path.to_vec();
+                path.push(decl.name.clone());
+                let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                self.types(out, &path, &decls);
+            }
+        }
+    }
+
+    /// `#define APP_TIMEOUT UINT32_C(30)`, prefixed by the class declaring it.
+    fn constant(
+        &self,
+        out: &mut CodeWriter,
+        path: &[Ident],
+        class: Option<&DeclRef>,
+        decl: &ConstDecl,
+    ) {
+        let name = match class {
+            Some(class) => Self::macro_name(class, decl.name.as_str()),
+            None => {
+                let mut names: Vec<String> = path
+                    .iter()
+                    .map(|segment| shouty_snake_case(segment.as_str()))
+                    .collect();
+                names.push(shouty_snake_case(decl.name.as_str()));
+                names.join("_")
+            }
+        };
+        out.blank();
+        out.line(format!("#define {} {}", name, self.literal(&decl.value)));
+    }
+
+    fn enumeration(&self, out: &mut CodeWriter, decl: &EnumDecl, this: &DeclRef) {
+        let repr = decl.repr_type();
+        out.blank();
+        if decl.is_primitive() {
+            out.line(format!("typedef {} {};", keyword_ty(repr), c_name(this)));
+        } else {
+            out.line(format!(
+                "/* The discriminants of `{}`, which is passed in the wire format. */",
+                decl.name.as_str()
+            ));
+        }
+        for (position, variant) in decl.variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => num(value),
+                None => index(repr, position),
+            };
+            out.line(format!(
+                "#define {} {}",
+                Self::macro_name(this, variant.name.as_str()),
+                value
+            ));
+        }
+    }
+
+    /// The models with a C layout, in declaration order.
+    fn plain_models(&self, path: &[Ident], decls: &[&Decl], models: &mut Vec<DeclRef>) {
+        for decl in decls {
+            match decl {
+                Decl::Model(model) if self.abi.is_plain_model(&model.fields) => {
+                    models.push(Self::decl_ref(path, DeclKind::Model, &model.name))
+                }
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                    self.plain_models(&path, &decls, models)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The struct of a model and its `SlimeResult_*`, after the models of its fields.
+    fn model(
+        &self,
+        out: &mut CodeWriter,
+        model: &DeclRef,
+        emitted: &mut HashSet<String>,
+    ) -> Result<(), CodegenError> {
+        let name = c_name(model);
+        if !emitted.insert(name.clone()) {
+            return Ok(());
+        }
+        let Some(Decl::Model(decl)) = self.get(model) else {
+            return Err(CodegenError::custom(format!("`{}` is not a model", name)));
+        };
+        let mut fields = vec![];
+        for field in &decl.fields {
+            let ty = match self.abi.c_type(&field.ty) {
+                AbiType::Scalar(keyword) => self.scalar(&field.ty, keyword),
+                AbiType::Struct(target) => {
+                    self.model(out, &target, emitted)?;
+                    c_name(&target)
+                }
+                _ => return Err(CodegenError::custom("plain models hold scalars and models")),
+            };
+            fields.push(format!("{} {};", ty, ident(field.name.as_str())));
+        }
+        out.blank();
+        out.open(format!("typedef struct {} {{", name));
+        fields.iter().for_each(|field| out.line(field));
+        out.close(format!("}} {};", name));
+        out.line(format!("SLIME_RESULT(SlimeResult_{}, {});", name, name));
+        Ok(())
+    }
+
+    fn prototype(out: &mut CodeWriter, ret: &str, symbol: &str, params: &[String]) {
+        let params = match params.is_empty() {
+            true => "void".to_string(),
+            false => params.join(", "),
+        };
+        out.line(format!("{} {}({});", ret, symbol, params));
+    }
+
+    fn export(&self, out: &mut CodeWriter, export: &Export) -> Result<(), CodegenError> {
+        let function = &export.function;
+        let symbol = &export.symbol;
+        let mut params = vec![];
+        if let Some(receiver) = export.receiver() {
+            params.push(format!("{} object", c_name(receiver)));
+        }
+        out.blank();
+        match &export.target {
+            Target::Free(decl) => {
+                out.line(format!(
+                    "/* Releases the handle of `{}`. */",
+                    decl.name.name.as_str()
+                ));
+                Self::prototype(out, "SlimeResult_void", symbol, &params);
+                return Ok(());
+            }
+            Target::Register(decl) => {
+                out.line(format!(
+                    "/* Registers the methods of host `{}` objects by slot, and what releases them. */",
+                    decl.name.name.as_str()
+                ));
+                let params = [
+                    "SlimeDtor dtor".to_string(),
+                    "const SlimeHostFn *methods".to_string(),
+                    "uint32_t len".to_string(),
+                ];
+                Self::prototype(out, "bool", symbol, &params);
+                return Ok(());
+            }
+            _ => {}
+        }
+        for param in &function.params {
+            params.push(format!(
+                "{} {}",
+                self.param_ty(&param.ty)?,
+                ident(param.name.as_str())
+            ));
+        }
+        let ret = function.return_type.as_deref();
+        out.line(format!("/* {} */", Self::signature(function)));
+        let poll = |out: &mut CodeWriter, name: &str, handle: &str| {
+            let params = [
+                format!("const void *{}", handle),
+                "SlimeContinuation continuation".to_string(),
+                "uint64_t data".to_string(),
+            ];
+            Self::prototype(out, "void", &format!("{}_{}", symbol, name), &params);
+        };
+        let handle = |handle: &str| [format!("const void *{}", handle)];
+        if function.is_async {
+            Self::prototype(out, "SlimeResult_ptr", symbol, &params);
+            poll(out, "poll", "future");
+            Self::prototype(
+                out,
+                &self.result(ret)?,
+                &format!("{}_complete", symbol),
+                &handle("future"),
+            );
+            for action in ["cancel", "free"] {
+                let name = format!("{}_{}", symbol, action);
+                Self::prototype(out, "void", &name, &handle("future"));
+            }
+        } else {
+            Self::prototype(out, &self.result(ret)?, symbol, &params);
+        }
+        if let Some(TypeKind::Builtin(BuiltinType::Stream(item))) = ret.map(|ty| &ty.kind) {
+            poll(out, "stream_poll", "stream");
+            out.line(format!(
+                "/* An Optional<{}>, empty after the last item. */",
+                Self::idl(item)
+            ));
+            let name = format!("{}_stream_next", symbol);
+            Self::prototype(out, "SlimeResult_buffer", &name, &handle("stream"));
+            for action in ["cancel", "free"] {
+                let name = format!("{}_stream_{}", symbol, action);
+                Self::prototype(out, "void", &name, &handle("stream"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::config::CConfig;
+
+    use super::CCodegen;
+    use crate::lang::rust::{RustCodegen, RustCodegenOutput, RustPath};
+    use crate::lang::test::{compile, file, load, temp_dir};
+    use crate::Codegen;
+
+    const APP: &str = r#"
+model Point {
+    x: i32,
+    y: i32 = 1,
+}
+
+model Rect {
+    origin: Point,
+    size: Point,
+}
+
+model Styled {
+    at: Point,
+    mode: Mode,
+}
+
+model Named {
+    name: String,
+    at: Point,
+}
+
+enum Mode: u8 {
+    Fast,
+    Slow = 4,
+}
+
+enum Shape {
+    Empty,
+    Circle(f64),
+}
+
+const TIMEOUT: u32 = 30;
+
+fn move_by(p: Point, dx: i32) -> Rect;
+fn rename(n: Named) -> Named;
+fn style(s: Styled) -> Styled;
+fn shape(s: Shape, m: Mode) -> Mode;
+"#;
+
+    fn header() -> String {
+        let ir = load(&[("app.slime", APP)]);
+        let config = CConfig {
+            library_name: "app".into(),
+            version: "1.0.0".into(),
+        };
+        let files = CCodegen::new(&ir, &config).generate().unwrap();
+        file(&files, "include/app.h").to_string()
+    }
+
+    #[test]
+    fn test_plain_models() {
+        let header = header();
+        assert!(header
+            .contains("typedef struct app_Point {\n    int32_t x;\n    int32_t y;\n} app_Point;"));
+        assert!(header.contains(
+            "typedef struct app_Rect {\n    app_Point origin;\n    app_Point size;\n} app_Rect;"
+        ));
+        assert!(header.contains("SlimeResult_app_Rect slime_app_move_by(app_Point p, int32_t dx);"));
+        assert!(!header.contains("app_Named"));
+        assert!(header.contains("SlimeResult_buffer slime_app_rename(SlimeSlice n);"));
+        assert!(!header.contains("app_Styled"));
+        assert!(header.contains("SlimeResult_buffer slime_app_style(SlimeSlice s);"));
+
+        // The Rust side passes the same models by value, with the C layout.
+        let ir = load(&[("app.slime", APP)]);
+        let RustCodegenOutput::Mod(_, files) = RustCodegen::new(&ir, RustPath::parse("crate::ffi"))
+            .generate()
+            .unwrap()
+        else {
+            panic!("the Rust output is a directory")
+        };
+        let app = files
+            .iter()
+            .find_map(|file| match file.as_ref() {
+                RustCodegenOutput::Source(path, source) if path.ends_with("app.rs") => Some(source),
+                _ => None,
+            })
+            .unwrap();
+        assert!(app
+            .contains("#[repr(C)]\n#[derive(Debug, Clone, Copy, PartialEq)]\npub struct Point {"));
+        assert!(
+            app.contains("#[repr(C)]\n#[derive(Debug, Clone, Copy, PartialEq)]\npub struct Rect {")
+        );
+        assert!(app.contains("#[repr(u8)]"));
+        assert!(app.contains("fn slime_app_move_by(p: $crate::ffi::app::Point, dx: i32)"));
+        assert!(!app.contains("#[repr(C)]\n#[derive(Debug, Clone, PartialEq)]\npub struct Named"));
+    }
+
+    #[test]
+    fn test_enums() {
+        let header = header();
+        assert!(header.contains(
+            "typedef uint8_t app_Mode;\n#define APP_MODE_FAST UINT8_C(0)\n\
+             #define APP_MODE_SLOW UINT8_C(4)"
+        ));
+        assert!(header.contains(
+            "/* The discriminants of `Shape`, which is passed in the wire format. */\n\
+             #define APP_SHAPE_EMPTY INT32_C(0)\n#define APP_SHAPE_CIRCLE INT32_C(1)"
+        ));
+        assert!(!header.contains("app_Shape"));
+        assert!(header.contains("#define APP_TIMEOUT UINT32_C(30)"));
+    }
+
+    #[test]
+    fn test_compile() {
+        let dir = temp_dir();
+        std::fs::create_dir(dir.join("include")).unwrap();
+        std::fs::write(dir.join("include/app.h"), header()).unwrap();
+        std::fs::write(dir.join("main.c"), "#include \"app.h\"\n").unwrap();
+        compile(
+            "cc",
+            &[
+                "-std=c99",
+                "-Wall",
+                "-Wextra",
+                "-pedantic",
+                "-Werror",
+                "-fsyntax-only",
+                "-I",
+                "include",
+                "main.c",
+            ],
+            &dir,
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,98 @@
+#ifndef SLIME_FFI_RUNTIME_H
+#define SLIME_FFI_RUNTIME_H
+
+/*
+ * The runtime of slime-ffi, shared by the headers of every library.
+ *
+ * Values without a C layout are passed in the wire format of slime-ffi: integers and floats
+ * are little-endian, bool is one byte, String and Bytes are a uint32_t length followed by the
+ * bytes, Optional is a bool followed by the value if true, List, Set and Map are a uint32_t
+ * count followed by the elements, arrays and tuples are their elements, models are their
+ * fields in order, enums are their discriminant followed by the fields of the variant and
+ * objects are their uint64_t handle.
+ */
+
+/* Bytes owned by Rust, given back to slime_buffer_free. */
+typedef struct SlimeBuffer {
+    uint8_t *data;
+    uint64_t len;
+    uint64_t capacity;
+} SlimeBuffer;
+
+/* Bytes borrowed from the host for the duration of a call. */
+typedef struct SlimeSlice {
+    const uint8_t *data;
+    uint64_t len;
+} SlimeSlice;
+
+/* Allocates a zeroed buffer the host fills and hands to Rust, e.g. the result of a callback. */
+SlimeBuffer slime_buffer_alloc(uint64_t len);
+
+/* Frees a buffer returned by Rust. */
+void slime_buffer_free(SlimeBuffer buffer);
+
+#define SLIME_LANGUAGE_C 0
+
+const void *slime_create_runtime(uint8_t language, uint64_t library_id, const void *dialect_data);
+
+/*
+ * The code of a SlimeResult. On SLIME_CALL_SUCCESS the data holds the value, on
+ * SLIME_CALL_ERROR the error: a uint8_t 0 followed by the thrown enum, or 1 followed by the
+ * message as String. On SLIME_CALL_PANIC it holds the message in UTF-8, without length. A
+ * SLIME_CALL_CANCELLED future has no data. The error and the panic are freed with
+ * slime_buffer_free.
+ */
+#define SLIME_CALL_SUCCESS 1
+#define SLIME_CALL_ERROR 2
+#define SLIME_CALL_PANIC 3
+#define SLIME_CALL_CANCELLED 4
+
+#define SLIME_RESULT(name, type) \
+    typedef struct name {        \
+        uint8_t code;            \
+        union {                  \
+            type value;          \
+            SlimeBuffer error;   \
+            SlimeBuffer panic;   \
+        } data;                  \
+    } name
+
+/* The result of functions without a return value, its value is 0. */
+SLIME_RESULT(SlimeResult_void, uint8_t);
+SLIME_RESULT(SlimeResult_bool, bool);
+SLIME_RESULT(SlimeResult_i8, int8_t);
+SLIME_RESULT(SlimeResult_i16, int16_t);
+SLIME_RESULT(SlimeResult_i32, int32_t);
+SLIME_RESULT(SlimeResult_i64, int64_t);
+SLIME_RESULT(SlimeResult_u8, uint8_t);
+SLIME_RESULT(SlimeResult_u16, uint16_t);
+SLIME_RESULT(SlimeResult_u32, uint32_t);
+/* Also the result of functions returning objects, as their handle. */
+SLIME_RESULT(SlimeResult_u64, uint64_t);
+SLIME_RESULT(SlimeResult_f32, float);
+SLIME_RESULT(SlimeResult_f64, double);
+/* The result of async functions and of functions returning a stream, as its handle. */
+SLIME_RESULT(SlimeResult_ptr, const void *);
+SLIME_RESULT(SlimeResult_buffer, SlimeBuffer);
+
+/* The future or stream is ready, otherwise poll again. */
+#define SLIME_FUTURE_READY 0
+#define SLIME_FUTURE_MAYBE_READY 1
+
+/*
+ * Called once per poll with its data and SLIME_FUTURE_READY or SLIME_FUTURE_MAYBE_READY, on any
+ * thread. It must not poll from within the call.
+ */
+typedef void (*SlimeContinuation)(uint64_t data, int8_t status);
+
+/*
+ * A method of a host object: the object and the arguments one after another in, the outcome
+ * out, allocated with slime_buffer_alloc. The outcome is the code of a SlimeResult followed by
+ * the value, the error or the message as String, or nothing when cancelled.
+ */
+typedef SlimeBuffer (*SlimeHostFn)(void *object, SlimeSlice args);
+
+/* Releases a host object once Rust drops it. */
+typedef void (*SlimeDtor)(void *object);
+
+#endif /* SLIME_FFI_RUNTIME_H */
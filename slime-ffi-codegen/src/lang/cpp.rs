@@ -0,0 +1,2125 @@
+//! A header-only C++17 wrapper over the header of [`crate::lang::c`].
+//!
+//! Modules are namespaces below the configured one. Newtypes, models and enums with payloads
+//! are aggregates of standard types, `List` as `std::vector`, `Set` and `Map` as
+//! `std::unordered_set` and `std::unordered_map`, unit enums are `enum class`es of their repr.
+//! Classes are move-only RAII wrappers of their handle. Interfaces are abstract classes the host
+//! implements and passes as `std::shared_ptr`, objects of Rust implement them as `{Name}Object`.
+//!
+//! Calls throw `slime::Error` and its subclasses, the error a function `throws` is thrown as
+//! `slime::Exception<E>` or returned in a `slime::Expected<T, E>`, see [`CppErrorStyle`]. Async
+//! functions return a `std::future` Rust runs on the thread waiting for it, streams are
+//! `slime::Stream`s. The runtime part is shared between libraries and guarded on its own.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use slime_ffi_ir::ast::{
+    BuiltinType, ClassDecl, ClassMember, ConstLit, Decl, DeclKind, DeclRef, EnumDecl,
+    EnumVariantFields, Expr, FnDecl, Ident, InterfaceDecl, KeywordType, Lit, ModelDecl, NumLit,
+    Pat, QualifiedName, RefType, Type, TypeKind, TypeParam, TypeWithArgs,
+};
+use slime_ffi_ir::config::{CConfig, CppConfig, CppErrorStyle};
+use slime_ffi_ir::resolved::ResolvedIr;
+use slime_ffi_ir::Lang;
+
+use crate::abi::{check_host_method, Abi, AbiType, Export, Target};
+use crate::lang::c::{self, c_name};
+use crate::lang::SourceFile;
+use crate::names::shouty_snake_case;
+use crate::writer::CodeWriter;
+use crate::{Codegen, CodegenError};
+
+const INDENT: &str = "    ";
+
+const RUNTIME: &str = include_str!("cpp/runtime.hpp");
+
+/// `int` as `int_`.
+fn ident(name: &str) -> String {
+    match c::KEYWORDS.contains(&name) {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+/// A method name, which must not hide the `handle()` and `release()` of wrappers.
+fn method(name: &str) -> String {
+    match name {
+        "handle" | "release" => format!("{}_", name),
+        name => ident(name),
+    }
+}
+
+fn keyword_ty(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::String => "std::string",
+        KeywordType::Bytes => "std::vector<uint8_t>",
+        keyword => c::keyword_ty(keyword),
+    }
+}
+
+fn float(value: f64, ty: &str) -> Option<String> {
+    match value {
+        value if value.is_nan() => Some(format!("std::numeric_limits<{}>::quiet_NaN()", ty)),
+        value if value == f64::INFINITY => Some(format!("std::numeric_limits<{}>::infinity()", ty)),
+        value if value == f64::NEG_INFINITY => {
+            Some(format!("-std::numeric_limits<{}>::infinity()", ty))
+        }
+        _ => None,
+    }
+}
+
+fn literal(value: &Lit) -> String {
+    match value {
+        Lit::Str(value) => c::string(value),
+        Lit::Bool(value) => value.to_string(),
+        Lit::Num(num) => match num {
+            NumLit::F32(value) => float(*value as f64, "float"),
+            NumLit::F64(value) => float(*value, "double"),
+            _ => None,
+        }
+        .unwrap_or_else(|| c::num(num)),
+    }
+}
+
+/// The fields of a variant as members of its struct: `value` for a single unnamed one, `v0`,
+/// `v1` and so on for several.
+fn variant_fields(fields: &EnumVariantFields) -> Vec<(String, &Type)> {
+    match fields {
+        EnumVariantFields::Unit => vec![],
+        EnumVariantFields::Unnamed(types) if types.len() == 1 => {
+            vec![("value".to_string(), &types[0])]
+        }
+        EnumVariantFields::Unnamed(types) => types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| (format!("v{}", index), ty))
+            .collect(),
+        EnumVariantFields::Named(fields) => fields
+            .iter()
+            .map(|field| (ident(field.name.as_str()), &field.ty))
+            .collect(),
+    }
+}
+
+/// `template <class K, class V>`.
+fn template(params: &[TypeParam]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| format!("class {}", ident(param.name.as_str())))
+        .collect();
+    format!("template <{}>", params.join(", "))
+}
+
+/// The lambda parameters of a host method, unnamed where unused.
+fn host_params(reads: bool, writes: bool) -> String {
+    format!(
+        "slime::Reader &{}, slime::Writer &{}",
+        if reads { "reader" } else { "" },
+        if writes { "writer" } else { "" }
+    )
+}
+
+pub struct CppCodegen<'ir> {
+    pub ir: &'ir ResolvedIr,
+    pub c: &'ir CConfig,
+    pub config: &'ir CppConfig,
+}
+
+impl<'ir> CppCodegen<'ir> {
+    pub fn new(ir: &'ir ResolvedIr, c: &'ir CConfig, config: &'ir CppConfig) -> Self {
+        CppCodegen { ir, c, config }
+    }
+}
+
+impl Codegen for CppCodegen<'_> {
+    const LANG: Lang = Lang::Cpp;
+
+    type Output = Vec<SourceFile>;
+
+    fn generate(&self) -> Result<Self::Output, CodegenError> {
+        let abi = Abi::new(self.ir);
+        let mut exports = vec![];
+        for module in &self.ir.modules {
+            exports.extend(abi.exports(module)?);
+        }
+        let header = Header {
+            abi: &abi,
+            library: &self.c.library_name,
+            config: self.config,
+            exports,
+            scope: RefCell::new(None),
+        };
+        Ok(vec![SourceFile {
+            path: PathBuf::from("include").join(format!("{}.hpp", self.c.library_name)),
+            source: header.header()?,
+        }])
+    }
+}
+
+struct Header<'a, 'ir> {
+    abi: &'a Abi<'ir>,
+    library: &'a str,
+    config: &'a CppConfig,
+    exports: Vec<Export>,
+    /// The namespace being written, whose names are not qualified. `None` outside of any.
+    scope: RefCell<Option<Vec<String>>>,
+}
+
+impl<'a, 'ir> Header<'a, 'ir> {
+    fn get(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.abi.ir.get(decl)
+    }
+
+    fn expected(&self) -> bool {
+        matches!(self.config.error_style, CppErrorStyle::Expected)
+    }
+
+    fn decl_ref(path: &[Ident], kind: DeclKind, name: &Ident) -> DeclRef {
+        DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(path.to_vec()),
+                name: name.clone(),
+            },
+        }
+    }
+
+    /// `acme::app::sub` for `app.sub` below the namespace `acme`.
+    fn namespace(&self, path: &[Ident]) -> Vec<String> {
+        self.config
+            .namespace
+            .split("::")
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .chain(path.iter().map(|segment| ident(segment.as_str())))
+            .collect()
+    }
+
+    /// Opens the namespace the following lines are in, closing the previous one.
+    fn enter(&self, out: &mut CodeWriter, namespace: Option<Vec<String>>) {
+        if *self.scope.borrow() == namespace {
+            return;
+        }
+        if let Some(previous) = self.scope.replace(namespace.clone()) {
+            out.blank();
+            out.line(format!("}} // namespace {}", previous.join("::")));
+        }
+        if let Some(namespace) = namespace {
+            out.blank();
+            out.line(format!("namespace {} {{", namespace.join("::")));
+            out.line("");
+        }
+    }
+
+    /// The name of a declaration with `suffix`, qualified unless it is in the namespace written.
+    fn suffixed(&self, decl: &DeclRef, suffix: &str) -> String {
+        let namespace = self.namespace(decl.name.path.segments());
+        let name = ident(&format!("{}{}", decl.name.name.as_str(), suffix));
+        match self.scope.borrow().as_ref() == Some(&namespace) {
+            true => name,
+            false => format!("::{}::{}", namespace.join("::"), name),
+        }
+    }
+
+    fn name(&self, decl: &DeclRef) -> String {
+        self.suffixed(decl, "")
+    }
+
+    /// The declaration `ty` names through aliases, e.g. the class to wrap a handle in.
+    fn target(&self, ty: &Type) -> Result<(DeclRef, &'ir Decl), CodegenError> {
+        let target = self.abi.resolve(ty).map(|(target, _)| target.clone());
+        match target.and_then(|target| Some((target.clone(), self.get(&target)?))) {
+            Some(target) => Ok(target),
+            None => Err(CodegenError::custom(
+                "types are resolved by semantic analysis",
+            )),
+        }
+    }
+
+    fn ty(&self, ty: &Type) -> Result<String, CodegenError> {
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => keyword_ty(*keyword).to_string(),
+            TypeKind::RefType(RefType::Resolved(target)) => match self.get(target) {
+                Some(Decl::Interface(_)) => format!("std::shared_ptr<{}>", self.name(target)),
+                _ => self.name(target),
+            },
+            TypeKind::RefType(RefType::TypeParam(name)) => ident(name.as_str()),
+            TypeKind::RefType(_) => {
+                return Err(CodegenError::custom(
+                    "types are resolved by semantic analysis",
+                ))
+            }
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => format!("std::optional<{}>", self.ty(ty)?),
+                BuiltinType::List(ty) => format!("std::vector<{}>", self.ty(ty)?),
+                BuiltinType::Set(ty) => format!("std::unordered_set<{}>", self.ty(ty)?),
+                BuiltinType::Stream(ty) => format!("slime::Stream<{}>", self.ty(ty)?),
+                BuiltinType::Map(map) => format!(
+                    "std::unordered_map<{}, {}>",
+                    self.ty(&map.key)?,
+                    self.ty(&map.value)?
+                ),
+                BuiltinType::Array(array) => {
+                    format!("std::array<{}, {}>", self.ty(&array.element)?, array.len)
+                }
+                BuiltinType::Tuple(types) => {
+                    let types = types
+                        .iter()
+                        .map(|ty| self.ty(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("std::tuple<{}>", types.join(", "))
+                }
+            },
+        })
+    }
+
+    /// `Base<int32_t>` of `extends Base<i32>`.
+    fn with_args(&self, with: &TypeWithArgs) -> Result<String, CodegenError> {
+        let Some(target) = &with.target else {
+            return Err(CodegenError::custom(
+                "types are resolved by semantic analysis",
+            ));
+        };
+        let mut name = self.name(target);
+        if let Some(args) = &with.type_args {
+            let args = args
+                .iter()
+                .map(|ty| self.ty(ty))
+                .collect::<Result<Vec<_>, _>>()?;
+            name.push_str(&format!("<{}>", args.join(", ")));
+        }
+        Ok(name)
+    }
+
+    /// Whether `ty` holds a value of `decl` without an allocation in between, the same rule
+    /// the Rust bindings box by.
+    fn contains(&self, ty: &Type, decl: &QualifiedName, seen: &mut Vec<QualifiedName>) -> bool {
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if &target.name == decl {
+                    return true;
+                }
+                if seen.contains(&target.name) {
+                    return false;
+                }
+                seen.push(target.name.clone());
+                match self.get(target) {
+                    Some(Decl::Model(model)) => model
+                        .fields
+                        .iter()
+                        .any(|field| self.contains(&field.ty, decl, seen)),
+                    Some(Decl::Enum(decl_enum)) => decl_enum.variants.iter().any(|variant| {
+                        variant_fields(&variant.fields)
+                            .iter()
+                            .any(|(_, ty)| self.contains(ty, decl, seen))
+                    }),
+                    _ => false,
+                }
+            }
+            TypeKind::Builtin(BuiltinType::Optional(ty)) => self.contains(ty, decl, seen),
+            TypeKind::Builtin(BuiltinType::Tuple(types)) => {
+                types.iter().any(|ty| self.contains(ty, decl, seen))
+            }
+            TypeKind::Builtin(BuiltinType::Array(array)) => {
+                self.contains(&array.element, decl, seen)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a field of `decl` is a `slime::Box`, so `decl` can be defined before its type.
+    fn is_boxed(&self, ty: &Type, decl: &QualifiedName) -> bool {
+        self.contains(ty, decl, &mut vec![])
+    }
+
+    /// The type of a model field or enum payload, boxed where it contains `decl`.
+    fn field_ty(&self, ty: &Type, decl: &QualifiedName) -> Result<String, CodegenError> {
+        if !self.is_boxed(ty, decl) {
+            return self.ty(ty);
+        }
+        Ok(match &ty.kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => {
+                format!("std::optional<slime::Box<{}>>", self.ty(inner)?)
+            }
+            _ => format!("slime::Box<{}>", self.ty(ty)?),
+        })
+    }
+
+    /// Whether `ty` holds a class or interface object, which are not compared.
+    fn has_objects(&self, ty: &Type, seen: &mut Vec<QualifiedName>) -> bool {
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::Keyword(_) => false,
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if seen.contains(&target.name) {
+                    return false;
+                }
+                seen.push(target.name.clone());
+                match self.get(target) {
+                    Some(Decl::Class(_) | Decl::Interface(_)) => true,
+                    Some(Decl::Model(model)) => model
+                        .fields
+                        .iter()
+                        .any(|field| self.has_objects(&field.ty, seen)),
+                    Some(Decl::Enum(decl)) => decl.variants.iter().any(|variant| {
+                        variant_fields(&variant.fields)
+                            .iter()
+                            .any(|(_, ty)| self.has_objects(ty, seen))
+                    }),
+                    _ => false,
+                }
+            }
+            TypeKind::RefType(_) => false,
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty)
+                | BuiltinType::List(ty)
+                | BuiltinType::Set(ty)
+                | BuiltinType::Stream(ty) => self.has_objects(ty, seen),
+                BuiltinType::Map(map) => {
+                    self.has_objects(&map.key, seen) || self.has_objects(&map.value, seen)
+                }
+                BuiltinType::Tuple(types) => types.iter().any(|ty| self.has_objects(ty, seen)),
+                BuiltinType::Array(array) => self.has_objects(&array.element, seen),
+            },
+        }
+    }
+
+    /// Whether `ty` names a generic class or interface, which C++ only has as templates.
+    fn is_template(&self, ty: &Type) -> bool {
+        match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => match self.get(target) {
+                Some(Decl::Class(decl)) => decl.type_params.is_some(),
+                Some(Decl::Interface(decl)) => decl.type_params.is_some(),
+                Some(Decl::Alias(decl)) => self.is_template(&decl.ty),
+                _ => false,
+            },
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty)
+                | BuiltinType::List(ty)
+                | BuiltinType::Set(ty)
+                | BuiltinType::Stream(ty) => self.is_template(ty),
+                BuiltinType::Map(map) => self.is_template(&map.key) || self.is_template(&map.value),
+                BuiltinType::Tuple(types) => types.iter().any(|ty| self.is_template(ty)),
+                BuiltinType::Array(array) => self.is_template(&array.element),
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether an interface declares the function as a virtual method: not generic itself,
+    /// which virtual methods cannot be.
+    fn is_virtual(&self, function: &FnDecl) -> bool {
+        function.type_params.as_ref().is_none_or(Vec::is_empty)
+            && function
+                .params
+                .iter()
+                .map(|param| &param.ty)
+                .chain(function.return_type.as_deref())
+                .chain(function.error_type.as_deref())
+                .all(|ty| !self.is_template(ty))
+    }
+
+    /// The newtypes a value of `ty` is wrapped in, outermost first.
+    fn newtypes(&self, ty: &Type) -> Vec<DeclRef> {
+        let mut newtypes = vec![];
+        let mut ty = self.abi.alias_target(ty);
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            match self.get(target) {
+                Some(Decl::Alias(alias)) if alias.distinct => {
+                    newtypes.push(target.clone());
+                    ty = self.abi.alias_target(&alias.ty);
+                }
+                _ => break,
+            }
+        }
+        newtypes
+    }
+
+    /// `expr` of a scalar `ty` wrapped in its newtypes.
+    fn wrap(&self, ty: &Type, expr: String) -> String {
+        self.newtypes(ty).iter().rev().fold(expr, |expr, newtype| {
+            format!("{}{{{}}}", self.name(newtype), expr)
+        })
+    }
+
+    /// The scalar in `expr` of type `ty`, `id.value` for a newtype.
+    fn unwrap(&self, ty: &Type, expr: String) -> String {
+        expr + &".value".repeat(self.newtypes(ty).len())
+    }
+
+    /// A default value or const of type `ty`.
+    fn value(&self, ty: &Type, value: &Expr) -> Result<String, CodegenError> {
+        let constant = match value {
+            Expr::Null => return Ok("std::nullopt".to_string()),
+            Expr::Const(constant) => constant,
+            _ => {
+                return Err(CodegenError::custom(
+                    "values are resolved by semantic analysis",
+                ))
+            }
+        };
+        let ty = self.abi.alias_target(ty);
+        match &ty.kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => return self.value(inner, value),
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if let Some(Decl::Alias(alias)) = self.get(target) {
+                    let inner = self.value(&alias.ty, value)?;
+                    return Ok(format!("{}{{{}}}", self.name(target), inner));
+                }
+            }
+            _ => {}
+        }
+        Ok(match constant {
+            ConstLit::Primitive(value) => literal(value),
+            ConstLit::UnitEnumVariant(variant) => format!(
+                "{}::{}",
+                self.ty(&variant.ty)?,
+                ident(variant.variant_name.as_str())
+            ),
+        })
+    }
+
+    /// A parameter of type `ty`, by value when small. `declared` is its type in the interface
+    /// declaring the method, a type parameter is passed by reference in every override.
+    fn param(&self, ty: &Type, declared: &Type, name: &str) -> Result<String, CodegenError> {
+        let by_value = !matches!(declared.kind, TypeKind::RefType(RefType::TypeParam(_)))
+            && matches!(self.abi.c_type(ty), AbiType::Scalar(_) | AbiType::Enum(_));
+        Ok(match by_value {
+            true => format!("{} {}", self.ty(ty)?, ident(name)),
+            false => format!("const {} &{}", self.ty(ty)?, ident(name)),
+        })
+    }
+
+    /// `const std::string &url, uint32_t timeout = UINT32_C(30)`.
+    fn params(
+        &self,
+        function: &FnDecl,
+        origin: Option<&FnDecl>,
+        defaults: bool,
+    ) -> Result<String, CodegenError> {
+        let mut params = vec![];
+        for (index, param) in function.params.iter().enumerate() {
+            let declared = origin
+                .and_then(|origin| origin.params.get(index))
+                .map_or(&param.ty, |param| &param.ty);
+            let mut param_str = self.param(&param.ty, declared, param.name.as_str())?;
+            if let (true, Some(default)) = (defaults, &param.default) {
+                param_str.push_str(&format!(" = {}", self.value(&param.ty, default)?));
+            }
+            params.push(param_str);
+        }
+        Ok(params.join(", "))
+    }
+
+    /// What a call of `function` results in, without the future of an async function.
+    fn output(&self, function: &FnDecl) -> Result<String, CodegenError> {
+        let ty = match &function.return_type {
+            Some(ty) => self.ty(ty)?,
+            None => "void".to_string(),
+        };
+        Ok(match (&function.error_type, self.expected()) {
+            (Some(error), true) => format!("slime::Expected<{}, {}>", ty, self.ty(error)?),
+            _ => ty,
+        })
+    }
+
+    fn ret(&self, function: &FnDecl) -> Result<String, CodegenError> {
+        let output = self.output(function)?;
+        Ok(match function.is_async {
+            true => format!("std::future<{}>", output),
+            false => output,
+        })
+    }
+
+    /// The function an interface declares as `name`, as written there with its type
+    /// parameters rather than the type arguments of `interface`.
+    fn origin(&self, interface: &InterfaceDecl, name: &str) -> Option<FnDecl> {
+        let own = interface
+            .member
+            .iter()
+            .flat_map(|member| member.functions())
+            .find(|function| function.name.as_str() == name);
+        own.or_else(|| {
+            interface.inherited.iter().find_map(|inherited| {
+                let from = match self.get(&inherited.from) {
+                    Some(Decl::Interface(from)) => from,
+                    _ => return None,
+                };
+                from.member
+                    .iter()
+                    .flat_map(|member| member.functions())
+                    .find(|function| function.name.as_str() == name)
+            })
+        })
+    }
+
+    /// The interface function a method of `class` overrides, if any.
+    fn overridden(&self, class: &ClassDecl, name: &str) -> Option<FnDecl> {
+        self.abi.class_interfaces(class).iter().find_map(|with| {
+            match with.target.as_ref().and_then(|t| self.get(t)) {
+                Some(Decl::Interface(decl)) => self.origin(decl, name),
+                _ => None,
+            }
+        })
+    }
+
+    fn header(&self) -> Result<String, CodegenError> {
+        let mut decls = vec![];
+        for module in &self.abi.ir.modules {
+            let items: Vec<&Decl> = module.decls.iter().collect();
+            Self::collect(&[Ident::from(&module.name)], &items, &mut decls);
+        }
+
+        let mut body = CodeWriter::new(INDENT);
+        self.forward(&mut body, &decls)?;
+        let mut defined = vec![];
+        for decl in &decls {
+            self.define(&mut body, decl, &mut defined)?;
+        }
+        self.hashes(&mut body, &decls)?;
+        for module in &self.abi.ir.modules {
+            let items: Vec<&Decl> = module.decls.iter().collect();
+            self.constants(&mut body, &[Ident::from(&module.name)], &items);
+        }
+        self.enter(&mut body, Some(vec!["slime".to_string()]));
+        for decl in &decls {
+            self.wire(&mut body, decl)?;
+        }
+        self.detail(&mut body, &decls)?;
+        for decl in &decls {
+            self.wire_definitions(&mut body, decl)?;
+        }
+        for decl in &decls {
+            self.equality(&mut body, decl)?;
+        }
+        for decl in &decls {
+            self.members(&mut body, decl)?;
+        }
+        for export in &self.exports {
+            if let Target::Function = export.target {
+                self.enter(&mut body, Some(self.namespace(&export.path)));
+                self.function(&mut body, export)?;
+            }
+        }
+        self.enter(&mut body, None);
+
+        let guard = format!("{}_HPP", shouty_snake_case(self.library));
+        let mut out = CodeWriter::new(INDENT);
+        out.line("// Generated by slime-ffi, do not edit.");
+        out.blank();
+        out.line(format!("#ifndef {}", guard));
+        out.line(format!("#define {}", guard));
+        out.blank();
+        out.line(format!("#include \"{}.h\"", self.library));
+        out.blank();
+        out.lines(RUNTIME.trim_end());
+        out.blank();
+        out.lines(body.finish().trim());
+        out.blank();
+        out.line(format!("#endif // {}", guard));
+        Ok(out.finish())
+    }
+
+    /// The aliases, models, enums, classes and interfaces of a module and its `mod`s.
+    fn collect(path: &[Ident], decls: &[&Decl], out: &mut Vec<DeclRef>) {
+        for decl in decls {
+            match decl {
+                Decl::Alias(alias) => out.push(Self::decl_ref(path, DeclKind::Alias, &alias.name)),
+                Decl::Model(model) => out.push(Self::decl_ref(path, DeclKind::Model, &model.name)),
+                Decl::Enum(decl) => out.push(Self::decl_ref(path, DeclKind::Enum, &decl.name)),
+                Decl::Interface(interface) => {
+                    out.push(Self::decl_ref(path, DeclKind::Interface, &interface.name))
+                }
+                Decl::Class(class) if class.type_params.is_none() => {
+                    out.push(Self::decl_ref(path, DeclKind::Class, &class.name))
+                }
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    let items: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                    Self::collect(&path, &items, out)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn enter_decl(&self, out: &mut CodeWriter, decl: &DeclRef) {
+        self.enter(out, Some(self.namespace(decl.name.path.segments())));
+    }
+
+    /// Declares every type up front, so definitions only wait for the types they hold.
+    fn forward(&self, out: &mut CodeWriter, decls: &[DeclRef]) -> Result<(), CodegenError> {
+        for decl in decls {
+            let name = ident(decl.name.name.as_str());
+            let line = match self.get(decl) {
+                Some(Decl::Alias(alias)) if alias.distinct => format!("struct {};", name),
+                Some(Decl::Model(_)) => format!("struct {};", name),
+                Some(Decl::Enum(decl)) if decl.is_primitive() => {
+                    format!("enum class {} : {};", name, keyword_ty(decl.repr_type()))
+                }
+                Some(Decl::Enum(_)) => format!("struct {};", name),
+                Some(Decl::Class(_)) => format!("class {};", name),
+                Some(Decl::Interface(interface)) => match &interface.type_params {
+                    Some(params) => format!("{} class {};", template(params), name),
+                    None => format!("class {};", name),
+                },
+                _ => continue,
+            };
+            self.enter_decl(out, decl);
+            out.line(line);
+        }
+        // `Set` elements and `Map` keys are hashed in types defined before their newtype.
+        for decl in decls.iter().filter(|decl| self.is_hashed(decl)) {
+            self.enter(out, Some(vec!["std".to_string()]));
+            let name = self.name(decl);
+            out.line("template <>");
+            out.open(format!("struct hash<{}> {{", name));
+            out.line(format!(
+                "std::size_t operator()(const {} &value) const noexcept;",
+                name
+            ));
+            out.close("};");
+            out.blank();
+        }
+        Ok(())
+    }
+
+    /// Whether `decl` is a newtype of a scalar or string, which `std::hash` is specialized for.
+    fn is_hashed(&self, decl: &DeclRef) -> bool {
+        match self.get(decl) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                self.abi.scalar(&alias.ty).is_some()
+                    || matches!(
+                        self.abi.ir.underlying(&alias.ty).kind,
+                        TypeKind::Keyword(KeywordType::String)
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    /// The `std::hash` of newtypes, hashing their value.
+    fn hashes(&self, out: &mut CodeWriter, decls: &[DeclRef]) -> Result<(), CodegenError> {
+        for decl in decls.iter().filter(|decl| self.is_hashed(decl)) {
+            let Some(Decl::Alias(alias)) = self.get(decl) else {
+                continue;
+            };
+            self.enter(out, Some(vec!["std".to_string()]));
+            let name = self.name(decl);
+            out.blank();
+            out.open(format!(
+                "inline std::size_t hash<{}>::operator()(const {} &value) const noexcept {{",
+                name, name
+            ));
+            out.line(format!(
+                "return hash<{}>{{}}(value.value);",
+                self.ty(&alias.ty)?
+            ));
+            out.close("}");
+        }
+        Ok(())
+    }
+
+    /// Adds what a definition using `ty` needs to be defined before it. Aliases are needed
+    /// wherever they are named, the other types where `ty` holds them by value.
+    fn deps(&self, ty: &Type, by_value: bool, deps: &mut Vec<DeclRef>) {
+        match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => match self.get(target) {
+                Some(Decl::Alias(alias)) if !alias.distinct => {
+                    deps.push(target.clone());
+                    if by_value {
+                        self.deps(&alias.ty, true, deps)
+                    }
+                }
+                Some(Decl::Alias(_) | Decl::Model(_) | Decl::Enum(_) | Decl::Class(_))
+                    if by_value =>
+                {
+                    deps.push(target.clone())
+                }
+                _ => {}
+            },
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) | BuiltinType::Set(ty) => self.deps(ty, by_value, deps),
+                BuiltinType::List(ty) | BuiltinType::Stream(ty) => self.deps(ty, false, deps),
+                BuiltinType::Map(map) => {
+                    self.deps(&map.key, by_value, deps);
+                    self.deps(&map.value, by_value, deps)
+                }
+                BuiltinType::Tuple(types) => {
+                    types.iter().for_each(|ty| self.deps(ty, by_value, deps))
+                }
+                BuiltinType::Array(array) => self.deps(&array.element, by_value, deps),
+            },
+            _ => {}
+        }
+    }
+
+    fn function_deps(&self, function: &FnDecl, deps: &mut Vec<DeclRef>) {
+        let types = function.params.iter().map(|param| &param.ty);
+        for ty in types
+            .chain(function.return_type.as_deref())
+            .chain(function.error_type.as_deref())
+        {
+            self.deps(ty, false, deps)
+        }
+    }
+
+    fn extends_deps(&self, with: &TypeWithArgs, deps: &mut Vec<DeclRef>) {
+        deps.extend(with.target.clone());
+        for ty in with.type_args.iter().flatten() {
+            self.deps(ty, false, deps)
+        }
+    }
+
+    /// Defines `decl` after the declarations it needs, which a cycle of models only gets
+    /// through a `slime::Box` or a `std::vector`.
+    fn define(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        defined: &mut Vec<QualifiedName>,
+    ) -> Result<(), CodegenError> {
+        if defined.contains(&decl.name) {
+            return Ok(());
+        }
+        defined.push(decl.name.clone());
+        let mut deps = vec![];
+        match self.get(decl) {
+            Some(Decl::Alias(alias)) => self.deps(&alias.ty, alias.distinct, &mut deps),
+            Some(Decl::Model(model)) => {
+                for field in &model.fields {
+                    let by_value = !self.is_boxed(&field.ty, &decl.name);
+                    self.deps(&field.ty, by_value, &mut deps)
+                }
+            }
+            Some(Decl::Enum(decl_enum)) => {
+                for variant in &decl_enum.variants {
+                    for (_, ty) in variant_fields(&variant.fields) {
+                        self.deps(ty, !self.is_boxed(ty, &decl.name), &mut deps)
+                    }
+                }
+            }
+            Some(Decl::Class(class)) => {
+                for with in self.abi.class_interfaces(class) {
+                    self.extends_deps(&with, &mut deps)
+                }
+                for function in self.abi.class_functions(class) {
+                    self.function_deps(&function, &mut deps)
+                }
+                for member in &class.member {
+                    match member {
+                        ClassMember::StaticMethod(function) => {
+                            self.function_deps(function, &mut deps)
+                        }
+                        ClassMember::Constructor(ctor) => {
+                            for param in &ctor.params {
+                                self.deps(&param.ty, false, &mut deps)
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some(Decl::Interface(interface)) => {
+                for with in &interface.extends {
+                    self.extends_deps(with, &mut deps)
+                }
+                for function in interface.vtable() {
+                    self.function_deps(&function, &mut deps)
+                }
+            }
+            _ => return Ok(()),
+        }
+        for dep in &deps {
+            self.define(out, dep, defined)?;
+        }
+        match self.get(decl) {
+            Some(Decl::Alias(alias)) if alias.distinct => self.newtype(out, decl, &alias.ty),
+            Some(Decl::Alias(alias)) => {
+                self.enter_decl(out, decl);
+                out.blank();
+                out.line(format!(
+                    "using {} = {};",
+                    ident(alias.name.as_str()),
+                    self.ty(&alias.ty)?
+                ));
+                Ok(())
+            }
+            Some(Decl::Model(model)) => self.model(out, decl, model),
+            Some(Decl::Enum(decl_enum)) if decl_enum.is_primitive() => {
+                self.unit_enum(out, decl, decl_enum);
+                Ok(())
+            }
+            Some(Decl::Enum(decl_enum)) => self.payload_enum(out, decl, decl_enum),
+            Some(Decl::Class(class)) => self.class(out, decl, class),
+            Some(Decl::Interface(interface)) => self.interface(out, decl, interface),
+            _ => Ok(()),
+        }
+    }
+
+    /// `friend bool operator==`, defined once every type is.
+    fn comparisons(out: &mut CodeWriter, name: &str) {
+        out.blank();
+        out.line(format!(
+            "friend bool operator==(const {} &a, const {} &b);",
+            name, name
+        ));
+        out.open(format!(
+            "friend bool operator!=(const {} &a, const {} &b) {{",
+            name, name
+        ));
+        out.line("return !(a == b);");
+        out.close("}");
+    }
+
+    fn newtype(&self, out: &mut CodeWriter, decl: &DeclRef, ty: &Type) -> Result<(), CodegenError> {
+        let name = ident(decl.name.name.as_str());
+        self.enter_decl(out, decl);
+        out.blank();
+        out.open(format!("struct {} {{", name));
+        out.line(format!("{} value;", self.ty(ty)?));
+        if self.has_objects(ty, &mut vec![]) {
+            out.close("};");
+            return Ok(());
+        }
+        out.blank();
+        out.open(format!(
+            "friend bool operator==(const {} &a, const {} &b) {{",
+            name, name
+        ));
+        out.line("return a.value == b.value;");
+        out.close("}");
+        out.blank();
+        out.open(format!(
+            "friend bool operator!=(const {} &a, const {} &b) {{",
+            name, name
+        ));
+        out.line("return !(a == b);");
+        out.close("}");
+        out.close("};");
+        Ok(())
+    }
+
+    fn model(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        model: &ModelDecl,
+    ) -> Result<(), CodegenError> {
+        let name = ident(model.name.as_str());
+        self.enter_decl(out, decl);
+        out.blank();
+        out.open(format!("struct {} {{", name));
+        for field in &model.fields {
+            let mut line = format!(
+                "{} {}",
+                self.field_ty(&field.ty, &decl.name)?,
+                ident(field.name.as_str())
+            );
+            if let Some(default) = &field.default {
+                line.push_str(&format!(" = {}", self.value(&field.ty, default)?));
+            }
+            out.line(format!("{};", line));
+        }
+        if !self.model_has_objects(model) {
+            Self::comparisons(out, &name);
+        }
+        out.close("};");
+        Ok(())
+    }
+
+    fn model_has_objects(&self, model: &ModelDecl) -> bool {
+        model
+            .fields
+            .iter()
+            .any(|field| self.has_objects(&field.ty, &mut vec![]))
+    }
+
+    fn enum_has_objects(&self, decl: &EnumDecl) -> bool {
+        decl.variants.iter().any(|variant| {
+            variant_fields(&variant.fields)
+                .iter()
+                .any(|(_, ty)| self.has_objects(ty, &mut vec![]))
+        })
+    }
+
+    /// The discriminant of the variant at `index`, as a literal of the repr.
+    fn discriminant(decl: &EnumDecl, index: usize) -> String {
+        match &decl.variants[index].discriminant {
+            Some(value) => c::num(value),
+            None => c::index(decl.repr_type(), index),
+        }
+    }
+
+    fn unit_enum(&self, out: &mut CodeWriter, decl: &DeclRef, decl_enum: &EnumDecl) {
+        self.enter_decl(out, decl);
+        out.blank();
+        out.open(format!(
+            "enum class {} : {} {{",
+            ident(decl_enum.name.as_str()),
+            keyword_ty(decl_enum.repr_type())
+        ));
+        for (index, variant) in decl_enum.variants.iter().enumerate() {
+            out.line(format!(
+                "{} = {},",
+                ident(variant.name.as_str()),
+                Self::discriminant(decl_enum, index)
+            ));
+        }
+        out.close("};");
+    }
+
+    /// A struct per variant and the `std::variant` of them. Names in it are qualified, so
+    /// variants cannot hide the types of their fields.
+    fn payload_enum(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        decl_enum: &EnumDecl,
+    ) -> Result<(), CodegenError> {
+        let compared = !self.enum_has_objects(decl_enum);
+        let name = ident(decl_enum.name.as_str());
+        self.enter_decl(out, decl);
+        let scope = self.scope.replace(None);
+        out.blank();
+        out.open(format!("struct {} {{", name));
+        let mut variants = vec![];
+        for variant in &decl_enum.variants {
+            let variant_name = ident(variant.name.as_str());
+            out.open(format!("struct {} {{", variant_name));
+            for (field, ty) in variant_fields(&variant.fields) {
+                out.line(format!("{} {};", self.field_ty(ty, &decl.name)?, field));
+            }
+            if compared {
+                Self::comparisons(out, &variant_name);
+            }
+            out.close("};");
+            out.blank();
+            variants.push(variant_name);
+        }
+        out.line(format!("std::variant<{}> value;", variants.join(", ")));
+        if compared {
+            Self::comparisons(out, &name);
+        }
+        out.close("};");
+        self.scope.replace(scope);
+        Ok(())
+    }
+
+    /// The exports of the class or interface `decl`.
+    fn exports_of(&self, decl: &DeclRef) -> Vec<&Export> {
+        self.exports
+            .iter()
+            .filter(|export| match &export.target {
+                Target::Constructor { class, .. } => class == decl,
+                Target::Static(target) | Target::Method(target) => target == decl,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Whether a constructor is the `create` function of the Expected error style.
+    fn is_create(&self, function: &FnDecl) -> bool {
+        self.expected() && function.error_type.is_some()
+    }
+
+    /// The release of a handle for destructors, which `free` of the class or interface.
+    fn destructor(out: &mut CodeWriter, decl: &DeclRef) {
+        out.line("if (handle_ == 0) return;");
+        out.line(format!(
+            "SlimeResult_void result = slime_{}_free(handle_);",
+            c_name(decl)
+        ));
+        out.line("slime::discard(result);");
+    }
+
+    fn handle_accessor(out: &mut CodeWriter) {
+        out.blank();
+        out.line("/** The handle of the object, borrowed by calls. */");
+        out.open("uint64_t handle() const noexcept {");
+        out.line("return handle_;");
+        out.close("}");
+    }
+
+    fn class(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        class: &ClassDecl,
+    ) -> Result<(), CodegenError> {
+        let name = ident(class.name.as_str());
+        let bases = self
+            .abi
+            .class_interfaces(class)
+            .iter()
+            .map(|with| Ok(format!("public virtual {}", self.with_args(with)?)))
+            .collect::<Result<Vec<String>, CodegenError>>()?;
+        self.enter_decl(out, decl);
+        out.blank();
+        out.line(format!(
+            "/** `{}` of Rust, releasing its handle on destruction. */",
+            class.name.as_str()
+        ));
+        match bases.is_empty() {
+            true => out.open(format!("class {} final {{", name)),
+            false => out.open(format!("class {} final : {} {{", name, bases.join(", "))),
+        }
+        out.reopen("public:");
+        let mut constants = false;
+        for member in &class.member {
+            if let ClassMember::Const(constant) = member {
+                out.line(format!(
+                    "static constexpr {} {} = {};",
+                    self.const_ty(&constant.ty),
+                    ident(constant.name.as_str()),
+                    literal(&constant.value)
+                ));
+                constants = true;
+            }
+        }
+        if constants {
+            out.blank();
+        }
+        out.line("/** Adopts a handle of Rust. */");
+        out.line(format!(
+            "explicit {}(slime::Handle handle) noexcept : handle_(handle.value) {{}}",
+            name
+        ));
+        let exports = self.exports_of(decl);
+        for export in &exports {
+            let function = &export.function;
+            let params = self.params(function, None, true)?;
+            match &export.target {
+                Target::Constructor { .. } if self.is_create(function) => out.line(format!(
+                    "static {} create({});",
+                    self.output(function)?,
+                    params
+                )),
+                Target::Constructor { .. } => match function.params.len() {
+                    1 => out.line(format!("explicit {}({});", name, params)),
+                    _ => out.line(format!("{}({});", name, params)),
+                },
+                _ => {}
+            }
+        }
+        out.blank();
+        out.line(format!(
+            "{}({} &&other) noexcept : handle_(std::exchange(other.handle_, 0)) {{}}",
+            name, name
+        ));
+        out.blank();
+        out.open(format!("{} &operator=({} &&other) noexcept {{", name, name));
+        out.line("std::swap(handle_, other.handle_);");
+        out.line("return *this;");
+        out.close("}");
+        out.blank();
+        out.line(format!("{}(const {} &) = delete;", name, name));
+        out.line(format!("{} &operator=(const {} &) = delete;", name, name));
+        out.blank();
+        out.open(format!("~{}() {{", name));
+        Self::destructor(out, decl);
+        out.close("}");
+        Self::handle_accessor(out);
+        out.blank();
+        out.line("/** Gives up the handle, which is not released on destruction then. */");
+        out.open("uint64_t release() noexcept {");
+        out.line("return std::exchange(handle_, 0);");
+        out.close("}");
+        out.blank();
+        for export in &exports {
+            let function = &export.function;
+            match &export.target {
+                Target::Static(_) => out.line(format!(
+                    "static {} {}({});",
+                    self.ret(function)?,
+                    method(function.name.as_str()),
+                    self.params(function, None, true)?
+                )),
+                Target::Method(_) => {
+                    let origin = self.overridden(class, function.name.as_str());
+                    out.line(format!(
+                        "{} {}({}){};",
+                        self.ret(function)?,
+                        method(function.name.as_str()),
+                        self.params(function, origin.as_ref(), origin.is_none())?,
+                        if origin.is_some() { " override" } else { "" }
+                    ))
+                }
+                _ => {}
+            }
+        }
+        out.blank();
+        out.reopen("private:");
+        out.line("uint64_t handle_;");
+        out.close("};");
+        Ok(())
+    }
+
+    fn const_ty(&self, ty: &Type) -> &'static str {
+        match self.abi.scalar(ty) {
+            Some(keyword) => keyword_ty(keyword),
+            None => "std::string_view",
+        }
+    }
+
+    fn interface(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        interface: &InterfaceDecl,
+    ) -> Result<(), CodegenError> {
+        let name = ident(interface.name.as_str());
+        let bases = interface
+            .extends
+            .iter()
+            .map(|with| Ok(format!("public virtual {}", self.with_args(with)?)))
+            .collect::<Result<Vec<String>, CodegenError>>()?;
+        self.enter_decl(out, decl);
+        out.blank();
+        out.line(format!(
+            "/** Implemented by the host, or by Rust as `{}Object`. */",
+            interface.name.as_str()
+        ));
+        if let Some(params) = &interface.type_params {
+            out.line(template(params));
+        }
+        match bases.is_empty() {
+            true => out.open(format!("class {} {{", name)),
+            false => out.open(format!("class {} : {} {{", name, bases.join(", "))),
+        }
+        out.reopen("public:");
+        out.line(format!("virtual ~{}() = default;", name));
+        for member in &interface.member {
+            for function in member.functions() {
+                if self.is_virtual(&function) {
+                    out.line(format!(
+                        "virtual {} {}({}) = 0;",
+                        self.ret(&function)?,
+                        method(function.name.as_str()),
+                        self.params(&function, None, false)?
+                    ));
+                }
+            }
+        }
+        out.close("};");
+        if interface.type_params.is_some() {
+            return Ok(());
+        }
+
+        out.blank();
+        out.line(format!(
+            "/** A `{}` of Rust, releasing its handle on destruction. */",
+            interface.name.as_str()
+        ));
+        let object = format!("{}Object", name);
+        out.open(format!("class {} final : public {} {{", object, name));
+        out.reopen("public:");
+        out.line(format!(
+            "explicit {}(slime::Handle handle) noexcept : handle_(handle.value) {{}}",
+            object
+        ));
+        out.line(format!("{}(const {} &) = delete;", object, object));
+        out.line(format!(
+            "{} &operator=(const {} &) = delete;",
+            object, object
+        ));
+        out.blank();
+        out.open(format!("~{}() override {{", object));
+        Self::destructor(out, decl);
+        out.close("}");
+        Self::handle_accessor(out);
+        out.blank();
+        for export in self.exports_of(decl) {
+            let function = &export.function;
+            let origin = self.origin(interface, function.name.as_str());
+            out.line(format!(
+                "{} {}({}) override;",
+                self.ret(function)?,
+                method(function.name.as_str()),
+                self.params(function, origin.as_ref(), false)?
+            ));
+        }
+        out.blank();
+        out.reopen("private:");
+        out.line("uint64_t handle_;");
+        out.close("};");
+
+        out.blank();
+        out.line(format!(
+            "/** Hands implementations of `{}` to Rust. */",
+            interface.name.as_str()
+        ));
+        let callback = format!("{}Callback", name);
+        out.open(format!("class {} final {{", callback));
+        out.reopen("public:");
+        out.line(format!(
+            "/** A pointer to `impl` for Rust, which releases it. Throws std::logic_error if another implementation of `{}` is registered. */",
+            interface.name.as_str()
+        ));
+        out.line(format!(
+            "static uint64_t retain(std::shared_ptr<{}> impl);",
+            name
+        ));
+        out.blank();
+        out.reopen("private:");
+        out.line("static void release(void *object) noexcept;");
+        for slot in Self::slots(interface) {
+            out.line(format!(
+                "static SlimeBuffer {}(void *object, SlimeSlice args) noexcept;",
+                slot
+            ));
+        }
+        out.close("};");
+        Ok(())
+    }
+
+    /// The host functions of an interface in slot order, `method_on` and so on.
+    fn slots(interface: &InterfaceDecl) -> Vec<String> {
+        let vtable = interface.vtable();
+        vtable
+            .iter()
+            .enumerate()
+            .map(|(slot, function)| {
+                let name = function.name.as_str();
+                match vtable
+                    .iter()
+                    .filter(|other| other.name.as_str() == name)
+                    .count()
+                {
+                    1 => format!("method_{}", name),
+                    _ => format!("method_{}_{}", name, slot),
+                }
+            })
+            .collect()
+    }
+
+    /// `inline constexpr uint32_t TIMEOUT = UINT32_C(30);` of a module and its `mod`s.
+    fn constants(&self, out: &mut CodeWriter, path: &[Ident], decls: &[&Decl]) {
+        for decl in decls {
+            match decl {
+                Decl::Const(constant) => {
+                    self.enter(out, Some(self.namespace(path)));
+                    out.line(format!(
+                        "inline constexpr {} {} = {};",
+                        self.const_ty(&constant.ty),
+                        ident(constant.name.as_str()),
+                        literal(&constant.value)
+                    ));
+                }
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    let items: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                    self.constants(out, &path, &items)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The C++ type the wire format of `decl` is specialized for.
+    fn wire_ty(&self, decl: &DeclRef) -> Option<String> {
+        match self.get(decl)? {
+            Decl::Alias(alias) if alias.distinct => Some(self.name(decl)),
+            Decl::Model(_) | Decl::Enum(_) | Decl::Class(_) => Some(self.name(decl)),
+            Decl::Interface(interface) if interface.type_params.is_none() => {
+                Some(format!("std::shared_ptr<{}>", self.name(decl)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Declares the `slime::Wire` of `decl`, in namespace `slime`.
+    fn wire(&self, out: &mut CodeWriter, decl: &DeclRef) -> Result<(), CodegenError> {
+        let Some(ty) = self.wire_ty(decl) else {
+            return Ok(());
+        };
+        out.blank();
+        out.line("template <>");
+        out.open(format!("struct Wire<{}> {{", ty));
+        out.line(format!(
+            "static void write(Writer &writer, const {} &value);",
+            ty
+        ));
+        out.line(format!("static {} read(Reader &reader);", ty));
+        out.close("};");
+        Ok(())
+    }
+
+    /// `slime::detail::lift` and `lower` between the C structs of plain models and theirs.
+    fn detail(&self, out: &mut CodeWriter, decls: &[DeclRef]) -> Result<(), CodegenError> {
+        let mut emitted = vec![];
+        for decl in decls {
+            if let Some(Decl::Model(model)) = self.get(decl) {
+                if self.abi.is_plain_model(&model.fields) {
+                    self.enter(out, Some(vec!["slime".to_string(), "detail".to_string()]));
+                    self.plain_model(out, decl, &mut emitted)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn plain_model(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        emitted: &mut Vec<QualifiedName>,
+    ) -> Result<(), CodegenError> {
+        if emitted.contains(&decl.name) {
+            return Ok(());
+        }
+        emitted.push(decl.name.clone());
+        let Some(Decl::Model(model)) = self.get(decl) else {
+            return Err(CodegenError::custom(format!(
+                "`{}` is not a model",
+                decl.name.name.as_str()
+            )));
+        };
+        let mut lifted = vec![];
+        let mut lowered = vec![];
+        for field in &model.fields {
+            let c_field = format!("value.{}", c::ident(field.name.as_str()));
+            let field_name = format!("value.{}", ident(field.name.as_str()));
+            match self.abi.c_type(&field.ty) {
+                AbiType::Scalar(_) => {
+                    lifted.push(self.wrap(&field.ty, c_field));
+                    lowered.push(self.unwrap(&field.ty, field_name));
+                }
+                AbiType::Struct(target) => {
+                    self.plain_model(out, &target, emitted)?;
+                    lifted.push(format!("lift({})", c_field));
+                    lowered.push(format!("lower({})", field_name));
+                }
+                _ => return Err(CodegenError::custom("plain models hold scalars and models")),
+            }
+        }
+        let name = self.name(decl);
+        let c_struct = c_name(decl);
+        out.blank();
+        out.open(format!(
+            "inline {} lift(const {} &value) {{",
+            name, c_struct
+        ));
+        out.line(format!("return {}{{{}}};", name, lifted.join(", ")));
+        out.close("}");
+        out.blank();
+        out.open(format!(
+            "inline {} lower(const {} &value) {{",
+            c_struct, name
+        ));
+        out.line(format!("return {}{{{}}};", c_struct, lowered.join(", ")));
+        out.close("}");
+        Ok(())
+    }
+
+    /// Defines the `slime::Wire` of `decl`.
+    fn wire_definitions(&self, out: &mut CodeWriter, decl: &DeclRef) -> Result<(), CodegenError> {
+        let Some(ty) = self.wire_ty(decl) else {
+            return Ok(());
+        };
+        self.enter(out, Some(vec!["slime".to_string()]));
+        let decl_item = self.get(decl);
+        let unused = match decl_item {
+            Some(Decl::Model(model)) => model.fields.is_empty(),
+            _ => false,
+        };
+        out.blank();
+        out.open(format!(
+            "inline void Wire<{}>::write(Writer &{}, const {} &{}) {{",
+            ty,
+            if unused { "" } else { "writer" },
+            ty,
+            if unused { "" } else { "value" }
+        ));
+        let mut read = CodeWriter::new(INDENT);
+        match decl_item {
+            Some(Decl::Alias(alias)) => {
+                let inner = self.ty(&alias.ty)?;
+                out.line(format!("Wire<{}>::write(writer, value.value);", inner));
+                read.line(format!("return {}{{Wire<{}>::read(reader)}};", ty, inner));
+            }
+            Some(Decl::Model(model)) => {
+                let mut fields = vec![];
+                for field in &model.fields {
+                    let field_ty = self.field_ty(&field.ty, &decl.name)?;
+                    out.line(format!(
+                        "Wire<{}>::write(writer, value.{});",
+                        field_ty,
+                        ident(field.name.as_str())
+                    ));
+                    fields.push(format!("Wire<{}>::read(reader)", field_ty));
+                }
+                read.line("// Braced initializers are evaluated in order.");
+                read.line(format!("return {}{{{}}};", ty, fields.join(", ")));
+            }
+            Some(Decl::Enum(decl_enum)) if decl_enum.is_primitive() => {
+                let repr = keyword_ty(decl_enum.repr_type());
+                out.line(format!(
+                    "Wire<{}>::write(writer, static_cast<{}>(value));",
+                    repr, repr
+                ));
+                read.line(format!(
+                    "auto value = static_cast<{}>(Wire<{}>::read(reader));",
+                    ty, repr
+                ));
+                read.open("switch (value) {");
+                for variant in &decl_enum.variants {
+                    read.line(format!("case {}::{}:", ty, ident(variant.name.as_str())));
+                }
+                if !decl_enum.variants.is_empty() {
+                    read.line(format!("{}return value;", INDENT));
+                }
+                read.close("}");
+                read.line(format!(
+                    "throw WireError(\"invalid value of `{}`\");",
+                    decl_enum.name.as_str()
+                ));
+            }
+            Some(Decl::Enum(decl_enum)) => self.enum_wire(out, &mut read, decl, decl_enum, &ty)?,
+            Some(Decl::Class(_)) => {
+                out.line("writer.number(value.handle());");
+                read.line(format!(
+                    "return {}(Handle{{reader.number<uint64_t>()}});",
+                    ty
+                ));
+            }
+            Some(Decl::Interface(_)) => {
+                out.line(format!(
+                    "writer.number({}::retain(value));",
+                    self.suffixed(decl, "Callback")
+                ));
+                read.line(format!(
+                    "return std::make_shared<{}>(Handle{{reader.number<uint64_t>()}});",
+                    self.suffixed(decl, "Object")
+                ));
+            }
+            _ => {}
+        }
+        out.close("}");
+        out.blank();
+        out.open(format!(
+            "inline {} Wire<{}>::read(Reader &{}) {{",
+            ty,
+            ty,
+            if unused { "" } else { "reader" }
+        ));
+        match unused {
+            true => out.line(format!("return {}{{}};", ty)),
+            false => out.lines(read.finish()),
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    /// The discriminant and then the fields of the variant.
+    fn enum_wire(
+        &self,
+        write: &mut CodeWriter,
+        read: &mut CodeWriter,
+        decl: &DeclRef,
+        decl_enum: &EnumDecl,
+        ty: &str,
+    ) -> Result<(), CodegenError> {
+        let repr = keyword_ty(decl_enum.repr_type());
+        write.open("switch (value.value.index()) {");
+        read.open(format!("switch (Wire<{}>::read(reader)) {{", repr));
+        for (index, variant) in decl_enum.variants.iter().enumerate() {
+            let discriminant = Self::discriminant(decl_enum, index);
+            let fields = variant_fields(&variant.fields);
+            write.open(format!("case {}: {{", index));
+            if !fields.is_empty() {
+                write.line(format!(
+                    "const auto &variant = std::get<{}>(value.value);",
+                    index
+                ));
+            }
+            write.line(format!("Wire<{}>::write(writer, {});", repr, discriminant));
+            let mut reads = vec![];
+            for (field, field_ty) in &fields {
+                let field_ty = self.field_ty(field_ty, &decl.name)?;
+                write.line(format!(
+                    "Wire<{}>::write(writer, variant.{});",
+                    field_ty, field
+                ));
+                reads.push(format!("Wire<{}>::read(reader)", field_ty));
+            }
+            write.line("break;");
+            write.close("}");
+            read.line(format!("case {}:", discriminant));
+            read.line(format!(
+                "{}return {}{{{}::{}{{{}}}}};",
+                INDENT,
+                ty,
+                ty,
+                ident(variant.name.as_str()),
+                reads.join(", ")
+            ));
+        }
+        write.close("}");
+        read.close("}");
+        read.line(format!(
+            "throw WireError(\"invalid discriminant of `{}`\");",
+            decl_enum.name.as_str()
+        ));
+        Ok(())
+    }
+
+    /// `operator==` of the models and enums without objects, by their fields.
+    fn equality(&self, out: &mut CodeWriter, decl: &DeclRef) -> Result<(), CodegenError> {
+        match self.get(decl) {
+            Some(Decl::Model(model)) if !self.model_has_objects(model) => {
+                self.enter_decl(out, decl);
+                let fields: Vec<String> = model
+                    .fields
+                    .iter()
+                    .map(|field| ident(field.name.as_str()))
+                    .collect();
+                Self::compare(out, &self.name(decl), &fields);
+            }
+            Some(Decl::Enum(decl_enum))
+                if !decl_enum.is_primitive() && !self.enum_has_objects(decl_enum) =>
+            {
+                self.enter_decl(out, decl);
+                let name = self.name(decl);
+                for variant in &decl_enum.variants {
+                    let fields: Vec<String> = variant_fields(&variant.fields)
+                        .into_iter()
+                        .map(|(field, _)| field)
+                        .collect();
+                    let variant = format!("{}::{}", name, ident(variant.name.as_str()));
+                    Self::compare(out, &variant, &fields);
+                }
+                Self::compare(out, &name, &["value".to_string()]);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn compare(out: &mut CodeWriter, name: &str, fields: &[String]) {
+        out.blank();
+        if fields.is_empty() {
+            out.open(format!(
+                "inline bool operator==(const {} &, const {} &) {{",
+                name, name
+            ));
+            out.line("return true;");
+            out.close("}");
+            return;
+        }
+        out.open(format!(
+            "inline bool operator==(const {} &a, const {} &b) {{",
+            name, name
+        ));
+        match fields {
+            [field] => out.line(format!("return a.{} == b.{};", field, field)),
+            fields => {
+                let tie = |side: &str| {
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .map(|field| format!("{}.{}", side, field))
+                        .collect();
+                    format!("std::tie({})", fields.join(", "))
+                };
+                out.line(format!("return {} == {};", tie("a"), tie("b")))
+            }
+        }
+        out.close("}");
+    }
+
+    /// The out-of-line members of a class, or of the object and callback of an interface.
+    fn members(&self, out: &mut CodeWriter, decl: &DeclRef) -> Result<(), CodegenError> {
+        match self.get(decl) {
+            Some(Decl::Class(class)) => {
+                self.enter_decl(out, decl);
+                let name = ident(class.name.as_str());
+                for export in self.exports_of(decl) {
+                    let function = &export.function;
+                    out.blank();
+                    match &export.target {
+                        Target::Constructor { .. } if self.is_create(function) => {
+                            out.open(format!(
+                                "inline {} {}::create({}) {{",
+                                self.output(function)?,
+                                name,
+                                self.params(function, None, false)?
+                            ));
+                            self.call(out, export, None, false)?;
+                        }
+                        Target::Constructor { .. } => {
+                            out.open(format!(
+                                "inline {}::{}({}) : handle_(0) {{",
+                                name,
+                                name,
+                                self.params(function, None, false)?
+                            ));
+                            self.call(out, export, None, true)?;
+                        }
+                        Target::Static(_) => {
+                            out.open(format!(
+                                "inline {} {}::{}({}) {{",
+                                self.ret(function)?,
+                                name,
+                                method(function.name.as_str()),
+                                self.params(function, None, false)?
+                            ));
+                            self.call(out, export, None, false)?;
+                        }
+                        _ => {
+                            let origin = self.overridden(class, function.name.as_str());
+                            out.open(format!(
+                                "inline {} {}::{}({}) {{",
+                                self.ret(function)?,
+                                name,
+                                method(function.name.as_str()),
+                                self.params(function, origin.as_ref(), false)?
+                            ));
+                            self.call(out, export, Some("handle_"), false)?;
+                        }
+                    }
+                    out.close("}");
+                }
+            }
+            Some(Decl::Interface(interface)) if interface.type_params.is_none() => {
+                self.enter_decl(out, decl);
+                let object = format!("{}Object", ident(interface.name.as_str()));
+                for export in self.exports_of(decl) {
+                    let function = &export.function;
+                    let origin = self.origin(interface, function.name.as_str());
+                    out.blank();
+                    out.open(format!(
+                        "inline {} {}::{}({}) {{",
+                        self.ret(function)?,
+                        object,
+                        method(function.name.as_str()),
+                        self.params(function, origin.as_ref(), false)?
+                    ));
+                    self.call(out, export, Some("handle_"), false)?;
+                    out.close("}");
+                }
+                self.callback(out, decl, interface)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn function(&self, out: &mut CodeWriter, export: &Export) -> Result<(), CodegenError> {
+        let function = &export.function;
+        out.blank();
+        out.open(format!(
+            "inline {} {}({}) {{",
+            self.ret(function)?,
+            ident(function.name.as_str()),
+            self.params(function, None, true)?
+        ));
+        self.call(out, export, None, false)?;
+        out.close("}");
+        Ok(())
+    }
+
+    /// The body of a function calling `export`, passing `receiver` first. A constructor
+    /// adopts the handle of the result instead of returning it.
+    fn call(
+        &self,
+        out: &mut CodeWriter,
+        export: &Export,
+        receiver: Option<&str>,
+        constructor: bool,
+    ) -> Result<(), CodegenError> {
+        let function = &export.function;
+        let symbol = &export.symbol;
+        let mut args: Vec<String> = receiver
+            .iter()
+            .map(|receiver| receiver.to_string())
+            .collect();
+        for param in &function.params {
+            let name = ident(param.name.as_str());
+            args.push(match self.abi.c_type(&param.ty) {
+                AbiType::Scalar(_) => self.unwrap(&param.ty, name),
+                AbiType::Enum(repr) => format!("static_cast<{}>({})", keyword_ty(repr), name),
+                AbiType::Struct(_) => format!("slime::detail::lower({})", name),
+                AbiType::Handle => match self.target(&param.ty)? {
+                    (target, Decl::Interface(_)) => {
+                        format!("{}::retain({})", self.suffixed(&target, "Callback"), name)
+                    }
+                    _ => format!("{}.handle()", name),
+                },
+                AbiType::Stream => {
+                    return Err(CodegenError::custom("streams are only returned"));
+                }
+                AbiType::Buffer => {
+                    let arg = format!("arg_{}", param.name.as_str());
+                    out.line(format!(
+                        "std::vector<uint8_t> {} = slime::encode({});",
+                        arg, name
+                    ));
+                    format!("slime::slice({})", arg)
+                }
+            });
+        }
+        out.line(format!("auto result = {}({});", symbol, args.join(", ")));
+        if !function.is_async {
+            return self.finish(out, export, constructor);
+        }
+        out.line(format!(
+            "auto future = std::make_shared<slime::Task>(slime::value(result), &{}_poll, &{}_cancel, &{}_free);",
+            symbol, symbol, symbol
+        ));
+        out.open(format!(
+            "return slime::await(std::move(future), [](slime::Task &task) -> {} {{",
+            self.output(function)?
+        ));
+        out.line(format!("auto result = {}_complete(task.handle());", symbol));
+        out.line("task.finish();");
+        self.finish(out, export, false)?;
+        out.close("});");
+        Ok(())
+    }
+
+    /// Returns the output of the call in `result`.
+    fn finish(
+        &self,
+        out: &mut CodeWriter,
+        export: &Export,
+        constructor: bool,
+    ) -> Result<(), CodegenError> {
+        let function = &export.function;
+        if let Some(error) = &function.error_type {
+            let error = self.ty(error)?;
+            out.open(format!(
+                "if (auto error = slime::thrown<{}>(result)) {{",
+                error
+            ));
+            match self.expected() && !constructor {
+                true => out.line("return slime::unexpected(std::move(*error));"),
+                false => out.line(format!(
+                    "throw slime::Exception<{}>(std::move(*error));",
+                    error
+                )),
+            }
+            out.close("}");
+        }
+        if constructor {
+            out.line("handle_ = slime::value(result);");
+            return Ok(());
+        }
+        let Some(ty) = function.return_type.as_deref() else {
+            out.line("slime::value(result);");
+            if self.expected() && function.error_type.is_some() {
+                out.line("return {};");
+            }
+            return Ok(());
+        };
+        let value = "slime::value(result)".to_string();
+        let lifted = match self.abi.c_type(ty) {
+            AbiType::Scalar(_) => self.wrap(ty, value),
+            AbiType::Enum(_) => format!("static_cast<{}>({})", self.ty(ty)?, value),
+            AbiType::Struct(_) => format!("slime::detail::lift({})", value),
+            AbiType::Handle => match self.target(ty)? {
+                (target, Decl::Interface(_)) => format!(
+                    "std::make_shared<{}>(slime::Handle{{{}}})",
+                    self.suffixed(&target, "Object"),
+                    value
+                ),
+                (target, _) => format!("{}(slime::Handle{{{}}})", self.name(&target), value),
+            },
+            AbiType::Buffer => format!("slime::decode<{}>({})", self.ty(ty)?, value),
+            AbiType::Stream => {
+                let TypeKind::Builtin(BuiltinType::Stream(item)) = &self.abi.ir.underlying(ty).kind
+                else {
+                    return Err(CodegenError::custom("`Stream` is a builtin"));
+                };
+                let item = self.ty(item)?;
+                let symbol = &export.symbol;
+                out.line(format!(
+                    "auto stream = std::make_shared<slime::Task>({}, &{}_stream_poll, &{}_stream_cancel, &{}_stream_free);",
+                    value, symbol, symbol, symbol
+                ));
+                out.open(format!(
+                    "return slime::Stream<{}>([stream]() -> std::optional<{}> {{",
+                    item, item
+                ));
+                out.line("stream->wait();");
+                out.line(format!(
+                    "auto next = {}_stream_next(stream->handle());",
+                    symbol
+                ));
+                out.line(format!(
+                    "return slime::decode<std::optional<{}>>(slime::value(next));",
+                    item
+                ));
+                out.close("});");
+                return Ok(());
+            }
+        };
+        out.line(format!("return {};", lifted));
+        Ok(())
+    }
+
+    /// `retain`, `release` and the methods of the vtable of an interface.
+    fn callback(
+        &self,
+        out: &mut CodeWriter,
+        decl: &DeclRef,
+        interface: &InterfaceDecl,
+    ) -> Result<(), CodegenError> {
+        let name = ident(interface.name.as_str());
+        let callback = format!("{}Callback", name);
+        let slots = Self::slots(interface);
+        out.blank();
+        out.open(format!(
+            "inline uint64_t {}::retain(std::shared_ptr<{}> impl) {{",
+            callback, name
+        ));
+        // The box is handed to Rust only once registered, it is freed if that throws.
+        out.line(format!(
+            "auto boxed = std::make_unique<std::shared_ptr<{}>>(std::move(impl));",
+            name
+        ));
+        out.open("static const bool registered = [] {");
+        let methods: Vec<String> = slots
+            .iter()
+            .map(|slot| format!("&{}::{}", callback, slot))
+            .collect();
+        match methods.is_empty() {
+            // Rust takes a pointer, even to no methods.
+            true => out.line("static const SlimeHostFn methods[] = {nullptr};"),
+            false => out.line(format!(
+                "static const SlimeHostFn methods[] = {{{}}};",
+                methods.join(", ")
+            )),
+        }
+        out.line(format!(
+            "return slime_{}_register(&{}::release, methods, {});",
+            c_name(decl),
+            callback,
+            slots.len()
+        ));
+        out.close("}();");
+        out.open("if (!registered) {");
+        out.line(format!(
+            "throw std::logic_error(\"another implementation of `{}` is registered\");",
+            interface.name.as_str()
+        ));
+        out.close("}");
+        out.line("return reinterpret_cast<uint64_t>(boxed.release());");
+        out.close("}");
+        out.blank();
+        out.open(format!(
+            "inline void {}::release(void *object) noexcept {{",
+            callback
+        ));
+        out.line(format!(
+            "delete static_cast<std::shared_ptr<{}> *>(object);",
+            name
+        ));
+        out.close("}");
+        for (function, slot) in interface.vtable().iter().zip(&slots) {
+            check_host_method(function)?;
+            out.blank();
+            if !self.is_virtual(function) {
+                out.open(format!(
+                    "inline SlimeBuffer {}::{}(void *, SlimeSlice args) noexcept {{",
+                    callback, slot
+                ));
+                out.open(format!(
+                    "return slime::host(args, []({}) {{",
+                    host_params(false, false)
+                ));
+                out.line(format!(
+                    "throw std::logic_error(\"`{}` is generic\");",
+                    function.name.as_str()
+                ));
+                out.close("}, &slime::catch_none);");
+                out.close("}");
+                continue;
+            }
+            out.open(format!(
+                "inline SlimeBuffer {}::{}(void *object, SlimeSlice args) noexcept {{",
+                callback, slot
+            ));
+            out.line(format!(
+                "{} &impl = **static_cast<std::shared_ptr<{}> *>(object);",
+                name, name
+            ));
+            self.host_method(out, function)?;
+            out.close("}");
+        }
+        Ok(())
+    }
+
+    /// Reads the arguments, calls the implementation and writes what it returns.
+    fn host_method(&self, out: &mut CodeWriter, function: &FnDecl) -> Result<(), CodegenError> {
+        let ty = function.return_type.as_deref();
+        let error = match &function.error_type {
+            Some(error) => Some(self.ty(error)?),
+            None => None,
+        };
+        let thrown = match &error {
+            Some(error) => format!("&slime::catch_thrown<{}>", error),
+            None => "&slime::catch_none".to_string(),
+        };
+        out.open(format!(
+            "return slime::host(args, [&]({}) {{",
+            host_params(!function.params.is_empty(), ty.is_some())
+        ));
+        let mut args = vec![];
+        for param in &function.params {
+            let arg = format!("arg_{}", param.name.as_str());
+            out.line(format!(
+                "auto {} = slime::Wire<{}>::read(reader);",
+                arg,
+                self.ty(&param.ty)?
+            ));
+            args.push(arg);
+        }
+        let mut call = format!(
+            "impl.{}({})",
+            method(function.name.as_str()),
+            args.join(", ")
+        );
+        let expected = self.expected() && error.is_some();
+        if let (true, Some(error)) = (self.expected(), &error) {
+            out.line(format!("auto result = {};", call));
+            out.open("if (!result) {");
+            out.line(format!(
+                "throw slime::Exception<{}>(std::move(result).error());",
+                error
+            ));
+            out.close("}");
+            call = "std::move(result).value()".to_string();
+        }
+        match ty {
+            Some(ty) => {
+                let wire = self.ty(ty)?;
+                match self.has_objects(ty, &mut vec![]) {
+                    true => {
+                        out.line(format!("{} value = {};", wire, call));
+                        out.line(format!("slime::Wire<{}>::write(writer, value);", wire));
+                        out.line("slime::keep(std::move(value));");
+                    }
+                    false => out.line(format!("slime::Wire<{}>::write(writer, {});", wire, call)),
+                }
+            }
+            None if !expected => out.line(format!("{};", call)),
+            None => {}
+        }
+        out.close(format!("}}, {});", thrown));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::config::{CConfig, CppConfig, CppErrorStyle};
+
+    use super::CppCodegen;
+    use crate::lang::c::CCodegen;
+    use crate::lang::test::{compile, file, load, temp_dir};
+    use crate::lang::SourceFile;
+    use crate::Codegen;
+
+    const APP: &str = r#"
+model Point {
+    x: i32,
+    y: i32,
+}
+
+enum IoError {
+    NotFound(String),
+    Denied,
+}
+
+enum Event {
+    Click { at: Point },
+    Key(u32),
+    Close,
+}
+
+interface Listener {
+    fn on_event(event: Event) throws IoError;
+    fn name() -> String;
+}
+
+class Client {
+    constructor(url: String);
+    fn send(p: Point) -> (i32, Optional<Bytes>);
+    async fn fetch(url: String) -> Bytes throws IoError;
+}
+
+fn watch(listener: Listener) -> Client;
+fn events() -> Stream<Event>;
+"#;
+
+    fn generate(error_style: CppErrorStyle) -> Vec<SourceFile> {
+        let ir = load(&[("app.slime", APP)]);
+        let c = CConfig {
+            library_name: "app".into(),
+            version: "1.0.0".into(),
+        };
+        let config = CppConfig {
+            namespace: "acme".into(),
+            error_style,
+        };
+        let mut files = CCodegen::new(&ir, &c).generate().unwrap();
+        files.extend(CppCodegen::new(&ir, &c, &config).generate().unwrap());
+        files
+    }
+
+    #[test]
+    fn test_callback_retain() {
+        let files = generate(CppErrorStyle::Exceptions);
+        let header = file(&files, "include/app.hpp");
+        let start = header
+            .find("inline uint64_t ListenerCallback::retain(std::shared_ptr<Listener> impl) {")
+            .unwrap();
+        let retain = &header[start..];
+        let retain = &retain[..retain.find("\n}\n").unwrap()];
+        let boxed = retain
+            .find("auto boxed = std::make_unique<std::shared_ptr<Listener>>(std::move(impl));")
+            .unwrap();
+        let check = retain.find("if (!registered) {").unwrap();
+        let release = retain
+            .find("return reinterpret_cast<uint64_t>(boxed.release());")
+            .unwrap();
+        assert!(boxed < check && check < release);
+        assert!(!retain.contains("new std::shared_ptr"));
+    }
+
+    fn check_compile(error_style: CppErrorStyle) {
+        let dir = temp_dir();
+        for file in generate(error_style) {
+            file.write_to(&dir).unwrap();
+        }
+        std::fs::write(dir.join("main.cpp"), "#include \"app.hpp\"\n").unwrap();
+        compile(
+            "c++",
+            &[
+                "-std=c++17",
+                "-Wall",
+                "-Wextra",
+                "-pedantic",
+                "-Werror",
+                "-fsyntax-only",
+                "-I",
+                "include",
+                "main.cpp",
+            ],
+            &dir,
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compile_exceptions() {
+        check_compile(CppErrorStyle::Exceptions);
+    }
+
+    #[test]
+    fn test_compile_expected() {
+        check_compile(CppErrorStyle::Expected);
+    }
+}
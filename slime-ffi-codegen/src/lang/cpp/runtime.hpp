@@ -0,0 +1,791 @@
+#ifndef SLIME_FFI_RUNTIME_HPP
+#define SLIME_FFI_RUNTIME_HPP
+
+/*
+ * The C++ runtime of slime-ffi, shared by the headers of every library: the wire format of
+ * runtime.h, the errors calls fail with, and the futures and streams of Rust.
+ */
+
+#include <array>
+#include <condition_variable>
+#include <cstddef>
+#include <cstdint>
+#include <cstring>
+#include <exception>
+#include <functional>
+#include <future>
+#include <limits>
+#include <memory>
+#include <mutex>
+#include <optional>
+#include <stdexcept>
+#include <string>
+#include <string_view>
+#include <tuple>
+#include <type_traits>
+#include <unordered_map>
+#include <unordered_set>
+#include <utility>
+#include <variant>
+#include <vector>
+
+namespace slime {
+
+/** What calls fail with, undeclared errors of Rust as well as the base of the others. */
+class Error : public std::runtime_error {
+public:
+    using std::runtime_error::runtime_error;
+};
+
+/** Rust panicked, with the message of the panic. */
+class Panic : public Error {
+public:
+    using Error::Error;
+};
+
+/** The future of the call was cancelled. */
+class Cancelled : public Error {
+public:
+    Cancelled() : Error("the call was cancelled") {}
+};
+
+/** Bytes not in the wire format, from a mismatch of the bindings and the library. */
+class WireError : public Error {
+public:
+    using Error::Error;
+};
+
+/** The error a function declares with `throws`. */
+template <class E>
+class Exception : public Error {
+public:
+    explicit Exception(E error) : Error("the call failed with its declared error"), error_(std::move(error)) {}
+
+    const E &error() const noexcept {
+        return error_;
+    }
+
+private:
+    E error_;
+};
+
+/** The error of an Expected. */
+template <class E>
+struct Unexpected {
+    E error;
+};
+
+template <class E>
+Unexpected<std::decay_t<E>> unexpected(E &&error) {
+    return {std::forward<E>(error)};
+}
+
+/** The value of a call, or the error it declares with `throws`. */
+template <class T, class E>
+class [[nodiscard]] Expected {
+public:
+    Expected(T value) : data_(std::in_place_index<0>, std::move(value)) {}
+    Expected(Unexpected<E> error) : data_(std::in_place_index<1>, std::move(error.error)) {}
+
+    bool has_value() const noexcept {
+        return data_.index() == 0;
+    }
+
+    explicit operator bool() const noexcept {
+        return has_value();
+    }
+
+    /** The value, throwing the error as Exception<E> if there is none. */
+    T &value() & {
+        check();
+        return std::get<0>(data_);
+    }
+
+    const T &value() const & {
+        check();
+        return std::get<0>(data_);
+    }
+
+    T &&value() && {
+        check();
+        return std::get<0>(std::move(data_));
+    }
+
+    T &operator*() & {
+        return value();
+    }
+
+    const T &operator*() const & {
+        return value();
+    }
+
+    T &&operator*() && {
+        return std::move(*this).value();
+    }
+
+    T *operator->() {
+        return &value();
+    }
+
+    const T *operator->() const {
+        return &value();
+    }
+
+    E &error() & {
+        return std::get<1>(data_);
+    }
+
+    const E &error() const & {
+        return std::get<1>(data_);
+    }
+
+    E &&error() && {
+        return std::get<1>(std::move(data_));
+    }
+
+private:
+    void check() const {
+        if (!has_value()) throw Exception<E>(std::get<1>(data_));
+    }
+
+    std::variant<T, E> data_;
+};
+
+/** The outcome of a call without a value, or the error it declares with `throws`. */
+template <class E>
+class [[nodiscard]] Expected<void, E> {
+public:
+    Expected() = default;
+    Expected(Unexpected<E> error) : error_(std::move(error.error)) {}
+
+    bool has_value() const noexcept {
+        return !error_.has_value();
+    }
+
+    explicit operator bool() const noexcept {
+        return has_value();
+    }
+
+    /** Throws the error as Exception<E> if there is one. */
+    void value() const {
+        if (error_) throw Exception<E>(*error_);
+    }
+
+    E &error() & {
+        return *error_;
+    }
+
+    const E &error() const & {
+        return *error_;
+    }
+
+    E &&error() && {
+        return *std::move(error_);
+    }
+
+private:
+    std::optional<E> error_;
+};
+
+/** A value on the heap with the copies and comparisons of the value, for recursive types. */
+template <class T>
+class Box {
+public:
+    Box(T value) : value_(std::make_unique<T>(std::move(value))) {}
+    Box(const Box &other) : value_(std::make_unique<T>(*other)) {}
+    Box(Box &&other) noexcept = default;
+
+    Box &operator=(const Box &other) {
+        if (this != &other) value_ = std::make_unique<T>(*other);
+        return *this;
+    }
+
+    Box &operator=(Box &&other) noexcept = default;
+
+    T &operator*() {
+        return *value_;
+    }
+
+    const T &operator*() const {
+        return *value_;
+    }
+
+    T *operator->() {
+        return value_.get();
+    }
+
+    const T *operator->() const {
+        return value_.get();
+    }
+
+    friend bool operator==(const Box &a, const Box &b) {
+        return *a == *b;
+    }
+
+    friend bool operator!=(const Box &a, const Box &b) {
+        return !(a == b);
+    }
+
+private:
+    std::unique_ptr<T> value_;
+};
+
+/** Writes values in the wire format. */
+class Writer {
+public:
+    void u8(uint8_t value) {
+        bytes_.push_back(value);
+    }
+
+    template <class T>
+    void number(T value) {
+        using U = std::make_unsigned_t<T>;
+        U bits = static_cast<U>(value);
+        for (std::size_t i = 0; i < sizeof(T); i++) bytes_.push_back(static_cast<uint8_t>(bits >> (8 * i)));
+    }
+
+    void f32(float value) {
+        uint32_t bits;
+        std::memcpy(&bits, &value, sizeof bits);
+        number(bits);
+    }
+
+    void f64(double value) {
+        uint64_t bits;
+        std::memcpy(&bits, &value, sizeof bits);
+        number(bits);
+    }
+
+    void len(std::size_t value) {
+        if (value > std::numeric_limits<uint32_t>::max()) throw WireError("too many elements");
+        number(static_cast<uint32_t>(value));
+    }
+
+    void raw(const uint8_t *data, std::size_t len) {
+        bytes_.insert(bytes_.end(), data, data + len);
+    }
+
+    const std::vector<uint8_t> &bytes() const & {
+        return bytes_;
+    }
+
+    std::vector<uint8_t> bytes() && {
+        return std::move(bytes_);
+    }
+
+private:
+    std::vector<uint8_t> bytes_;
+};
+
+/** Reads values in the wire format, throwing WireError past the end. */
+class Reader {
+public:
+    Reader(const uint8_t *data, std::size_t len) : data_(data), len_(len) {}
+
+    uint8_t u8() {
+        return *take(1);
+    }
+
+    template <class T>
+    T number() {
+        using U = std::make_unsigned_t<T>;
+        const uint8_t *data = take(sizeof(T));
+        U bits = 0;
+        for (std::size_t i = 0; i < sizeof(T); i++) bits |= static_cast<U>(static_cast<U>(data[i]) << (8 * i));
+        return static_cast<T>(bits);
+    }
+
+    float f32() {
+        uint32_t bits = number<uint32_t>();
+        float value;
+        std::memcpy(&value, &bits, sizeof value);
+        return value;
+    }
+
+    double f64() {
+        uint64_t bits = number<uint64_t>();
+        double value;
+        std::memcpy(&value, &bits, sizeof value);
+        return value;
+    }
+
+    std::size_t len() {
+        return number<uint32_t>();
+    }
+
+    const uint8_t *take(std::size_t len) {
+        if (len > len_ - pos_) throw WireError("unexpected end of input");
+        const uint8_t *data = data_ + pos_;
+        pos_ += len;
+        return data;
+    }
+
+    /** Throws WireError if bytes are left. */
+    void finish() const {
+        if (pos_ != len_) throw WireError("trailing bytes");
+    }
+
+private:
+    const uint8_t *data_;
+    std::size_t len_;
+    std::size_t pos_ = 0;
+};
+
+/** Writes and reads values of T, the bindings specialize it for the types they declare. */
+template <class T, class = void>
+struct Wire;
+
+template <class T>
+struct Wire<T, std::enable_if_t<std::is_integral_v<T>>> {
+    static void write(Writer &writer, T value) {
+        if constexpr (std::is_same_v<T, bool>) writer.u8(value ? 1 : 0);
+        else writer.number(value);
+    }
+
+    static T read(Reader &reader) {
+        if constexpr (std::is_same_v<T, bool>) {
+            uint8_t value = reader.u8();
+            if (value > 1) throw WireError("invalid bool");
+            return value == 1;
+        } else {
+            return reader.number<T>();
+        }
+    }
+};
+
+template <>
+struct Wire<float> {
+    static void write(Writer &writer, float value) {
+        writer.f32(value);
+    }
+
+    static float read(Reader &reader) {
+        return reader.f32();
+    }
+};
+
+template <>
+struct Wire<double> {
+    static void write(Writer &writer, double value) {
+        writer.f64(value);
+    }
+
+    static double read(Reader &reader) {
+        return reader.f64();
+    }
+};
+
+template <>
+struct Wire<std::string> {
+    static void write(Writer &writer, const std::string &value) {
+        writer.len(value.size());
+        writer.raw(reinterpret_cast<const uint8_t *>(value.data()), value.size());
+    }
+
+    static std::string read(Reader &reader) {
+        std::size_t len = reader.len();
+        return std::string(reinterpret_cast<const char *>(reader.take(len)), len);
+    }
+};
+
+/* Bytes and List<u8> share their encoding. */
+template <>
+struct Wire<std::vector<uint8_t>> {
+    static void write(Writer &writer, const std::vector<uint8_t> &value) {
+        writer.len(value.size());
+        writer.raw(value.data(), value.size());
+    }
+
+    static std::vector<uint8_t> read(Reader &reader) {
+        std::size_t len = reader.len();
+        const uint8_t *data = reader.take(len);
+        return std::vector<uint8_t>(data, data + len);
+    }
+};
+
+template <class T>
+struct Wire<std::optional<T>> {
+    static void write(Writer &writer, const std::optional<T> &value) {
+        writer.u8(value ? 1 : 0);
+        if (value) Wire<T>::write(writer, *value);
+    }
+
+    static std::optional<T> read(Reader &reader) {
+        if (!Wire<bool>::read(reader)) return std::nullopt;
+        return Wire<T>::read(reader);
+    }
+};
+
+template <class T>
+struct Wire<Box<T>> {
+    static void write(Writer &writer, const Box<T> &value) {
+        Wire<T>::write(writer, *value);
+    }
+
+    static Box<T> read(Reader &reader) {
+        return Box<T>(Wire<T>::read(reader));
+    }
+};
+
+template <class T>
+struct Wire<std::vector<T>> {
+    static void write(Writer &writer, const std::vector<T> &value) {
+        writer.len(value.size());
+        for (const auto &element : value) Wire<T>::write(writer, element);
+    }
+
+    static std::vector<T> read(Reader &reader) {
+        std::size_t len = reader.len();
+        std::vector<T> value;
+        value.reserve(len);
+        for (std::size_t i = 0; i < len; i++) value.push_back(Wire<T>::read(reader));
+        return value;
+    }
+};
+
+template <class T>
+struct Wire<std::unordered_set<T>> {
+    static void write(Writer &writer, const std::unordered_set<T> &value) {
+        writer.len(value.size());
+        for (const auto &element : value) Wire<T>::write(writer, element);
+    }
+
+    static std::unordered_set<T> read(Reader &reader) {
+        std::size_t len = reader.len();
+        std::unordered_set<T> value;
+        for (std::size_t i = 0; i < len; i++) value.insert(Wire<T>::read(reader));
+        return value;
+    }
+};
+
+template <class K, class V>
+struct Wire<std::unordered_map<K, V>> {
+    static void write(Writer &writer, const std::unordered_map<K, V> &value) {
+        writer.len(value.size());
+        for (const auto &entry : value) {
+            Wire<K>::write(writer, entry.first);
+            Wire<V>::write(writer, entry.second);
+        }
+    }
+
+    static std::unordered_map<K, V> read(Reader &reader) {
+        std::size_t len = reader.len();
+        std::unordered_map<K, V> value;
+        for (std::size_t i = 0; i < len; i++) {
+            K key = Wire<K>::read(reader);
+            value.emplace(std::move(key), Wire<V>::read(reader));
+        }
+        return value;
+    }
+};
+
+template <class T, std::size_t N>
+struct Wire<std::array<T, N>> {
+    static void write(Writer &writer, const std::array<T, N> &value) {
+        for (const auto &element : value) Wire<T>::write(writer, element);
+    }
+
+    static std::array<T, N> read(Reader &reader) {
+        return read(reader, std::make_index_sequence<N>());
+    }
+
+private:
+    template <std::size_t... I>
+    static std::array<T, N> read(Reader &reader, std::index_sequence<I...>) {
+        /* Braced initializers are evaluated in order. */
+        return {{(static_cast<void>(I), Wire<T>::read(reader))...}};
+    }
+};
+
+template <class... T>
+struct Wire<std::tuple<T...>> {
+    static void write(Writer &writer, const std::tuple<T...> &value) {
+        std::apply([&](const auto &...element) { (Wire<T>::write(writer, element), ...); }, value);
+    }
+
+    static std::tuple<T...> read(Reader &reader) {
+        return std::tuple<T...>{Wire<T>::read(reader)...};
+    }
+};
+
+template <class T>
+std::vector<uint8_t> encode(const T &value) {
+    Writer writer;
+    Wire<T>::write(writer, value);
+    return std::move(writer).bytes();
+}
+
+inline SlimeSlice slice(const std::vector<uint8_t> &bytes) {
+    return SlimeSlice{bytes.data(), bytes.size()};
+}
+
+/** A buffer of Rust, freed on destruction. */
+class Buffer {
+public:
+    explicit Buffer(SlimeBuffer buffer) noexcept : buffer_(buffer) {}
+    Buffer(const Buffer &) = delete;
+    Buffer &operator=(const Buffer &) = delete;
+
+    ~Buffer() {
+        slime_buffer_free(buffer_);
+    }
+
+    Reader reader() const {
+        return Reader(buffer_.data, static_cast<std::size_t>(buffer_.len));
+    }
+
+    std::string string() const {
+        return std::string(reinterpret_cast<const char *>(buffer_.data), static_cast<std::size_t>(buffer_.len));
+    }
+
+private:
+    SlimeBuffer buffer_;
+};
+
+/** Reads a T from a buffer of Rust and frees it. */
+template <class T>
+T decode(SlimeBuffer buffer) {
+    Buffer owned(buffer);
+    Reader reader = owned.reader();
+    T value = Wire<T>::read(reader);
+    reader.finish();
+    return value;
+}
+
+/** The error a call declares if it failed with it, any other outcome is left to value(). */
+template <class E, class R>
+std::optional<E> thrown(R &result) {
+    SlimeBuffer &error = result.data.error;
+    if (result.code != SLIME_CALL_ERROR || error.len == 0 || error.data[0] != 0) return std::nullopt;
+    Buffer owned(error);
+    Reader reader = owned.reader();
+    reader.u8();
+    E value = Wire<E>::read(reader);
+    reader.finish();
+    return value;
+}
+
+/** The value of a call, throwing what it failed with. */
+template <class R>
+auto value(R &result) -> decltype(result.data.value) {
+    switch (result.code) {
+        case SLIME_CALL_SUCCESS:
+            return result.data.value;
+        case SLIME_CALL_ERROR: {
+            Buffer owned(result.data.error);
+            Reader reader = owned.reader();
+            if (reader.u8() != 1) throw Error("undeclared error");
+            std::string message = Wire<std::string>::read(reader);
+            throw Error(message);
+        }
+        case SLIME_CALL_PANIC:
+            throw Panic(Buffer(result.data.panic).string());
+        case SLIME_CALL_CANCELLED:
+            throw Cancelled();
+        default:
+            throw WireError("invalid call status");
+    }
+}
+
+/** Frees what a call failed with, for destructors which cannot throw. */
+template <class R>
+void discard(R &result) noexcept {
+    if (result.code == SLIME_CALL_ERROR || result.code == SLIME_CALL_PANIC) slime_buffer_free(result.data.error);
+}
+
+/**
+ * Keeps the answer of a host method alive until Rust read it, as the handles of objects in it
+ * are borrowed. Rust reads it before the thread answers again, which releases it.
+ */
+inline std::shared_ptr<void> &kept() {
+    thread_local std::shared_ptr<void> value;
+    return value;
+}
+
+template <class T>
+void keep(T value) {
+    kept() = std::make_shared<T>(std::move(value));
+}
+
+/** Answers a call of Rust with its outcome, in a buffer Rust frees. */
+inline SlimeBuffer answer(const std::vector<uint8_t> &bytes) noexcept {
+    SlimeBuffer buffer = slime_buffer_alloc(bytes.size());
+    if (!bytes.empty()) std::memcpy(buffer.data, bytes.data(), bytes.size());
+    return buffer;
+}
+
+/** Writes the exception of a host method if it is Exception<E>. */
+template <class E>
+bool catch_thrown(std::exception_ptr error, Writer &writer) {
+    try {
+        std::rethrow_exception(error);
+    } catch (const Exception<E> &exception) {
+        Wire<E>::write(writer, exception.error());
+        return true;
+    } catch (...) {
+        return false;
+    }
+}
+
+inline bool catch_none(std::exception_ptr, Writer &) {
+    return false;
+}
+
+/**
+ * Runs a host method on its arguments. `call` reads them and writes the value, `thrown` writes
+ * the error the method declares, any other exception panics in Rust.
+ */
+template <class Call, class Thrown>
+SlimeBuffer host(SlimeSlice args, Call call, Thrown thrown) noexcept {
+    try {
+        try {
+            Reader reader(args.data, static_cast<std::size_t>(args.len));
+            Writer writer;
+            writer.u8(SLIME_CALL_SUCCESS);
+            call(reader, writer);
+            reader.finish();
+            return answer(writer.bytes());
+        } catch (...) {
+            std::exception_ptr error = std::current_exception();
+            Writer writer;
+            writer.u8(SLIME_CALL_ERROR);
+            writer.u8(0);
+            if (thrown(error, writer)) return answer(writer.bytes());
+            std::string message = "unknown exception";
+            try {
+                std::rethrow_exception(error);
+            } catch (const std::exception &exception) {
+                message = exception.what();
+            } catch (...) {
+            }
+            Writer panic;
+            panic.u8(SLIME_CALL_PANIC);
+            Wire<std::string>::write(panic, message);
+            return answer(panic.bytes());
+        }
+    } catch (...) {
+        /* Out of memory while answering, Rust panics on the empty outcome. */
+        return answer({});
+    }
+}
+
+/** A future or stream of Rust, cancelled and freed on destruction unless finished. */
+class Task {
+public:
+    using Poll = void (*)(const void *, SlimeContinuation, uint64_t);
+    using Release = void (*)(const void *);
+
+    Task(const void *handle, Poll poll, Release cancel, Release free) noexcept
+        : handle_(handle), poll_(poll), cancel_(cancel), free_(free) {}
+
+    Task(const Task &) = delete;
+    Task &operator=(const Task &) = delete;
+
+    ~Task() {
+        if (handle_ == nullptr) return;
+        cancel_(handle_);
+        free_(handle_);
+    }
+
+    const void *handle() const noexcept {
+        return handle_;
+    }
+
+    /** Polls until the handle is ready, blocking the thread. */
+    void wait() {
+        for (;;) {
+            Waiter waiter;
+            poll_(handle_, &Waiter::resume, reinterpret_cast<uint64_t>(&waiter));
+            if (waiter.wait() == SLIME_FUTURE_READY) return;
+        }
+    }
+
+    /** Frees the handle once its output is taken. */
+    void finish() noexcept {
+        free_(handle_);
+        handle_ = nullptr;
+    }
+
+private:
+    /** Rust continues a poll here, possibly from another thread. */
+    struct Waiter {
+        std::mutex mutex;
+        std::condition_variable ready;
+        std::optional<int8_t> status;
+
+        static void resume(uint64_t data, int8_t status) {
+            Waiter *waiter = reinterpret_cast<Waiter *>(data);
+            std::lock_guard<std::mutex> lock(waiter->mutex);
+            waiter->status = status;
+            waiter->ready.notify_one();
+        }
+
+        int8_t wait() {
+            std::unique_lock<std::mutex> lock(mutex);
+            ready.wait(lock, [this] { return status.has_value(); });
+            return *status;
+        }
+    };
+
+    const void *handle_;
+    Poll poll_;
+    Release cancel_;
+    Release free_;
+};
+
+/**
+ * The future of an async function: Rust runs it on the thread calling get() or wait().
+ * Destroying it unfinished cancels the call.
+ */
+template <class F>
+auto await(std::shared_ptr<Task> task, F complete) {
+    return std::async(std::launch::deferred, [task = std::move(task), complete = std::move(complete)] {
+        task->wait();
+        return complete(*task);
+    });
+}
+
+/**
+ * The items of a stream, from Rust or from the host. next() blocks until the next item is
+ * ready and is empty after the last one. Destroying it unfinished cancels a Rust stream.
+ */
+template <class T>
+class Stream {
+public:
+    explicit Stream(std::function<std::optional<T>()> next) : next_(std::move(next)) {}
+
+    /** The items of a host method answering with a stream. */
+    explicit Stream(std::vector<T> items) {
+        auto state = std::make_shared<std::pair<std::vector<T>, std::size_t>>(std::move(items), 0);
+        next_ = [state]() -> std::optional<T> {
+            if (state->second == state->first.size()) return std::nullopt;
+            return std::move(state->first[state->second++]);
+        };
+    }
+
+    std::optional<T> next() {
+        if (!next_) return std::nullopt;
+        std::optional<T> item = next_();
+        if (!item) next_ = nullptr;
+        return item;
+    }
+
+    /** The remaining items. */
+    std::vector<T> collect() {
+        std::vector<T> items;
+        while (std::optional<T> item = next()) items.push_back(std::move(*item));
+        return items;
+    }
+
+private:
+    std::function<std::optional<T>()> next_;
+};
+
+/** Adopts a handle of Rust, for the constructors of classes wrapping one. */
+struct Handle {
+    uint64_t value;
+};
+
+} // namespace slime
+
+#endif /* SLIME_FFI_RUNTIME_HPP */
@@ -0,0 +1,2489 @@
+//! Java 8 bindings over the JNI functions of [`crate::abi`], for a library built with its `jvm`
+//! feature and the Rust side generated with the same [`JvmConfig`].
+//!
+//! Every IDL module and `mod` becomes a package below the one of the config, with a file per
+//! declaration and a final class named after the module holding its consts and functions.
+//! `SlimeNative.java` next to them declares the `native` methods. Models become immutable POJOs
+//! with a builder, unit enums Java enums, payload enums an abstract class with a final class per
+//! variant, and classes `AutoCloseable` wrappers of their handle which a [`Cleaner`] frees once
+//! they are unreachable. IDL errors become checked or unchecked exceptions, as configured.
+//! Interfaces become Java interfaces, with an adapter handing implementations of the app to
+//! Rust. Async functions return `CompletableFuture`s and streams are blocking `Iterator`s. The
+//! support code the runtime calls into is generated to `com.slime.ffi`.
+//!
+//! Java has no unsigned integers, they are the signed type of the same size and bits. Optional
+//! values are `null` for none, so `Optional<Optional<T>>` cannot tell the levels apart. Type
+//! mappings of the Java config win over those of the JVM one, their `{}` is the wire value, a
+//! mapped newtype is not wrapped in its class.
+//!
+//! Generic classes and functions are not exported and left out, generic methods of interfaces
+//! cannot be called through Rust. Java has no tuples, every tuple type gets a holder class in
+//! the package of the config named after the types it holds, `Tuple2I32String` for
+//! `(i32, String)`.
+//!
+//! [`Cleaner`]: https://docs.oracle.com/javase/9/docs/api/java/lang/ref/Cleaner.html
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use slime_ffi_ir::ast::{
+    BuiltinType, ClassDecl, ClassMember, ConstDecl, ConstLit, Decl, DeclKind, DeclRef, EnumDecl,
+    EnumVariantFields, Expr, FieldDecl, FnDecl, Function, Ident, InterfaceDecl, KeywordType, Lit,
+    ModelDecl, NumLit, Pat, QualifiedName, RefType, Type, TypeKind, TypeParam, TypeWithArgs,
+};
+use slime_ffi_ir::config::{JavaConfig, JavaExceptionStyle, JvmConfig, TypeMapping};
+use slime_ffi_ir::resolved::ResolvedIr;
+use slime_ffi_ir::Lang;
+
+use crate::abi::{
+    check_host_method, constructor_function, constructors, interface_members,
+    own_interface_members, Abi, AbiType, Export, Member, Target, JNI_CLASS,
+};
+use crate::lang::SourceFile;
+use crate::names::{camel_case, pascal_case, shouty_snake_case};
+use crate::writer::CodeWriter;
+use crate::{Codegen, CodegenError};
+
+const INDENT: &str = "    ";
+
+/// The indentation of continued lines.
+const CONTINUE: &str = "        ";
+
+/// Keywords and literals of Java, which get an underscore as names.
+const KEYWORDS: &[&str] = &[
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "true",
+    "try",
+    "void",
+    "volatile",
+    "while",
+];
+
+/// Methods of `Object` and the wrappers, which IDL methods of the same name would hide.
+const METHODS: &[&str] = &[
+    "clone",
+    "equals",
+    "finalize",
+    "getClass",
+    "handle",
+    "hashCode",
+    "notify",
+    "notifyAll",
+    "toString",
+    "wait",
+];
+
+/// Names the generated bodies use, parameters and fields of the same name get an underscore.
+const LOCALS: &[&str] = &[
+    "reader", "writer", "handle", "error", "other", "that", "raw", "variant", "stream",
+];
+
+/// The package of the support code, fixed by the runtime.
+const SUPPORT_PACKAGE: &str = "com.slime.ffi";
+
+const SUPPORT: &[(&str, &str)] = &[
+    ("Callback.java", include_str!("java/Callback.java")),
+    ("Cleaner.java", include_str!("java/Cleaner.java")),
+    ("Futures.java", include_str!("java/Futures.java")),
+    ("Handle.java", include_str!("java/Handle.java")),
+    ("Outcome.java", include_str!("java/Outcome.java")),
+    (
+        "SlimeException.java",
+        include_str!("java/SlimeException.java"),
+    ),
+    ("SlimePanic.java", include_str!("java/SlimePanic.java")),
+    ("SlimeStream.java", include_str!("java/SlimeStream.java")),
+    (
+        "WireException.java",
+        include_str!("java/WireException.java"),
+    ),
+    ("WireReader.java", include_str!("java/WireReader.java")),
+    ("WireWriter.java", include_str!("java/WireWriter.java")),
+];
+
+const IMPORTS: &str = "\
+import com.slime.ffi.*;
+import java.util.Arrays;
+import java.util.Iterator;
+import java.util.List;
+import java.util.Map;
+import java.util.Objects;
+import java.util.Set;
+import java.util.concurrent.CompletableFuture;
+import java.util.concurrent.atomic.AtomicLong;";
+
+/// `int` as `int_`.
+fn ident(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("{}_", name),
+        false => name.to_string(),
+    }
+}
+
+/// A method name, clear of [`METHODS`].
+fn method(name: &str) -> String {
+    let name = camel_case(name);
+    match METHODS.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => ident(&name),
+    }
+}
+
+/// A parameter or field name, clear of [`LOCALS`].
+fn local(name: &str) -> String {
+    let name = camel_case(name);
+    match LOCALS.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => ident(&name),
+    }
+}
+
+/// `getName` for the field `name`.
+fn getter(field: &str) -> String {
+    format!("get{}", pascal_case(field.trim_end_matches('_')))
+}
+
+/// The `WireWriter` and `WireReader` methods of a keyword type.
+fn wire_fn(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 | KeywordType::Uint8 => "i8",
+        KeywordType::Int16 | KeywordType::Uint16 => "i16",
+        KeywordType::Int32 | KeywordType::Uint32 => "i32",
+        KeywordType::Int64 | KeywordType::Uint64 => "i64",
+        KeywordType::Float32 => "f32",
+        KeywordType::Float64 => "f64",
+        KeywordType::Bool => "bool",
+        KeywordType::String => "string",
+        KeywordType::Bytes => "bytes",
+    }
+}
+
+/// The Java type of a keyword type, also the one it is passed to JNI as.
+fn keyword_ty(keyword: KeywordType, boxed: bool) -> &'static str {
+    match (keyword, boxed) {
+        (KeywordType::Int8 | KeywordType::Uint8, false) => "byte",
+        (KeywordType::Int8 | KeywordType::Uint8, true) => "Byte",
+        (KeywordType::Int16 | KeywordType::Uint16, false) => "short",
+        (KeywordType::Int16 | KeywordType::Uint16, true) => "Short",
+        (KeywordType::Int32 | KeywordType::Uint32, false) => "int",
+        (KeywordType::Int32 | KeywordType::Uint32, true) => "Integer",
+        (KeywordType::Int64 | KeywordType::Uint64, false) => "long",
+        (KeywordType::Int64 | KeywordType::Uint64, true) => "Long",
+        (KeywordType::Float32, false) => "float",
+        (KeywordType::Float32, true) => "Float",
+        (KeywordType::Float64, false) => "double",
+        (KeywordType::Float64, true) => "Double",
+        (KeywordType::Bool, false) => "boolean",
+        (KeywordType::Bool, true) => "Boolean",
+        (KeywordType::String, _) => "String",
+        (KeywordType::Bytes, _) => "byte[]",
+    }
+}
+
+fn float(value: f64, ty: &str, suffix: &str) -> String {
+    match value {
+        value if value.is_nan() => format!("{}.NaN", ty),
+        value if value == f64::INFINITY => format!("{}.POSITIVE_INFINITY", ty),
+        value if value == f64::NEG_INFINITY => format!("{}.NEGATIVE_INFINITY", ty),
+        value => format!("{:?}{}", value, suffix),
+    }
+}
+
+fn int(value: i32) -> String {
+    match value {
+        i32::MIN => "Integer.MIN_VALUE".to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn long(value: i64) -> String {
+    match value {
+        i64::MIN => "Long.MIN_VALUE".to_string(),
+        value => format!("{}L", value),
+    }
+}
+
+/// A number literal, unsigned ones as the signed value of the same bits.
+fn num(value: &NumLit) -> String {
+    match value {
+        NumLit::I8(value) => format!("(byte) {}", value),
+        NumLit::I16(value) => format!("(short) {}", value),
+        NumLit::I32(value) => int(*value),
+        NumLit::I64(value) => long(*value),
+        NumLit::U8(value) => format!("(byte) {}", *value as i8),
+        NumLit::U16(value) => format!("(short) {}", *value as i16),
+        NumLit::U32(value) => int(*value as i32),
+        NumLit::U64(value) => long(*value as i64),
+        NumLit::F32(value) => float(*value as f64, "Float", "f"),
+        NumLit::F64(value) => float(*value, "Double", ""),
+    }
+}
+
+/// A discriminant as the `long` of its bits, to compare a signed read against.
+fn discriminant(value: &NumLit) -> String {
+    let value = match value {
+        NumLit::I8(value) => *value as i64,
+        NumLit::I16(value) => *value as i64,
+        NumLit::I32(value) => *value as i64,
+        NumLit::I64(value) => *value,
+        NumLit::U8(value) => *value as i8 as i64,
+        NumLit::U16(value) => *value as i16 as i64,
+        NumLit::U32(value) => *value as i32 as i64,
+        NumLit::U64(value) => *value as i64,
+        NumLit::F32(_) | NumLit::F64(_) => 0,
+    };
+    long(value)
+}
+
+/// A string literal. Control characters are octal escapes, Java reads `\u` escapes before it
+/// sees the string.
+fn string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\{:03o}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `head(` with an argument per continued line, then `)`.
+fn call_lines(head: &str, args: Vec<String>) -> Vec<String> {
+    if args.is_empty() {
+        return vec![format!("{}()", head)];
+    }
+    let mut lines = vec![format!("{}(", head)];
+    let last = args.len().saturating_sub(1);
+    for (index, arg) in args.into_iter().enumerate() {
+        let end = if index == last { ")" } else { "," };
+        lines.push(format!("{}{}{}", CONTINUE, arg, end));
+    }
+    lines
+}
+
+pub struct JavaCodegen<'ir> {
+    pub ir: &'ir ResolvedIr,
+    /// The package of the bindings, the one the Rust side was generated with.
+    pub jvm: &'ir JvmConfig,
+    pub config: &'ir JavaConfig,
+}
+
+impl<'ir> JavaCodegen<'ir> {
+    pub fn new(ir: &'ir ResolvedIr, jvm: &'ir JvmConfig, config: &'ir JavaConfig) -> Self {
+        JavaCodegen { ir, jvm, config }
+    }
+
+    /// The package of the IDL module or `mod` at `path`.
+    fn package(&self, path: &[Ident]) -> String {
+        let mut segments = vec![self.jvm.package_name.clone()];
+        segments.extend(path.iter().map(|segment| ident(segment.as_str())));
+        segments.join(".")
+    }
+
+    fn dir(package: &str) -> PathBuf {
+        package.split('.').collect()
+    }
+
+    /// The mapping of the Java config for `decl`, or else the one of the JVM config.
+    fn type_mapping(&self, decl: &DeclRef) -> Option<&'ir TypeMapping> {
+        self.config
+            .type_mappings
+            .iter()
+            .chain(&self.jvm.type_mappings)
+            .find(|mapping| mapping.matches(decl))
+    }
+
+    fn unit(
+        &self,
+        abi: &Abi,
+        tuples: &Tuples,
+        path: &[Ident],
+        decls: &[&Decl],
+        files: &mut Vec<SourceFile>,
+    ) -> Result<(), CodegenError> {
+        for decl in decls {
+            if let Decl::Mod(decl) = decl {
+                let mut path = path.to_vec();
+                path.push(decl.name.clone());
+                let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                self.unit(abi, tuples, &path, &decls, files)?;
+            }
+        }
+        let generator = Generator {
+            abi,
+            codegen: self,
+            package: self.package(path),
+            path: path.to_vec(),
+            tuples,
+        };
+        for (name, source) in generator.unit(decls)? {
+            files.push(SourceFile {
+                path: Self::dir(&generator.package).join(format!("{}.java", name)),
+                source,
+            });
+        }
+        Ok(())
+    }
+
+    /// `SlimeNative` with a `native` method per export.
+    fn native(&self, abi: &Abi, exports: &[Export]) -> String {
+        let mut out = CodeWriter::new(INDENT);
+        out.line("// Generated by slime-ffi, do not edit.");
+        out.blank();
+        out.line(format!("package {};", self.jvm.package_name));
+        out.blank();
+        out.line(
+            "/** The JNI functions of the library, load it with {@code System.loadLibrary} first. */",
+        );
+        out.open(format!("public final class {} {{", JNI_CLASS));
+        out.line(format!("private {}() {{}}", JNI_CLASS));
+        let native = |out: &mut CodeWriter, ret: &str, name: &str, params: &[String]| {
+            out.blank();
+            out.line(format!(
+                "public static native {} {}({});",
+                ret,
+                name,
+                params.join(", ")
+            ));
+        };
+        native(&mut out, "long", "retain", &["Object obj".to_string()]);
+        let handle = ["long handle".to_string()];
+        let poll = ["long handle".to_string(), "long data".to_string()];
+        for export in exports {
+            let name = export.jni_method();
+            if let Target::Register(_) = export.target {
+                native(&mut out, "boolean", name, &["Class<?> adapter".to_string()]);
+                continue;
+            }
+            let mut params = vec![];
+            if export.receiver().is_some() {
+                params.push("long handle".to_string());
+            }
+            for param in &export.function.params {
+                let ty = match abi.jni_type(&param.ty) {
+                    AbiType::Scalar(keyword) | AbiType::Enum(keyword) => keyword_ty(keyword, false),
+                    AbiType::Handle => "long",
+                    AbiType::Struct(_) | AbiType::Stream | AbiType::Buffer => "byte[]",
+                };
+                params.push(format!("{} {}", ty, local(param.name.as_str())));
+            }
+            native(&mut out, "byte[]", name, &params);
+            let mut extras = vec![];
+            if export.function.is_async {
+                extras.push(("_", "complete"));
+            }
+            if export.returns_stream() {
+                extras.push(("_stream_", "next"));
+            }
+            for (prefix, next) in extras {
+                let function = |action: &str| format!("{}{}{}", name, prefix, action);
+                native(&mut out, "void", &function("poll"), &poll);
+                native(&mut out, "byte[]", &function(next), &handle);
+                native(&mut out, "void", &function("cancel"), &handle);
+                native(&mut out, "void", &function("free"), &handle);
+            }
+        }
+        out.close("}");
+        out.finish()
+    }
+}
+
+impl Codegen for JavaCodegen<'_> {
+    const LANG: Lang = Lang::Java;
+
+    type Output = Vec<SourceFile>;
+
+    fn generate(&self) -> Result<Self::Output, CodegenError> {
+        let abi = Abi::new(self.ir);
+        let mut files: Vec<SourceFile> = SUPPORT
+            .iter()
+            .map(|(name, source)| SourceFile {
+                path: Self::dir(SUPPORT_PACKAGE).join(name),
+                source: source.to_string(),
+            })
+            .collect();
+        let mut exports = vec![];
+        for module in &self.ir.modules {
+            exports.extend(abi.exports(module)?);
+        }
+        files.push(SourceFile {
+            path: Self::dir(&self.jvm.package_name).join(format!("{}.java", JNI_CLASS)),
+            source: self.native(&abi, &exports),
+        });
+        let tuples = RefCell::new(BTreeMap::new());
+        for module in &self.ir.modules {
+            let decls: Vec<&Decl> = module.decls.iter().collect();
+            self.unit(
+                &abi,
+                &tuples,
+                &[Ident::from(&module.name)],
+                &decls,
+                &mut files,
+            )?;
+        }
+        let generator = Generator {
+            abi: &abi,
+            codegen: self,
+            package: self.jvm.package_name.clone(),
+            path: vec![],
+            tuples: &tuples,
+        };
+        // Holders of tuples may hold tuples themselves, which are added while writing them.
+        let mut done = BTreeSet::new();
+        loop {
+            let next = tuples
+                .borrow()
+                .iter()
+                .find(|(name, _)| !done.contains(*name))
+                .map(|(name, types)| (name.clone(), types.clone()));
+            let Some((name, types)) = next else {
+                break;
+            };
+            files.push(SourceFile {
+                path: Self::dir(&generator.package).join(format!("{}.java", name)),
+                source: generator.file(|out| generator.tuple_class(out, &name, &types))?,
+            });
+            done.insert(name);
+        }
+        Ok(files)
+    }
+}
+
+/// The tuple types in use by the name of their holder class.
+type Tuples = RefCell<BTreeMap<String, Vec<Type>>>;
+
+struct Generator<'a, 'ir> {
+    abi: &'a Abi<'ir>,
+    codegen: &'a JavaCodegen<'ir>,
+    /// The package being generated, to refer to its declarations by their name.
+    package: String,
+    /// The IDL module and `mod`s of the package, none for the package of the config.
+    path: Vec<Ident>,
+    tuples: &'a Tuples,
+}
+
+impl<'a, 'ir> Generator<'a, 'ir> {
+    fn get(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.abi.ir.get(decl)
+    }
+
+    fn decl_ref(&self, kind: DeclKind, name: &Ident) -> DeclRef {
+        DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(self.path.clone()),
+                name: name.clone(),
+            },
+        }
+    }
+
+    /// `decl` with `suffix`, by its name in the package being generated.
+    fn name(&self, decl: &DeclRef, suffix: &str) -> String {
+        let name = ident(&format!("{}{}", decl.name.name.as_str(), suffix));
+        let package = self.codegen.package(decl.name.path.segments());
+        match package == self.package {
+            true => name,
+            false => format!("{}.{}", package, name),
+        }
+    }
+
+    /// The first declaration along the aliases of `ty` with a type mapping.
+    fn mapping<'t>(&self, mut ty: &'t Type) -> Option<(&'t DeclRef, &'ir TypeMapping)>
+    where
+        'ir: 't,
+    {
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            if let Some(mapping) = self.codegen.type_mapping(target) {
+                return Some((target, mapping));
+            }
+            match self.get(target) {
+                Some(Decl::Alias(alias)) if !alias.distinct => ty = &alias.ty,
+                _ => break,
+            }
+        }
+        None
+    }
+
+    /// The wrapped type of a mapped newtype, whose class the host type replaces.
+    fn unwrapped(&self, target: &DeclRef) -> Option<&'ir Type> {
+        match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => Some(&alias.ty),
+            _ => None,
+        }
+    }
+
+    /// The holder class of the tuple of `types`, by its name in the package being generated.
+    fn tuple(&self, types: &[Type]) -> Result<String, CodegenError> {
+        let mut name = format!("Tuple{}", types.len());
+        for ty in types {
+            name.push_str(&self.shape(ty)?);
+        }
+        self.tuples
+            .borrow_mut()
+            .entry(name.clone())
+            .or_insert_with(|| types.to_vec());
+        Ok(match self.package == self.codegen.jvm.package_name {
+            true => name,
+            false => format!("{}.{}", self.codegen.jvm.package_name, name),
+        })
+    }
+
+    /// `ty` spelled out for the name of a tuple holder, `ListString` for `List<String>`.
+    fn shape(&self, ty: &Type) -> Result<String, CodegenError> {
+        let decl = |target: &DeclRef| {
+            let mut name: String = target
+                .name
+                .path
+                .segments()
+                .iter()
+                .map(|segment| pascal_case(segment.as_str()))
+                .collect();
+            name.push_str(&pascal_case(target.name.name.as_str()));
+            name
+        };
+        if let Some((target, _)) = self.mapping(ty) {
+            return Ok(decl(target));
+        }
+        Ok(match &self.abi.alias_target(ty).kind {
+            TypeKind::Keyword(keyword) => pascal_case(keyword.name()),
+            TypeKind::RefType(RefType::Resolved(target)) => decl(target),
+            TypeKind::RefType(_) => return Err(generic()),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => format!("Optional{}", self.shape(ty)?),
+                BuiltinType::List(ty) => format!("List{}", self.shape(ty)?),
+                BuiltinType::Set(ty) => format!("Set{}", self.shape(ty)?),
+                BuiltinType::Map(map) => {
+                    format!("Map{}{}", self.shape(&map.key)?, self.shape(&map.value)?)
+                }
+                BuiltinType::Array(array) => {
+                    format!("Array{}{}", array.len, self.shape(&array.element)?)
+                }
+                BuiltinType::Tuple(types) => {
+                    let name = self.tuple(types)?;
+                    name.rsplit('.').next().unwrap_or(&name).to_string()
+                }
+                BuiltinType::Stream(_) => {
+                    return Err(CodegenError::custom("streams are only returned"))
+                }
+            },
+        })
+    }
+
+    /// The keyword type a value of `ty` is in Java, through aliases but not newtypes.
+    fn keyword(&self, ty: &Type) -> Option<KeywordType> {
+        if self.mapping(ty).is_some() {
+            return None;
+        }
+        match self.abi.alias_target(ty).kind {
+            TypeKind::Keyword(keyword) => Some(keyword),
+            _ => None,
+        }
+    }
+
+    /// Whether `ty` is a Java primitive, which cannot be `null`.
+    fn is_primitive(&self, ty: &Type) -> bool {
+        !matches!(
+            self.keyword(ty),
+            None | Some(KeywordType::String | KeywordType::Bytes)
+        )
+    }
+
+    /// Whether `ty` is a `byte[]`, also an optional one, compared by content.
+    fn is_bytes(&self, ty: &Type) -> bool {
+        if self.mapping(ty).is_some() {
+            return false;
+        }
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::Keyword(keyword) => *keyword == KeywordType::Bytes,
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => self.is_bytes(inner),
+            _ => false,
+        }
+    }
+
+    fn is_optional(&self, ty: &Type) -> bool {
+        self.mapping(ty).is_none()
+            && matches!(
+                self.abi.alias_target(ty).kind,
+                TypeKind::Builtin(BuiltinType::Optional(_))
+            )
+    }
+
+    /// The Java type of `ty`, `boxed` where a primitive is not allowed.
+    fn ty(&self, ty: &Type, boxed: bool) -> Result<String, CodegenError> {
+        if let Some((_, mapping)) = self.mapping(ty) {
+            return Ok(mapping.host_type.clone());
+        }
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => keyword_ty(*keyword, boxed).to_string(),
+            TypeKind::RefType(RefType::TypeParam(name)) => ident(name.as_str()),
+            TypeKind::RefType(RefType::Resolved(target)) => match self.get(target) {
+                Some(Decl::Alias(alias)) if !alias.distinct => self.ty(&alias.ty, boxed)?,
+                _ => self.decl_ty(target),
+            },
+            TypeKind::RefType(_) => {
+                return Err(CodegenError::custom(
+                    "types are resolved by semantic analysis",
+                ))
+            }
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => self.ty(ty, true)?,
+                BuiltinType::List(ty) => format!("List<{}>", self.ty(ty, true)?),
+                BuiltinType::Set(ty) => format!("Set<{}>", self.ty(ty, true)?),
+                BuiltinType::Map(map) => format!(
+                    "Map<{}, {}>",
+                    self.ty(&map.key, true)?,
+                    self.ty(&map.value, true)?
+                ),
+                BuiltinType::Array(array) => format!("List<{}>", self.ty(&array.element, true)?),
+                BuiltinType::Stream(ty) => format!("SlimeStream<{}>", self.ty(ty, true)?),
+                BuiltinType::Tuple(types) => self.tuple(types)?,
+            },
+        })
+    }
+
+    /// A declared type. Generic interfaces are referred to with their own type parameters, the
+    /// only ones in scope where the IDL can name them.
+    fn decl_ty(&self, target: &DeclRef) -> String {
+        let name = self.name(target, "");
+        let params = match self.get(target) {
+            Some(Decl::Class(decl)) => decl.type_params.as_deref(),
+            Some(Decl::Interface(decl)) => decl.type_params.as_deref(),
+            _ => None,
+        };
+        match params {
+            Some(params) if !params.is_empty() => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|param| ident(param.name.as_str()))
+                    .collect();
+                format!("{}<{}>", name, params.join(", "))
+            }
+            _ => name,
+        }
+    }
+
+    /// `Base<Integer>` of `extends` and `implements`.
+    fn with_args(&self, with: &TypeWithArgs) -> Result<String, CodegenError> {
+        let target = with
+            .target
+            .as_ref()
+            .ok_or_else(|| CodegenError::custom("types are resolved by semantic analysis"))?;
+        let name = self.name(target, "");
+        Ok(match with.type_args.as_deref() {
+            Some(args) if !args.is_empty() => {
+                let args = args
+                    .iter()
+                    .map(|ty| self.ty(ty, true))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("{}<{}>", name, args.join(", "))
+            }
+            _ => name,
+        })
+    }
+
+    /// `<T extends Listener, U> `.
+    fn type_params(&self, params: &[TypeParam]) -> Result<String, CodegenError> {
+        if params.is_empty() {
+            return Ok(String::new());
+        }
+        let params = params
+            .iter()
+            .map(|param| {
+                let name = ident(param.name.as_str());
+                Ok(match &param.constraint {
+                    Some(ty) => format!("{} extends {}", name, self.ty(ty, true)?),
+                    None => name,
+                })
+            })
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+        Ok(format!("<{}> ", params.join(", ")))
+    }
+
+    /// A default value or const of type `ty`.
+    fn value(&self, ty: &Type, value: &Expr) -> Result<String, CodegenError> {
+        let constant = match value {
+            Expr::Null => return Ok("null".to_string()),
+            Expr::Const(constant) => constant,
+            _ => {
+                return Err(CodegenError::custom(
+                    "values are resolved by semantic analysis",
+                ))
+            }
+        };
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = match self.unwrapped(target) {
+                Some(inner) => self.value(inner, value)?,
+                None => self.decl_value(target, value, constant)?,
+            };
+            return Ok(mapping.lift(&value));
+        }
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => self.value(inner, value),
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                self.decl_value(target, value, constant)
+            }
+            _ => self.literal(constant),
+        }
+    }
+
+    /// A value of a declared type, below its type mapping.
+    fn decl_value(
+        &self,
+        target: &DeclRef,
+        value: &Expr,
+        constant: &ConstLit,
+    ) -> Result<String, CodegenError> {
+        match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => Ok(format!(
+                "new {}({})",
+                self.name(target, ""),
+                self.value(&alias.ty, value)?
+            )),
+            Some(Decl::Alias(alias)) => self.value(&alias.ty, value),
+            _ => self.literal(constant),
+        }
+    }
+
+    fn literal(&self, constant: &ConstLit) -> Result<String, CodegenError> {
+        Ok(match constant {
+            ConstLit::Primitive(Lit::Str(value)) => string(value),
+            ConstLit::Primitive(Lit::Bool(value)) => value.to_string(),
+            ConstLit::Primitive(Lit::Num(value)) => num(value),
+            ConstLit::UnitEnumVariant(variant) => {
+                let (target, _) = self.abi.resolve(&variant.ty).ok_or_else(|| {
+                    CodegenError::custom("values are resolved by semantic analysis")
+                })?;
+                format!(
+                    "{}.{}",
+                    self.name(target, ""),
+                    shouty_snake_case(variant.variant_name.as_str())
+                )
+            }
+        })
+    }
+
+    /// A statement writing `value` of type `ty` to `writer`, without its `;`. Lambdas nested
+    /// `depth` deep number their parameters by it.
+    fn write(&self, ty: &Type, value: &str, depth: usize) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = mapping.lower(value);
+            return match self.unwrapped(target) {
+                Some(inner) => self.write(inner, &value, depth),
+                None => self.write_decl(target, &value, depth),
+            };
+        }
+        let var = |name: &str| format!("{}{}", name, depth);
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => format!("writer.{}({})", wire_fn(*keyword), value),
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                self.write_decl(target, value, depth)?
+            }
+            TypeKind::RefType(_) => return Err(generic()),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(inner) => format!(
+                    "writer.optional({}, {} -> {})",
+                    value,
+                    var("e"),
+                    self.write(inner, &var("e"), depth + 1)?
+                ),
+                BuiltinType::List(inner) | BuiltinType::Set(inner) => format!(
+                    "writer.collection({}, {} -> {})",
+                    value,
+                    var("e"),
+                    self.write(inner, &var("e"), depth + 1)?
+                ),
+                BuiltinType::Array(array) => format!(
+                    "writer.array({}, {}, {} -> {})",
+                    value,
+                    array.len,
+                    var("e"),
+                    self.write(&array.element, &var("e"), depth + 1)?
+                ),
+                BuiltinType::Map(map) => format!(
+                    "writer.map({}, ({}, {}) -> {{ {}; {}; }})",
+                    value,
+                    var("k"),
+                    var("v"),
+                    self.write(&map.key, &var("k"), depth + 1)?,
+                    self.write(&map.value, &var("v"), depth + 1)?
+                ),
+                BuiltinType::Tuple(types) => {
+                    self.tuple(types)?;
+                    format!("{}.write(writer)", value)
+                }
+                BuiltinType::Stream(_) => {
+                    return Err(CodegenError::custom("streams are only returned"))
+                }
+            },
+        })
+    }
+
+    fn write_decl(
+        &self,
+        target: &DeclRef,
+        value: &str,
+        depth: usize,
+    ) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                self.write(&alias.ty, &format!("{}.getValue()", value), depth)?
+            }
+            Some(Decl::Alias(alias)) => self.write(&alias.ty, value, depth)?,
+            Some(Decl::Enum(decl)) if decl.is_primitive() => {
+                format!("writer.{}({}.getValue())", wire_fn(decl.repr_type()), value)
+            }
+            Some(Decl::Model(_) | Decl::Enum(_)) => format!("{}.write(writer)", value),
+            Some(Decl::Class(_)) => format!("writer.i64({}.handle())", value),
+            Some(Decl::Interface(_)) => format!(
+                "writer.i64({}.handle({}))",
+                self.name(target, "Callback"),
+                value
+            ),
+            _ => return Err(unknown(target)),
+        })
+    }
+
+    /// An expression reading a value of type `ty` from `reader`.
+    fn read(&self, ty: &Type) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = match self.unwrapped(target) {
+                Some(inner) => self.read(inner)?,
+                None => self.read_decl(target)?,
+            };
+            return Ok(mapping.lift(&value));
+        }
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => format!("reader.{}()", wire_fn(*keyword)),
+            TypeKind::RefType(RefType::Resolved(target)) => self.read_decl(target)?,
+            TypeKind::RefType(_) => return Err(generic()),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(inner) => {
+                    format!("reader.optional(() -> {})", self.read(inner)?)
+                }
+                BuiltinType::List(inner) => format!("reader.list(() -> {})", self.read(inner)?),
+                BuiltinType::Set(inner) => format!("reader.set(() -> {})", self.read(inner)?),
+                BuiltinType::Array(array) => format!(
+                    "reader.array({}, () -> {})",
+                    array.len,
+                    self.read(&array.element)?
+                ),
+                BuiltinType::Map(map) => format!(
+                    "reader.map(() -> {}, () -> {})",
+                    self.read(&map.key)?,
+                    self.read(&map.value)?
+                ),
+                BuiltinType::Tuple(types) => format!("{}.read(reader)", self.tuple(types)?),
+                BuiltinType::Stream(_) => {
+                    return Err(CodegenError::custom("streams are only returned"))
+                }
+            },
+        })
+    }
+
+    fn read_decl(&self, target: &DeclRef) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                format!("new {}({})", self.name(target, ""), self.read(&alias.ty)?)
+            }
+            Some(Decl::Alias(alias)) => self.read(&alias.ty)?,
+            Some(Decl::Enum(decl)) if decl.is_primitive() => format!(
+                "{}.fromValue(reader.{}())",
+                self.name(target, ""),
+                wire_fn(decl.repr_type())
+            ),
+            Some(Decl::Model(_) | Decl::Enum(_)) => {
+                format!("{}.read(reader)", self.name(target, ""))
+            }
+            Some(Decl::Class(_)) => {
+                format!("new {}(new Handle(reader.i64()))", self.name(target, ""))
+            }
+            Some(Decl::Interface(_)) => format!(
+                "new {}(new Handle(reader.i64()))",
+                self.name(target, "Object")
+            ),
+            _ => return Err(unknown(target)),
+        })
+    }
+
+    /// `reader -> ...` reading the value of `ty`, `null` for none.
+    fn read_lambda(&self, ty: Option<&Type>) -> Result<String, CodegenError> {
+        Ok(match ty {
+            Some(ty) => format!("reader -> {}", self.read(ty)?),
+            None => "reader -> null".to_string(),
+        })
+    }
+
+    /// The value of a scalar, unit enum or object type below its mapping and newtypes: the
+    /// primitive, enum value or handle.
+    fn peel(&self, ty: &Type, value: &str) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = mapping.lower(value);
+            return match self.unwrapped(target) {
+                Some(inner) => self.peel(inner, &value),
+                None => self.peel_decl(target, &value),
+            };
+        }
+        match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => self.peel_decl(target, value),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    fn peel_decl(&self, target: &DeclRef, value: &str) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                self.peel(&alias.ty, &format!("{}.getValue()", value))?
+            }
+            Some(Decl::Alias(alias)) => self.peel(&alias.ty, value)?,
+            Some(Decl::Enum(_)) => format!("{}.getValue()", value),
+            Some(Decl::Class(_)) => format!("{}.handle()", value),
+            Some(Decl::Interface(_)) => {
+                format!("{}.handle({})", self.name(target, "Callback"), value)
+            }
+            _ => value.to_string(),
+        })
+    }
+
+    /// An argument of a JNI function.
+    fn arg(&self, ty: &Type, value: &str) -> Result<String, CodegenError> {
+        Ok(match self.abi.jni_type(ty) {
+            AbiType::Scalar(_) | AbiType::Enum(_) | AbiType::Handle => self.peel(ty, value)?,
+            AbiType::Struct(_) | AbiType::Stream | AbiType::Buffer => {
+                format!("WireWriter.encode(writer -> {})", self.write(ty, value, 0)?)
+            }
+        })
+    }
+
+    /// The exception thrown for the error of `function` and the enum it holds.
+    fn exception<'t>(
+        &self,
+        function: &'t Function,
+    ) -> Result<Option<(String, &'t Type)>, CodegenError> {
+        let Some(error) = function.error_type.as_deref() else {
+            return Ok(None);
+        };
+        match self.abi.resolve(error) {
+            Some((target, Decl::Enum(_))) => Ok(Some((self.name(target, "Exception"), error))),
+            _ => Err(CodegenError::custom("functions throw enums")),
+        }
+    }
+
+    /// `reader -> IoErrorException.of(...)` reading the error of `function`.
+    fn thrown(&self, function: &Function) -> Result<Option<String>, CodegenError> {
+        Ok(match self.exception(function)? {
+            Some((exception, error)) => {
+                Some(format!("reader -> {}.of({})", exception, self.read(error)?))
+            }
+            None => None,
+        })
+    }
+
+    /// `SlimeNative.method(args)`, the receiver `handle()` first.
+    fn native_call(
+        &self,
+        method: &str,
+        function: &FnDecl,
+        receiver: bool,
+    ) -> Result<String, CodegenError> {
+        let mut args = vec![];
+        if receiver {
+            args.push("handle()".to_string());
+        }
+        for param in &function.params {
+            args.push(self.arg(&param.ty, &local(param.name.as_str()))?);
+        }
+        Ok(format!("{}.{}({})", JNI_CLASS, method, args.join(", ")))
+    }
+
+    /// The lines of the expression calling `method` of `SlimeNative` for `function`.
+    fn call(
+        &self,
+        method: &str,
+        function: &FnDecl,
+        receiver: bool,
+    ) -> Result<Vec<String>, CodegenError> {
+        let native = self.native_call(method, function, receiver)?;
+        let thrown = self.thrown(function)?;
+        let reference = |suffix: &str| format!("{}::{}{}", JNI_CLASS, method, suffix);
+        let stream = match function.return_type.as_deref().map(|ty| &ty.kind) {
+            Some(TypeKind::Builtin(BuiltinType::Stream(item))) => Some(item.as_ref()),
+            _ => None,
+        };
+        let value = match stream {
+            Some(_) => "WireReader::i64".to_string(),
+            None => self.read_lambda(function.return_type.as_deref())?,
+        };
+        let mut lines = match function.is_async {
+            true => {
+                let mut args = vec![
+                    native,
+                    reference("_poll"),
+                    reference("_complete"),
+                    reference("_cancel"),
+                    reference("_free"),
+                    value,
+                ];
+                args.extend(thrown);
+                call_lines("Futures.await", args)
+            }
+            false => {
+                let mut args = vec![native, value];
+                args.extend(thrown);
+                vec![format!("Outcome.unwrap({})", args.join(", "))]
+            }
+        };
+        if let Some(item) = stream {
+            let start = match function.is_async {
+                true => "stream".to_string(),
+                false => lines.remove(0),
+            };
+            let stream = call_lines(
+                &format!("new SlimeStream<{}>", self.ty(item, true)?),
+                vec![
+                    start,
+                    reference("_stream_poll"),
+                    reference("_stream_next"),
+                    reference("_stream_cancel"),
+                    reference("_stream_free"),
+                    self.read_lambda(Some(item))?,
+                ],
+            );
+            match function.is_async {
+                true => {
+                    lines.push(format!("{}.thenApply(stream -> {}", CONTINUE, stream[0]));
+                    let last = stream.len() - 1;
+                    for (index, line) in stream[1..].iter().enumerate() {
+                        let end = if index + 1 == last { ")" } else { "" };
+                        lines.push(format!("{}{}{}", CONTINUE, line, end));
+                    }
+                }
+                false => lines = stream,
+            }
+        }
+        Ok(lines)
+    }
+
+    /// The return type of `function`, `boxed` for a type parameter in the interface declaring
+    /// it. Interfaces return streams as `Iterator`s, which apps can implement.
+    fn return_ty(
+        &self,
+        function: &FnDecl,
+        interface: bool,
+        boxed: bool,
+    ) -> Result<String, CodegenError> {
+        let ret = match function.return_type.as_deref() {
+            Some(ty) => match &ty.kind {
+                TypeKind::Builtin(BuiltinType::Stream(item)) if interface => {
+                    Some(format!("Iterator<{}>", self.ty(item, true)?))
+                }
+                _ => Some(self.ty(ty, function.is_async || boxed)?),
+            },
+            None => None,
+        };
+        Ok(match (function.is_async, ret) {
+            (true, Some(ret)) => format!("CompletableFuture<{}>", ret),
+            (true, None) => "CompletableFuture<Void>".to_string(),
+            (false, Some(ret)) => ret,
+            (false, None) => "void".to_string(),
+        })
+    }
+
+    /// `int parse(String text) throws ParseErrorException`, `modifiers` like `public ` in front.
+    /// Async methods throw nothing, their `CompletableFuture` fails with the exception. An
+    /// override of a method `declared` with type parameters of its interface boxes the values of
+    /// those.
+    fn signature(
+        &self,
+        name: &str,
+        function: &FnDecl,
+        modifiers: &str,
+        interface: bool,
+        declared: Option<&FnDecl>,
+    ) -> Result<String, CodegenError> {
+        let is_param = |ty: Option<&Type>| {
+            matches!(
+                ty.map(|ty| &ty.kind),
+                Some(TypeKind::RefType(RefType::TypeParam(_)))
+            )
+        };
+        let params = function
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                let boxed = declared.is_some_and(|declared| {
+                    is_param(declared.params.get(index).map(|param| &param.ty))
+                });
+                Ok(format!(
+                    "{} {}",
+                    self.ty(&param.ty, boxed)?,
+                    local(param.name.as_str())
+                ))
+            })
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+        let boxed = declared.is_some_and(|declared| is_param(declared.return_type.as_deref()));
+        let type_params = self.type_params(function.type_params.as_deref().unwrap_or_default())?;
+        let throws = match self.exception(function)? {
+            Some((exception, _)) if !function.is_async => format!(" throws {}", exception),
+            _ => String::new(),
+        };
+        Ok(format!(
+            "{}{}{} {}({}){}",
+            modifiers,
+            type_params,
+            self.return_ty(function, interface, boxed)?,
+            name,
+            params.join(", "),
+            throws
+        ))
+    }
+
+    fn params(&self, params: &[FieldDecl]) -> Result<Vec<String>, CodegenError> {
+        params
+            .iter()
+            .map(|param| {
+                Ok(format!(
+                    "{} {}",
+                    self.ty(&param.ty, false)?,
+                    local(param.name.as_str())
+                ))
+            })
+            .collect()
+    }
+
+    /// A method with the body `lines`, returning their value unless it returns nothing.
+    fn method_body(
+        &self,
+        out: &mut CodeWriter,
+        signature: &str,
+        function: &FnDecl,
+        mut lines: Vec<String>,
+    ) {
+        out.open(format!("{} {{", signature));
+        if function.return_type.is_some() || function.is_async {
+            lines[0] = format!("return {}", lines[0]);
+        }
+        self.statement(out, lines);
+        out.close("}");
+    }
+
+    /// The lines of a statement, `;` after the last.
+    fn statement(&self, out: &mut CodeWriter, lines: Vec<String>) {
+        let last = lines.len() - 1;
+        for (index, line) in lines.into_iter().enumerate() {
+            match index == last {
+                true => out.line(format!("{};", line)),
+                false => out.line(line),
+            }
+        }
+    }
+
+    /// A file of the package, `body` writing the declaration.
+    fn file(
+        &self,
+        body: impl FnOnce(&mut CodeWriter) -> Result<(), CodegenError>,
+    ) -> Result<String, CodegenError> {
+        let names: Vec<&str> = self.path.iter().map(Ident::as_str).collect();
+        let mut out = CodeWriter::new(INDENT);
+        match names.is_empty() {
+            true => out.line("// Generated by slime-ffi, do not edit."),
+            false => out.line(format!(
+                "// Generated by slime-ffi from the `{}` module, do not edit.",
+                names.join(".")
+            )),
+        }
+        out.blank();
+        out.line(format!("package {};", self.package));
+        out.blank();
+        out.line(format!(
+            "import {}.{};",
+            self.codegen.jvm.package_name, JNI_CLASS
+        ));
+        out.lines(IMPORTS);
+        out.blank();
+        body(&mut out)?;
+        Ok(out.finish())
+    }
+
+    /// The files of the package by class name.
+    fn unit(&self, decls: &[&Decl]) -> Result<Vec<(String, String)>, CodegenError> {
+        let mut files = vec![];
+        let mut functions = vec![];
+        for decl in decls {
+            match decl {
+                Decl::Const(_) => functions.push(*decl),
+                Decl::Method(function) if self.abi.is_exported(function) => functions.push(*decl),
+                Decl::Alias(alias) if alias.distinct => {
+                    let name = ident(alias.name.as_str());
+                    let source = self.file(|out| {
+                        out.open(format!("public final class {} {{", name));
+                        self.value_class(
+                            out,
+                            &name,
+                            &[("value".to_string(), &alias.ty)],
+                            "public",
+                        )?;
+                        out.close("}");
+                        Ok(())
+                    })?;
+                    files.push((name, source));
+                }
+                Decl::Model(model) => {
+                    let name = ident(model.name.as_str());
+                    files.push((name, self.file(|out| self.model(out, model))?));
+                }
+                Decl::Enum(enum_decl) => {
+                    let name = ident(enum_decl.name.as_str());
+                    let source = self.file(|out| match enum_decl.is_primitive() {
+                        true => self.unit_enum(out, enum_decl),
+                        false => self.payload_enum(out, enum_decl),
+                    })?;
+                    files.push((name, source));
+                    let this = self.decl_ref(DeclKind::Enum, &enum_decl.name);
+                    if self.abi.is_thrown(&this.name) {
+                        let name = ident(&format!("{}Exception", enum_decl.name.as_str()));
+                        let source = self.file(|out| self.exception_class(out, enum_decl))?;
+                        files.push((name, source));
+                    }
+                }
+                Decl::Interface(interface) => {
+                    let this = self.decl_ref(DeclKind::Interface, &interface.name);
+                    let name = ident(interface.name.as_str());
+                    files.push((name, self.file(|out| self.interface(out, interface))?));
+                    if interface
+                        .type_params
+                        .as_ref()
+                        .is_some_and(|params| !params.is_empty())
+                    {
+                        continue;
+                    }
+                    let name = ident(&format!("{}Callback", interface.name.as_str()));
+                    let source = self.file(|out| self.adapter(out, interface, &this))?;
+                    files.push((name, source));
+                    let name = ident(&format!("{}Object", interface.name.as_str()));
+                    let source = self.file(|out| self.object(out, interface, &this))?;
+                    files.push((name, source));
+                }
+                Decl::Class(class) if class.type_params.is_none() => {
+                    let this = self.decl_ref(DeclKind::Class, &class.name);
+                    let name = ident(class.name.as_str());
+                    files.push((name, self.file(|out| self.class(out, class, &this))?));
+                }
+                _ => {}
+            }
+        }
+        if !functions.is_empty() {
+            let name = pascal_case(self.path.last().map(Ident::as_str).unwrap_or_default());
+            let name = ident(&name);
+            if files.iter().any(|(file, _)| *file == name) {
+                return Err(CodegenError::custom(format!(
+                    "`{}` declares `{}`, the Java class of its functions",
+                    self.path.last().map(Ident::as_str).unwrap_or_default(),
+                    name
+                )));
+            }
+            let source = self.file(|out| self.functions(out, &name, &functions))?;
+            files.push((name, source));
+        }
+        Ok(files)
+    }
+
+    /// The class of the consts and functions of a module.
+    fn functions(
+        &self,
+        out: &mut CodeWriter,
+        name: &str,
+        decls: &[&Decl],
+    ) -> Result<(), CodegenError> {
+        let names: Vec<&str> = self.path.iter().map(Ident::as_str).collect();
+        out.line(format!(
+            "/** The consts and functions of `{}`. */",
+            names.join(".")
+        ));
+        out.open(format!("public final class {} {{", name));
+        out.line(format!("private {}() {{}}", name));
+        for decl in decls {
+            out.blank();
+            match decl {
+                Decl::Const(decl) => self.constant(out, decl)?,
+                Decl::Method(function) => {
+                    let native = format!("{}_{}", names.join("_"), function.name.as_str());
+                    let signature = self.signature(
+                        &method(function.name.as_str()),
+                        function,
+                        "public static ",
+                        false,
+                        None,
+                    )?;
+                    let lines = self.call(&native, function, false)?;
+                    self.method_body(out, &signature, function, lines);
+                }
+                _ => {}
+            }
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    fn constant(&self, out: &mut CodeWriter, decl: &ConstDecl) -> Result<(), CodegenError> {
+        let value = Expr::Const(ConstLit::Primitive(decl.value.clone()));
+        out.line(format!(
+            "public static final {} {} = {};",
+            self.ty(&decl.ty, false)?,
+            ident(decl.name.as_str()),
+            self.value(&decl.ty, &value)?
+        ));
+        Ok(())
+    }
+
+    /// The fields, constructor, getters, `equals`, `hashCode` and `toString` of an immutable
+    /// class. Fields that are not optional must not be `null`.
+    fn value_class(
+        &self,
+        out: &mut CodeWriter,
+        name: &str,
+        fields: &[(String, &Type)],
+        visibility: &str,
+    ) -> Result<(), CodegenError> {
+        for (field, ty) in fields {
+            out.line(format!("private final {} {};", self.ty(ty, false)?, field));
+        }
+        if !fields.is_empty() {
+            out.blank();
+        }
+        let params = fields
+            .iter()
+            .map(|(field, ty)| Ok(format!("{} {}", self.ty(ty, false)?, field)))
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+        out.open(format!("{} {}({}) {{", visibility, name, params.join(", ")));
+        for (field, ty) in fields {
+            match self.is_primitive(ty) || self.is_optional(ty) {
+                true => out.line(format!("this.{} = {};", field, field)),
+                false => out.line(format!(
+                    "this.{} = Objects.requireNonNull({}, \"{}\");",
+                    field, field, field
+                )),
+            }
+        }
+        out.close("}");
+        for (field, ty) in fields {
+            out.blank();
+            out.open(format!(
+                "public {} {}() {{",
+                self.ty(ty, false)?,
+                getter(field)
+            ));
+            out.line(format!("return {};", field));
+            out.close("}");
+        }
+        out.blank();
+        out.line("@Override");
+        out.open("public boolean equals(Object other) {");
+        out.line("if (this == other) return true;");
+        out.line(format!("if (!(other instanceof {})) return false;", name));
+        if fields.is_empty() {
+            out.line("return true;");
+        } else {
+            out.line(format!("{} that = ({}) other;", name, name));
+            let equals: Vec<String> = fields
+                .iter()
+                .enumerate()
+                .map(|(index, (field, ty))| {
+                    let start = if index == 0 { "return " } else { "&& " };
+                    match self.is_bytes(ty) {
+                        true => format!("{}Arrays.equals({}, that.{})", start, field, field),
+                        false => format!("{}Objects.equals({}, that.{})", start, field, field),
+                    }
+                })
+                .collect();
+            let lines = equals
+                .into_iter()
+                .enumerate()
+                .map(|(index, line)| match index {
+                    0 => line,
+                    _ => format!("{}{}", CONTINUE, line),
+                })
+                .collect();
+            self.statement(out, lines);
+        }
+        out.close("}");
+        out.blank();
+        out.line("@Override");
+        out.open("public int hashCode() {");
+        let hashes: Vec<String> = fields
+            .iter()
+            .map(|(field, ty)| match self.is_bytes(ty) {
+                true => format!("Arrays.hashCode({})", field),
+                false => field.clone(),
+            })
+            .collect();
+        out.line(format!("return Objects.hash({});", hashes.join(", ")));
+        out.close("}");
+        out.blank();
+        out.line("@Override");
+        out.open("public String toString() {");
+        let mut to_string = format!("\"{}{{", name.trim_end_matches('_'));
+        for (index, (field, ty)) in fields.iter().enumerate() {
+            let separator = if index == 0 { "" } else { ", " };
+            let value = match self.is_bytes(ty) {
+                true => format!("Arrays.toString({})", field),
+                false => field.clone(),
+            };
+            to_string.push_str(&format!(
+                "{}{}=\" + {} + \"",
+                separator,
+                field.trim_end_matches('_'),
+                value
+            ));
+        }
+        out.line(format!("return {}}}\";", to_string));
+        out.close("}");
+        Ok(())
+    }
+
+    /// `write` and `read` of a class with `fields`, in the wire format of a model.
+    fn wire_methods(
+        &self,
+        out: &mut CodeWriter,
+        name: &str,
+        fields: &[(String, &Type)],
+    ) -> Result<(), CodegenError> {
+        out.open("public void write(WireWriter writer) {");
+        for (field, ty) in fields {
+            out.line(format!("{};", self.write(ty, field, 0)?));
+        }
+        out.close("}");
+        out.blank();
+        out.open(format!("public static {} read(WireReader reader) {{", name));
+        let reads = fields
+            .iter()
+            .map(|(_, ty)| self.read(ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.statement(out, call_lines(&format!("return new {}", name), reads));
+        out.close("}");
+        Ok(())
+    }
+
+    /// The holder class of a tuple, with its elements as fields `v0`, `v1` and so on.
+    fn tuple_class(
+        &self,
+        out: &mut CodeWriter,
+        name: &str,
+        types: &[Type],
+    ) -> Result<(), CodegenError> {
+        let fields: Vec<(String, &Type)> = types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| (format!("v{}", index), ty))
+            .collect();
+        out.open(format!("public final class {} {{", name));
+        self.value_class(out, name, &fields, "public")?;
+        out.blank();
+        self.wire_methods(out, name, &fields)?;
+        out.close("}");
+        Ok(())
+    }
+
+    fn model(&self, out: &mut CodeWriter, decl: &ModelDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let fields: Vec<(String, &Type)> = decl
+            .fields
+            .iter()
+            .map(|field| (local(field.name.as_str()), &field.ty))
+            .collect();
+        out.open(format!("public final class {} {{", name));
+        self.value_class(out, &name, &fields, "public")?;
+        out.blank();
+        out.open("public static Builder builder() {");
+        out.line("return new Builder();");
+        out.close("}");
+        out.blank();
+        out.line("/** A builder with the values of this one. */");
+        out.open("public Builder toBuilder() {");
+        let setters: String = fields
+            .iter()
+            .map(|(field, _)| format!(".{}({})", field, field))
+            .collect();
+        out.line(format!("return new Builder(){};", setters));
+        out.close("}");
+        out.blank();
+        self.wire_methods(out, &name, &fields)?;
+        out.blank();
+        out.open("public static final class Builder {");
+        for (field, decl_field) in fields.iter().zip(&decl.fields) {
+            let default = match &decl_field.default {
+                Some(value) => format!(" = {}", self.value(&decl_field.ty, value)?),
+                None => String::new(),
+            };
+            out.line(format!(
+                "private {} {}{};",
+                self.ty(&decl_field.ty, true)?,
+                field.0,
+                default
+            ));
+        }
+        if !fields.is_empty() {
+            out.blank();
+        }
+        out.line("private Builder() {}");
+        for (field, ty) in &fields {
+            out.blank();
+            out.open(format!(
+                "public Builder {}({} {}) {{",
+                field,
+                self.ty(ty, false)?,
+                field
+            ));
+            out.line(format!("this.{} = {};", field, field));
+            out.line("return this;");
+            out.close("}");
+        }
+        out.blank();
+        out.open(format!("public {} build() {{", name));
+        let args: Vec<String> = fields
+            .iter()
+            .zip(&decl.fields)
+            .map(|((field, ty), decl_field)| {
+                match decl_field.default.is_none() && !self.is_optional(ty) {
+                    true => format!(
+                        "Objects.requireNonNull({}, \"`{}` is not set\")",
+                        field, field
+                    ),
+                    false => field.clone(),
+                }
+            })
+            .collect();
+        self.statement(out, call_lines(&format!("return new {}", name), args));
+        out.close("}");
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    fn unit_enum(&self, out: &mut CodeWriter, decl: &EnumDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let repr = keyword_ty(decl.repr_type(), false);
+        out.open(format!("public enum {} {{", name));
+        for (index, variant) in decl.variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => num(value),
+                None => num(&index_lit(decl.repr_type(), index)),
+            };
+            let end = if index + 1 == decl.variants.len() {
+                ";"
+            } else {
+                ","
+            };
+            out.line(format!(
+                "{}({}){}",
+                shouty_snake_case(variant.name.as_str()),
+                value,
+                end
+            ));
+        }
+        out.blank();
+        out.line(format!("private final {} value;", repr));
+        out.blank();
+        out.open(format!("{}({} value) {{", name, repr));
+        out.line("this.value = value;");
+        out.close("}");
+        out.blank();
+        out.open(format!("public {} getValue() {{", repr));
+        out.line("return value;");
+        out.close("}");
+        out.blank();
+        out.open(format!(
+            "public static {} fromValue({} value) {{",
+            name, repr
+        ));
+        out.open(format!("for ({} variant : values()) {{", name));
+        out.line("if (variant.value == value) return variant;");
+        out.close("}");
+        out.line(format!(
+            "throw new WireException(\"`{}` has no variant \" + value);",
+            decl.name.as_str()
+        ));
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    fn payload_enum(&self, out: &mut CodeWriter, decl: &EnumDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let repr = decl.repr_type();
+        out.open(format!("public abstract class {} {{", name));
+        out.line(format!("private {}() {{}}", name));
+        // The fields of every variant, by the name of their Java field.
+        let mut variants = vec![];
+        for variant in &decl.variants {
+            let fields: Vec<(String, &Type)> = match &variant.fields {
+                EnumVariantFields::Unit => vec![],
+                EnumVariantFields::Unnamed(types) if types.len() == 1 => {
+                    vec![("value".to_string(), &types[0])]
+                }
+                EnumVariantFields::Unnamed(types) => types
+                    .iter()
+                    .enumerate()
+                    .map(|(index, ty)| (format!("v{}", index), ty))
+                    .collect(),
+                EnumVariantFields::Named(fields) => fields
+                    .iter()
+                    .map(|field| (local(field.name.as_str()), &field.ty))
+                    .collect(),
+            };
+            let variant_name = ident(variant.name.as_str());
+            out.blank();
+            out.open(format!(
+                "public static final class {} extends {} {{",
+                variant_name, name
+            ));
+            if fields.is_empty() {
+                out.line(format!(
+                    "public static final {} INSTANCE = new {}();",
+                    variant_name, variant_name
+                ));
+                out.blank();
+                out.line(format!("private {}() {{}}", variant_name));
+                out.blank();
+                out.line("@Override");
+                out.open("public String toString() {");
+                out.line(format!("return \"{}\";", variant.name.as_str()));
+                out.close("}");
+            } else {
+                self.value_class(out, &variant_name, &fields, "public")?;
+            }
+            out.close("}");
+            variants.push((variant_name, variant, fields));
+        }
+        out.blank();
+        out.open("public void write(WireWriter writer) {");
+        for (index, (variant_name, variant, fields)) in variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => num(value),
+                None => num(&index_lit(repr, index)),
+            };
+            let head = format!("if (this instanceof {}) {{", variant_name);
+            match index {
+                0 => out.open(head),
+                _ => out.reopen(format!("}} else {}", head)),
+            }
+            if !fields.is_empty() {
+                out.line(format!(
+                    "{} variant = ({}) this;",
+                    variant_name, variant_name
+                ));
+            }
+            out.line(format!("writer.{}({});", wire_fn(repr), value));
+            for (field, ty) in fields {
+                out.line(format!(
+                    "{};",
+                    self.write(ty, &format!("variant.{}", field), 0)?
+                ));
+            }
+        }
+        if !variants.is_empty() {
+            out.close("}");
+        }
+        out.close("}");
+        out.blank();
+        out.open(format!("public static {} read(WireReader reader) {{", name));
+        out.line(format!("long value = reader.{}();", wire_fn(repr)));
+        for (index, (variant_name, variant, fields)) in variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => discriminant(value),
+                None => long(index as i64),
+            };
+            let value_of = match fields.is_empty() {
+                true => format!("{}.INSTANCE", variant_name),
+                false => {
+                    let reads = fields
+                        .iter()
+                        .map(|(_, ty)| self.read(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("new {}({})", variant_name, reads.join(", "))
+                }
+            };
+            out.line(format!("if (value == {}) return {};", value, value_of));
+        }
+        out.line(format!(
+            "throw new WireException(\"`{}` has no variant \" + value);",
+            decl.name.as_str()
+        ));
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    /// `IoErrorException` with a subclass per variant of `IoError`, which `of` picks.
+    fn exception_class(&self, out: &mut CodeWriter, decl: &EnumDecl) -> Result<(), CodegenError> {
+        let error = ident(decl.name.as_str());
+        let name = ident(&format!("{}Exception", decl.name.as_str()));
+        let base = match self.codegen.config.exception_style {
+            JavaExceptionStyle::Checked => "Exception",
+            JavaExceptionStyle::Unchecked => "RuntimeException",
+        };
+        out.line(format!(
+            "/** Thrown with the `{}` of a call, as the subclass of its variant. */",
+            decl.name.as_str()
+        ));
+        out.open(format!(
+            "public abstract class {} extends {} {{",
+            name, base
+        ));
+        out.line("private static final long serialVersionUID = 1L;");
+        out.blank();
+        out.line(format!("private final {} error;", error));
+        out.blank();
+        out.open(format!("private {}({} error) {{", name, error));
+        out.line("super(error.toString());");
+        out.line("this.error = error;");
+        out.close("}");
+        out.blank();
+        out.open(format!("public {} getError() {{", error));
+        out.line("return error;");
+        out.close("}");
+        out.blank();
+        out.open(format!("public static {} of({} error) {{", name, error));
+        for variant in &decl.variants {
+            let variant_name = ident(variant.name.as_str());
+            out.line(match (&variant.fields, decl.is_primitive()) {
+                (EnumVariantFields::Unit, true) => format!(
+                    "if (error == {}.{}) return new {}();",
+                    error,
+                    shouty_snake_case(variant.name.as_str()),
+                    variant_name
+                ),
+                (EnumVariantFields::Unit, false) => format!(
+                    "if (error == {}.{}.INSTANCE) return new {}();",
+                    error, variant_name, variant_name
+                ),
+                _ => format!(
+                    "if (error instanceof {}.{}) return new {}(({}.{}) error);",
+                    error, variant_name, variant_name, error, variant_name
+                ),
+            });
+        }
+        out.line("throw new AssertionError(error);");
+        out.close("}");
+        for variant in &decl.variants {
+            let variant_name = ident(variant.name.as_str());
+            out.blank();
+            out.open(format!(
+                "public static final class {} extends {} {{",
+                variant_name, name
+            ));
+            out.line("private static final long serialVersionUID = 1L;");
+            out.blank();
+            match (&variant.fields, decl.is_primitive()) {
+                (EnumVariantFields::Unit, true) => {
+                    out.open(format!("public {}() {{", variant_name));
+                    out.line(format!(
+                        "super({}.{});",
+                        error,
+                        shouty_snake_case(variant.name.as_str())
+                    ));
+                }
+                (EnumVariantFields::Unit, false) => {
+                    out.open(format!("public {}() {{", variant_name));
+                    out.line(format!("super({}.{}.INSTANCE);", error, variant_name));
+                }
+                _ => {
+                    out.open(format!(
+                        "public {}({}.{} error) {{",
+                        variant_name, error, variant_name
+                    ));
+                    out.line("super(error);");
+                }
+            }
+            out.close("}");
+            out.close("}");
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    fn interface(&self, out: &mut CodeWriter, decl: &InterfaceDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let params = decl.type_params.as_deref().unwrap_or_default();
+        let supertypes = decl
+            .extends
+            .iter()
+            .map(|with| self.with_args(with))
+            .collect::<Result<Vec<_>, _>>()?;
+        let supertypes = match supertypes.is_empty() {
+            true => String::new(),
+            false => format!(" extends {}", supertypes.join(", ")),
+        };
+        out.open(format!(
+            "public interface {}{}{} {{",
+            name,
+            self.type_params(params)?.trim_end(),
+            supertypes
+        ));
+        for (index, member) in own_interface_members(decl).iter().enumerate() {
+            if index > 0 {
+                out.blank();
+            }
+            let function = member.function();
+            let signature =
+                self.signature(&method(function.name.as_str()), function, "", true, None)?;
+            out.line(format!("{};", signature));
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    /// The adapter Rust calls the methods of an app implementation through, by slot name.
+    fn adapter(
+        &self,
+        out: &mut CodeWriter,
+        decl: &InterfaceDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let adapter = ident(&format!("{}Callback", decl.name.as_str()));
+        out.line(format!(
+            "/** Hands implementations of `{}` to Rust, used by the bindings. */",
+            decl.name.as_str()
+        ));
+        out.open(format!(
+            "public final class {} implements Callback {{",
+            adapter
+        ));
+        out.line(format!(
+            "private static final boolean registered = {}.{}_register({}.class);",
+            JNI_CLASS,
+            self.symbol(this),
+            adapter
+        ));
+        out.blank();
+        out.line(format!("private final {} impl;", name));
+        out.blank();
+        out.open(format!("private {}({} impl) {{", adapter, name));
+        out.line("this.impl = impl;");
+        out.close("}");
+        out.blank();
+        out.line("/** A new handle of {@code impl} for Rust, which releases it. */");
+        out.open(format!("public static long handle({} impl) {{", name));
+        out.open("if (!registered) {");
+        out.line(format!(
+            "throw new IllegalStateException(\"another implementation of `{}` is registered\");",
+            decl.name.as_str()
+        ));
+        out.close("}");
+        out.line(format!(
+            "return {}.retain(new {}(impl));",
+            JNI_CLASS, adapter
+        ));
+        out.close("}");
+        out.blank();
+        out.line("@Override");
+        out.line("public void retain() {}");
+        out.blank();
+        out.line("@Override");
+        out.line("public void release() {}");
+        for member in interface_members(decl) {
+            let function = member.function();
+            // Rust looks the methods up by the camel case slot name.
+            let slot = camel_case(function.name.as_str());
+            if KEYWORDS.contains(&slot.as_str()) {
+                return Err(CodegenError::custom(format!(
+                    "`{}` of `{}` is a Java keyword, Java cannot implement the interface",
+                    slot,
+                    decl.name.as_str()
+                )));
+            }
+            check_host_method(function)?;
+            out.blank();
+            out.open(format!("public byte[] {}(byte[] args) {{", slot));
+            let reads = function
+                .params
+                .iter()
+                .map(|param| self.read(&param.ty))
+                .collect::<Result<Vec<_>, _>>()?;
+            let call = format!(
+                "impl.{}({})",
+                method(function.name.as_str()),
+                reads.join(", ")
+            );
+            let statement = match function.return_type.as_deref() {
+                Some(ty) => self.write(ty, &call, 0)?,
+                None => call,
+            };
+            match self.exception(function)? {
+                None => out.line(format!(
+                    "return Outcome.host(args, (reader, writer) -> {});",
+                    statement
+                )),
+                Some((exception, error)) => {
+                    out.open(format!(
+                        "return Outcome.host(args, (reader, writer) -> {}, (error, writer) -> {{",
+                        statement
+                    ));
+                    out.line(format!(
+                        "if (!(error instanceof {})) return false;",
+                        exception
+                    ));
+                    out.line(format!(
+                        "{};",
+                        self.write(error, &format!("(({}) error).getError()", exception), 0)?
+                    ));
+                    out.line("return true;");
+                    out.close("});");
+                }
+            }
+            out.close("}");
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    /// An interface implemented in Rust.
+    fn object(
+        &self,
+        out: &mut CodeWriter,
+        decl: &InterfaceDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let object = ident(&format!("{}Object", decl.name.as_str()));
+        let members = interface_members(decl);
+        let mut overrides = HashMap::new();
+        self.declared(decl, &mut overrides);
+        out.line(format!(
+            "/** A `{}` implemented in Rust, used by the bindings. */",
+            decl.name.as_str()
+        ));
+        out.open(format!(
+            "public final class {} implements {}, AutoCloseable {{",
+            object,
+            ident(decl.name.as_str())
+        ));
+        self.handle(out, &object);
+        self.accessors(out, &object, &self.symbol(this));
+        self.members(out, &members, &self.symbol(this), &overrides)?;
+        out.blank();
+        self.close(out, &members, &self.symbol(this))?;
+        out.close("}");
+        Ok(())
+    }
+
+    /// The JNI method prefix of a class or interface, `app_Client`.
+    fn symbol(&self, decl: &DeclRef) -> String {
+        let mut names: Vec<&str> = decl
+            .name
+            .path
+            .segments()
+            .iter()
+            .map(Ident::as_str)
+            .collect();
+        names.push(decl.name.name.as_str());
+        names.join("_")
+    }
+
+    /// The handle of a wrapper, zero once closed, and the constructor taking it.
+    fn handle(&self, out: &mut CodeWriter, name: &str) {
+        out.line("private final AtomicLong raw;");
+        out.line("private final Cleaner.Cleanable cleanable;");
+        out.blank();
+        out.line("/** Wraps the handle of an object, used by the bindings. */");
+        out.open(format!("public {}(Handle handle) {{", name));
+        out.line("AtomicLong raw = new AtomicLong(handle.raw);");
+        out.line("this.raw = raw;");
+        out.line("this.cleanable = Cleaner.register(this, () -> free(raw));");
+        out.close("}");
+    }
+
+    /// `handle()` of a wrapper and the `free` of its cleaner.
+    fn accessors(&self, out: &mut CodeWriter, name: &str, prefix: &str) {
+        out.blank();
+        out.line("/** The handle of the object, used by the bindings. */");
+        out.open("public long handle() {");
+        out.line("long handle = raw.get();");
+        out.line(format!(
+            "if (handle == 0) throw new IllegalStateException(\"`{}` is closed\");",
+            name
+        ));
+        out.line("return handle;");
+        out.close("}");
+        out.blank();
+        out.open("private static void free(AtomicLong raw) {");
+        out.line("long handle = raw.getAndSet(0);");
+        out.line(format!(
+            "if (handle != 0) Outcome.unwrap({}.{}_free(handle), reader -> null);",
+            JNI_CLASS, prefix
+        ));
+        out.close("}");
+    }
+
+    /// Whether `member` is a `close()` of the IDL, which `AutoCloseable.close` calls.
+    fn is_close(member: &Member) -> bool {
+        match member {
+            Member::Method(function) => {
+                method(function.name.as_str()) == "close" && function.params.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    /// `close` of a wrapper, calling a `close` of the IDL before it frees the handle.
+    fn close(
+        &self,
+        out: &mut CodeWriter,
+        members: &[Member],
+        prefix: &str,
+    ) -> Result<(), CodegenError> {
+        let idl_close = members.iter().find(|member| Self::is_close(member));
+        let throws = match idl_close {
+            Some(member) => match self.exception(member.function())? {
+                Some((exception, _)) => format!(" throws {}", exception),
+                None => String::new(),
+            },
+            None => String::new(),
+        };
+        out.line("@Override");
+        out.open(format!("public void close(){} {{", throws));
+        if let Some(member) = idl_close {
+            let function = member.function();
+            let lines = self.call(
+                &format!("{}_{}", prefix, function.name.as_str()),
+                function,
+                true,
+            )?;
+            out.open("try {");
+            out.open("if (raw.get() != 0) {");
+            self.statement(out, lines);
+            out.close("}");
+            out.reopen("} finally {");
+            out.line("cleanable.clean();");
+            out.close("}");
+        } else {
+            out.line("cleanable.clean();");
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    /// The methods of `decl` and the interfaces it extends by name, as they are declared.
+    fn declared(&self, decl: &InterfaceDecl, declared: &mut HashMap<String, FnDecl>) {
+        for member in own_interface_members(decl) {
+            let function = member.function();
+            declared
+                .entry(function.name.as_str().to_string())
+                .or_insert_with(|| function.clone());
+        }
+        for with in &decl.extends {
+            if let Some(Decl::Interface(base)) =
+                with.target.as_ref().and_then(|target| self.get(target))
+            {
+                self.declared(base, declared);
+            }
+        }
+    }
+
+    /// The methods of a wrapper calling Rust, `overrides` the methods of its interfaces by name.
+    fn members(
+        &self,
+        out: &mut CodeWriter,
+        members: &[Member],
+        prefix: &str,
+        overrides: &HashMap<String, FnDecl>,
+    ) -> Result<(), CodegenError> {
+        for member in members {
+            let function = member.function();
+            if Self::is_close(member) {
+                continue;
+            }
+            let name = method(function.name.as_str());
+            let declared = overrides.get(function.name.as_str());
+            if !self.abi.is_exported(function) {
+                if declared.is_some() {
+                    out.blank();
+                    out.line("@Override");
+                    let signature = self.signature(&name, function, "public ", false, declared)?;
+                    out.open(format!("{} {{", signature));
+                    out.line(format!(
+                        "throw new UnsupportedOperationException(\"`{}` is generic\");",
+                        function.name.as_str()
+                    ));
+                    out.close("}");
+                }
+                continue;
+            }
+            out.blank();
+            if declared.is_some() {
+                out.line("@Override");
+            }
+            let signature = self.signature(&name, function, "public ", false, declared)?;
+            let lines = self.call(
+                &format!("{}_{}", prefix, function.name.as_str()),
+                function,
+                true,
+            )?;
+            self.method_body(out, &signature, function, lines);
+        }
+        Ok(())
+    }
+
+    fn class(
+        &self,
+        out: &mut CodeWriter,
+        decl: &ClassDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let prefix = self.symbol(this);
+        let mut overrides = HashMap::new();
+        let mut supertypes = vec![];
+        for with in self.abi.class_interfaces(decl) {
+            if let Some(Decl::Interface(interface)) =
+                with.target.as_ref().and_then(|target| self.get(target))
+            {
+                self.declared(interface, &mut overrides);
+            }
+            let supertype = self.with_args(&with)?;
+            if !supertypes.contains(&supertype) {
+                supertypes.push(supertype);
+            }
+        }
+        supertypes.push("AutoCloseable".to_string());
+        out.open(format!(
+            "public final class {} implements {} {{",
+            name,
+            supertypes.join(", ")
+        ));
+        self.handle(out, &name);
+        for (index, ctor) in constructors(decl).enumerate() {
+            let function = constructor_function(this, index, ctor);
+            if !self.abi.is_exported(&function) {
+                continue;
+            }
+            let native = self.native_call(
+                &format!("{}_{}", prefix, function.name.as_str()),
+                &function,
+                false,
+            )?;
+            let mut unwrap = vec![native, "WireReader::i64".to_string()];
+            let throws = match self.exception(&function)? {
+                Some((exception, _)) => format!(" throws {}", exception),
+                None => String::new(),
+            };
+            unwrap.extend(self.thrown(&function)?);
+            out.blank();
+            out.open(format!(
+                "public {}({}){} {{",
+                name,
+                self.params(&function.params)?.join(", "),
+                throws
+            ));
+            out.line(format!(
+                "this(new Handle(Outcome.unwrap({})));",
+                unwrap.join(", ")
+            ));
+            out.close("}");
+        }
+        self.accessors(out, &name, &prefix);
+        let members = self.abi.class_members(decl);
+        self.members(out, &members, &prefix, &overrides)?;
+        for member in &decl.member {
+            match member {
+                ClassMember::Const(decl) => {
+                    out.blank();
+                    self.constant(out, decl)?
+                }
+                ClassMember::StaticMethod(function) if self.abi.is_exported(function) => {
+                    out.blank();
+                    let signature = self.signature(
+                        &method(function.name.as_str()),
+                        function,
+                        "public static ",
+                        false,
+                        None,
+                    )?;
+                    let native = format!("{}_{}", prefix, function.name.as_str());
+                    let lines = self.call(&native, function, false)?;
+                    self.method_body(out, &signature, function, lines);
+                }
+                _ => {}
+            }
+        }
+        out.blank();
+        self.close(out, &members, &prefix)?;
+        out.close("}");
+        Ok(())
+    }
+}
+
+/// The implicit discriminant of the variant at `index`, as a literal of the repr type.
+fn index_lit(repr: KeywordType, index: usize) -> NumLit {
+    match repr {
+        KeywordType::Int8 | KeywordType::Uint8 => NumLit::I8(index as i8),
+        KeywordType::Int16 | KeywordType::Uint16 => NumLit::I16(index as i16),
+        KeywordType::Int64 | KeywordType::Uint64 => NumLit::I64(index as i64),
+        _ => NumLit::I32(index as i32),
+    }
+}
+
+fn generic() -> CodegenError {
+    CodegenError::custom("generic types are not exported")
+}
+
+fn unknown(target: &DeclRef) -> CodegenError {
+    CodegenError::custom(format!("`{}` is not a type", target.name.name.as_str()))
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::config::{JavaConfig, JvmConfig, TypeMapping};
+
+    use super::JavaCodegen;
+    use crate::lang::test::{compile, file, load, temp_dir};
+    use crate::lang::SourceFile;
+    use crate::Codegen;
+
+    const APP: &str = r#"
+newtype Timestamp = i64;
+newtype UserId = u64;
+
+model Blob {
+    data: Bytes,
+    thumbnail: Optional<Bytes>,
+    at: Timestamp = 5,
+    owner: UserId,
+}
+
+enum IoError {
+    NotFound(String),
+    Denied,
+}
+
+enum Packet {
+    Data(Bytes),
+    Pair((i32, i32)),
+}
+
+fn touch(at: Timestamp) -> Timestamp;
+fn pair(p: (i32, String)) -> (Blob, Optional<(u8, Bytes)>);
+fn load(id: UserId) -> Blob throws IoError;
+async fn fetch(url: String) -> Bytes throws IoError;
+
+class Remote {
+    async fn call(x: i32) -> (i32, Bytes) throws IoError;
+    fn sync(x: i32) throws IoError;
+}
+
+mod sub {
+    model Inner {
+        id: UserId,
+    }
+
+    fn both(t: (Inner, Timestamp)) -> (Inner, Timestamp);
+}
+"#;
+
+    fn generate() -> Vec<SourceFile> {
+        let ir = load(&[("app.slime", APP)]);
+        let jvm = JvmConfig {
+            package_name: "com.example".into(),
+            ..Default::default()
+        };
+        let config = JavaConfig {
+            type_mappings: vec![TypeMapping {
+                idl_type: "Timestamp".into(),
+                host_type: "java.time.Instant".into(),
+                lift: "java.time.Instant.ofEpochMilli({})".into(),
+                lower: "{}.toEpochMilli()".into(),
+            }],
+            ..Default::default()
+        };
+        JavaCodegen::new(&ir, &jvm, &config).generate().unwrap()
+    }
+
+    #[test]
+    fn test_mapped_newtype() {
+        let files = generate();
+        let blob = file(&files, "com/example/app/Blob.java");
+        assert!(blob.contains("writer.i64(at.toEpochMilli());"));
+        assert!(blob.contains("java.time.Instant.ofEpochMilli(reader.i64()),"));
+        assert!(blob.contains("private java.time.Instant at = java.time.Instant.ofEpochMilli(5L);"));
+        assert!(blob.contains("writer.i64(owner.getValue());"));
+        let app = file(&files, "com/example/app/App.java");
+        assert!(app.contains("SlimeNative.app_touch(at.toEpochMilli())"));
+        assert!(!app.contains("Timestamp("));
+    }
+
+    #[test]
+    fn test_byte_array_equality() {
+        let files = generate();
+        let blob = file(&files, "com/example/app/Blob.java");
+        assert!(blob.contains("return Arrays.equals(data, that.data)"));
+        assert!(blob.contains("&& Arrays.equals(thumbnail, that.thumbnail)"));
+        assert!(blob.contains("Arrays.hashCode(thumbnail)"));
+        assert!(blob.contains("\", thumbnail=\" + Arrays.toString(thumbnail) + "));
+    }
+
+    #[test]
+    fn test_tuples() {
+        let files = generate();
+        let holder = file(&files, "com/example/Tuple2I32String.java");
+        assert!(holder.contains("public final class Tuple2I32String {"));
+        assert!(holder.contains("public Tuple2I32String(int v0, String v1) {"));
+        assert!(holder.contains("public static Tuple2I32String read(WireReader reader) {"));
+        let nested = file(
+            &files,
+            "com/example/Tuple2AppBlobOptionalTuple2U8Bytes.java",
+        );
+        assert!(nested.contains("private final com.example.app.Blob v0;"));
+        assert!(nested.contains("private final Tuple2U8Bytes v1;"));
+        file(&files, "com/example/Tuple2U8Bytes.java");
+        file(&files, "com/example/Tuple2I32I32.java");
+        file(&files, "com/example/Tuple2I32Bytes.java");
+        let mapped = file(&files, "com/example/Tuple2AppSubInnerAppTimestamp.java");
+        assert!(mapped.contains("private final java.time.Instant v1;"));
+        let app = file(&files, "com/example/app/App.java");
+        assert!(app.contains(
+            "public static com.example.Tuple2AppBlobOptionalTuple2U8Bytes pair(\
+             com.example.Tuple2I32String p) {"
+        ));
+        assert!(app.contains("writer -> p.write(writer)"));
+    }
+
+    #[test]
+    fn test_async_throws() {
+        let files = generate();
+        let app = file(&files, "com/example/app/App.java");
+        assert!(app.contains("public static Blob load(UserId id) throws IoErrorException {"));
+        assert!(app.contains("public static CompletableFuture<byte[]> fetch(String url) {"));
+        let remote = file(&files, "com/example/app/Remote.java");
+        assert!(
+            remote.contains("public CompletableFuture<com.example.Tuple2I32Bytes> call(int x) {")
+        );
+        assert!(remote.contains("public void sync(int x) throws IoErrorException {"));
+    }
+
+    #[test]
+    fn test_exception_hierarchy() {
+        let files = generate();
+        let exception = file(&files, "com/example/app/IoErrorException.java");
+        assert!(exception.contains("public abstract class IoErrorException extends Exception {"));
+        assert!(exception.contains("public static IoErrorException of(IoError error) {"));
+        assert!(exception.contains(
+            "if (error instanceof IoError.NotFound) return new NotFound((IoError.NotFound) error);"
+        ));
+        assert!(exception.contains("if (error == IoError.Denied.INSTANCE) return new Denied();"));
+        assert!(exception.contains("public static final class NotFound extends IoErrorException {"));
+        assert!(exception.contains("super(IoError.Denied.INSTANCE);"));
+        let app = file(&files, "com/example/app/App.java");
+        assert!(app.contains("reader -> IoErrorException.of(IoError.read(reader))"));
+    }
+
+    #[test]
+    fn test_compile() {
+        let files = generate();
+        let dir = temp_dir();
+        for file in &files {
+            file.write_to(&dir).unwrap();
+        }
+        let sources: Vec<String> = files
+            .iter()
+            .map(|file| file.path.to_string_lossy().into_owned())
+            .collect();
+        let mut args = vec!["--release", "8", "-Werror", "-d", "classes"];
+        args.extend(sources.iter().map(String::as_str));
+        compile("javac", &args, &dir);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Polls a handle that resumes itself from within every poll until the last one.
+    const SELF_WAKING: &str = r#"
+package com.slime.ffi;
+
+public final class SelfWaking {
+    public static void main(String[] args) {
+        int[] polls = {0};
+        boolean[] ready = {false};
+        Futures.Task task = new Futures.Task(1, handle -> {});
+        Futures.whenReady(task, (handle, data) -> {
+            Futures.resume(data, (byte) (++polls[0] == 100000 ? 0 : 1));
+        }, () -> ready[0] = true);
+        if (!ready[0] || polls[0] != 100000) throw new AssertionError(polls[0]);
+    }
+}
+"#;
+
+    #[test]
+    fn test_self_waking_future() {
+        let files = generate();
+        let dir = temp_dir();
+        let mut sources = vec!["com/slime/ffi/SelfWaking.java".to_string()];
+        for file in &files {
+            file.write_to(&dir).unwrap();
+            if file.path.starts_with("com/slime/ffi") {
+                sources.push(file.path.to_string_lossy().into_owned());
+            }
+        }
+        std::fs::write(dir.join(&sources[0]), SELF_WAKING).unwrap();
+        let mut args = vec!["--release", "8", "-d", "classes"];
+        args.extend(sources.iter().map(String::as_str));
+        if compile("javac", &args, &dir) {
+            // A stack this small overflows long before the last poll if resuming recurses.
+            let args = ["-Xss256k", "-cp", "classes", "com.slime.ffi.SelfWaking"];
+            compile("java", &args, &dir);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,13 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+/**
+ * Implemented by the adapters handing host objects to Rust. Rust holds a global reference to
+ * the adapter and calls {@code release} before it deletes the reference.
+ */
+public interface Callback {
+    void retain();
+
+    void release();
+}
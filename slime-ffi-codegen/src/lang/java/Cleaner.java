@@ -0,0 +1,66 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.lang.ref.PhantomReference;
+import java.lang.ref.ReferenceQueue;
+import java.util.Collections;
+import java.util.IdentityHashMap;
+import java.util.Set;
+
+/**
+ * Runs an action once an object is unreachable, the {@code java.lang.ref.Cleaner} of Java 9 for
+ * Java 8. The action must not refer to the object, or it stays reachable.
+ */
+public final class Cleaner {
+    private static final ReferenceQueue<Object> queue = new ReferenceQueue<>();
+    private static final Set<Cleanable> pending =
+            Collections.synchronizedSet(Collections.newSetFromMap(new IdentityHashMap<>()));
+
+    static {
+        Thread thread = new Thread(Cleaner::run, "slime-ffi-cleaner");
+        thread.setDaemon(true);
+        thread.start();
+    }
+
+    private Cleaner() {}
+
+    /** Runs {@code action} once {@code object} is unreachable or {@link Cleanable#clean} is called. */
+    public static Cleanable register(Object object, Runnable action) {
+        Cleanable cleanable = new Cleanable(object, action);
+        pending.add(cleanable);
+        return cleanable;
+    }
+
+    private static void run() {
+        while (true) {
+            try {
+                ((Cleanable) queue.remove()).clean();
+            } catch (Throwable e) {
+                // The action failed, which must not stop the cleaning of other objects.
+            }
+        }
+    }
+
+    /** A registered action. */
+    public static final class Cleanable extends PhantomReference<Object> {
+        private Runnable action;
+
+        private Cleanable(Object object, Runnable action) {
+            super(object, queue);
+            this.action = action;
+        }
+
+        /** Runs the action unless it ran already. */
+        public void clean() {
+            Runnable action;
+            synchronized (this) {
+                action = this.action;
+                this.action = null;
+            }
+            if (action == null) return;
+            pending.remove(this);
+            action.run();
+        }
+    }
+}
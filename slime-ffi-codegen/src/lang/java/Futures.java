@@ -0,0 +1,178 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.util.concurrent.CompletableFuture;
+import java.util.concurrent.ConcurrentHashMap;
+import java.util.concurrent.atomic.AtomicInteger;
+import java.util.concurrent.atomic.AtomicLong;
+import java.util.function.Consumer;
+import java.util.function.LongConsumer;
+import java.util.function.LongFunction;
+
+/**
+ * Drives the futures and streams of Rust. Rust calls {@link #resume} when a polled handle can
+ * make progress, possibly from its own thread, which polls again or completes the call there.
+ */
+public final class Futures {
+    /** The handle has an output, any other status asks to poll again. */
+    private static final byte READY = 0;
+
+    private static final AtomicLong next = new AtomicLong(1);
+    private static final ConcurrentHashMap<Long, Consumer<Byte>> waiting = new ConcurrentHashMap<>();
+
+    private Futures() {}
+
+    /** Polls a handle, Rust resumes with {@code data}. */
+    public interface Poll {
+        void poll(long handle, long data);
+    }
+
+    public static void resume(long data, byte status) {
+        Consumer<Byte> waiter = waiting.remove(data);
+        if (waiter != null) waiter.accept(status);
+    }
+
+    /** Polls {@code task} until it is ready or released, then runs {@code ready}. */
+    static void whenReady(Task task, Poll poll, Runnable ready) {
+        new Waiter(task, poll, ready).run();
+    }
+
+    /**
+     * Polls a task in a loop. A resume only records its status, and polls again itself unless
+     * a poll is running, which then loops instead, so a handle that keeps waking itself from
+     * within its poll does not grow the stack.
+     */
+    private static final class Waiter implements Consumer<Byte> {
+        private final Task task;
+        private final Poll poll;
+        private final Runnable ready;
+        /** The loop is running while positive, one more for every resume it has to handle. */
+        private final AtomicInteger resumes = new AtomicInteger(1);
+        private volatile boolean done = false;
+
+        Waiter(Task task, Poll poll, Runnable ready) {
+            this.task = task;
+            this.poll = poll;
+            this.ready = ready;
+        }
+
+        @Override
+        public void accept(Byte status) {
+            if (status == READY) done = true;
+            if (resumes.getAndIncrement() == 0) run();
+        }
+
+        void run() {
+            do {
+                if (done) {
+                    ready.run();
+                    return;
+                }
+                long data = next.getAndIncrement();
+                waiting.put(data, this);
+                if (!task.poll(poll, data)) {
+                    waiting.remove(data);
+                    ready.run();
+                    return;
+                }
+            } while (resumes.decrementAndGet() != 0);
+        }
+    }
+
+    /**
+     * The future whose handle is the value of {@code start}, completed with its output read by
+     * {@code read}. Cancelling it drops the Rust future.
+     */
+    public static <T> CompletableFuture<T> await(
+            byte[] start,
+            Poll poll,
+            LongFunction<byte[]> complete,
+            LongConsumer cancel,
+            LongConsumer free,
+            Outcome.Read<T> read,
+            Outcome.Thrown<?> thrown) {
+        CompletableFuture<T> future = new CompletableFuture<>();
+        Task task;
+        try {
+            task = new Task(Outcome.unwrap(start, WireReader::i64, thrown), free);
+        } catch (Throwable e) {
+            future.completeExceptionally(e);
+            return future;
+        }
+        future.whenComplete((value, error) -> {
+            if (future.isCancelled()) task.release(cancel);
+        });
+        whenReady(task, poll, () -> {
+            byte[] output = task.take(complete);
+            if (output == null) return;
+            try {
+                future.complete(Outcome.unwrap(output, read, thrown));
+            } catch (Throwable e) {
+                future.completeExceptionally(e);
+            }
+        });
+        return future;
+    }
+
+    /** {@link #await} of a call without an error. */
+    public static <T> CompletableFuture<T> await(
+            byte[] start,
+            Poll poll,
+            LongFunction<byte[]> complete,
+            LongConsumer cancel,
+            LongConsumer free,
+            Outcome.Read<T> read) {
+        return await(start, poll, complete, cancel, free, read, null);
+    }
+
+    /** A handle of Rust, freed once. */
+    static final class Task {
+        final long handle;
+        private final LongConsumer free;
+        private boolean released = false;
+
+        Task(long handle, LongConsumer free) {
+            this.handle = handle;
+            this.free = free;
+        }
+
+        synchronized boolean isReleased() {
+            return released;
+        }
+
+        /** Polls the handle unless it is released. */
+        synchronized boolean poll(Poll poll, long data) {
+            if (released) return false;
+            poll.poll(handle, data);
+            return true;
+        }
+
+        /** The output of {@code function}, or {@code null} if released. */
+        synchronized byte[] apply(LongFunction<byte[]> function) {
+            return released ? null : function.apply(handle);
+        }
+
+        /** Runs {@code action}, then frees the handle, unless it is released. */
+        synchronized void release(LongConsumer action) {
+            if (released) return;
+            released = true;
+            try {
+                action.accept(handle);
+            } finally {
+                free.accept(handle);
+            }
+        }
+
+        /** The output of {@code complete}, then frees the handle, or {@code null} if released. */
+        synchronized byte[] take(LongFunction<byte[]> complete) {
+            if (released) return null;
+            released = true;
+            try {
+                return complete.apply(handle);
+            } finally {
+                free.accept(handle);
+            }
+        }
+    }
+}
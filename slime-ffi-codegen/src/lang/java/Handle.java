@@ -0,0 +1,12 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+/** The handle of an object owned by Rust, which the bindings wrap. */
+public final class Handle {
+    public final long raw;
+
+    public Handle(long raw) {
+        this.raw = raw;
+    }
+}
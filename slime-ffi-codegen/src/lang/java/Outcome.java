@@ -0,0 +1,95 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.util.concurrent.CancellationException;
+
+/**
+ * The {@code Outcome} record JNI functions return and host callbacks answer with: a status byte,
+ * then the value, the error or the panic message.
+ */
+public final class Outcome {
+    private static final byte SUCCESS = 1;
+    private static final byte ERROR = 2;
+    private static final byte PANIC = 3;
+    private static final byte CANCELLED = 4;
+    private static final byte THROWN = 0;
+
+    private Outcome() {}
+
+    /** Reads a value. */
+    public interface Read<T> {
+        T read(WireReader reader);
+    }
+
+    /** Reads the declared error of a call as its exception. */
+    public interface Thrown<E extends Exception> {
+        E read(WireReader reader);
+    }
+
+    /** The body of a host callback. */
+    public interface Call {
+        void call(WireReader reader, WireWriter writer) throws Exception;
+    }
+
+    /** Writes the exception of a host callback if it is the declared error. */
+    public interface Catch {
+        boolean write(Throwable error, WireWriter writer);
+    }
+
+    /** The value of a call, {@code thrown} reads the error it declares. */
+    public static <T, E extends Exception> T unwrap(byte[] bytes, Read<T> read, Thrown<E> thrown)
+            throws E {
+        WireReader reader = new WireReader(bytes);
+        switch (reader.i8()) {
+            case SUCCESS:
+                T value = read.read(reader);
+                reader.finish();
+                return value;
+            case ERROR:
+                if (reader.i8() != THROWN) throw new SlimeException(reader.string());
+                if (thrown == null) throw new SlimeException("undeclared error");
+                throw thrown.read(reader);
+            case PANIC:
+                throw new SlimePanic(reader.string());
+            case CANCELLED:
+                throw new CancellationException("the call was cancelled");
+            default:
+                throw new WireException("invalid outcome");
+        }
+    }
+
+    /** The value of a call without an error. */
+    public static <T> T unwrap(byte[] bytes, Read<T> read) {
+        return unwrap(bytes, read, (Thrown<RuntimeException>) null);
+    }
+
+    /**
+     * Runs a host callback on its encoded arguments. {@code thrown} writes the exception if it is
+     * the declared error, any other one panics in Rust.
+     */
+    public static byte[] host(byte[] args, Call call, Catch thrown) {
+        try {
+            WireReader reader = new WireReader(args);
+            WireWriter writer = new WireWriter();
+            writer.i8(SUCCESS);
+            call.call(reader, writer);
+            reader.finish();
+            return writer.toByteArray();
+        } catch (Throwable e) {
+            WireWriter writer = new WireWriter();
+            writer.i8(ERROR);
+            writer.i8(THROWN);
+            if (thrown.write(e, writer)) return writer.toByteArray();
+            return WireWriter.encode(panic -> {
+                panic.i8(PANIC);
+                panic.string(e.toString());
+            });
+        }
+    }
+
+    /** Runs a host callback without an error. */
+    public static byte[] host(byte[] args, Call call) {
+        return host(args, call, (error, writer) -> false);
+    }
+}
@@ -0,0 +1,12 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+/** A call that failed in Rust other than with the error it declares. */
+public class SlimeException extends RuntimeException {
+    private static final long serialVersionUID = 1L;
+
+    public SlimeException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,12 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+/** Rust panicked during the call. */
+public final class SlimePanic extends SlimeException {
+    private static final long serialVersionUID = 1L;
+
+    public SlimePanic(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,77 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.util.Collections;
+import java.util.Iterator;
+import java.util.List;
+import java.util.NoSuchElementException;
+import java.util.concurrent.CompletableFuture;
+import java.util.function.LongConsumer;
+import java.util.function.LongFunction;
+
+/**
+ * The items of a Rust stream, blocking until the next one is ready. Closing it, or dropping it
+ * unfinished, drops the Rust stream.
+ */
+public final class SlimeStream<T> implements Iterator<T>, AutoCloseable {
+    private final Futures.Task task;
+    private final Futures.Poll poll;
+    private final LongFunction<byte[]> next;
+    private final LongConsumer cancel;
+    private final Outcome.Read<T> read;
+    private final Cleaner.Cleanable cleanable;
+    /** The item {@link #hasNext} read, a list since items can be {@code null}. */
+    private List<T> item = null;
+
+    public SlimeStream(
+            long handle,
+            Futures.Poll poll,
+            LongFunction<byte[]> next,
+            LongConsumer cancel,
+            LongConsumer free,
+            Outcome.Read<T> read) {
+        Futures.Task task = new Futures.Task(handle, free);
+        this.task = task;
+        this.poll = poll;
+        this.next = next;
+        this.cancel = cancel;
+        this.read = read;
+        this.cleanable = Cleaner.register(this, () -> task.release(cancel));
+    }
+
+    @Override
+    public synchronized boolean hasNext() {
+        if (item != null) return true;
+        if (task.isReleased()) return false;
+        CompletableFuture<Void> ready = new CompletableFuture<>();
+        Futures.whenReady(task, poll, () -> ready.complete(null));
+        ready.join();
+        byte[] output = task.apply(next);
+        if (output == null) return false;
+        List<T> item = Outcome.unwrap(
+                output,
+                reader -> reader.bool()
+                        ? Collections.singletonList(read.read(reader))
+                        : Collections.<T>emptyList());
+        if (item.isEmpty()) {
+            close();
+            return false;
+        }
+        this.item = item;
+        return true;
+    }
+
+    @Override
+    public synchronized T next() {
+        if (!hasNext()) throw new NoSuchElementException();
+        T value = item.get(0);
+        item = null;
+        return value;
+    }
+
+    @Override
+    public void close() {
+        cleanable.clean();
+    }
+}
@@ -0,0 +1,12 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+/** A value that is not in the wire format of slime-ffi. */
+public final class WireException extends RuntimeException {
+    private static final long serialVersionUID = 1L;
+
+    public WireException(String message) {
+        super(message);
+    }
+}
@@ -0,0 +1,116 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.nio.charset.StandardCharsets;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.LinkedHashMap;
+import java.util.LinkedHashSet;
+import java.util.List;
+import java.util.Map;
+import java.util.Set;
+import java.util.function.Supplier;
+
+/** Reads values in the wire format, see {@link WireWriter}. */
+public final class WireReader {
+    private final byte[] buf;
+    private int pos = 0;
+
+    public WireReader(byte[] buf) {
+        this.buf = buf;
+    }
+
+    public byte i8() {
+        return buf[take(1)];
+    }
+
+    public short i16() {
+        return (short) number(2);
+    }
+
+    public int i32() {
+        return (int) number(4);
+    }
+
+    public long i64() {
+        return number(8);
+    }
+
+    public float f32() {
+        return Float.intBitsToFloat(i32());
+    }
+
+    public double f64() {
+        return Double.longBitsToDouble(i64());
+    }
+
+    public boolean bool() {
+        return i8() != 0;
+    }
+
+    public int len() {
+        int len = i32();
+        if (len < 0) throw new WireException("length " + Integer.toUnsignedString(len) + " is too large");
+        return len;
+    }
+
+    public byte[] bytes() {
+        int size = len();
+        int at = take(size);
+        return Arrays.copyOfRange(buf, at, at + size);
+    }
+
+    public String string() {
+        return new String(bytes(), StandardCharsets.UTF_8);
+    }
+
+    /** {@code Optional<T>}, {@code null} for none. */
+    public <T> T optional(Supplier<T> read) {
+        return bool() ? read.get() : null;
+    }
+
+    public <T> List<T> list(Supplier<T> read) {
+        int size = len();
+        // Every value takes at least one byte, so this bounds the allocation by the input.
+        List<T> values = new ArrayList<>(Math.min(size, buf.length - pos));
+        for (int i = 0; i < size; i++) values.add(read.get());
+        return values;
+    }
+
+    public <T> Set<T> set(Supplier<T> read) {
+        return new LinkedHashSet<>(list(read));
+    }
+
+    public <T> List<T> array(int size, Supplier<T> read) {
+        List<T> values = new ArrayList<>(size);
+        for (int i = 0; i < size; i++) values.add(read.get());
+        return values;
+    }
+
+    public <K, V> Map<K, V> map(Supplier<K> key, Supplier<V> value) {
+        int size = len();
+        Map<K, V> values = new LinkedHashMap<>();
+        for (int i = 0; i < size; i++) values.put(key.get(), value.get());
+        return values;
+    }
+
+    /** Fails if bytes are left after the value. */
+    public void finish() {
+        if (pos != buf.length) throw new WireException("unexpected bytes after the value");
+    }
+
+    private long number(int size) {
+        int at = take(size);
+        long value = 0;
+        for (int i = 0; i < size; i++) value |= (buf[at + i] & 0xffL) << (8 * i);
+        return value;
+    }
+
+    private int take(int size) {
+        if (buf.length - pos < size) throw new WireException("unexpected end of input");
+        int at = pos;
+        pos += size;
+        return at;
+    }
+}
@@ -0,0 +1,108 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi;
+
+import java.nio.charset.StandardCharsets;
+import java.util.Arrays;
+import java.util.Collection;
+import java.util.List;
+import java.util.Map;
+import java.util.function.BiConsumer;
+import java.util.function.Consumer;
+
+/**
+ * Writes values in the wire format: little-endian numbers, one byte booleans, strings and
+ * collections prefixed with their {@code u32} length.
+ */
+public final class WireWriter {
+    private byte[] buf = new byte[64];
+    private int len = 0;
+
+    public void i8(byte value) {
+        reserve(1);
+        buf[len++] = value;
+    }
+
+    public void i16(short value) {
+        number(value, 2);
+    }
+
+    public void i32(int value) {
+        number(value, 4);
+    }
+
+    public void i64(long value) {
+        number(value, 8);
+    }
+
+    public void f32(float value) {
+        i32(Float.floatToRawIntBits(value));
+    }
+
+    public void f64(double value) {
+        i64(Double.doubleToRawLongBits(value));
+    }
+
+    public void bool(boolean value) {
+        i8((byte) (value ? 1 : 0));
+    }
+
+    public void len(int value) {
+        i32(value);
+    }
+
+    public void bytes(byte[] value) {
+        len(value.length);
+        reserve(value.length);
+        System.arraycopy(value, 0, buf, len, value.length);
+        len += value.length;
+    }
+
+    public void string(String value) {
+        bytes(value.getBytes(StandardCharsets.UTF_8));
+    }
+
+    /** {@code Optional<T>}, {@code null} for none. */
+    public <T> void optional(T value, Consumer<T> write) {
+        bool(value != null);
+        if (value != null) write.accept(value);
+    }
+
+    /** {@code List} and {@code Set}. */
+    public <T> void collection(Collection<T> value, Consumer<T> write) {
+        len(value.size());
+        value.forEach(write);
+    }
+
+    /** {@code Array<T, N>}, the elements without a length. */
+    public <T> void array(List<T> value, int size, Consumer<T> write) {
+        if (value.size() != size) {
+            throw new WireException("expected " + size + " elements, got " + value.size());
+        }
+        value.forEach(write);
+    }
+
+    public <K, V> void map(Map<K, V> value, BiConsumer<K, V> write) {
+        len(value.size());
+        value.forEach(write);
+    }
+
+    public byte[] toByteArray() {
+        return Arrays.copyOf(buf, len);
+    }
+
+    public static byte[] encode(Consumer<WireWriter> write) {
+        WireWriter writer = new WireWriter();
+        write.accept(writer);
+        return writer.toByteArray();
+    }
+
+    private void number(long value, int size) {
+        reserve(size);
+        for (int i = 0; i < size; i++) buf[len++] = (byte) (value >>> (8 * i));
+    }
+
+    private void reserve(int size) {
+        if (len + size > buf.length) buf = Arrays.copyOf(buf, Math.max(buf.length * 2, len + size));
+    }
+}
@@ -0,0 +1,2032 @@
+//! Kotlin bindings over the JNI functions of [`crate::abi`], for a library built with its `jvm`
+//! feature and the Rust side generated with the same [`JvmConfig`].
+//!
+//! Every IDL module and `mod` becomes a file in a package below the one of the config, next to
+//! `SlimeNative.kt` with the `external` declarations. Models become data classes, payload enums
+//! sealed classes, unit enums enum classes and classes `AutoCloseable` wrappers of their handle.
+//! Interfaces become Kotlin interfaces, with an adapter handing implementations of the app to
+//! Rust. Async functions are `suspend` functions and streams are `Flow`s, which takes
+//! `kotlinx-coroutines-core`. The support code the runtime calls into is generated to
+//! `com.slime.ffi`.
+//!
+//! Type mappings of the Kotlin config win over those of the JVM one. Their `{}` is the wire
+//! value, a mapped newtype is not wrapped in its value class.
+//!
+//! Generic classes and functions are not exported and left out, generic methods of interfaces
+//! cannot be called through Rust. Tuples of up to three elements are `Pair` and `Triple`.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use slime_ffi_ir::ast::{
+    BuiltinType, ClassDecl, ClassMember, ConstDecl, ConstLit, Decl, DeclKind, DeclRef, EnumDecl,
+    EnumVariantFields, Expr, FieldDecl, FnDecl, Function, Ident, InterfaceDecl, KeywordType, Lit,
+    ModelDecl, NumLit, Pat, QualifiedName, RefType, Type, TypeKind, TypeParam, TypeWithArgs,
+};
+use slime_ffi_ir::config::{JvmConfig, KotlinConfig, KotlinUnsignedStrategy, TypeMapping};
+use slime_ffi_ir::resolved::ResolvedIr;
+use slime_ffi_ir::Lang;
+
+use crate::abi::{
+    check_host_method, constructor_function, constructors, has_getter, has_setter,
+    interface_members, own_interface_members, Abi, AbiType, Export, Member, Target, JNI_CLASS,
+};
+use crate::lang::SourceFile;
+use crate::names::{camel_case, pascal_case};
+use crate::writer::CodeWriter;
+use crate::{Codegen, CodegenError};
+
+const INDENT: &str = "    ";
+
+/// Hard keywords of Kotlin, which are names only in backticks.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+/// Names the generated bodies use, parameters and fields of the same name get an underscore.
+const LOCALS: &[&str] = &["reader", "writer", "error", "handle"];
+
+/// The package of the support code, fixed by the runtime.
+const SUPPORT_PACKAGE: &str = "com.slime.ffi";
+
+const SUPPORT: &[(&str, &str)] = &[
+    ("Callback.kt", include_str!("kotlin/Callback.kt")),
+    ("Futures.kt", include_str!("kotlin/Futures.kt")),
+    ("Outcome.kt", include_str!("kotlin/Outcome.kt")),
+    ("Wire.kt", include_str!("kotlin/Wire.kt")),
+];
+
+const IMPORTS: &str = "\
+import com.slime.ffi.Callback
+import com.slime.ffi.Futures
+import com.slime.ffi.Handle
+import com.slime.ffi.Outcome
+import com.slime.ffi.WireException
+import com.slime.ffi.WireReader
+import com.slime.ffi.WireWriter
+import java.util.concurrent.atomic.AtomicLong
+import kotlinx.coroutines.flow.Flow";
+
+/// `in` as `` `in` ``.
+fn ident(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("`{}`", name),
+        false => name.to_string(),
+    }
+}
+
+/// A function or property name.
+fn member(name: &str) -> String {
+    ident(&camel_case(name))
+}
+
+/// A parameter or field name, clear of [`LOCALS`].
+fn local(name: &str) -> String {
+    let name = camel_case(name);
+    match LOCALS.contains(&name.as_str()) {
+        true => format!("{}_", name),
+        false => ident(&name),
+    }
+}
+
+/// The `WireWriter` and `WireReader` functions of a keyword type.
+fn wire_fn(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 | KeywordType::Uint8 => "i8",
+        KeywordType::Int16 | KeywordType::Uint16 => "i16",
+        KeywordType::Int32 | KeywordType::Uint32 => "i32",
+        KeywordType::Int64 | KeywordType::Uint64 => "i64",
+        KeywordType::Float32 => "f32",
+        KeywordType::Float64 => "f64",
+        KeywordType::Bool => "bool",
+        KeywordType::String => "string",
+        KeywordType::Bytes => "bytes",
+    }
+}
+
+/// The type a scalar is passed to JNI as, Java has no unsigned integers.
+fn jni_ty(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 | KeywordType::Uint8 => "Byte",
+        KeywordType::Int16 | KeywordType::Uint16 => "Short",
+        KeywordType::Int32 | KeywordType::Uint32 => "Int",
+        KeywordType::Int64 | KeywordType::Uint64 => "Long",
+        KeywordType::Float32 => "Float",
+        KeywordType::Float64 => "Double",
+        KeywordType::Bool => "Boolean",
+        KeywordType::String | KeywordType::Bytes => "ByteArray",
+    }
+}
+
+fn is_unsigned(keyword: KeywordType) -> bool {
+    matches!(
+        keyword,
+        KeywordType::Uint8 | KeywordType::Uint16 | KeywordType::Uint32 | KeywordType::Uint64
+    )
+}
+
+fn float(value: f64, ty: &str, suffix: &str) -> String {
+    match value {
+        value if value.is_nan() => format!("{}.NaN", ty),
+        value if value == f64::INFINITY => format!("{}.POSITIVE_INFINITY", ty),
+        value if value == f64::NEG_INFINITY => format!("{}.NEGATIVE_INFINITY", ty),
+        value => format!("{:?}{}", value, suffix),
+    }
+}
+
+fn int(value: i32) -> String {
+    match value {
+        i32::MIN => "Int.MIN_VALUE".to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn long(value: i64) -> String {
+    match value {
+        i64::MIN => "Long.MIN_VALUE".to_string(),
+        value => format!("{}L", value),
+    }
+}
+
+/// A number literal, unsigned ones as Kotlin unsigned literals or as the signed value of the
+/// same bits.
+fn num(value: &NumLit, unsigned: bool) -> String {
+    match value {
+        NumLit::I8(value) => value.to_string(),
+        NumLit::I16(value) => value.to_string(),
+        NumLit::I32(value) => int(*value),
+        NumLit::I64(value) => long(*value),
+        NumLit::U8(value) if unsigned => format!("{}u", value),
+        NumLit::U16(value) if unsigned => format!("{}u", value),
+        NumLit::U32(value) if unsigned => format!("{}u", value),
+        NumLit::U64(value) if unsigned => format!("{}uL", value),
+        NumLit::U8(value) => (*value as i8).to_string(),
+        NumLit::U16(value) => (*value as i16).to_string(),
+        NumLit::U32(value) => int(*value as i32),
+        NumLit::U64(value) => long(*value as i64),
+        NumLit::F32(value) => float(*value as f64, "Float", "f"),
+        NumLit::F64(value) => float(*value, "Double", ""),
+    }
+}
+
+/// A discriminant as the `Long` of its bits, to match a signed read against.
+fn discriminant(value: &NumLit) -> String {
+    let value = match value {
+        NumLit::I8(value) => *value as i64,
+        NumLit::I16(value) => *value as i64,
+        NumLit::I32(value) => *value as i64,
+        NumLit::I64(value) => *value,
+        NumLit::U8(value) => *value as i8 as i64,
+        NumLit::U16(value) => *value as i16 as i64,
+        NumLit::U32(value) => *value as i32 as i64,
+        NumLit::U64(value) => *value as i64,
+        NumLit::F32(_) | NumLit::F64(_) => 0,
+    };
+    long(value)
+}
+
+/// A string literal, with `$` escaped from templates.
+fn string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '$' => out.push_str("\\$"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `head(` with an argument of one or more lines per entry, then `)`.
+fn call_lines(head: &str, args: Vec<Vec<String>>) -> Vec<String> {
+    let mut lines = vec![format!("{}(", head)];
+    for arg in args {
+        let last = arg.len() - 1;
+        for (index, line) in arg.into_iter().enumerate() {
+            let comma = if index == last { "," } else { "" };
+            lines.push(format!("{}{}{}", INDENT, line, comma));
+        }
+    }
+    lines.push(")".to_string());
+    lines
+}
+
+/// Whether a property of Kotlin type `ty` is an array, compared by identity in data classes.
+fn is_bytes(ty: &str) -> bool {
+    ty.trim_end_matches('?') == "ByteArray"
+}
+
+/// `equals` and `hashCode` of the data class `name`, comparing `ByteArray` properties by content.
+/// Nothing is written, and `false` returned, if there are none.
+fn content_equality(out: &mut CodeWriter, name: &str, properties: &[(String, String)]) -> bool {
+    if !properties.iter().any(|(_, ty)| is_bytes(ty)) {
+        return false;
+    }
+    out.open("override fun equals(other: Any?): Boolean {");
+    out.line("if (this === other) return true");
+    out.line(format!("if (other !is {}) return false", name));
+    for (property, ty) in properties {
+        match is_bytes(ty) {
+            true => out.line(format!(
+                "if (!{}.contentEquals(other.{})) return false",
+                property, property
+            )),
+            false => out.line(format!(
+                "if ({} != other.{}) return false",
+                property, property
+            )),
+        }
+    }
+    out.line("return true");
+    out.close("}");
+    out.blank();
+    out.open("override fun hashCode(): Int {");
+    out.line("var result = 0");
+    for (property, ty) in properties {
+        let hash = match is_bytes(ty) {
+            true => "contentHashCode",
+            false => "hashCode",
+        };
+        out.line(format!("result = 31 * result + {}.{}()", property, hash));
+    }
+    out.line("return result");
+    out.close("}");
+    true
+}
+
+pub struct KotlinCodegen<'ir> {
+    pub ir: &'ir ResolvedIr,
+    /// The package of the bindings, the one the Rust side was generated with.
+    pub jvm: &'ir JvmConfig,
+    pub config: &'ir KotlinConfig,
+}
+
+impl<'ir> KotlinCodegen<'ir> {
+    pub fn new(ir: &'ir ResolvedIr, jvm: &'ir JvmConfig, config: &'ir KotlinConfig) -> Self {
+        KotlinCodegen { ir, jvm, config }
+    }
+
+    /// The package of the IDL module or `mod` at `path`.
+    fn package(&self, path: &[Ident]) -> String {
+        let mut segments = vec![self.jvm.package_name.clone()];
+        segments.extend(path.iter().map(|segment| ident(segment.as_str())));
+        segments.join(".")
+    }
+
+    fn dir(package: &str) -> PathBuf {
+        package
+            .split('.')
+            .map(|segment| segment.trim_matches('`'))
+            .collect()
+    }
+
+    /// The mapping of the Kotlin config for `decl`, or else the one of the JVM config.
+    fn type_mapping(&self, decl: &DeclRef) -> Option<&'ir TypeMapping> {
+        self.config
+            .type_mappings
+            .iter()
+            .chain(&self.jvm.type_mappings)
+            .find(|mapping| mapping.matches(decl))
+    }
+
+    fn unit(
+        &self,
+        abi: &Abi,
+        path: &[Ident],
+        decls: &[&Decl],
+        files: &mut Vec<SourceFile>,
+    ) -> Result<(), CodegenError> {
+        for decl in decls {
+            if let Decl::Mod(decl) = decl {
+                let mut path = path.to_vec();
+                path.push(decl.name.clone());
+                let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                self.unit(abi, &path, &decls, files)?;
+            }
+        }
+        if decls
+            .iter()
+            .all(|decl| matches!(decl, Decl::Mod(_) | Decl::Use(_)))
+        {
+            return Ok(());
+        }
+        let package = self.package(path);
+        let generator = Generator {
+            abi,
+            codegen: self,
+            package: package.clone(),
+            path: path.to_vec(),
+        };
+        let mut out = CodeWriter::new(INDENT);
+        generator.unit(&mut out, decls)?;
+        let name = path.last().map(Ident::as_str).unwrap_or_default();
+        files.push(SourceFile {
+            path: Self::dir(&package).join(format!("{}.kt", pascal_case(name))),
+            source: out.finish(),
+        });
+        Ok(())
+    }
+
+    /// `SlimeNative` with an `external` function per export.
+    fn native(&self, abi: &Abi, exports: &[Export]) -> Result<String, CodegenError> {
+        let mut out = CodeWriter::new(INDENT);
+        out.line("// Generated by slime-ffi, do not edit.");
+        out.blank();
+        out.line(format!("package {}", self.jvm.package_name));
+        out.blank();
+        out.line(
+            "/** The JNI functions of the library, load it with `System.loadLibrary` first. */",
+        );
+        out.open(format!("internal object {} {{", JNI_CLASS));
+        let external = |out: &mut CodeWriter, name: &str, params: &[String], ret: Option<&str>| {
+            out.blank();
+            out.line("@JvmStatic");
+            let ret = ret.map(|ret| format!(": {}", ret)).unwrap_or_default();
+            out.line(format!(
+                "external fun {}({}){}",
+                name,
+                params.join(", "),
+                ret
+            ));
+        };
+        external(&mut out, "retain", &["obj: Any".to_string()], Some("Long"));
+        let handle = ["handle: Long".to_string()];
+        let poll = ["handle: Long".to_string(), "data: Long".to_string()];
+        for export in exports {
+            let method = export.jni_method();
+            if let Target::Register(_) = export.target {
+                let params = ["adapter: Class<*>".to_string()];
+                external(&mut out, method, &params, Some("Boolean"));
+                continue;
+            }
+            let mut params = vec![];
+            if export.receiver().is_some() {
+                params.push("handle: Long".to_string());
+            }
+            for param in &export.function.params {
+                let ty = match abi.jni_type(&param.ty) {
+                    AbiType::Scalar(keyword) | AbiType::Enum(keyword) => jni_ty(keyword),
+                    AbiType::Handle => "Long",
+                    AbiType::Struct(_) | AbiType::Stream | AbiType::Buffer => "ByteArray",
+                };
+                params.push(format!("{}: {}", local(param.name.as_str()), ty));
+            }
+            external(&mut out, method, &params, Some("ByteArray"));
+            let mut extras = vec![];
+            if export.function.is_async {
+                extras.push("_");
+            }
+            if export.returns_stream() {
+                extras.push("_stream_");
+            }
+            for prefix in extras {
+                let name = |action: &str| format!("{}{}{}", method, prefix, action);
+                external(&mut out, &name("poll"), &poll, None);
+                let next = match prefix {
+                    "_" => "complete",
+                    _ => "next",
+                };
+                external(&mut out, &name(next), &handle, Some("ByteArray"));
+                external(&mut out, &name("cancel"), &handle, None);
+                external(&mut out, &name("free"), &handle, None);
+            }
+        }
+        out.close("}");
+        Ok(out.finish())
+    }
+}
+
+impl Codegen for KotlinCodegen<'_> {
+    const LANG: Lang = Lang::Kotlin;
+
+    type Output = Vec<SourceFile>;
+
+    fn generate(&self) -> Result<Self::Output, CodegenError> {
+        let abi = Abi::new(self.ir);
+        let mut files: Vec<SourceFile> = SUPPORT
+            .iter()
+            .map(|(name, source)| SourceFile {
+                path: Self::dir(SUPPORT_PACKAGE).join(name),
+                source: source.to_string(),
+            })
+            .collect();
+        let mut exports = vec![];
+        for module in &self.ir.modules {
+            exports.extend(abi.exports(module)?);
+        }
+        files.push(SourceFile {
+            path: Self::dir(&self.jvm.package_name).join(format!("{}.kt", JNI_CLASS)),
+            source: self.native(&abi, &exports)?,
+        });
+        for module in &self.ir.modules {
+            let decls: Vec<&Decl> = module.decls.iter().collect();
+            self.unit(&abi, &[Ident::from(&module.name)], &decls, &mut files)?;
+        }
+        Ok(files)
+    }
+}
+
+struct Generator<'a, 'ir> {
+    abi: &'a Abi<'ir>,
+    codegen: &'a KotlinCodegen<'ir>,
+    /// The package being generated, to refer to its declarations by their name.
+    package: String,
+    /// The IDL module and `mod`s of the package.
+    path: Vec<Ident>,
+}
+
+impl<'a, 'ir> Generator<'a, 'ir> {
+    fn get(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.abi.ir.get(decl)
+    }
+
+    fn decl_ref(&self, kind: DeclKind, name: &Ident) -> DeclRef {
+        DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(self.path.clone()),
+                name: name.clone(),
+            },
+        }
+    }
+
+    fn unsigned(&self) -> bool {
+        matches!(
+            self.codegen.config.unsigned_strategy,
+            KotlinUnsignedStrategy::KotlinSigned
+        )
+    }
+
+    fn keyword_ty(&self, keyword: KeywordType) -> &'static str {
+        match keyword {
+            KeywordType::Uint8 if self.unsigned() => "UByte",
+            KeywordType::Uint16 if self.unsigned() => "UShort",
+            KeywordType::Uint32 if self.unsigned() => "UInt",
+            KeywordType::Uint64 if self.unsigned() => "ULong",
+            KeywordType::Bytes => "ByteArray",
+            KeywordType::String => "String",
+            keyword => jni_ty(keyword),
+        }
+    }
+
+    /// A Kotlin scalar as the JNI one.
+    fn lower_jni(&self, keyword: KeywordType, value: &str) -> String {
+        match is_unsigned(keyword) && self.unsigned() {
+            true => format!("{}.to{}()", value, jni_ty(keyword)),
+            false => value.to_string(),
+        }
+    }
+
+    /// A JNI scalar as the Kotlin one.
+    fn lift_jni(&self, keyword: KeywordType, value: &str) -> String {
+        match is_unsigned(keyword) && self.unsigned() {
+            true => format!("{}.to{}()", value, self.keyword_ty(keyword)),
+            false => value.to_string(),
+        }
+    }
+
+    /// `decl` with `suffix`, by its name in the package being generated.
+    fn name(&self, decl: &DeclRef, suffix: &str) -> String {
+        let name = ident(&format!("{}{}", decl.name.name.as_str(), suffix));
+        let package = self.codegen.package(decl.name.path.segments());
+        match package == self.package {
+            true => name,
+            false => format!("{}.{}", package, name),
+        }
+    }
+
+    /// The first declaration along the aliases of `ty` with a type mapping.
+    fn mapping<'t>(&self, mut ty: &'t Type) -> Option<(&'t DeclRef, &'ir TypeMapping)>
+    where
+        'ir: 't,
+    {
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            if let Some(mapping) = self.codegen.type_mapping(target) {
+                return Some((target, mapping));
+            }
+            match self.get(target) {
+                Some(Decl::Alias(alias)) if !alias.distinct => ty = &alias.ty,
+                _ => break,
+            }
+        }
+        None
+    }
+
+    /// The wrapped type of a mapped newtype, whose value class the host type replaces.
+    fn unwrapped(&self, target: &DeclRef) -> Option<&'ir Type> {
+        match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => Some(&alias.ty),
+            _ => None,
+        }
+    }
+
+    fn ty(&self, ty: &Type) -> Result<String, CodegenError> {
+        if let Some((_, mapping)) = self.mapping(ty) {
+            return Ok(mapping.host_type.clone());
+        }
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => self.keyword_ty(*keyword).to_string(),
+            TypeKind::RefType(RefType::TypeParam(name)) => ident(name.as_str()),
+            TypeKind::RefType(RefType::Resolved(target)) => self.decl_ty(target),
+            TypeKind::RefType(_) => {
+                return Err(CodegenError::custom(
+                    "types are resolved by semantic analysis",
+                ))
+            }
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => {
+                    // Kotlin has one level of null, `Optional<Optional<T>>` is `T?` as well.
+                    let ty = self.ty(ty)?;
+                    match ty.ends_with('?') {
+                        true => ty,
+                        false => format!("{}?", ty),
+                    }
+                }
+                BuiltinType::List(ty) => format!("List<{}>", self.ty(ty)?),
+                BuiltinType::Set(ty) => format!("Set<{}>", self.ty(ty)?),
+                BuiltinType::Map(map) => {
+                    format!("Map<{}, {}>", self.ty(&map.key)?, self.ty(&map.value)?)
+                }
+                BuiltinType::Tuple(types) => {
+                    let types = types
+                        .iter()
+                        .map(|ty| self.ty(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("{}<{}>", tuple(types.len())?, types.join(", "))
+                }
+                BuiltinType::Array(array) => format!("List<{}>", self.ty(&array.element)?),
+                BuiltinType::Stream(ty) => format!("Flow<{}>", self.ty(ty)?),
+            },
+        })
+    }
+
+    /// A declared type. Generic interfaces are referred to with their own type parameters, the
+    /// only ones in scope where the IDL can name them.
+    fn decl_ty(&self, target: &DeclRef) -> String {
+        let name = self.name(target, "");
+        let params = match self.get(target) {
+            Some(Decl::Class(decl)) => decl.type_params.as_deref(),
+            Some(Decl::Interface(decl)) => decl.type_params.as_deref(),
+            _ => None,
+        };
+        match params {
+            Some(params) if !params.is_empty() => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|param| ident(param.name.as_str()))
+                    .collect();
+                format!("{}<{}>", name, params.join(", "))
+            }
+            _ => name,
+        }
+    }
+
+    /// `Base<Int>` of `extends` and `implements`.
+    fn with_args(&self, with: &TypeWithArgs) -> Result<String, CodegenError> {
+        let target = with
+            .target
+            .as_ref()
+            .ok_or_else(|| CodegenError::custom("types are resolved by semantic analysis"))?;
+        let name = self.name(target, "");
+        Ok(match with.type_args.as_deref() {
+            Some(args) if !args.is_empty() => {
+                let args = args
+                    .iter()
+                    .map(|ty| self.ty(ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("{}<{}>", name, args.join(", "))
+            }
+            _ => name,
+        })
+    }
+
+    /// `<T : Listener, U> `.
+    fn type_params(&self, params: &[TypeParam]) -> Result<String, CodegenError> {
+        if params.is_empty() {
+            return Ok(String::new());
+        }
+        let params = params
+            .iter()
+            .map(|param| {
+                let name = ident(param.name.as_str());
+                Ok(match &param.constraint {
+                    Some(ty) => format!("{} : {}", name, self.ty(ty)?),
+                    None => name,
+                })
+            })
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+        Ok(format!("<{}>", params.join(", ")))
+    }
+
+    /// A default value or const of type `ty`.
+    fn value(&self, ty: &Type, value: &Expr) -> Result<String, CodegenError> {
+        let constant = match value {
+            Expr::Null => return Ok("null".to_string()),
+            Expr::Const(constant) => constant,
+            _ => {
+                return Err(CodegenError::custom(
+                    "values are resolved by semantic analysis",
+                ))
+            }
+        };
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = match self.unwrapped(target) {
+                Some(inner) => self.value(inner, value)?,
+                None => self.decl_value(target, value, constant)?,
+            };
+            return Ok(mapping.lift(&value));
+        }
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => self.value(inner, value),
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                self.decl_value(target, value, constant)
+            }
+            _ => self.literal(constant),
+        }
+    }
+
+    /// A value of a declared type, below its type mapping.
+    fn decl_value(
+        &self,
+        target: &DeclRef,
+        value: &Expr,
+        constant: &ConstLit,
+    ) -> Result<String, CodegenError> {
+        match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => Ok(format!(
+                "{}({})",
+                self.name(target, ""),
+                self.value(&alias.ty, value)?
+            )),
+            Some(Decl::Alias(alias)) => self.value(&alias.ty, value),
+            _ => self.literal(constant),
+        }
+    }
+
+    fn literal(&self, constant: &ConstLit) -> Result<String, CodegenError> {
+        Ok(match constant {
+            ConstLit::Primitive(Lit::Str(value)) => string(value),
+            ConstLit::Primitive(Lit::Bool(value)) => value.to_string(),
+            ConstLit::Primitive(Lit::Num(value)) => num(value, self.unsigned()),
+            ConstLit::UnitEnumVariant(variant) => {
+                let (target, _) = self.abi.resolve(&variant.ty).ok_or_else(|| {
+                    CodegenError::custom("values are resolved by semantic analysis")
+                })?;
+                format!(
+                    "{}.{}",
+                    self.name(target, ""),
+                    ident(variant.variant_name.as_str())
+                )
+            }
+        })
+    }
+
+    /// A statement writing `value` of type `ty` to `writer`. Lambdas nested `depth` deep
+    /// number their parameters by it.
+    fn write(&self, ty: &Type, value: &str, depth: usize) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = mapping.lower(value);
+            return match self.unwrapped(target) {
+                Some(inner) => self.write(inner, &value, depth),
+                None => self.write_decl(target, &value, depth),
+            };
+        }
+        let var = |name: &str| format!("{}{}", name, depth);
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => {
+                format!(
+                    "writer.{}({})",
+                    wire_fn(*keyword),
+                    self.lower_jni(*keyword, value)
+                )
+            }
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                self.write_decl(target, value, depth)?
+            }
+            TypeKind::RefType(_) => return Err(generic()),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(inner) => format!(
+                    "writer.optional({}) {{ {} -> {} }}",
+                    value,
+                    var("e"),
+                    self.write(inner, &var("e"), depth + 1)?
+                ),
+                BuiltinType::List(inner) | BuiltinType::Set(inner) => format!(
+                    "writer.collection({}) {{ {} -> {} }}",
+                    value,
+                    var("e"),
+                    self.write(inner, &var("e"), depth + 1)?
+                ),
+                BuiltinType::Array(array) => format!(
+                    "writer.array({}, {}) {{ {} -> {} }}",
+                    value,
+                    array.len,
+                    var("e"),
+                    self.write(&array.element, &var("e"), depth + 1)?
+                ),
+                BuiltinType::Map(map) => format!(
+                    "writer.map({}) {{ {}, {} -> {}; {} }}",
+                    value,
+                    var("k"),
+                    var("v"),
+                    self.write(&map.key, &var("k"), depth + 1)?,
+                    self.write(&map.value, &var("v"), depth + 1)?
+                ),
+                BuiltinType::Tuple(types) => {
+                    tuple(types.len())?;
+                    let writes = types
+                        .iter()
+                        .zip(["first", "second", "third"])
+                        .map(|(ty, field)| {
+                            self.write(ty, &format!("{}.{}", var("t"), field), depth + 1)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("{}.let {{ {} -> {} }}", value, var("t"), writes.join("; "))
+                }
+                BuiltinType::Stream(_) => {
+                    return Err(CodegenError::custom("streams are only returned"))
+                }
+            },
+        })
+    }
+
+    fn write_decl(
+        &self,
+        target: &DeclRef,
+        value: &str,
+        depth: usize,
+    ) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                self.write(&alias.ty, &format!("{}.value", value), depth)?
+            }
+            Some(Decl::Alias(alias)) => self.write(&alias.ty, value, depth)?,
+            Some(Decl::Enum(decl)) if decl.is_primitive() => {
+                let repr = decl.repr_type();
+                let value = format!("{}.value", value);
+                format!("writer.{}({})", wire_fn(repr), self.lower_jni(repr, &value))
+            }
+            Some(Decl::Model(_) | Decl::Enum(_)) => format!("{}.write(writer)", value),
+            Some(Decl::Class(_)) => format!("writer.i64({}.handle)", value),
+            Some(Decl::Interface(_)) => {
+                format!(
+                    "writer.i64({}.handle({}))",
+                    self.name(target, "Callback"),
+                    value
+                )
+            }
+            _ => return Err(unknown(target)),
+        })
+    }
+
+    /// An expression reading a value of type `ty` from `reader`.
+    fn read(&self, ty: &Type) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = match self.unwrapped(target) {
+                Some(inner) => self.read(inner)?,
+                None => self.read_decl(target)?,
+            };
+            return Ok(mapping.lift(&value));
+        }
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => {
+                self.lift_jni(*keyword, &format!("reader.{}()", wire_fn(*keyword)))
+            }
+            TypeKind::RefType(RefType::Resolved(target)) => self.read_decl(target)?,
+            TypeKind::RefType(_) => return Err(generic()),
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(inner) => {
+                    format!("reader.optional {{ {} }}", self.read(inner)?)
+                }
+                BuiltinType::List(inner) => format!("reader.list {{ {} }}", self.read(inner)?),
+                BuiltinType::Set(inner) => format!("reader.set {{ {} }}", self.read(inner)?),
+                BuiltinType::Array(array) => format!(
+                    "reader.array({}) {{ {} }}",
+                    array.len,
+                    self.read(&array.element)?
+                ),
+                BuiltinType::Map(map) => format!(
+                    "reader.map({{ {} }}, {{ {} }})",
+                    self.read(&map.key)?,
+                    self.read(&map.value)?
+                ),
+                BuiltinType::Tuple(types) => {
+                    let reads = types
+                        .iter()
+                        .map(|ty| self.read(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("{}({})", tuple(types.len())?, reads.join(", "))
+                }
+                BuiltinType::Stream(_) => {
+                    return Err(CodegenError::custom("streams are only returned"))
+                }
+            },
+        })
+    }
+
+    fn read_decl(&self, target: &DeclRef) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                format!("{}({})", self.name(target, ""), self.read(&alias.ty)?)
+            }
+            Some(Decl::Alias(alias)) => self.read(&alias.ty)?,
+            Some(Decl::Enum(decl)) if decl.is_primitive() => {
+                let repr = decl.repr_type();
+                let value = self.lift_jni(repr, &format!("reader.{}()", wire_fn(repr)));
+                format!("{}.fromValue({})", self.name(target, ""), value)
+            }
+            Some(Decl::Model(_) | Decl::Enum(_)) => {
+                format!("{}.read(reader)", self.name(target, ""))
+            }
+            Some(Decl::Class(_)) => format!("{}(Handle(reader.i64()))", self.name(target, "")),
+            Some(Decl::Interface(_)) => {
+                format!("{}(Handle(reader.i64()))", self.name(target, "Object"))
+            }
+            _ => return Err(unknown(target)),
+        })
+    }
+
+    /// `{ reader -> ... }` reading the value of `ty`, `{}` for none.
+    fn read_lambda(&self, ty: Option<&Type>) -> Result<String, CodegenError> {
+        Ok(match ty {
+            Some(ty) => format!("{{ reader -> {} }}", self.read(ty)?),
+            None => "{}".to_string(),
+        })
+    }
+
+    /// The value of a scalar, unit enum or object type below its mapping and newtypes: the
+    /// Kotlin scalar, enum value or handle.
+    fn peel(&self, ty: &Type, value: &str) -> Result<String, CodegenError> {
+        if let Some((target, mapping)) = self.mapping(ty) {
+            let value = mapping.lower(value);
+            return match self.unwrapped(target) {
+                Some(inner) => self.peel(inner, &value),
+                None => self.peel_decl(target, &value),
+            };
+        }
+        match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => self.peel_decl(target, value),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    fn peel_decl(&self, target: &DeclRef, value: &str) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Alias(alias)) if alias.distinct => {
+                self.peel(&alias.ty, &format!("{}.value", value))?
+            }
+            Some(Decl::Alias(alias)) => self.peel(&alias.ty, value)?,
+            Some(Decl::Enum(_)) => format!("{}.value", value),
+            Some(Decl::Class(_)) => format!("{}.handle", value),
+            Some(Decl::Interface(_)) => {
+                format!("{}.handle({})", self.name(target, "Callback"), value)
+            }
+            _ => value.to_string(),
+        })
+    }
+
+    /// An argument of a JNI function.
+    fn arg(&self, ty: &Type, value: &str) -> Result<String, CodegenError> {
+        Ok(match self.abi.jni_type(ty) {
+            AbiType::Scalar(keyword) | AbiType::Enum(keyword) => {
+                self.lower_jni(keyword, &self.peel(ty, value)?)
+            }
+            AbiType::Handle => self.peel(ty, value)?,
+            AbiType::Struct(_) | AbiType::Stream | AbiType::Buffer => format!(
+                "WireWriter.encode {{ writer -> {} }}",
+                self.write(ty, value, 0)?
+            ),
+        })
+    }
+
+    /// The exception thrown for the error of `function` and the enum it holds.
+    fn exception<'t>(
+        &self,
+        function: &'t Function,
+    ) -> Result<Option<(String, &'t Type)>, CodegenError> {
+        let Some(error) = function.error_type.as_deref() else {
+            return Ok(None);
+        };
+        match self.abi.resolve(error) {
+            Some((target, Decl::Enum(_))) => Ok(Some((self.name(target, "Exception"), error))),
+            _ => Err(CodegenError::custom("functions throw enums")),
+        }
+    }
+
+    /// `{ reader -> IoErrorException.of(...) }` reading the error of `function`.
+    fn thrown(&self, function: &Function) -> Result<Option<String>, CodegenError> {
+        Ok(match self.exception(function)? {
+            Some((exception, error)) => Some(format!(
+                "{{ reader -> {}.of({}) }}",
+                exception,
+                self.read(error)?
+            )),
+            None => None,
+        })
+    }
+
+    /// `SlimeNative.method(args)`, the receiver `handle` first.
+    fn native_call(
+        &self,
+        method: &str,
+        function: &FnDecl,
+        receiver: bool,
+    ) -> Result<String, CodegenError> {
+        let mut args = vec![];
+        if receiver {
+            args.push("handle".to_string());
+        }
+        for param in &function.params {
+            args.push(self.arg(&param.ty, &local(param.name.as_str()))?);
+        }
+        Ok(format!("{}.{}({})", JNI_CLASS, method, args.join(", ")))
+    }
+
+    /// The lines of the expression calling `method` of `SlimeNative` for `function`.
+    fn call(
+        &self,
+        method: &str,
+        function: &FnDecl,
+        receiver: bool,
+    ) -> Result<Vec<String>, CodegenError> {
+        let native = self.native_call(method, function, receiver)?;
+        let thrown = self.thrown(function)?;
+        let extras = |prefix: &str, next: &str| {
+            let function = |action: &str| format!("{}.{}{}{}", JNI_CLASS, method, prefix, action);
+            vec![
+                vec![format!(
+                    "{{ handle, data -> {}(handle, data) }}",
+                    function("poll")
+                )],
+                vec![format!("{{ {}(it) }}", function(next))],
+                vec![format!("{{ {}(it) }}", function("cancel"))],
+                vec![format!("{{ {}(it) }}", function("free"))],
+            ]
+        };
+        let unwrap = |bytes: String, read: String| match &thrown {
+            Some(thrown) => format!("Outcome.unwrap({}, {}, {})", bytes, read, thrown),
+            None => format!("Outcome.unwrap({}, {})", bytes, read),
+        };
+        let stream = match function.return_type.as_deref().map(|ty| &ty.kind) {
+            Some(TypeKind::Builtin(BuiltinType::Stream(item))) => Some(item.as_ref()),
+            _ => None,
+        };
+        let value = match stream {
+            Some(_) => "{ it.i64() }".to_string(),
+            None => self.read_lambda(function.return_type.as_deref())?,
+        };
+        let mut lines = match function.is_async {
+            true => {
+                let mut args = vec![vec![native.clone()]];
+                args.extend(extras("_", "complete"));
+                args.push(vec![value.clone()]);
+                args.extend(thrown.iter().map(|thrown| vec![thrown.clone()]));
+                call_lines("Futures.await", args)
+            }
+            false => vec![unwrap(native.clone(), value.clone())],
+        };
+        if let Some(item) = stream {
+            let start = match lines.len() {
+                1 => vec![format!("{{ {} }}", lines[0])],
+                _ => {
+                    let mut start = vec![format!("{{ {}", lines[0])];
+                    start.extend(lines[1..lines.len() - 1].iter().cloned());
+                    start.push(format!("{} }}", lines[lines.len() - 1]));
+                    start
+                }
+            };
+            let mut args = vec![start];
+            args.extend(extras("_stream_", "next"));
+            args.push(vec![self.read_lambda(Some(item))?]);
+            lines = call_lines("Futures.stream", args);
+        }
+        Ok(lines)
+    }
+
+    /// `suspend fun name(a: A): R`, `modifiers` like `override ` in front.
+    fn signature(
+        &self,
+        name: &str,
+        function: &FnDecl,
+        modifiers: &str,
+    ) -> Result<String, CodegenError> {
+        let stream = matches!(
+            function.return_type.as_deref().map(|ty| &ty.kind),
+            Some(TypeKind::Builtin(BuiltinType::Stream(_)))
+        );
+        let suspend = match function.is_async && !stream {
+            true => "suspend ",
+            false => "",
+        };
+        let type_params =
+            match self.type_params(function.type_params.as_deref().unwrap_or_default())? {
+                params if params.is_empty() => params,
+                params => format!("{} ", params),
+            };
+        let params = self.params(&function.params)?;
+        let ret = match &function.return_type {
+            Some(ty) => format!(": {}", self.ty(ty)?),
+            None => String::new(),
+        };
+        Ok(format!(
+            "{}{}fun {}{}({}){}",
+            modifiers,
+            suspend,
+            type_params,
+            name,
+            params.join(", "),
+            ret
+        ))
+    }
+
+    fn params(&self, params: &[FieldDecl]) -> Result<Vec<String>, CodegenError> {
+        params
+            .iter()
+            .map(|param| {
+                let default = match &param.default {
+                    Some(value) => format!(" = {}", self.value(&param.ty, value)?),
+                    None => String::new(),
+                };
+                Ok(format!(
+                    "{}: {}{}",
+                    local(param.name.as_str()),
+                    self.ty(&param.ty)?,
+                    default
+                ))
+            })
+            .collect()
+    }
+
+    /// `@Throws` for Java callers of a function with an error.
+    fn throws(&self, out: &mut CodeWriter, function: &Function) -> Result<(), CodegenError> {
+        if let Some((exception, _)) = self.exception(function)? {
+            out.line(format!("@Throws({}::class)", exception));
+        }
+        Ok(())
+    }
+
+    /// A function with the body `lines`, an expression unless it returns nothing.
+    fn function_body(
+        &self,
+        out: &mut CodeWriter,
+        signature: &str,
+        function: &FnDecl,
+        lines: &[String],
+    ) {
+        match function.return_type {
+            Some(_) => {
+                out.line(format!("{} = {}", signature, lines[0]));
+                lines[1..].iter().for_each(|line| out.line(line));
+            }
+            None => {
+                out.open(format!("{} {{", signature));
+                lines.iter().for_each(|line| out.line(line));
+                out.close("}");
+            }
+        }
+    }
+
+    fn unit(&self, out: &mut CodeWriter, decls: &[&Decl]) -> Result<(), CodegenError> {
+        let names: Vec<&str> = self.path.iter().map(Ident::as_str).collect();
+        out.line(format!(
+            "// Generated by slime-ffi from the `{}` module, do not edit.",
+            names.join(".")
+        ));
+        out.blank();
+        out.line(format!("package {}", self.package));
+        out.blank();
+        out.line(format!(
+            "import {}.{}",
+            self.codegen.jvm.package_name, JNI_CLASS
+        ));
+        out.lines(IMPORTS);
+        for decl in decls {
+            match decl {
+                Decl::Const(decl) => {
+                    out.blank();
+                    self.constant(out, decl)?
+                }
+                Decl::Alias(decl) => {
+                    out.blank();
+                    let name = ident(decl.name.as_str());
+                    match decl.distinct {
+                        true => {
+                            out.line("@JvmInline");
+                            out.line(format!(
+                                "value class {}(val value: {})",
+                                name,
+                                self.ty(&decl.ty)?
+                            ));
+                        }
+                        false => out.line(format!("typealias {} = {}", name, self.ty(&decl.ty)?)),
+                    }
+                }
+                Decl::Model(decl) => {
+                    out.blank();
+                    self.model(out, decl)?
+                }
+                Decl::Enum(enum_decl) => {
+                    out.blank();
+                    match enum_decl.is_primitive() {
+                        true => self.unit_enum(out, enum_decl)?,
+                        false => self.payload_enum(out, enum_decl)?,
+                    }
+                    if self
+                        .abi
+                        .is_thrown(&self.decl_ref(DeclKind::Enum, &enum_decl.name).name)
+                    {
+                        out.blank();
+                        self.exception_class(out, enum_decl);
+                    }
+                }
+                Decl::Interface(interface) => {
+                    let this = self.decl_ref(DeclKind::Interface, &interface.name);
+                    self.interface(out, interface, &this)?
+                }
+                Decl::Class(class) if class.type_params.is_none() => {
+                    out.blank();
+                    self.class(out, class, &self.decl_ref(DeclKind::Class, &class.name))?
+                }
+                Decl::Method(function) if self.abi.is_exported(function) => {
+                    out.blank();
+                    let method = format!("{}_{}", names.join("_"), function.name.as_str());
+                    self.throws(out, function)?;
+                    let signature =
+                        self.signature(&member(function.name.as_str()), function, "")?;
+                    let lines = self.call(&method, function, false)?;
+                    self.function_body(out, &signature, function, &lines);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn constant(&self, out: &mut CodeWriter, decl: &ConstDecl) -> Result<(), CodegenError> {
+        let value = Expr::Const(ConstLit::Primitive(decl.value.clone()));
+        let keyword = match (
+            self.mapping(&decl.ty),
+            &self.abi.alias_target(&decl.ty).kind,
+        ) {
+            (None, TypeKind::Keyword(keyword)) if *keyword != KeywordType::Bytes => "const val",
+            _ => "val",
+        };
+        out.line(format!(
+            "{} {}: {} = {}",
+            keyword,
+            ident(decl.name.as_str()),
+            self.ty(&decl.ty)?,
+            self.value(&decl.ty, &value)?
+        ));
+        Ok(())
+    }
+
+    fn model(&self, out: &mut CodeWriter, decl: &ModelDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        if decl.fields.is_empty() {
+            out.open(format!("class {} {{", name));
+            out.line(format!(
+                "override fun equals(other: Any?) = other is {}",
+                name
+            ));
+            out.blank();
+            out.line("override fun hashCode() = 0");
+            out.blank();
+            out.line(format!("override fun toString() = \"{}()\"", name));
+            out.blank();
+            out.line("internal fun write(writer: WireWriter) {}");
+            out.blank();
+            out.open("companion object {");
+            out.line(format!(
+                "internal fun read(reader: WireReader) = {}()",
+                name
+            ));
+            out.close("}");
+            out.close("}");
+            return Ok(());
+        }
+        out.open(format!("data class {}(", name));
+        let mut properties = vec![];
+        let mut writes = vec![];
+        let mut reads = vec![];
+        for field in &decl.fields {
+            let field_name = local(field.name.as_str());
+            let ty = self.ty(&field.ty)?;
+            let default = match &field.default {
+                Some(value) => format!(" = {}", self.value(&field.ty, value)?),
+                None => String::new(),
+            };
+            out.line(format!("val {}: {}{},", field_name, ty, default));
+            writes.push(self.write(&field.ty, &field_name, 0)?);
+            reads.push(self.read(&field.ty)?);
+            properties.push((field_name, ty));
+        }
+        out.reopen(") {");
+        if content_equality(out, &name, &properties) {
+            out.blank();
+        }
+        out.open("internal fun write(writer: WireWriter) {");
+        writes.iter().for_each(|write| out.line(write));
+        out.close("}");
+        out.blank();
+        out.open("companion object {");
+        out.open(format!(
+            "internal fun read(reader: WireReader): {} = {}(",
+            name, name
+        ));
+        reads.iter().for_each(|read| out.line(format!("{},", read)));
+        out.close(")");
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    fn unit_enum(&self, out: &mut CodeWriter, decl: &EnumDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let repr = decl.repr_type();
+        out.open(format!(
+            "enum class {}(val value: {}) {{",
+            name,
+            self.keyword_ty(repr)
+        ));
+        for (index, variant) in decl.variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => num(value, self.unsigned()),
+                None => index.to_string(),
+            };
+            let end = if index + 1 == decl.variants.len() {
+                ";"
+            } else {
+                ","
+            };
+            out.line(format!(
+                "{}({}){}",
+                ident(variant.name.as_str()),
+                value,
+                end
+            ));
+        }
+        out.blank();
+        out.open("companion object {");
+        out.line(format!(
+            "fun fromValue(value: {}): {} = values().firstOrNull {{ it.value == value }}",
+            self.keyword_ty(repr),
+            name
+        ));
+        out.line(format!(
+            "{}?: throw WireException(\"`{}` has no variant $value\")",
+            INDENT,
+            decl.name.as_str()
+        ));
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    /// `IoErrorException` with a subclass per variant of `IoError`, which `of` picks.
+    fn exception_class(&self, out: &mut CodeWriter, decl: &EnumDecl) {
+        let error = ident(decl.name.as_str());
+        let name = ident(&format!("{}Exception", decl.name.as_str()));
+        out.open(format!(
+            "sealed class {}(val error: {}) : Exception(error.toString()) {{",
+            name, error
+        ));
+        let mut cases = vec![];
+        for variant in &decl.variants {
+            let variant_name = ident(variant.name.as_str());
+            let value = format!("{}.{}", error, variant_name);
+            match &variant.fields {
+                EnumVariantFields::Unit => {
+                    out.line(format!("class {} : {}({})", variant_name, name, value));
+                    cases.push(format!("{} -> {}()", value, variant_name));
+                }
+                _ => {
+                    out.line(format!(
+                        "class {}(error: {}) : {}(error)",
+                        variant_name, value, name
+                    ));
+                    cases.push(format!("is {} -> {}(error)", value, variant_name));
+                }
+            }
+        }
+        out.blank();
+        out.open("companion object {");
+        out.open(format!(
+            "fun of(error: {}): {} = when (error) {{",
+            error, name
+        ));
+        for case in cases {
+            out.line(case);
+        }
+        out.close("}");
+        out.close("}");
+        out.close("}");
+    }
+
+    fn payload_enum(&self, out: &mut CodeWriter, decl: &EnumDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let repr = decl.repr_type();
+        out.open(format!("sealed class {} {{", name));
+        // The fields of every variant, by the name of their Kotlin property.
+        let mut variants = vec![];
+        for variant in &decl.variants {
+            let fields: Vec<(String, &Type)> = match &variant.fields {
+                EnumVariantFields::Unit => vec![],
+                EnumVariantFields::Unnamed(types) if types.len() == 1 => {
+                    vec![("value".to_string(), &types[0])]
+                }
+                EnumVariantFields::Unnamed(types) => types
+                    .iter()
+                    .enumerate()
+                    .map(|(index, ty)| (format!("v{}", index), ty))
+                    .collect(),
+                EnumVariantFields::Named(fields) => fields
+                    .iter()
+                    .map(|field| (local(field.name.as_str()), &field.ty))
+                    .collect(),
+            };
+            let variant_name = ident(variant.name.as_str());
+            out.blank();
+            if fields.is_empty() {
+                out.open(format!("object {} : {}() {{", variant_name, name));
+                out.line(format!(
+                    "override fun toString() = \"{}\"",
+                    variant.name.as_str()
+                ));
+                out.close("}");
+            } else {
+                let properties = fields
+                    .iter()
+                    .map(|(field, ty)| Ok((field.clone(), self.ty(ty)?)))
+                    .collect::<Result<Vec<_>, CodegenError>>()?;
+                let params: Vec<String> = properties
+                    .iter()
+                    .map(|(field, ty)| format!("val {}: {}", field, ty))
+                    .collect();
+                let class = format!(
+                    "data class {}({}) : {}()",
+                    variant_name,
+                    params.join(", "),
+                    name
+                );
+                match properties.iter().any(|(_, ty)| is_bytes(ty)) {
+                    true => {
+                        out.open(format!("{} {{", class));
+                        content_equality(out, &variant_name, &properties);
+                        out.close("}");
+                    }
+                    false => out.line(class),
+                }
+            }
+            variants.push((variant_name, variant, fields));
+        }
+        out.blank();
+        out.open("internal fun write(writer: WireWriter) {");
+        out.open("when (val variant = this) {");
+        for (index, (variant_name, variant, fields)) in variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => num(value, false),
+                None => index.to_string(),
+            };
+            let tag = format!("writer.{}({})", wire_fn(repr), value);
+            if fields.is_empty() {
+                out.line(format!("is {} -> {}", variant_name, tag));
+                continue;
+            }
+            out.open(format!("is {} -> {{", variant_name));
+            out.line(tag);
+            for (field, ty) in fields {
+                out.line(self.write(ty, &format!("variant.{}", field), 0)?);
+            }
+            out.close("}");
+        }
+        out.close("}");
+        out.close("}");
+        out.blank();
+        out.open("companion object {");
+        out.open(format!(
+            "internal fun read(reader: WireReader): {} = when (val value = reader.{}().toLong()) {{",
+            name,
+            wire_fn(repr)
+        ));
+        for (index, (variant_name, variant, fields)) in variants.iter().enumerate() {
+            let value = match &variant.discriminant {
+                Some(value) => discriminant(value),
+                None => long(index as i64),
+            };
+            let value_of = match fields.is_empty() {
+                true => variant_name.clone(),
+                false => {
+                    let reads = fields
+                        .iter()
+                        .map(|(_, ty)| self.read(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("{}({})", variant_name, reads.join(", "))
+                }
+            };
+            out.line(format!("{} -> {}", value, value_of));
+        }
+        out.line(format!(
+            "else -> throw WireException(\"`{}` has no variant $value\")",
+            decl.name.as_str()
+        ));
+        out.close("}");
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    fn interface(
+        &self,
+        out: &mut CodeWriter,
+        decl: &InterfaceDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let params = decl.type_params.as_deref().unwrap_or_default();
+        let supertypes = decl
+            .extends
+            .iter()
+            .map(|with| self.with_args(with))
+            .collect::<Result<Vec<_>, _>>()?;
+        let supertypes = match supertypes.is_empty() {
+            true => String::new(),
+            false => format!(" : {}", supertypes.join(", ")),
+        };
+        let members: Vec<Member> = own_interface_members(decl);
+        out.blank();
+        let header = format!(
+            "interface {}{}{}",
+            name,
+            self.type_params(params)?,
+            supertypes
+        );
+        if members.is_empty() {
+            out.line(header);
+        } else {
+            out.open(format!("{} {{", header));
+            self.declarations(out, &members)?;
+            out.close("}");
+        }
+        if !params.is_empty() {
+            return Ok(());
+        }
+        out.blank();
+        self.adapter(out, decl, this)?;
+        out.blank();
+        let prefix = format!("{}_{}", self.jni_prefix(), decl.name.as_str());
+        let members = interface_members(decl);
+        let overrides = members
+            .iter()
+            .map(|member| member.function().name.as_str().to_string())
+            .collect();
+        let object = format!("{}Object", decl.name.as_str());
+        out.line(format!(
+            "/** A `{}` implemented in Rust. */",
+            decl.name.as_str()
+        ));
+        out.open(format!(
+            "internal class {}(handle: Handle) : {}, AutoCloseable {{",
+            ident(&object),
+            name
+        ));
+        self.handle(out, &object)?;
+        self.members(out, &members, &prefix, &overrides)?;
+        out.blank();
+        self.close(out, &members, &prefix)?;
+        out.close("}");
+        Ok(())
+    }
+
+    /// The abstract members of an interface.
+    fn declarations(&self, out: &mut CodeWriter, members: &[Member]) -> Result<(), CodegenError> {
+        let mut first = true;
+        for member_decl in members {
+            let (property, function) = match member_decl {
+                Member::Get(property, function) => (Some(property), function),
+                Member::Set(property, function) if !has_getter(members, property) => {
+                    (None, function)
+                }
+                Member::Set(..) => continue,
+                Member::Method(function) => (None, function),
+            };
+            if !first {
+                out.blank();
+            }
+            first = false;
+            match property {
+                Some(property) => {
+                    let keyword = match has_setter(members, property) {
+                        true => "var",
+                        false => "val",
+                    };
+                    let ty = function.return_type.as_deref().ok_or_else(getter_type)?;
+                    out.line(format!(
+                        "{} {}: {}",
+                        keyword,
+                        local(property.as_str()),
+                        self.ty(ty)?
+                    ));
+                }
+                None => {
+                    self.throws(out, function)?;
+                    out.line(self.signature(&member(function.name.as_str()), function, "")?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The adapter Rust calls the methods of an app implementation through, by slot name.
+    fn adapter(
+        &self,
+        out: &mut CodeWriter,
+        decl: &InterfaceDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let adapter = ident(&format!("{}Callback", decl.name.as_str()));
+        out.line(format!(
+            "/** Hands implementations of `{}` to Rust. */",
+            decl.name.as_str()
+        ));
+        out.open(format!(
+            "internal class {}(private val impl: {}) : Callback {{",
+            adapter, name
+        ));
+        out.line("override fun retain() {}");
+        out.blank();
+        out.line("override fun release() {}");
+        for member_decl in interface_members(decl) {
+            let function = member_decl.function();
+            check_host_method(function)?;
+            out.blank();
+            let head = format!(
+                "fun {}(args: ByteArray): ByteArray = Outcome.host(args, {{ ",
+                member(function.name.as_str())
+            );
+            let reads = function
+                .params
+                .iter()
+                .map(|param| self.read(&param.ty))
+                .collect::<Result<Vec<_>, _>>()?;
+            let call = match &member_decl {
+                Member::Get(property, _) => format!("impl.{}", local(property.as_str())),
+                Member::Set(property, _) if has_getter(&interface_members(decl), property) => {
+                    format!("impl.{} = {}", local(property.as_str()), reads.join(", "))
+                }
+                _ => format!(
+                    "impl.{}({})",
+                    member(function.name.as_str()),
+                    reads.join(", ")
+                ),
+            };
+            let reader = match reads.is_empty() {
+                true => "_",
+                false => "reader",
+            };
+            let (writer, statement) = match &function.return_type {
+                Some(ty) => ("writer", self.write(ty, &call, 0)?),
+                None => ("_", call),
+            };
+            let lambda = format!("{}{}, {} -> {} }}", head, reader, writer, statement);
+            match self.exception(function)? {
+                None => out.line(format!("{})", lambda)),
+                Some((exception, error)) => {
+                    out.open(format!("{}) {{ error, writer ->", lambda));
+                    out.line(format!(
+                        "if (error is {}) {{ {}; true }} else false",
+                        exception,
+                        self.write(error, "error.error", 0)?
+                    ));
+                    out.close("}");
+                }
+            }
+        }
+        out.blank();
+        out.open("companion object {");
+        out.line(format!(
+            "private val registered by lazy {{ {}.{}_register({}::class.java) }}",
+            JNI_CLASS,
+            self.symbol(this),
+            adapter
+        ));
+        out.blank();
+        out.line("/** A new handle of `impl` for Rust, which releases it. */");
+        out.open(format!("fun handle(impl: {}): Long {{", name));
+        out.line(format!(
+            "check(registered) {{ \"another implementation of `{}` is registered\" }}",
+            decl.name.as_str()
+        ));
+        out.line(format!("return {}.retain({}(impl))", JNI_CLASS, adapter));
+        out.close("}");
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    /// The JNI method prefix of the module or `mod` being generated, `app_sub`.
+    fn jni_prefix(&self) -> String {
+        let names: Vec<&str> = self.path.iter().map(Ident::as_str).collect();
+        names.join("_")
+    }
+
+    /// The JNI method prefix of a class or interface, `app_Client`.
+    fn symbol(&self, decl: &DeclRef) -> String {
+        let mut names: Vec<&str> = decl
+            .name
+            .path
+            .segments()
+            .iter()
+            .map(Ident::as_str)
+            .collect();
+        names.push(decl.name.name.as_str());
+        names.join("_")
+    }
+
+    /// The handle of a wrapper, zero once closed.
+    fn handle(&self, out: &mut CodeWriter, name: &str) -> Result<(), CodegenError> {
+        out.line("private val raw = AtomicLong(handle.raw)");
+        out.blank();
+        out.line("internal val handle: Long");
+        out.line(format!(
+            "{}get() = raw.get().also {{ check(it != 0L) {{ \"`{}` is closed\" }} }}",
+            INDENT, name
+        ));
+        Ok(())
+    }
+
+    /// `close` of a wrapper, calling a `close` of the IDL before it frees the handle.
+    fn close(
+        &self,
+        out: &mut CodeWriter,
+        members: &[Member],
+        prefix: &str,
+    ) -> Result<(), CodegenError> {
+        let idl_close = members.iter().find_map(|member_decl| match member_decl {
+            Member::Method(function)
+                if member(function.name.as_str()) == "close" && function.params.is_empty() =>
+            {
+                Some(function)
+            }
+            _ => None,
+        });
+        out.open("override fun close() {");
+        if let Some(function) = idl_close {
+            let lines = self.call(
+                &format!("{}_{}", prefix, function.name.as_str()),
+                function,
+                true,
+            )?;
+            out.open("if (raw.get() != 0L) {");
+            lines.iter().for_each(|line| out.line(line));
+            out.close("}");
+        }
+        out.line("val handle = raw.getAndSet(0)");
+        out.line(format!(
+            "if (handle != 0L) Outcome.unwrap({}.{}_free(handle), {{}})",
+            JNI_CLASS, prefix
+        ));
+        out.close("}");
+        Ok(())
+    }
+
+    /// The members of a wrapper calling Rust, `overrides` by function name.
+    fn members(
+        &self,
+        out: &mut CodeWriter,
+        members: &[Member],
+        prefix: &str,
+        overrides: &HashSet<String>,
+    ) -> Result<(), CodegenError> {
+        let modifier = |function: &FnDecl| match overrides.contains(function.name.as_str()) {
+            true => "override ",
+            false => "",
+        };
+        for member_decl in members {
+            let function = member_decl.function();
+            let method = format!("{}_{}", prefix, function.name.as_str());
+            if !self.abi.is_exported(function) {
+                if overrides.contains(function.name.as_str()) {
+                    out.blank();
+                    let signature =
+                        self.signature(&member(function.name.as_str()), function, "override ")?;
+                    out.line(format!(
+                        "{} = throw UnsupportedOperationException(\"`{}` is generic\")",
+                        signature,
+                        function.name.as_str()
+                    ));
+                }
+                continue;
+            }
+            match member_decl {
+                Member::Get(property, _) => {
+                    let setter = members.iter().find_map(|other| match other {
+                        Member::Set(name, setter) if name == property => Some(setter),
+                        _ => None,
+                    });
+                    let keyword = if setter.is_some() { "var" } else { "val" };
+                    let ty = function.return_type.as_deref().ok_or_else(getter_type)?;
+                    out.blank();
+                    out.line(format!(
+                        "{}{} {}: {}",
+                        modifier(function),
+                        keyword,
+                        local(property.as_str()),
+                        self.ty(ty)?
+                    ));
+                    let lines = self.call(&method, function, true)?;
+                    out.line(format!("{}get() = {}", INDENT, lines[0]));
+                    for line in &lines[1..] {
+                        out.line(format!("{}{}", INDENT, line));
+                    }
+                    if let Some(setter) = setter {
+                        let method = format!("{}_{}", prefix, setter.name.as_str());
+                        out.line(format!("{}set(value) {{", INDENT));
+                        for line in self.call(&method, setter, true)? {
+                            out.line(format!("{}{}{}", INDENT, INDENT, line));
+                        }
+                        out.line(format!("{}}}", INDENT));
+                    }
+                }
+                Member::Set(property, _) if has_getter(members, property) => {}
+                _ if member(function.name.as_str()) == "close" && function.params.is_empty() => {}
+                _ => {
+                    out.blank();
+                    self.throws(out, function)?;
+                    let signature = self.signature(
+                        &member(function.name.as_str()),
+                        function,
+                        modifier(function),
+                    )?;
+                    let lines = self.call(&method, function, true)?;
+                    self.function_body(out, &signature, function, &lines);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn class(
+        &self,
+        out: &mut CodeWriter,
+        decl: &ClassDecl,
+        this: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let prefix = self.symbol(this);
+        // Interfaces of the class and its base classes, which the wrapper implements.
+        let interfaces = self.abi.class_interfaces(decl);
+        let mut overrides = HashSet::new();
+        let mut supertypes = vec![];
+        for with in &interfaces {
+            if let Some(Decl::Interface(interface)) =
+                with.target.as_ref().and_then(|target| self.get(target))
+            {
+                overrides.extend(
+                    interface
+                        .vtable()
+                        .iter()
+                        .map(|function| function.name.as_str().to_string()),
+                );
+            }
+            let supertype = self.with_args(with)?;
+            if !supertypes.contains(&supertype) {
+                supertypes.push(supertype);
+            }
+        }
+        supertypes.push("AutoCloseable".to_string());
+        out.open(format!(
+            "class {} internal constructor(handle: Handle) : {} {{",
+            name,
+            supertypes.join(", ")
+        ));
+        self.handle(out, decl.name.as_str())?;
+        for (index, ctor) in constructors(decl).enumerate() {
+            let function = constructor_function(this, index, ctor);
+            if !self.abi.is_exported(&function) {
+                continue;
+            }
+            out.blank();
+            self.throws(out, &function)?;
+            let native = self.native_call(
+                &format!("{}_{}", prefix, function.name.as_str()),
+                &function,
+                false,
+            )?;
+            let thrown = match self.thrown(&function)? {
+                Some(thrown) => format!(", {}", thrown),
+                None => String::new(),
+            };
+            out.line(format!(
+                "constructor({}) : this(Handle(Outcome.unwrap({}, {{ it.i64() }}{})))",
+                self.params(&function.params)?.join(", "),
+                native,
+                thrown
+            ));
+        }
+        let members = self.abi.class_members(decl);
+        self.members(out, &members, &prefix, &overrides)?;
+        out.blank();
+        self.close(out, &members, &prefix)?;
+        let statics: Vec<&ClassMember> = decl
+            .member
+            .iter()
+            .filter(|member_decl| match member_decl {
+                ClassMember::StaticMethod(function) => self.abi.is_exported(function),
+                ClassMember::Const(_) => true,
+                _ => false,
+            })
+            .collect();
+        if !statics.is_empty() {
+            out.blank();
+            out.open("companion object {");
+            let mut first = true;
+            for member_decl in statics {
+                if !first {
+                    out.blank();
+                }
+                first = false;
+                match member_decl {
+                    ClassMember::Const(decl) => self.constant(out, decl)?,
+                    ClassMember::StaticMethod(function) => {
+                        self.throws(out, function)?;
+                        let signature =
+                            self.signature(&member(function.name.as_str()), function, "")?;
+                        let method = format!("{}_{}", prefix, function.name.as_str());
+                        let lines = self.call(&method, function, false)?;
+                        self.function_body(out, &signature, function, &lines);
+                    }
+                    _ => {}
+                }
+            }
+            out.close("}");
+        }
+        out.close("}");
+        Ok(())
+    }
+}
+
+/// `Pair` or `Triple` for a tuple of `len` elements.
+fn tuple(len: usize) -> Result<&'static str, CodegenError> {
+    match len {
+        2 => Ok("Pair"),
+        3 => Ok("Triple"),
+        len => Err(CodegenError::custom(format!(
+            "Kotlin has no type for tuples of {} elements, use a model",
+            len
+        ))),
+    }
+}
+
+fn generic() -> CodegenError {
+    CodegenError::custom("generic types are not exported")
+}
+
+fn unknown(target: &DeclRef) -> CodegenError {
+    CodegenError::custom(format!("`{}` is not a type", target.name.name.as_str()))
+}
+
+fn getter_type() -> CodegenError {
+    CodegenError::custom("getters return the type of their property")
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::config::{JvmConfig, KotlinConfig, TypeMapping};
+
+    use super::KotlinCodegen;
+    use crate::lang::test::{file, load};
+    use crate::Codegen;
+
+    const APP: &str = r#"
+newtype Timestamp = i64;
+
+model Blob {
+    id: u32,
+    data: Bytes,
+    thumbnail: Optional<Bytes>,
+    at: Timestamp = 5,
+}
+
+enum Packet {
+    Empty,
+    Data(Bytes),
+    Stamp(Timestamp),
+}
+
+enum IoError {
+    NotFound(String),
+    Denied,
+}
+
+fn touch(at: Timestamp) -> Timestamp;
+fn store(blob: Blob);
+fn open(path: String) throws IoError;
+"#;
+
+    fn generate(mappings: Vec<TypeMapping>) -> String {
+        let ir = load(&[("app.slime", APP)]);
+        let jvm = JvmConfig {
+            package_name: "com.example".into(),
+            ..Default::default()
+        };
+        let config = KotlinConfig {
+            type_mappings: mappings,
+            ..Default::default()
+        };
+        let files = KotlinCodegen::new(&ir, &jvm, &config).generate().unwrap();
+        file(&files, "com/example/app/App.kt").to_string()
+    }
+
+    #[test]
+    fn test_newtype() {
+        let app = generate(vec![]);
+        assert!(app.contains("value class Timestamp(val value: Long)"));
+        assert!(app.contains("val at: Timestamp = Timestamp(5L),"));
+        assert!(app.contains("writer.i64(at.value)"));
+        assert!(app.contains("Timestamp(reader.i64()),"));
+    }
+
+    #[test]
+    fn test_mapped_newtype() {
+        let app = generate(vec![TypeMapping {
+            idl_type: "Timestamp".into(),
+            host_type: "java.time.Instant".into(),
+            lift: "java.time.Instant.ofEpochMilli({})".into(),
+            lower: "{}.toEpochMilli()".into(),
+        }]);
+        assert!(app.contains("val at: java.time.Instant = java.time.Instant.ofEpochMilli(5L),"));
+        assert!(app.contains("writer.i64(at.toEpochMilli())"));
+        assert!(app.contains("java.time.Instant.ofEpochMilli(reader.i64()),"));
+        assert!(app.contains("writer.i64(variant.value.toEpochMilli())"));
+        assert!(app.contains(
+            "fun touch(at: java.time.Instant): java.time.Instant = \
+             Outcome.unwrap(SlimeNative.app_touch(at.toEpochMilli()), \
+             { reader -> java.time.Instant.ofEpochMilli(reader.i64()) })"
+        ));
+        assert!(!app.contains("at.value.toEpochMilli()"));
+        assert!(!app.contains("ofEpochMilli(Timestamp("));
+    }
+
+    #[test]
+    fn test_byte_array_equality() {
+        let app = generate(vec![]);
+        assert!(app.contains("if (id != other.id) return false"));
+        assert!(app.contains("if (!data.contentEquals(other.data)) return false"));
+        assert!(app.contains("if (!thumbnail.contentEquals(other.thumbnail)) return false"));
+        assert!(app.contains("result = 31 * result + thumbnail.contentHashCode()"));
+        assert!(app.contains("data class Data(val value: ByteArray) : Packet() {"));
+        assert!(app.contains("if (other !is Data) return false"));
+        assert!(app.contains("data class Stamp(val value: Timestamp) : Packet()\n"));
+    }
+
+    #[test]
+    fn test_exception_hierarchy() {
+        let app = generate(vec![]);
+        assert!(app.contains(
+            "sealed class IoErrorException(val error: IoError) : Exception(error.toString()) {"
+        ));
+        assert!(
+            app.contains("    class NotFound(error: IoError.NotFound) : IoErrorException(error)\n")
+        );
+        assert!(app.contains("    class Denied : IoErrorException(IoError.Denied)\n"));
+        assert!(app.contains("is IoError.NotFound -> NotFound(error)"));
+        assert!(app.contains("IoError.Denied -> Denied()"));
+        assert!(app.contains("{ reader -> IoErrorException.of(IoError.read(reader)) }"));
+    }
+}
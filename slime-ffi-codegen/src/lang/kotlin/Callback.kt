@@ -0,0 +1,17 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi
+
+/**
+ * Implemented by the adapters handing host objects to Rust. Rust holds a global reference to
+ * the adapter and calls `release` before it deletes the reference.
+ */
+interface Callback {
+    fun retain()
+
+    fun release()
+}
+
+/** The handle of an object owned by Rust. */
+@JvmInline
+value class Handle(val raw: Long)
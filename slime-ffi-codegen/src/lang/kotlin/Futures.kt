@@ -0,0 +1,92 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi
+
+import java.util.concurrent.ConcurrentHashMap
+import java.util.concurrent.atomic.AtomicLong
+import kotlin.coroutines.resume
+import kotlinx.coroutines.CancellationException
+import kotlinx.coroutines.flow.Flow
+import kotlinx.coroutines.flow.flow
+import kotlinx.coroutines.suspendCancellableCoroutine
+
+/**
+ * Drives the futures and streams of Rust from coroutines. Rust calls [resume] when a polled
+ * handle can make progress, possibly from its own thread, and the waiting coroutine polls
+ * again through its dispatcher.
+ */
+object Futures {
+    /** The handle has an output, any other status asks to poll again. */
+    private const val READY: Byte = 0
+
+    private val next = AtomicLong(1)
+    private val waiting = ConcurrentHashMap<Long, (Byte) -> Unit>()
+
+    @JvmStatic
+    fun resume(data: Long, status: Byte) {
+        waiting.remove(data)?.invoke(status)
+    }
+
+    /** Suspends until `poll`, given the data to resume with, reports the handle ready. */
+    suspend fun ready(poll: (Long) -> Unit) {
+        while (true) {
+            val status = suspendCancellableCoroutine<Byte> { continuation ->
+                val data = next.getAndIncrement()
+                waiting[data] = { status -> continuation.resume(status) }
+                poll(data)
+            }
+            if (status == READY) return
+        }
+    }
+
+    /**
+     * Awaits the future whose handle is the value of `start`, reading its output with `read`.
+     * Cancelling the coroutine drops the Rust future.
+     */
+    suspend fun <T> await(
+        start: ByteArray,
+        poll: (Long, Long) -> Unit,
+        complete: (Long) -> ByteArray,
+        cancel: (Long) -> Unit,
+        free: (Long) -> Unit,
+        read: (WireReader) -> T,
+        thrown: ((WireReader) -> Throwable)? = null,
+    ): T {
+        val handle = Outcome.unwrap(start, { it.i64() }, thrown)
+        try {
+            ready { data -> poll(handle, data) }
+            return Outcome.unwrap(complete(handle), read, thrown)
+        } catch (e: CancellationException) {
+            cancel(handle)
+            throw e
+        } finally {
+            free(handle)
+        }
+    }
+
+    /**
+     * The items of the Rust stream whose handle `start` returns, which is called on collection.
+     * The flow can be collected once, stopping the collection drops the Rust stream.
+     */
+    fun <T> stream(
+        start: suspend () -> Long,
+        poll: (Long, Long) -> Unit,
+        next: (Long) -> ByteArray,
+        cancel: (Long) -> Unit,
+        free: (Long) -> Unit,
+        read: (WireReader) -> T,
+    ): Flow<T> = flow {
+        val handle = start()
+        try {
+            while (true) {
+                ready { data -> poll(handle, data) }
+                val item = Outcome.unwrap(next(handle), { if (it.bool()) listOf(read(it)) else emptyList() })
+                if (item.isEmpty()) break
+                emit(item[0])
+            }
+        } finally {
+            cancel(handle)
+            free(handle)
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi
+
+import java.util.concurrent.CancellationException
+
+/** A call that failed in Rust other than with the error it declares. */
+open class SlimeException(message: String) : RuntimeException(message)
+
+/** Rust panicked during the call. */
+class SlimePanic(message: String) : SlimeException(message)
+
+/**
+ * The `Outcome` record JNI functions return and host callbacks answer with: a status byte,
+ * then the value, the error or the panic message.
+ */
+object Outcome {
+    private const val SUCCESS: Byte = 1
+    private const val ERROR: Byte = 2
+    private const val PANIC: Byte = 3
+    private const val CANCELLED: Byte = 4
+    private const val THROWN: Byte = 0
+
+    /** The value of a call, `thrown` reads the error it declares. */
+    fun <T> unwrap(
+        bytes: ByteArray,
+        read: (WireReader) -> T,
+        thrown: ((WireReader) -> Throwable)? = null,
+    ): T {
+        val reader = WireReader(bytes)
+        when (reader.i8()) {
+            SUCCESS -> return read(reader).also { reader.finish() }
+            ERROR -> when (reader.i8()) {
+                THROWN -> throw thrown?.invoke(reader) ?: SlimeException("undeclared error")
+                else -> throw SlimeException(reader.string())
+            }
+            PANIC -> throw SlimePanic(reader.string())
+            CANCELLED -> throw CancellationException("the call was cancelled")
+            else -> throw WireException("invalid outcome")
+        }
+    }
+
+    /**
+     * Runs a host callback on its encoded arguments. `thrown` writes the exception if it is the
+     * declared error, any other one panics in Rust.
+     */
+    fun host(
+        args: ByteArray,
+        call: (WireReader, WireWriter) -> Unit,
+        thrown: (Throwable, WireWriter) -> Boolean = { _, _ -> false },
+    ): ByteArray {
+        try {
+            val reader = WireReader(args)
+            val writer = WireWriter()
+            writer.i8(SUCCESS)
+            call(reader, writer)
+            reader.finish()
+            return writer.toByteArray()
+        } catch (e: Throwable) {
+            val writer = WireWriter()
+            writer.i8(ERROR)
+            writer.i8(THROWN)
+            if (thrown(e, writer)) return writer.toByteArray()
+            return WireWriter.encode {
+                it.i8(PANIC)
+                it.string(e.toString())
+            }
+        }
+    }
+}
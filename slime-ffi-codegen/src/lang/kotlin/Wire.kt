@@ -0,0 +1,149 @@
+// Generated by slime-ffi, do not edit.
+
+package com.slime.ffi
+
+/** A value that is not in the wire format of slime-ffi. */
+class WireException(message: String) : RuntimeException(message)
+
+/**
+ * Writes values in the wire format: little-endian numbers, one byte `Boolean`s, strings and
+ * collections prefixed with their `u32` length.
+ */
+class WireWriter {
+    private var buf = ByteArray(64)
+    private var len = 0
+
+    fun i8(value: Byte) {
+        reserve(1)
+        buf[len++] = value
+    }
+
+    fun i16(value: Short) = number(value.toLong(), 2)
+
+    fun i32(value: Int) = number(value.toLong(), 4)
+
+    fun i64(value: Long) = number(value, 8)
+
+    fun f32(value: Float) = i32(value.toRawBits())
+
+    fun f64(value: Double) = i64(value.toRawBits())
+
+    fun bool(value: Boolean) = i8(if (value) 1 else 0)
+
+    fun len(value: Int) = i32(value)
+
+    fun bytes(value: ByteArray) {
+        len(value.size)
+        reserve(value.size)
+        value.copyInto(buf, len)
+        len += value.size
+    }
+
+    fun string(value: String) = bytes(value.toByteArray(Charsets.UTF_8))
+
+    fun <T> optional(value: T?, write: (T) -> Unit) {
+        bool(value != null)
+        if (value != null) write(value)
+    }
+
+    /** `List` and `Set`. */
+    fun <T> collection(value: Collection<T>, write: (T) -> Unit) {
+        len(value.size)
+        value.forEach(write)
+    }
+
+    /** `Array<T, N>`, the elements without a length. */
+    fun <T> array(value: List<T>, size: Int, write: (T) -> Unit) {
+        if (value.size != size) throw WireException("expected $size elements, got ${value.size}")
+        value.forEach(write)
+    }
+
+    fun <K, V> map(value: Map<K, V>, write: (K, V) -> Unit) {
+        len(value.size)
+        value.forEach { (key, item) -> write(key, item) }
+    }
+
+    fun toByteArray(): ByteArray = buf.copyOf(len)
+
+    private fun number(value: Long, size: Int) {
+        reserve(size)
+        for (i in 0 until size) buf[len++] = (value ushr (8 * i)).toByte()
+    }
+
+    private fun reserve(size: Int) {
+        if (len + size > buf.size) buf = buf.copyOf(maxOf(buf.size * 2, len + size))
+    }
+
+    companion object {
+        fun encode(write: (WireWriter) -> Unit): ByteArray = WireWriter().also(write).toByteArray()
+    }
+}
+
+/** Reads values in the wire format, see [WireWriter]. */
+class WireReader(private val buf: ByteArray) {
+    private var pos = 0
+
+    fun i8(): Byte = buf[take(1)]
+
+    fun i16(): Short = number(2).toShort()
+
+    fun i32(): Int = number(4).toInt()
+
+    fun i64(): Long = number(8)
+
+    fun f32(): Float = Float.fromBits(i32())
+
+    fun f64(): Double = Double.fromBits(i64())
+
+    fun bool(): Boolean = i8() != 0.toByte()
+
+    fun len(): Int = i32().also { if (it < 0) throw WireException("length $it is too large") }
+
+    fun bytes(): ByteArray {
+        val size = len()
+        val at = take(size)
+        return buf.copyOfRange(at, at + size)
+    }
+
+    fun string(): String = String(bytes(), Charsets.UTF_8)
+
+    fun <T> optional(read: () -> T): T? = if (bool()) read() else null
+
+    fun <T> list(read: () -> T): List<T> {
+        val size = len()
+        // Every value takes at least one byte, so this bounds the allocation by the input.
+        val values = ArrayList<T>(minOf(size, buf.size - pos))
+        repeat(size) { values.add(read()) }
+        return values
+    }
+
+    fun <T> set(read: () -> T): Set<T> = list(read).toSet()
+
+    fun <T> array(size: Int, read: () -> T): List<T> = List(size) { read() }
+
+    fun <K, V> map(key: () -> K, value: () -> V): Map<K, V> {
+        val size = len()
+        val values = LinkedHashMap<K, V>()
+        repeat(size) { values[key()] = value() }
+        return values
+    }
+
+    /** Fails if bytes are left after the value. */
+    fun finish() {
+        if (pos != buf.size) throw WireException("unexpected bytes after the value")
+    }
+
+    private fun number(size: Int): Long {
+        val at = take(size)
+        var value = 0L
+        for (i in 0 until size) value = value or ((buf[at + i].toLong() and 0xff) shl (8 * i))
+        return value
+    }
+
+    private fun take(size: Int): Int {
+        if (buf.size - pos < size) throw WireException("unexpected end of input")
+        val at = pos
+        pos += size
+        return at
+    }
+}
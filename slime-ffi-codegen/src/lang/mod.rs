@@ -1,39 +1,93 @@
-pub mod rust {
-    use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-    use dashmap::DashMap;
-    use slime_ffi_ir::{ast::Ident, Lang};
+pub mod c;
+pub mod cpp;
+pub mod java;
+pub mod kotlin;
+pub mod rust;
 
-    use crate::Codegen;
+/// A generated file, relative to the directory the output is written to.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+}
 
-    pub struct RustCodegen {}
+impl SourceFile {
+    /// Writes the file below `dir`, creating the directories on the way.
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        let path = dir.join(&self.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &self.source)
+    }
+}
 
-    #[derive(Debug)]
-    pub struct RustPath(Vec<Ident>);
+#[cfg(test)]
+pub(crate) mod test {
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
-    #[derive(Debug)]
-    pub struct RustType {
-        pub pat: RustPath,
-        pub name: Ident,
-    }
+    use slime_ffi_ir::resolved::ResolvedIr;
+    use slime_ffi_ir_parser::Loader;
 
-    pub struct Context {
-        pub ns: Vec<String>,
-        pub type_locs: DashMap<RustType, RustPath>,
-    }
+    use super::SourceFile;
 
-    pub enum RustCodegenOutput {
-        Source(PathBuf),
-        Mod(PathBuf, Vec<Box<RustCodegenOutput>>),
+    /// A fresh directory for the files of one test.
+    pub(crate) fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "slime-ffi-codegen-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
     }
 
-    impl Codegen for RustCodegen {
-        const LANG: Lang = Lang::Rust;
+    /// Loads the IDL files, the first one is the root module.
+    pub(crate) fn load(files: &[(&str, &str)]) -> ResolvedIr {
+        let dir = temp_dir();
+        for (name, text) in files {
+            std::fs::write(dir.join(name), text).unwrap();
+        }
+        let graph = Loader::load(&dir.join(files[0].0)).unwrap_or_else(|e| panic!("{}", e));
+        let _ = std::fs::remove_dir_all(&dir);
+        graph.ir
+    }
 
-        type Output = RustCodegenOutput;
+    /// The content of the generated file at `path`.
+    pub(crate) fn file<'f>(files: &'f [SourceFile], path: &str) -> &'f str {
+        files
+            .iter()
+            .find(|file| file.path == Path::new(path))
+            .map(|file| file.source.as_str())
+            .unwrap_or_else(|| panic!("no file {}", path))
+    }
 
-        fn generate(&self) -> Result<Self::Output, crate::CodegenError> {
-            todo!()
-        }
+    /// Runs a compiler over the files written to `dir`. Returns `false` without running
+    /// anything if `program` is not installed, so that tests only compile where they can.
+    pub(crate) fn compile(program: &str, args: &[&str], dir: &Path) -> bool {
+        let output = match Command::new(program).args(args).current_dir(dir).output() {
+            Ok(output) => output,
+            Err(_) => {
+                eprintln!("`{}` not found, skipping the compile check", program);
+                return false;
+            }
+        };
+        assert!(
+            output.status.success(),
+            "`{} {}` failed:\n{}{}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        true
     }
 }
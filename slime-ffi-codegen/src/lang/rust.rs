@@ -0,0 +1,2496 @@
+//! The Rust side of the bindings, placed at a module of the library crate, e.g. `crate::ffi`.
+//!
+//! Every IDL module becomes a Rust module, with a nested one per `mod`, holding:
+//!
+//! - models, enums, newtypes, aliases and consts, implementing `Wire`,
+//! - a trait per class and interface for the library to implement. Interfaces also get a
+//!   wrapper implementing the trait for objects of the host,
+//! - a `Module` trait with the functions, constructors and static methods of the module,
+//! - in the module of the IDL file, an `export_<module>!` macro taking the type implementing the
+//!   `Module` traits. It expands to the `extern "C"` functions of [`crate::abi`] and, behind the
+//!   `jvm` feature of the library, to their JNI counterparts.
+//!
+//! Interface parameters are always objects of the host, an object the host got from Rust
+//! cannot be passed back where an interface is expected.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use dashmap::DashMap;
+use slime_ffi_ir::ast::{
+    AliasDecl, BuiltinType, ClassDecl, ClassMember, ConstDecl, ConstLit, Decl, DeclKind, DeclRef,
+    EnumDecl, EnumVariantFields, Expr, FnDecl, Function, Ident, InterfaceDecl, InterfaceMember,
+    KeywordType, Lit, ModelDecl, Module, NumLit, Pat, QualifiedName, RefType, Type, TypeKind,
+    TypeParam, TypeWithArgs,
+};
+use slime_ffi_ir::config::JvmConfig;
+use slime_ffi_ir::resolved::ResolvedIr;
+use slime_ffi_ir::Lang;
+
+use crate::abi::{
+    check_host_method, constructor_function, constructor_name, constructors, jni_symbol,
+    own_functions, Abi, AbiType, Export, Substitute, Target,
+};
+use crate::names::{camel_case, shouty_snake_case, snake_case};
+use crate::writer::CodeWriter;
+use crate::{Codegen, CodegenError};
+
+const INDENT: &str = "    ";
+
+/// Strict and reserved keywords of Rust 2021.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// `type` as `r#type`. `self` and the like cannot be raw and get an underscore.
+fn ident(name: &str) -> String {
+    match name {
+        "self" | "Self" | "super" | "crate" => format!("{}_", name),
+        name if KEYWORDS.contains(&name) => format!("r#{}", name),
+        name => name.to_string(),
+    }
+}
+
+fn path_segment(name: &str) -> String {
+    match name {
+        "crate" | "self" | "super" => name.to_string(),
+        name => ident(name),
+    }
+}
+
+/// The file of a module, `type.rs` for `r#type`.
+fn file_name(name: &str) -> String {
+    ident(name).trim_start_matches("r#").to_string()
+}
+
+fn keyword_ty(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 => "i8",
+        KeywordType::Int16 => "i16",
+        KeywordType::Int32 => "i32",
+        KeywordType::Int64 => "i64",
+        KeywordType::Uint8 => "u8",
+        KeywordType::Uint16 => "u16",
+        KeywordType::Uint32 => "u32",
+        KeywordType::Uint64 => "u64",
+        KeywordType::Float32 => "f32",
+        KeywordType::Float64 => "f64",
+        KeywordType::Bool => "bool",
+        KeywordType::String => "String",
+        KeywordType::Bytes => "Vec<u8>",
+    }
+}
+
+/// The JNI type a scalar is passed as, Java has no unsigned integers.
+fn jni_ty(keyword: KeywordType) -> &'static str {
+    match keyword {
+        KeywordType::Int8 | KeywordType::Uint8 => "jbyte",
+        KeywordType::Int16 | KeywordType::Uint16 => "jshort",
+        KeywordType::Int32 | KeywordType::Uint32 => "jint",
+        KeywordType::Int64 | KeywordType::Uint64 => "jlong",
+        KeywordType::Float32 => "jfloat",
+        KeywordType::Float64 => "jdouble",
+        KeywordType::Bool => "jboolean",
+        KeywordType::String | KeywordType::Bytes => "JByteArray<'local>",
+    }
+}
+
+/// A JNI scalar as the Rust one.
+fn from_jni(keyword: KeywordType, value: &str) -> String {
+    match keyword {
+        KeywordType::Bool => format!("{} != 0", value),
+        KeywordType::Uint8 | KeywordType::Uint16 | KeywordType::Uint32 | KeywordType::Uint64 => {
+            format!("{} as {}", value, keyword_ty(keyword))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn num(value: &NumLit) -> String {
+    match value {
+        NumLit::I8(value) => value.to_string(),
+        NumLit::I16(value) => value.to_string(),
+        NumLit::I32(value) => value.to_string(),
+        NumLit::I64(value) => value.to_string(),
+        NumLit::U8(value) => value.to_string(),
+        NumLit::U16(value) => value.to_string(),
+        NumLit::U32(value) => value.to_string(),
+        NumLit::U64(value) => value.to_string(),
+        NumLit::F32(value) => format!("{:?}", value),
+        NumLit::F64(value) => format!("{:?}", value),
+    }
+}
+
+fn lit(value: &Lit) -> String {
+    match value {
+        Lit::Str(value) => format!("{:?}", value),
+        Lit::Num(value) => num(value),
+        Lit::Bool(value) => value.to_string(),
+    }
+}
+
+/// A path of Rust modules, `crate::ffi::net`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RustPath(Vec<Ident>);
+
+impl RustPath {
+    pub fn new(segments: Vec<Ident>) -> Self {
+        RustPath(segments)
+    }
+
+    /// `crate::ffi` into its segments.
+    pub fn parse(path: &str) -> Self {
+        RustPath(
+            path.split("::")
+                .filter(|segment| !segment.is_empty())
+                .map(Ident::from)
+                .collect(),
+        )
+    }
+
+    pub fn segments(&self) -> &[Ident] {
+        &self.0
+    }
+
+    fn rendered(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|segment| path_segment(segment.as_str()))
+            .collect()
+    }
+}
+
+impl fmt::Display for RustPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.rendered().join("::"))
+    }
+}
+
+/// A declaration by its IDL module path and name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RustType {
+    pub pat: RustPath,
+    pub name: Ident,
+}
+
+/// Where the generated declarations live, to refer to them from other modules.
+pub struct Context {
+    /// The module being generated, `["crate", "ffi", "net"]`. Empty in the export macros,
+    /// where every path starts at `$crate`.
+    pub ns: Vec<String>,
+    /// The Rust module of every declaration.
+    pub type_locs: DashMap<RustType, RustPath>,
+    root: RustPath,
+}
+
+impl Context {
+    pub fn new(ir: &ResolvedIr, root: &RustPath) -> Self {
+        let context = Context {
+            ns: vec![],
+            type_locs: DashMap::new(),
+            root: root.clone(),
+        };
+        for module in &ir.modules {
+            let path = [Ident::from(&module.name)];
+            context.register(root, &path, module.decls.iter());
+        }
+        context
+    }
+
+    fn register<'a>(&self, root: &RustPath, path: &[Ident], decls: impl Iterator<Item = &'a Decl>) {
+        for decl in decls {
+            match decl {
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    self.register(root, &path, decl.items.iter().map(Box::as_ref))
+                }
+                Decl::Use(_) => {}
+                decl => {
+                    let mut loc = root.clone();
+                    loc.0.extend(path.iter().cloned());
+                    let ty = RustType {
+                        pat: RustPath::new(path.to_vec()),
+                        name: decl.name().clone(),
+                    };
+                    self.type_locs.insert(ty, loc);
+                }
+            }
+        }
+    }
+
+    /// `decl` as written in [`Context::ns`], by its name within the same module.
+    pub fn path(&self, decl: &DeclRef) -> String {
+        let name = ident(decl.name.name.as_str());
+        let ty = RustType {
+            pat: RustPath::new(decl.name.path.segments().to_vec()),
+            name: decl.name.name.clone(),
+        };
+        let Some(loc) = self.type_locs.get(&ty) else {
+            return name;
+        };
+        let segments = loc.rendered();
+        if segments == self.ns {
+            return name;
+        }
+        self.module(&segments) + "::" + &name
+    }
+
+    /// The Rust module of the IDL module or `mod` at `path`, as written in [`Context::ns`].
+    pub fn module_path(&self, path: &[Ident]) -> String {
+        let mut loc = self.root.clone();
+        loc.0.extend(path.iter().cloned());
+        self.module(&loc.rendered())
+    }
+
+    fn module(&self, segments: &[String]) -> String {
+        let path = segments.join("::");
+        match self.ns.is_empty() && segments.first().is_some_and(|first| first == "crate") {
+            true => format!("${}", path),
+            false => path,
+        }
+    }
+}
+
+/// Files of the generated modules, relative to the directory of the module they belong to.
+#[derive(Debug)]
+pub enum RustCodegenOutput {
+    /// A file and its content.
+    Source(PathBuf, String),
+    /// A directory and the files in it.
+    Mod(PathBuf, Vec<Box<RustCodegenOutput>>),
+}
+
+impl RustCodegenOutput {
+    /// Writes the files below `dir`.
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        match self {
+            RustCodegenOutput::Source(path, source) => fs::write(dir.join(path), source),
+            RustCodegenOutput::Mod(path, items) => {
+                let dir = dir.join(path);
+                fs::create_dir_all(&dir)?;
+                items.iter().try_for_each(|item| item.write_to(&dir))
+            }
+        }
+    }
+}
+
+pub struct RustCodegen<'ir> {
+    pub ir: &'ir ResolvedIr,
+    /// The module the output is placed at, `crate::ffi`.
+    pub root: RustPath,
+    /// Adds the JNI functions of the package.
+    pub jvm: Option<&'ir JvmConfig>,
+}
+
+impl<'ir> RustCodegen<'ir> {
+    pub fn new(ir: &'ir ResolvedIr, root: RustPath) -> Self {
+        RustCodegen {
+            ir,
+            root,
+            jvm: None,
+        }
+    }
+
+    pub fn with_jvm(self, config: &'ir JvmConfig) -> Self {
+        RustCodegen {
+            jvm: Some(config),
+            ..self
+        }
+    }
+
+    /// The Rust module of an IDL module, with the export macro at the end.
+    fn module(
+        &self,
+        context: &mut Context,
+        module: &Module,
+    ) -> Result<RustCodegenOutput, CodegenError> {
+        let abi = Abi::new(self.ir);
+        let exports = abi.exports(module)?;
+        // Paths in the macro start at `$crate`.
+        context.ns = vec![];
+        let mut out = CodeWriter::new(INDENT);
+        let is_root = self.ir.root().name == module.name;
+        Generator::new(&abi, context)
+            .export_macro(&mut out, module, &exports, self.jvm, is_root)?;
+        let path = [Ident::from(&module.name)];
+        let decls: Vec<&Decl> = module.decls.iter().collect();
+        self.unit(context, &path, &decls, Some(out.finish()))
+    }
+
+    /// The Rust module of the IDL module or `mod` at `path`.
+    fn unit(
+        &self,
+        context: &mut Context,
+        path: &[Ident],
+        decls: &[&Decl],
+        tail: Option<String>,
+    ) -> Result<RustCodegenOutput, CodegenError> {
+        let mut children = vec![];
+        for decl in decls {
+            if let Decl::Mod(decl) = decl {
+                let mut path = path.to_vec();
+                path.push(decl.name.clone());
+                let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                children.push(Box::new(self.unit(context, &path, &decls, None)?));
+            }
+        }
+        let mut loc = self.root.clone();
+        loc.0.extend(path.iter().cloned());
+        context.ns = loc.rendered();
+        let abi = Abi::new(self.ir);
+        let generator = Generator::new(&abi, context);
+        let mut out = CodeWriter::new(INDENT);
+        generator.unit(&mut out, path, decls)?;
+        if let Some(tail) = tail {
+            out.blank();
+            out.lines(tail);
+        }
+        let name = file_name(path.last().map(Ident::as_str).unwrap_or_default());
+        Ok(match children.is_empty() {
+            true => RustCodegenOutput::Source(PathBuf::from(format!("{}.rs", name)), out.finish()),
+            false => {
+                let source = RustCodegenOutput::Source(PathBuf::from("mod.rs"), out.finish());
+                children.insert(0, Box::new(source));
+                RustCodegenOutput::Mod(PathBuf::from(name), children)
+            }
+        })
+    }
+}
+
+impl Codegen for RustCodegen<'_> {
+    const LANG: Lang = Lang::Rust;
+
+    type Output = RustCodegenOutput;
+
+    fn generate(&self) -> Result<Self::Output, CodegenError> {
+        let mut context = Context::new(self.ir, &self.root);
+        let mut out = CodeWriter::new(INDENT);
+        out.line("// Generated by slime-ffi, do not edit.");
+        out.blank();
+        let mut items = vec![];
+        for module in &self.ir.modules {
+            out.line(format!("pub mod {};", path_segment(&module.name)));
+            items.push(Box::new(self.module(&mut context, module)?));
+        }
+        items.insert(
+            0,
+            Box::new(RustCodegenOutput::Source(
+                PathBuf::from("mod.rs"),
+                out.finish(),
+            )),
+        );
+        let dir = match self.root.segments().last() {
+            Some(name) if name.as_str() != "crate" => file_name(name.as_str()),
+            _ => String::new(),
+        };
+        Ok(RustCodegenOutput::Mod(PathBuf::from(dir), items))
+    }
+}
+
+/// A function of a `Module` trait.
+struct ModuleFn {
+    name: String,
+    function: FnDecl,
+    /// Type parameters of the class of a constructor or static method.
+    class_params: Vec<TypeParam>,
+}
+
+/// How a field refers to a type containing the declaration of the field.
+enum Boxing {
+    None,
+    /// `Box<T>`.
+    Direct,
+    /// `Option<Box<T>>` for `Optional<T>`.
+    Optional,
+}
+
+struct Generator<'a, 'ir> {
+    abi: &'a Abi<'ir>,
+    context: &'a Context,
+    /// Type parameters in scope, the only way to refer to a generic class or interface.
+    params: RefCell<Vec<Ident>>,
+}
+
+impl<'a, 'ir> Generator<'a, 'ir> {
+    fn new(abi: &'a Abi<'ir>, context: &'a Context) -> Self {
+        Generator {
+            abi,
+            context,
+            params: RefCell::new(vec![]),
+        }
+    }
+
+    fn get(&self, decl: &DeclRef) -> Option<&'ir Decl> {
+        self.abi.ir.get(decl)
+    }
+
+    fn enter(&self, params: Option<&[TypeParam]>) -> usize {
+        let mut scope = self.params.borrow_mut();
+        let mark = scope.len();
+        scope.extend(
+            params
+                .unwrap_or_default()
+                .iter()
+                .map(|param| param.name.clone()),
+        );
+        mark
+    }
+
+    fn leave(&self, mark: usize) {
+        self.params.borrow_mut().truncate(mark)
+    }
+
+    fn ty(&self, ty: &Type) -> Result<String, CodegenError> {
+        Ok(match &ty.kind {
+            TypeKind::Keyword(keyword) => keyword_ty(*keyword).to_string(),
+            TypeKind::RefType(RefType::TypeParam(name)) => ident(name.as_str()),
+            TypeKind::RefType(RefType::Resolved(target)) => self.decl_ty(target)?,
+            TypeKind::RefType(_) => {
+                return Err(CodegenError::custom(
+                    "types are resolved by semantic analysis",
+                ))
+            }
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty) => format!("Option<{}>", self.ty(ty)?),
+                BuiltinType::List(ty) => format!("Vec<{}>", self.ty(ty)?),
+                BuiltinType::Set(ty) => format!("HashSet<{}>", self.ty(ty)?),
+                BuiltinType::Map(map) => {
+                    format!("HashMap<{}, {}>", self.ty(&map.key)?, self.ty(&map.value)?)
+                }
+                BuiltinType::Tuple(types) => format!("({})", self.types(types.iter())?),
+                BuiltinType::Array(array) => {
+                    format!("[{}; {}]", self.ty(&array.element)?, array.len)
+                }
+                BuiltinType::Stream(ty) => format!("BoxStream<{}>", self.ty(ty)?),
+            },
+        })
+    }
+
+    fn types<'t>(&self, types: impl Iterator<Item = &'t Type>) -> Result<String, CodegenError> {
+        let types = types.map(|ty| self.ty(ty)).collect::<Result<Vec<_>, _>>()?;
+        Ok(types.join(", "))
+    }
+
+    fn decl_ty(&self, target: &DeclRef) -> Result<String, CodegenError> {
+        Ok(match self.get(target) {
+            Some(Decl::Class(decl)) => format!(
+                "Arc<dyn {}>",
+                self.trait_path(target, decl.type_params.as_deref())?
+            ),
+            Some(Decl::Interface(decl)) => format!(
+                "Arc<dyn {}>",
+                self.trait_path(target, decl.type_params.as_deref())?
+            ),
+            _ => self.context.path(target),
+        })
+    }
+
+    /// The trait of a class or interface. The IDL has no type arguments for generic ones, so
+    /// they are referred to with their own type parameters, which must be in scope.
+    fn trait_path(
+        &self,
+        target: &DeclRef,
+        params: Option<&[TypeParam]>,
+    ) -> Result<String, CodegenError> {
+        let path = self.context.path(target);
+        let params = params.unwrap_or_default();
+        if params.is_empty() {
+            return Ok(path);
+        }
+        let scope = self.params.borrow();
+        if let Some(param) = params.iter().find(|param| !scope.contains(&param.name)) {
+            return Err(CodegenError::custom(format!(
+                "`{}` is generic and can only be used where its type parameter `{}` is in scope",
+                target.name.name.as_str(),
+                param.name.as_str()
+            )));
+        }
+        let params: Vec<String> = params
+            .iter()
+            .map(|param| ident(param.name.as_str()))
+            .collect();
+        Ok(format!("{}<{}>", path, params.join(", ")))
+    }
+
+    /// `Base<i32>` of `extends` and `implements`.
+    fn with_args(&self, with: &TypeWithArgs) -> Result<String, CodegenError> {
+        let target = with
+            .target
+            .as_ref()
+            .ok_or_else(|| CodegenError::custom("types are resolved by semantic analysis"))?;
+        let path = self.context.path(target);
+        Ok(match with.type_args.as_deref() {
+            Some(args) if !args.is_empty() => {
+                format!("{}<{}>", path, self.types(args.iter().map(Box::as_ref))?)
+            }
+            _ => path,
+        })
+    }
+
+    /// `<T: Listener, U>`, bounded by the constraints naming a trait.
+    fn type_params(&self, params: &[TypeParam]) -> Result<String, CodegenError> {
+        if params.is_empty() {
+            return Ok(String::new());
+        }
+        let params = params
+            .iter()
+            .map(|param| {
+                let name = ident(param.name.as_str());
+                let bound = param
+                    .constraint
+                    .as_ref()
+                    .and_then(|ty| self.abi.resolve(ty));
+                Ok(match bound {
+                    Some((target, Decl::Class(decl))) => {
+                        format!(
+                            "{}: {}",
+                            name,
+                            self.trait_path(target, decl.type_params.as_deref())?
+                        )
+                    }
+                    Some((target, Decl::Interface(decl))) => {
+                        format!(
+                            "{}: {}",
+                            name,
+                            self.trait_path(target, decl.type_params.as_deref())?
+                        )
+                    }
+                    _ => name,
+                })
+            })
+            .collect::<Result<Vec<_>, CodegenError>>()?;
+        Ok(format!("<{}>", params.join(", ")))
+    }
+
+    fn return_ty(&self, function: &Function, is_async: bool) -> Result<String, CodegenError> {
+        let mut ty = match &function.return_type {
+            Some(ty) => self.ty(ty)?,
+            None => "()".to_string(),
+        };
+        if let Some(error) = &function.error_type {
+            ty = format!("Result<{}, {}>", ty, self.ty(error)?);
+        }
+        if is_async {
+            ty = format!("BoxFuture<{}>", ty);
+        }
+        Ok(ty)
+    }
+
+    /// `fn name<T>(&self, a: A) -> R`, generic methods are left out of trait objects.
+    fn signature(
+        &self,
+        name: &str,
+        function: &FnDecl,
+        receiver: bool,
+        class_params: &[TypeParam],
+    ) -> Result<String, CodegenError> {
+        let mut type_params = class_params.to_vec();
+        type_params.extend(function.type_params.iter().flatten().cloned());
+        let mark = self.enter(Some(&type_params));
+        let mut params = vec![];
+        if receiver {
+            params.push("&self".to_string());
+        }
+        for param in &function.params {
+            params.push(format!(
+                "{}: {}",
+                ident(param.name.as_str()),
+                self.ty(&param.ty)?
+            ));
+        }
+        let mut signature = format!(
+            "fn {}{}({})",
+            ident(name),
+            self.type_params(&type_params)?,
+            params.join(", ")
+        );
+        let ty = self.return_ty(function, function.is_async)?;
+        if ty != "()" {
+            signature = format!("{} -> {}", signature, ty);
+        }
+        if receiver && !type_params.is_empty() {
+            signature.push_str(" where Self: Sized");
+        }
+        self.leave(mark);
+        Ok(signature)
+    }
+
+    /// Whether `ty` holds a value of `decl` without an allocation in between.
+    fn contains(&self, ty: &Type, decl: &QualifiedName, seen: &mut Vec<QualifiedName>) -> bool {
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if &target.name == decl {
+                    return true;
+                }
+                if seen.contains(&target.name) {
+                    return false;
+                }
+                seen.push(target.name.clone());
+                match self.get(target) {
+                    Some(Decl::Model(model)) => model
+                        .fields
+                        .iter()
+                        .any(|field| self.contains(&field.ty, decl, seen)),
+                    Some(Decl::Enum(decl_enum)) => decl_enum.variants.iter().any(|variant| {
+                        variant_types(&variant.fields).any(|ty| self.contains(ty, decl, seen))
+                    }),
+                    _ => false,
+                }
+            }
+            TypeKind::Builtin(BuiltinType::Optional(ty)) => self.contains(ty, decl, seen),
+            TypeKind::Builtin(BuiltinType::Tuple(types)) => {
+                types.iter().any(|ty| self.contains(ty, decl, seen))
+            }
+            TypeKind::Builtin(BuiltinType::Array(array)) => {
+                self.contains(&array.element, decl, seen)
+            }
+            _ => false,
+        }
+    }
+
+    fn boxing(&self, ty: &Type, decl: &QualifiedName) -> Boxing {
+        if let TypeKind::Builtin(BuiltinType::Optional(inner)) = &ty.kind {
+            if self.contains(inner, decl, &mut vec![]) {
+                return Boxing::Optional;
+            }
+        }
+        match self.contains(ty, decl, &mut vec![]) {
+            true => Boxing::Direct,
+            false => Boxing::None,
+        }
+    }
+
+    /// The type of a model field or enum payload, boxed where it contains `decl`.
+    fn field_ty(&self, ty: &Type, decl: &QualifiedName) -> Result<String, CodegenError> {
+        Ok(match (self.boxing(ty, decl), &ty.kind) {
+            (Boxing::Optional, TypeKind::Builtin(BuiltinType::Optional(inner))) => {
+                format!("Option<Box<{}>>", self.ty(inner)?)
+            }
+            (Boxing::None, _) => self.ty(ty)?,
+            _ => format!("Box<{}>", self.ty(ty)?),
+        })
+    }
+
+    /// Whether `ty` holds a class or interface object, which have no `Debug` or `PartialEq`.
+    fn has_objects(&self, ty: &Type, seen: &mut Vec<QualifiedName>) -> bool {
+        match &self.abi.alias_target(ty).kind {
+            TypeKind::Keyword(_) => false,
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if seen.contains(&target.name) {
+                    return false;
+                }
+                seen.push(target.name.clone());
+                match self.get(target) {
+                    Some(Decl::Class(_) | Decl::Interface(_)) => true,
+                    Some(Decl::Model(model)) => model
+                        .fields
+                        .iter()
+                        .any(|field| self.has_objects(&field.ty, seen)),
+                    Some(Decl::Enum(decl)) => decl.variants.iter().any(|variant| {
+                        variant_types(&variant.fields).any(|ty| self.has_objects(ty, seen))
+                    }),
+                    _ => false,
+                }
+            }
+            TypeKind::RefType(_) => false,
+            TypeKind::Builtin(builtin) => match builtin {
+                BuiltinType::Optional(ty)
+                | BuiltinType::List(ty)
+                | BuiltinType::Set(ty)
+                | BuiltinType::Stream(ty) => self.has_objects(ty, seen),
+                BuiltinType::Map(map) => {
+                    self.has_objects(&map.key, seen) || self.has_objects(&map.value, seen)
+                }
+                BuiltinType::Tuple(types) => types.iter().any(|ty| self.has_objects(ty, seen)),
+                BuiltinType::Array(array) => self.has_objects(&array.element, seen),
+            },
+        }
+    }
+
+    /// A default value or const of type `ty`.
+    fn value(&self, ty: &Type, value: &Expr) -> Result<String, CodegenError> {
+        let constant = match value {
+            Expr::Null => return Ok("None".to_string()),
+            Expr::Const(constant) => constant,
+            _ => {
+                return Err(CodegenError::custom(
+                    "values are resolved by semantic analysis",
+                ))
+            }
+        };
+        let ty = self.abi.alias_target(ty);
+        match &ty.kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => {
+                return Ok(format!("Some({})", self.value(inner, value)?))
+            }
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                if let Some(Decl::Alias(alias)) = self.get(target) {
+                    let inner = self.value(&alias.ty, value)?;
+                    return Ok(format!("{}({})", self.context.path(target), inner));
+                }
+            }
+            _ => {}
+        }
+        Ok(match constant {
+            ConstLit::Primitive(Lit::Str(value)) => format!("{:?}.to_string()", value),
+            ConstLit::Primitive(value) => lit(value),
+            ConstLit::UnitEnumVariant(variant) => {
+                format!(
+                    "{}::{}",
+                    self.ty(&variant.ty)?,
+                    ident(variant.variant_name.as_str())
+                )
+            }
+        })
+    }
+
+    fn unit(
+        &self,
+        out: &mut CodeWriter,
+        path: &[Ident],
+        decls: &[&Decl],
+    ) -> Result<(), CodegenError> {
+        let names: Vec<&str> = path.iter().map(Ident::as_str).collect();
+        out.line(format!(
+            "// Generated by slime-ffi from the `{}` module, do not edit.",
+            names.join(".")
+        ));
+        out.blank();
+        out.line("#![allow(unused_imports, unused_variables, unused_mut, dead_code, non_camel_case_types, non_snake_case, clippy::all)]");
+        out.blank();
+        out.line("use std::collections::{HashMap, HashSet};");
+        out.line("use std::sync::Arc;");
+        out.blank();
+        out.line("use slime_ffi::call::future::BoxFuture;");
+        out.line("use slime_ffi::call::stream::BoxStream;");
+        out.line("use slime_ffi::types::bytes::{Wire, WireError};");
+        out.blank();
+        for decl in decls {
+            if let Decl::Mod(decl) = decl {
+                out.line(format!("pub mod {};", ident(decl.name.as_str())));
+            }
+        }
+        let decl_ref = |kind, name: &Ident| DeclRef {
+            kind,
+            name: QualifiedName {
+                path: Pat::new(path.to_vec()),
+                name: name.clone(),
+            },
+        };
+        for decl in decls {
+            out.blank();
+            match decl {
+                Decl::Const(decl) => self.constant(out, decl, "pub const")?,
+                Decl::Alias(decl) => self.alias(out, decl)?,
+                Decl::Model(decl) => {
+                    self.model(out, decl, &decl_ref(DeclKind::Model, &decl.name).name)?
+                }
+                Decl::Enum(decl) => {
+                    self.enumeration(out, decl, &decl_ref(DeclKind::Enum, &decl.name).name)?
+                }
+                Decl::Interface(decl) => self.interface(out, decl)?,
+                Decl::Class(decl) => self.class(out, decl)?,
+                Decl::Mod(_) | Decl::Method(_) | Decl::Use(_) => {}
+            }
+        }
+        let functions = self.module_functions(path, decls);
+        if !functions.is_empty() {
+            out.blank();
+            out.line(
+                "/// Functions of the module, implemented by the type given to the export macro.",
+            );
+            out.open("pub trait Module {");
+            for function in &functions {
+                let signature = self.signature(
+                    &function.name,
+                    &function.function,
+                    false,
+                    &function.class_params,
+                )?;
+                out.line(format!("{};", signature));
+            }
+            out.close("}");
+        }
+        Ok(())
+    }
+
+    fn constant(
+        &self,
+        out: &mut CodeWriter,
+        decl: &ConstDecl,
+        keyword: &str,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        match (&self.abi.ir.underlying(&decl.ty).kind, &decl.value) {
+            (TypeKind::Keyword(KeywordType::String), value) => {
+                out.line(format!("{} {}: &str = {};", keyword, name, lit(value)))
+            }
+            (_, value) => {
+                let value = Expr::Const(ConstLit::Primitive(value.clone()));
+                let value = self.value(&decl.ty, &value)?;
+                out.line(format!(
+                    "{} {}: {} = {};",
+                    keyword,
+                    name,
+                    self.ty(&decl.ty)?,
+                    value
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn alias(&self, out: &mut CodeWriter, decl: &AliasDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let ty = self.ty(&decl.ty)?;
+        if !decl.distinct {
+            out.line(format!("pub type {} = {};", name, ty));
+            return Ok(());
+        }
+        let derives = match self.abi.ir.underlying(&decl.ty).kind {
+            TypeKind::Keyword(KeywordType::Float32 | KeywordType::Float64) => {
+                "Debug, Clone, Copy, PartialEq"
+            }
+            TypeKind::Keyword(KeywordType::String | KeywordType::Bytes) => {
+                "Debug, Clone, PartialEq, Eq, Hash"
+            }
+            TypeKind::Keyword(_) => "Debug, Clone, Copy, PartialEq, Eq, Hash",
+            _ => "Debug, Clone, PartialEq",
+        };
+        out.line("#[repr(transparent)]");
+        out.line(format!("#[derive({})]", derives));
+        out.line(format!("pub struct {}(pub {});", name, ty));
+        out.blank();
+        out.open(format!("impl Wire for {} {{", name));
+        out.open("fn write(&self, out: &mut Vec<u8>) {");
+        out.line("self.0.write(out)");
+        out.close("}");
+        out.blank();
+        out.open("fn read(input: &mut &[u8]) -> Result<Self, WireError> {");
+        out.line(format!("Wire::read(input).map({})", name));
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    fn model(
+        &self,
+        out: &mut CodeWriter,
+        decl: &ModelDecl,
+        this: &QualifiedName,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let objects = decl
+            .fields
+            .iter()
+            .any(|field| self.has_objects(&field.ty, &mut vec![]));
+        if self.abi.is_plain_model(&decl.fields) {
+            out.line("#[repr(C)]");
+            out.line("#[derive(Debug, Clone, Copy, PartialEq)]");
+        } else if objects {
+            out.line("#[derive(Clone)]");
+        } else if decl.fields.is_empty() {
+            out.line("#[derive(Debug, Clone, Default, PartialEq)]");
+        } else {
+            out.line("#[derive(Debug, Clone, PartialEq)]");
+        }
+        let mut fields = vec![];
+        for field in &decl.fields {
+            let ty = self.field_ty(&field.ty, this)?;
+            fields.push((ident(field.name.as_str()), ty, field));
+        }
+        if fields.is_empty() {
+            out.line(format!("pub struct {} {{}}", name));
+        } else {
+            out.open(format!("pub struct {} {{", name));
+            for (field, ty, _) in &fields {
+                out.line(format!("pub {}: {},", field, ty));
+            }
+            out.close("}");
+        }
+        if !fields.is_empty() && decl.fields.iter().all(|field| field.default.is_some()) {
+            out.blank();
+            out.open(format!("impl Default for {} {{", name));
+            out.open("fn default() -> Self {");
+            out.open(format!("{} {{", name));
+            for (field, _, decl) in &fields {
+                let value = self.value(&decl.ty, decl.default.as_ref().unwrap())?;
+                let value = match self.boxing(&decl.ty, this) {
+                    Boxing::None => value,
+                    Boxing::Direct => format!("Box::new({})", value),
+                    Boxing::Optional => match value.strip_prefix("Some(") {
+                        Some(inner) => format!("Some(Box::new({})", inner),
+                        None => value,
+                    },
+                };
+                out.line(format!("{}: {},", field, value));
+            }
+            out.close("}");
+            out.close("}");
+            out.close("}");
+        }
+        out.blank();
+        out.open(format!("impl Wire for {} {{", name));
+        if fields.is_empty() {
+            out.line("fn write(&self, _out: &mut Vec<u8>) {}");
+            out.blank();
+            out.open("fn read(_input: &mut &[u8]) -> Result<Self, WireError> {");
+            out.line(format!("Ok({} {{}})", name));
+            out.close("}");
+        } else {
+            out.open("fn write(&self, out: &mut Vec<u8>) {");
+            for (field, _, _) in &fields {
+                out.line(format!("self.{}.write(out);", field));
+            }
+            out.close("}");
+            out.blank();
+            out.open("fn read(input: &mut &[u8]) -> Result<Self, WireError> {");
+            out.open(format!("Ok({} {{", name));
+            for (field, _, _) in &fields {
+                out.line(format!("{}: Wire::read(input)?,", field));
+            }
+            out.close("})");
+            out.close("}");
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    fn enumeration(
+        &self,
+        out: &mut CodeWriter,
+        decl: &EnumDecl,
+        this: &QualifiedName,
+    ) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let repr = keyword_ty(decl.repr_type());
+        let discriminants: Vec<String> = decl
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(index, variant)| match &variant.discriminant {
+                Some(value) => num(value),
+                None => index.to_string(),
+            })
+            .collect();
+        let objects = decl.variants.iter().any(|variant| {
+            variant_types(&variant.fields).any(|ty| self.has_objects(ty, &mut vec![]))
+        });
+        let primitive = decl.is_primitive();
+        if !decl.variants.is_empty() {
+            out.line(format!("#[repr({})]", repr));
+        }
+        out.line(match (primitive, objects) {
+            (true, _) => "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]",
+            (false, true) => "#[derive(Clone)]",
+            (false, false) => "#[derive(Debug, Clone, PartialEq)]",
+        });
+        out.open(format!("pub enum {} {{", name));
+        for (variant, discriminant) in decl.variants.iter().zip(&discriminants) {
+            let variant_name = ident(variant.name.as_str());
+            match &variant.fields {
+                EnumVariantFields::Unit => {
+                    out.line(format!("{} = {},", variant_name, discriminant))
+                }
+                EnumVariantFields::Unnamed(types) => {
+                    let types = types
+                        .iter()
+                        .map(|ty| self.field_ty(ty, this))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    out.line(format!(
+                        "{}({}) = {},",
+                        variant_name,
+                        types.join(", "),
+                        discriminant
+                    ))
+                }
+                EnumVariantFields::Named(fields) => {
+                    out.open(format!("{} {{", variant_name));
+                    for field in fields {
+                        out.line(format!(
+                            "{}: {},",
+                            ident(field.name.as_str()),
+                            self.field_ty(&field.ty, this)?
+                        ));
+                    }
+                    out.close(format!("}} = {},", discriminant));
+                }
+            }
+        }
+        out.close("}");
+        let value_error = format!(
+            "value => Err(WireError::Discriminant {{ r#enum: {:?}, value: value as i128 }}),",
+            decl.name.as_str()
+        );
+        out.blank();
+        if primitive {
+            out.open(format!("impl TryFrom<{}> for {} {{", repr, name));
+            out.line("type Error = WireError;");
+            out.blank();
+            out.open(format!(
+                "fn try_from(value: {}) -> Result<Self, WireError> {{",
+                repr
+            ));
+            out.open("match value {");
+            for (variant, discriminant) in decl.variants.iter().zip(&discriminants) {
+                out.line(format!(
+                    "{} => Ok({}::{}),",
+                    discriminant,
+                    name,
+                    ident(variant.name.as_str())
+                ));
+            }
+            out.line(&value_error);
+            out.close("}");
+            out.close("}");
+            out.close("}");
+            out.blank();
+            out.open(format!("impl Wire for {} {{", name));
+            out.open("fn write(&self, out: &mut Vec<u8>) {");
+            out.line(format!(
+                "<{} as Wire>::write(&(*self as {}), out)",
+                repr, repr
+            ));
+            out.close("}");
+            out.blank();
+            out.open("fn read(input: &mut &[u8]) -> Result<Self, WireError> {");
+            out.line(format!("Self::try_from(<{} as Wire>::read(input)?)", repr));
+            out.close("}");
+            out.close("}");
+        } else {
+            out.open(format!("impl Wire for {} {{", name));
+            out.open("fn write(&self, out: &mut Vec<u8>) {");
+            out.open("match self {");
+            for (variant, discriminant) in decl.variants.iter().zip(&discriminants) {
+                let (pattern, count) =
+                    variant_pattern(&name, variant.name.as_str(), &variant.fields);
+                out.open(format!("{} => {{", pattern));
+                out.line(format!(
+                    "<{} as Wire>::write(&{}, out);",
+                    repr, discriminant
+                ));
+                for index in 0..count {
+                    out.line(format!("f{}.write(out);", index));
+                }
+                out.close("}");
+            }
+            out.close("}");
+            out.close("}");
+            out.blank();
+            out.open("fn read(input: &mut &[u8]) -> Result<Self, WireError> {");
+            out.open(format!("match <{} as Wire>::read(input)? {{", repr));
+            for (variant, discriminant) in decl.variants.iter().zip(&discriminants) {
+                let variant_name = ident(variant.name.as_str());
+                let value = match &variant.fields {
+                    EnumVariantFields::Unit => format!("{}::{}", name, variant_name),
+                    EnumVariantFields::Unnamed(types) => {
+                        let reads = vec!["Wire::read(input)?"; types.len()];
+                        format!("{}::{}({})", name, variant_name, reads.join(", "))
+                    }
+                    EnumVariantFields::Named(fields) => {
+                        let reads: Vec<String> = fields
+                            .iter()
+                            .map(|field| {
+                                format!("{}: Wire::read(input)?", ident(field.name.as_str()))
+                            })
+                            .collect();
+                        format!("{}::{} {{ {} }}", name, variant_name, reads.join(", "))
+                    }
+                };
+                out.line(format!("{} => Ok({}),", discriminant, value));
+            }
+            out.line(&value_error);
+            out.close("}");
+            out.close("}");
+            out.close("}");
+        }
+        if self.abi.is_thrown(this) && !objects {
+            out.blank();
+            out.open(format!("impl std::fmt::Display for {} {{", name));
+            out.open("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {");
+            out.line("std::fmt::Debug::fmt(self, f)");
+            out.close("}");
+            out.close("}");
+            out.blank();
+            out.line(format!("impl std::error::Error for {} {{}}", name));
+        }
+        Ok(())
+    }
+
+    fn interface(&self, out: &mut CodeWriter, decl: &InterfaceDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let mark = self.enter(decl.type_params.as_deref());
+        let mut supertraits = decl
+            .extends
+            .iter()
+            .map(|with| self.with_args(with))
+            .collect::<Result<Vec<_>, _>>()?;
+        supertraits.extend(["Send".to_string(), "Sync".to_string()]);
+        let header = format!(
+            "pub trait {}{}: {}",
+            name,
+            self.type_params(decl.type_params.as_deref().unwrap_or_default())?,
+            supertraits.join(" + ")
+        );
+        let functions: Vec<FnDecl> = decl
+            .member
+            .iter()
+            .flat_map(InterfaceMember::functions)
+            .collect();
+        self.trait_decl(out, &header, &functions)?;
+        if decl
+            .type_params
+            .as_ref()
+            .is_some_and(|params| !params.is_empty())
+        {
+            self.leave(mark);
+            return Ok(());
+        }
+        self.host(out, decl)?;
+        self.leave(mark);
+        Ok(())
+    }
+
+    fn trait_decl(
+        &self,
+        out: &mut CodeWriter,
+        header: &str,
+        functions: &[FnDecl],
+    ) -> Result<(), CodegenError> {
+        if functions.is_empty() {
+            out.line(format!("{} {{}}", header));
+            return Ok(());
+        }
+        out.open(format!("{} {{", header));
+        for function in functions {
+            out.line(format!(
+                "{};",
+                self.signature(function.name.as_str(), function, true, &[])?
+            ));
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    /// The implementation of the interface by host objects.
+    fn host(&self, out: &mut CodeWriter, decl: &InterfaceDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let host = format!("{}Host", decl.name.as_str());
+        let vtable = vtable_static(decl.name.as_str());
+        // Slots are numbered over the inherited members first, like `InterfaceDecl::vtable`.
+        let mut slots: Vec<(Option<&DeclRef>, FnDecl, u32)> = vec![];
+        let members = decl
+            .inherited
+            .iter()
+            .map(|inherited| (Some(&inherited.from), &inherited.member))
+            .chain(decl.member.iter().map(|member| (None, member)));
+        for (from, member) in members {
+            for function in member.functions() {
+                let slot = slots.len() as u32;
+                slots.push((from, function, slot));
+            }
+        }
+        out.blank();
+        out.line(format!("struct {}(slime_ffi::types::r#ref::FfiRef);", host));
+        let mut traits = vec![(None, name.clone())];
+        for (target, args) in self.supertraits(decl) {
+            let path = self.context.path(&target);
+            let path = match args.is_empty() {
+                true => path,
+                false => format!("{}<{}>", path, self.types(args.iter())?),
+            };
+            traits.push((Some(target), path));
+        }
+        for (target, path) in &traits {
+            // The functions the trait declares, by the slot the host implements them at.
+            let names: Vec<String> = match target {
+                None => decl
+                    .member
+                    .iter()
+                    .flat_map(InterfaceMember::functions)
+                    .map(|function| function.name.as_str().to_string())
+                    .collect(),
+                Some(target) => match self.get(target) {
+                    Some(Decl::Interface(base)) => base
+                        .member
+                        .iter()
+                        .flat_map(InterfaceMember::functions)
+                        .map(|function| function.name.as_str().to_string())
+                        .collect(),
+                    _ => vec![],
+                },
+            };
+            let from = target.as_ref().map(|target| &target.name);
+            let functions: Vec<&(Option<&DeclRef>, FnDecl, u32)> = names
+                .iter()
+                .filter_map(|name| {
+                    let named =
+                        |slot: &&(Option<&DeclRef>, FnDecl, u32)| slot.1.name.as_str() == name;
+                    // A member redeclared by `decl` is implemented at its own slot.
+                    slots
+                        .iter()
+                        .filter(named)
+                        .find(|slot| slot.0.map(|from| &from.name) == from)
+                        .or_else(|| slots.iter().find(named))
+                })
+                .collect();
+            out.blank();
+            if functions.is_empty() {
+                out.line(format!("impl {} for {} {{}}", path, host));
+                continue;
+            }
+            out.open(format!("impl {} for {} {{", path, host));
+            for (index, (_, function, slot)) in functions.into_iter().enumerate() {
+                if index > 0 {
+                    out.blank();
+                }
+                self.host_method(out, function, *slot)?;
+            }
+            out.close("}");
+        }
+        out.blank();
+        out.line(format!(
+            "/// Host implementations of `{}`, set by its register function.",
+            decl.name.as_str()
+        ));
+        out.line(format!(
+            "pub(crate) static {}: std::sync::OnceLock<&'static slime_ffi::types::r#ref::FfiRefVTable> = std::sync::OnceLock::new();",
+            vtable
+        ));
+        out.blank();
+        out.open(format!(
+            "impl slime_ffi::types::handle::Object for dyn {} {{",
+            name
+        ));
+        out.open("unsafe fn from_handle(handle: u64) -> Result<Arc<Self>, slime_ffi::types::handle::HandleError> {");
+        out.line("use slime_ffi::types::handle::HandleError;");
+        out.blank();
+        out.line(format!(
+            "let vtable = {}.get().ok_or(HandleError::Invalid)?;",
+            vtable
+        ));
+        out.line(
+            "let object = std::ptr::NonNull::new(handle as *mut ()).ok_or(HandleError::Invalid)?;",
+        );
+        out.line(format!(
+            "Ok(Arc::new({}(unsafe {{ slime_ffi::types::r#ref::FfiRef::host::<{}>(object, vtable) }})))",
+            host, host
+        ));
+        out.close("}");
+        out.close("}");
+        Ok(())
+    }
+
+    /// Interfaces `decl` extends, directly or not, with the type arguments it gets them with.
+    fn supertraits(&self, decl: &InterfaceDecl) -> Vec<(DeclRef, Vec<Type>)> {
+        let mut supertraits: Vec<(DeclRef, Vec<Type>)> = vec![];
+        for with in &decl.extends {
+            let Some(target) = &with.target else {
+                continue;
+            };
+            let Some(Decl::Interface(base)) = self.get(target) else {
+                continue;
+            };
+            let args: Vec<Type> = with
+                .type_args
+                .iter()
+                .flatten()
+                .map(|ty| ty.as_ref().clone())
+                .collect();
+            if !supertraits
+                .iter()
+                .any(|(known, _)| known.name == target.name)
+            {
+                supertraits.push((target.clone(), args));
+            }
+            let substitute = Substitute::new(base.type_params.as_deref(), with);
+            for (target, mut args) in self.supertraits(base) {
+                args.iter_mut().for_each(|ty| substitute.ty(ty));
+                if !supertraits
+                    .iter()
+                    .any(|(known, _)| known.name == target.name)
+                {
+                    supertraits.push((target, args));
+                }
+            }
+        }
+        supertraits
+    }
+
+    fn host_method(
+        &self,
+        out: &mut CodeWriter,
+        function: &FnDecl,
+        slot: u32,
+    ) -> Result<(), CodegenError> {
+        check_host_method(function)?;
+        let signature = self.signature(function.name.as_str(), function, true, &[])?;
+        out.open(format!("{} {{", signature));
+        if function.params.is_empty() {
+            out.line("let __args = Vec::new();");
+        } else {
+            out.line("let mut __args = Vec::new();");
+            for param in &function.params {
+                out.line(format!(
+                    "Wire::write(&{}, &mut __args);",
+                    ident(param.name.as_str())
+                ));
+            }
+        }
+        let value = match &function.return_type {
+            Some(ty) => self.ty(ty)?,
+            None => "()".to_string(),
+        };
+        let error = match &function.error_type {
+            Some(error) => self.ty(error)?,
+            None => "std::convert::Infallible".to_string(),
+        };
+        // The trait has no place for a host that failed other than by throwing, the panic
+        // unwinds to the exported function calling it, which reports it to its caller.
+        out.open(format!(
+            "let __result = match unsafe {{ slime_ffi::types::callback::call_host::<{}, {}>(&self.0, {}, __args) }} {{",
+            value, error, slot
+        ));
+        out.line("Ok(result) => result,");
+        out.line(format!(
+            "Err(error) => panic!(\"`{}` of a host object failed: {{}}\", error),",
+            function.name.as_str()
+        ));
+        out.close("};");
+        match function.error_type.is_some() {
+            true => out.line("__result"),
+            false => out.line("__result.unwrap_or_else(|error| match error {})"),
+        }
+        out.close("}");
+        Ok(())
+    }
+
+    fn class(&self, out: &mut CodeWriter, decl: &ClassDecl) -> Result<(), CodegenError> {
+        let name = ident(decl.name.as_str());
+        let params = decl.type_params.as_deref().unwrap_or_default();
+        let mark = self.enter(Some(params));
+        let mut supertraits = vec![];
+        for with in decl
+            .extend
+            .iter()
+            .map(Box::as_ref)
+            .chain(decl.impls.iter().flatten().map(Box::as_ref))
+        {
+            supertraits.push(self.with_args(with)?);
+        }
+        supertraits.extend(["Send".to_string(), "Sync".to_string()]);
+        let inherited: HashSet<String> = self
+            .abi
+            .supertrait_functions(decl)
+            .iter()
+            .map(|function| function.name.as_str().to_string())
+            .collect();
+        let functions: Vec<FnDecl> = own_functions(decl)
+            .into_iter()
+            .filter(|function| !inherited.contains(function.name.as_str()))
+            .collect();
+        let type_params = self.type_params(params)?;
+        let header = format!(
+            "pub trait {}{}: {}",
+            name,
+            type_params,
+            supertraits.join(" + ")
+        );
+        self.trait_decl(out, &header, &functions)?;
+        let consts: Vec<&ConstDecl> = decl
+            .member
+            .iter()
+            .filter_map(|member| match member {
+                ClassMember::Const(decl) => Some(decl),
+                _ => None,
+            })
+            .collect();
+        let generics: Vec<String> = params
+            .iter()
+            .map(|param| ident(param.name.as_str()))
+            .collect();
+        let this = match generics.is_empty() {
+            true => name.clone(),
+            false => format!("{}<{}>", name, generics.join(", ")),
+        };
+        if !consts.is_empty() {
+            out.blank();
+            let impl_params = match generics.is_empty() {
+                true => String::new(),
+                false => format!(
+                    "<{}>",
+                    generics
+                        .iter()
+                        .map(|param| format!("{}: 'static", param))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            out.open(format!("impl{} dyn {} {{", impl_params, this));
+            for decl in consts {
+                self.constant(out, decl, "pub const")?;
+            }
+            out.close("}");
+        }
+        if generics.is_empty() {
+            out.blank();
+            out.line(format!(
+                "impl slime_ffi::types::handle::Object for dyn {} {{}}",
+                name
+            ));
+        }
+        self.leave(mark);
+        Ok(())
+    }
+
+    /// The functions, constructors and static methods of the module or `mod` at `path`.
+    fn module_functions(&self, path: &[Ident], decls: &[&Decl]) -> Vec<ModuleFn> {
+        let mut functions = vec![];
+        for decl in decls {
+            match decl {
+                Decl::Method(function) => functions.push(ModuleFn {
+                    name: function.name.as_str().to_string(),
+                    function: function.clone(),
+                    class_params: vec![],
+                }),
+                Decl::Class(class) => {
+                    let class_ref = DeclRef {
+                        kind: DeclKind::Class,
+                        name: QualifiedName {
+                            path: Pat::new(path.to_vec()),
+                            name: class.name.clone(),
+                        },
+                    };
+                    let prefix = snake_case(class.name.as_str());
+                    let class_params = class.type_params.clone().unwrap_or_default();
+                    for (index, ctor) in constructors(class).enumerate() {
+                        functions.push(ModuleFn {
+                            name: format!("{}_{}", prefix, constructor_name(index)),
+                            function: constructor_function(&class_ref, index, ctor),
+                            class_params: class_params.clone(),
+                        })
+                    }
+                    for member in &class.member {
+                        if let ClassMember::StaticMethod(function) = member {
+                            functions.push(ModuleFn {
+                                name: format!("{}_{}", prefix, function.name.as_str()),
+                                function: function.clone(),
+                                class_params: class_params.clone(),
+                            })
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        functions
+    }
+
+    /// Whether the module or one of its `mod`s has a `Module` trait.
+    fn has_module_trait(&self, path: &[Ident], decls: &[&Decl]) -> bool {
+        !self.module_functions(path, decls).is_empty()
+            || decls.iter().any(|decl| match decl {
+                Decl::Mod(decl) => {
+                    let mut path = path.to_vec();
+                    path.push(decl.name.clone());
+                    let decls: Vec<&Decl> = decl.items.iter().map(Box::as_ref).collect();
+                    self.has_module_trait(&path, &decls)
+                }
+                _ => false,
+            })
+    }
+
+    /// `value` of the type below the newtypes of `ty`, wrapped in them.
+    fn wrap(&self, ty: &Type, value: String) -> String {
+        match self.newtype(ty) {
+            Some((target, inner)) => {
+                format!("{}({})", self.context.path(target), self.wrap(inner, value))
+            }
+            None => value,
+        }
+    }
+
+    /// `value` of type `ty` without its newtypes.
+    fn unwrap(&self, ty: &Type, value: String) -> String {
+        match self.newtype(ty) {
+            Some((_, inner)) => self.unwrap(inner, format!("{}.0", value)),
+            None => value,
+        }
+    }
+
+    fn newtype<'t>(&'t self, ty: &'t Type) -> Option<(&'t DeclRef, &'t Type)> {
+        match self.abi.resolve(ty) {
+            Some((target, Decl::Alias(alias))) if alias.distinct => Some((target, &alias.ty)),
+            _ => None,
+        }
+    }
+
+    /// `dyn Client` for a class or interface type.
+    fn object(&self, ty: &Type) -> Result<String, CodegenError> {
+        let ty = self.abi.ir.underlying(ty);
+        match self.abi.resolve(ty) {
+            Some((target, Decl::Class(decl))) => Ok(format!(
+                "dyn {}",
+                self.trait_path(target, decl.type_params.as_deref())?
+            )),
+            Some((target, Decl::Interface(decl))) => Ok(format!(
+                "dyn {}",
+                self.trait_path(target, decl.type_params.as_deref())?
+            )),
+            _ => Err(CodegenError::custom("handles are classes or interfaces")),
+        }
+    }
+
+    /// `<Color as TryFrom<i32>>::try_from(value)?` for a unit enum type.
+    fn enum_lift(
+        &self,
+        ty: &Type,
+        repr: KeywordType,
+        value: String,
+    ) -> Result<String, CodegenError> {
+        let base = self.ty(self.abi.ir.underlying(ty))?;
+        let value = format!(
+            "<{} as TryFrom<{}>>::try_from({})?",
+            base,
+            keyword_ty(repr),
+            value
+        );
+        Ok(self.wrap(ty, value))
+    }
+
+    fn export_macro(
+        &self,
+        out: &mut CodeWriter,
+        module: &Module,
+        exports: &[Export],
+        jvm: Option<&JvmConfig>,
+        is_root: bool,
+    ) -> Result<(), CodegenError> {
+        let path = [Ident::from(&module.name)];
+        let decls: Vec<&Decl> = module.decls.iter().collect();
+        let (arm, doc) = match self.has_module_trait(&path, &decls) {
+            true => ("($module:ty)", "`$module` implements its `Module` traits"),
+            false => ("()", "it has no functions to implement"),
+        };
+        out.line(format!(
+            "/// Exports the `{}` module from the library, {}.",
+            module.name, doc
+        ));
+        out.line("#[macro_export]");
+        out.open(format!(
+            "macro_rules! export_{} {{",
+            snake_case(&module.name)
+        ));
+        out.open(format!("{} => {{", arm));
+        out.line("#[allow(non_snake_case, unused_imports, unused_unsafe, clippy::all)]");
+        out.open("const _: () = {");
+        out.lines(MACRO_IMPORTS);
+        if jvm.is_some() {
+            out.lines(MACRO_JNI_IMPORTS);
+        }
+        for export in exports {
+            out.blank();
+            self.c_shim(out, export)?;
+            if let Some(jvm) = jvm {
+                out.blank();
+                self.jni_shim(out, export, &jvm.package_name)?;
+            }
+        }
+        if let (Some(jvm), true) = (jvm, is_root) {
+            out.blank();
+            let params = [
+                "mut env: JNIEnv<'local>",
+                "_class: JClass<'local>",
+                "object: JObject<'local>",
+            ];
+            let body = ["::slime_ffi::types::callback::retain_jvm(&mut env, &object)".to_string()];
+            shim(
+                out,
+                true,
+                &jni_symbol(&jvm.package_name, "retain"),
+                &params,
+                Some("jlong"),
+                &body,
+            );
+        }
+        out.close("};");
+        out.close("};");
+        out.close("}");
+        Ok(())
+    }
+
+    /// What an export calls, the arguments already lifted into their names.
+    fn call(&self, export: &Export) -> String {
+        let function = &export.function;
+        let args: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| ident(param.name.as_str()))
+            .collect();
+        let args = args.join(", ");
+        let module = format!(
+            "<$module as {}::Module>",
+            self.context.module_path(&export.path)
+        );
+        match &export.target {
+            Target::Function => format!("{}::{}({})", module, ident(function.name.as_str()), args),
+            Target::Constructor { class, index } => {
+                let name = format!(
+                    "{}_{}",
+                    snake_case(class.name.name.as_str()),
+                    constructor_name(*index)
+                );
+                format!("{}::{}({})", module, ident(&name), args)
+            }
+            Target::Static(class) => {
+                let name = format!(
+                    "{}_{}",
+                    snake_case(class.name.name.as_str()),
+                    function.name.as_str()
+                );
+                format!("{}::{}({})", module, ident(&name), args)
+            }
+            Target::Method(_) | Target::Free(_) | Target::Register(_) => {
+                format!("(**this).{}({})", ident(function.name.as_str()), args)
+            }
+        }
+    }
+
+    /// The body of `Outcome::catch`: lifting the arguments, the call and its result.
+    fn catch_body(
+        &self,
+        export: &Export,
+        this: &str,
+        lifts: &[(String, String)],
+    ) -> Result<Vec<String>, CodegenError> {
+        let mut body = vec![];
+        let receiver = match export.receiver() {
+            Some(receiver) => {
+                let ty = Type::from(TypeKind::RefType(RefType::Resolved(receiver.clone())));
+                Some(format!("Arc<{}>", self.object(&ty)?))
+            }
+            None => None,
+        };
+        if let (Some(receiver), Target::Free(_)) = (&receiver, &export.target) {
+            body.push(format!(
+                "unsafe {{ handle::release::<{}>({}) }}?;",
+                receiver, this
+            ));
+            body.push("Ok(())".to_string());
+            return Ok(body);
+        }
+        if let Some(receiver) = &receiver {
+            body.push(format!(
+                "let this = unsafe {{ handle::resolve::<{}>({}) }}?;",
+                receiver, this
+            ));
+        }
+        for (name, lift) in lifts.iter().filter(|(name, lift)| name != lift) {
+            body.push(format!("let {} = {};", name, lift));
+        }
+        let call = self.call(export);
+        let function = &export.function;
+        match (function.is_async, function.error_type.is_some()) {
+            (true, thrown) => {
+                body.push(format!("let future = {};", call));
+                let output = match thrown {
+                    true => "future.await.map_err(CallError::thrown)",
+                    false => "Ok::<_, CallError>(future.await)",
+                };
+                body.push(format!(
+                    "Ok(future::new_future(async move {{ {} }}))",
+                    output
+                ));
+            }
+            (false, true) => body.push(format!("{}.map_err(CallError::thrown)", call)),
+            (false, false) => body.push(format!("Ok({})", call)),
+        }
+        Ok(body)
+    }
+
+    /// The Rust type an export returns, without its error.
+    fn value_ty(&self, function: &FnDecl) -> Result<String, CodegenError> {
+        match &function.return_type {
+            Some(ty) => self.ty(ty),
+            None => Ok("()".to_string()),
+        }
+    }
+
+    /// The C type of a return value and the closure lowering it.
+    fn c_return(&self, ty: Option<&Type>) -> Result<(String, String), CodegenError> {
+        let Some(ty) = ty else {
+            return Ok(("u8".to_string(), "|()| 0".to_string()));
+        };
+        let value = self.unwrap(ty, "value".to_string());
+        Ok(match self.abi.c_type(ty) {
+            AbiType::Scalar(keyword) => (
+                keyword_ty(keyword).to_string(),
+                format!("|value| {}", value),
+            ),
+            AbiType::Enum(repr) => {
+                let repr = keyword_ty(repr);
+                (repr.to_string(), format!("|value| {} as {}", value, repr))
+            }
+            AbiType::Struct(decl) => (self.context.path(&decl), format!("|value| {}", value)),
+            AbiType::Handle => (
+                "u64".to_string(),
+                format!("|value| handle::export(Arc::new({}))", value),
+            ),
+            AbiType::Stream => (
+                VOID_PTR.to_string(),
+                "|value| stream::new_stream(value)".to_string(),
+            ),
+            AbiType::Buffer => (
+                "FfiBuffer".to_string(),
+                "|value| FfiBuffer::from_vec(encode(&value))".to_string(),
+            ),
+        })
+    }
+
+    fn c_shim(&self, out: &mut CodeWriter, export: &Export) -> Result<(), CodegenError> {
+        let function = &export.function;
+        if let Target::Register(interface) = &export.target {
+            return self.c_register(out, export, interface);
+        }
+        let mut params = vec![];
+        if export.receiver().is_some() {
+            params.push("this: u64".to_string());
+        }
+        let mut lifts = vec![];
+        for param in &function.params {
+            let name = ident(param.name.as_str());
+            let (ty, lift) = match self.abi.c_type(&param.ty) {
+                AbiType::Scalar(keyword) => (
+                    keyword_ty(keyword).to_string(),
+                    self.wrap(&param.ty, name.clone()),
+                ),
+                AbiType::Enum(repr) => (
+                    keyword_ty(repr).to_string(),
+                    self.enum_lift(&param.ty, repr, name.clone())?,
+                ),
+                AbiType::Struct(decl) => {
+                    (self.context.path(&decl), self.wrap(&param.ty, name.clone()))
+                }
+                AbiType::Handle => {
+                    let object = self.object(&param.ty)?;
+                    let lift = format!(
+                        "unsafe {{ <{} as handle::Object>::from_handle({}) }}?",
+                        object, name
+                    );
+                    ("u64".to_string(), self.wrap(&param.ty, lift))
+                }
+                AbiType::Stream | AbiType::Buffer => (
+                    "FfiSlice".to_string(),
+                    format!("decode(unsafe {{ {}.as_slice() }})?", name),
+                ),
+            };
+            params.push(format!("{}: {}", name, ty));
+            lifts.push((name, lift));
+        }
+        let (ret, lower) = match function.is_async {
+            true => (VOID_PTR.to_string(), "|future| future".to_string()),
+            false => self.c_return(function.return_type.as_deref())?,
+        };
+        let mut body = vec!["Outcome::catch(|| {".to_string()];
+        body.extend(
+            self.catch_body(export, "this", &lifts)?
+                .into_iter()
+                .map(|line| format!("{}{}", INDENT, line)),
+        );
+        body.push("})".to_string());
+        body.push(format!(".into_result({})", lower));
+        let ret = format!("CallResult<Fallible<{}, FfiBuffer, FfiBuffer>>", ret);
+        shim(out, false, &export.symbol, &params, Some(&ret), &body);
+        if function.is_async {
+            let output = format!("Result<{}, CallError>", self.value_ty(function)?);
+            let (ret, lower) = self.c_return(function.return_type.as_deref())?;
+            let ret = format!("CallResult<Fallible<{}, FfiBuffer, FfiBuffer>>", ret);
+            let handle = format!("handle: {}", VOID_PTR);
+            out.blank();
+            let body = [format!(
+                "unsafe {{ future::poll_future::<{}>(handle, continuation, data) }}",
+                output
+            )];
+            let params = [
+                handle.as_str(),
+                "continuation: future::Continuation",
+                "data: u64",
+            ];
+            shim(
+                out,
+                false,
+                &format!("{}_poll", export.symbol),
+                &params,
+                None,
+                &body,
+            );
+            out.blank();
+            let body = [
+                format!(
+                    "Outcome::from_future(unsafe {{ future::complete_future::<{}>(handle) }})",
+                    output
+                ),
+                format!("{}.into_result({})", INDENT, lower),
+            ];
+            shim(
+                out,
+                false,
+                &format!("{}_complete", export.symbol),
+                &[handle.as_str()],
+                Some(&ret),
+                &body,
+            );
+            for action in ["cancel", "free"] {
+                out.blank();
+                let body = [format!(
+                    "unsafe {{ future::{}_future::<{}>(handle) }}",
+                    action, output
+                )];
+                shim(
+                    out,
+                    false,
+                    &format!("{}_{}", export.symbol, action),
+                    &[handle.as_str()],
+                    None,
+                    &body,
+                );
+            }
+        }
+        if let Some(item) = self.stream_item(function)? {
+            let handle = format!("handle: {}", VOID_PTR);
+            out.blank();
+            let body = [format!(
+                "unsafe {{ stream::poll_stream::<{}>(handle, continuation, data) }}",
+                item
+            )];
+            let params = [
+                handle.as_str(),
+                "continuation: future::Continuation",
+                "data: u64",
+            ];
+            shim(
+                out,
+                false,
+                &format!("{}_stream_poll", export.symbol),
+                &params,
+                None,
+                &body,
+            );
+            out.blank();
+            let body = [
+                format!(
+                    "Outcome::from_stream(unsafe {{ stream::next_item::<{}>(handle) }})",
+                    item
+                ),
+                format!(
+                    "{}.into_result(|item| FfiBuffer::from_vec(encode(&item)))",
+                    INDENT
+                ),
+            ];
+            let ret = "CallResult<Fallible<FfiBuffer, FfiBuffer, FfiBuffer>>";
+            shim(
+                out,
+                false,
+                &format!("{}_stream_next", export.symbol),
+                &[handle.as_str()],
+                Some(ret),
+                &body,
+            );
+            for action in ["cancel", "free"] {
+                out.blank();
+                let body = [format!(
+                    "unsafe {{ stream::{}_stream::<{}>(handle) }}",
+                    action, item
+                )];
+                shim(
+                    out,
+                    false,
+                    &format!("{}_stream_{}", export.symbol, action),
+                    &[handle.as_str()],
+                    None,
+                    &body,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The item type of a function returning a stream.
+    fn stream_item(&self, function: &FnDecl) -> Result<Option<String>, CodegenError> {
+        match function.return_type.as_deref().map(|ty| &ty.kind) {
+            Some(TypeKind::Builtin(BuiltinType::Stream(item))) => Ok(Some(self.ty(item)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn vtable_path(&self, interface: &DeclRef) -> String {
+        let module = self.context.module_path(interface.name.path.segments());
+        format!(
+            "{}::{}",
+            module,
+            vtable_static(interface.name.name.as_str())
+        )
+    }
+
+    fn c_register(
+        &self,
+        out: &mut CodeWriter,
+        export: &Export,
+        interface: &DeclRef,
+    ) -> Result<(), CodegenError> {
+        let Some(Decl::Interface(decl)) = self.get(interface) else {
+            return Err(CodegenError::custom("only interfaces are registered"));
+        };
+        let params = [
+            "dtor: Option<::slime_ffi::types::r#ref::DtorFn>",
+            "methods: *const ::std::ptr::NonNull<()>",
+            "len: u32",
+        ];
+        let body = [
+            format!("if len != {} {{", decl.vtable().len()),
+            format!("{}return false;", INDENT),
+            "}".to_string(),
+            "let vtable = unsafe { ::slime_ffi::types::callback::slime_callback_vtable(dtor, methods, len) };".to_string(),
+            "let vtable = unsafe { &*(vtable as *const ::slime_ffi::types::r#ref::FfiRefVTable) };".to_string(),
+            format!("{}.set(vtable).is_ok()", self.vtable_path(interface)),
+        ];
+        shim(out, false, &export.symbol, &params, Some("bool"), &body);
+        Ok(())
+    }
+
+    fn jni_shim(
+        &self,
+        out: &mut CodeWriter,
+        export: &Export,
+        package: &str,
+    ) -> Result<(), CodegenError> {
+        let function = &export.function;
+        let symbol =
+            |suffix: &str| jni_symbol(package, &format!("{}{}", export.jni_method(), suffix));
+        if let Target::Register(interface) = &export.target {
+            let Some(Decl::Interface(decl)) = self.get(interface) else {
+                return Err(CodegenError::custom("only interfaces are registered"));
+            };
+            let methods: Vec<String> = decl
+                .vtable()
+                .iter()
+                .map(|function| format!("({:?}, \"([B)[B\")", camel_case(function.name.as_str())))
+                .collect();
+            let params = [
+                "mut env: JNIEnv<'local>",
+                "_class: JClass<'local>",
+                "interface: JClass<'local>",
+            ];
+            let body = [
+                format!(
+                    "match ::slime_ffi::types::callback::register_jvm(&mut env, &interface, &[{}]) {{",
+                    methods.join(", ")
+                ),
+                format!("{}Ok(vtable) => {}.set(vtable).is_ok() as jboolean,", INDENT, self.vtable_path(interface)),
+                format!("{}Err(error) => {{", INDENT),
+                format!("{0}{0}let _ = env.throw_new(\"java/lang/IllegalStateException\", error.to_string());", INDENT),
+                format!("{0}{0}0", INDENT),
+                format!("{}}}", INDENT),
+                "}".to_string(),
+            ];
+            shim(out, true, &symbol(""), &params, Some("jboolean"), &body);
+            return Ok(());
+        }
+        let mut params = vec![
+            "env: JNIEnv<'local>".to_string(),
+            "_class: JClass<'local>".to_string(),
+        ];
+        if export.receiver().is_some() {
+            params.push("this: jlong".to_string());
+        }
+        let mut lifts = vec![];
+        for param in &function.params {
+            let name = ident(param.name.as_str());
+            let (ty, lift) = match self.abi.jni_type(&param.ty) {
+                AbiType::Scalar(keyword) => (
+                    jni_ty(keyword).to_string(),
+                    self.wrap(&param.ty, from_jni(keyword, &name)),
+                ),
+                AbiType::Enum(repr) => (
+                    jni_ty(repr).to_string(),
+                    self.enum_lift(&param.ty, repr, from_jni(repr, &name))?,
+                ),
+                AbiType::Handle => {
+                    let object = self.object(&param.ty)?;
+                    let lift = format!(
+                        "unsafe {{ <{} as handle::Object>::from_handle({} as u64) }}?",
+                        object, name
+                    );
+                    ("jlong".to_string(), self.wrap(&param.ty, lift))
+                }
+                AbiType::Struct(_) | AbiType::Stream | AbiType::Buffer => (
+                    "JByteArray<'local>".to_string(),
+                    format!("decode(&from_jbytes(&env, &{})?)?", name),
+                ),
+            };
+            params.push(format!("{}: {}", name, ty));
+            lifts.push((name, lift));
+        }
+        let stream_lower = match self.stream_item(function)? {
+            Some(_) => ".map(|value| stream::new_stream(value) as u64)",
+            None => "",
+        };
+        let lower = match function.is_async {
+            true => ".map(|future| future as u64)",
+            false => stream_lower,
+        };
+        let mut body = vec!["let outcome = Outcome::catch(|| {".to_string()];
+        body.extend(
+            self.catch_body(export, "this as u64", &lifts)?
+                .into_iter()
+                .map(|line| format!("{}{}", INDENT, line)),
+        );
+        body.push("});".to_string());
+        body.push(format!("to_jbytes(&env, &outcome{}.to_bytes())", lower));
+        let params: Vec<&str> = params.iter().map(String::as_str).collect();
+        shim(out, true, &symbol(""), &params, Some("jbyteArray"), &body);
+        let poll = |out: &mut CodeWriter, name: &str, poll: String| {
+            let params = [
+                "mut env: JNIEnv<'local>",
+                "_class: JClass<'local>",
+                "handle: jlong",
+                "data: jlong",
+            ];
+            let body = [
+                "match ::slime_ffi::runtime::jvm::continuation(&mut env) {".to_string(),
+                format!("{}Ok(continuation) => unsafe {{ {}(handle as _, continuation, data as u64) }},", INDENT, poll),
+                format!("{}Err(error) => {{", INDENT),
+                format!("{0}{0}let _ = env.throw_new(\"java/lang/IllegalStateException\", error.to_string());", INDENT),
+                format!("{}}}", INDENT),
+                "}".to_string(),
+            ];
+            shim(out, true, name, &params, None, &body);
+        };
+        let handle_params = [
+            "_env: JNIEnv<'local>",
+            "_class: JClass<'local>",
+            "handle: jlong",
+        ];
+        let next_params = [
+            "env: JNIEnv<'local>",
+            "_class: JClass<'local>",
+            "handle: jlong",
+        ];
+        if function.is_async {
+            let output = format!("Result<{}, CallError>", self.value_ty(function)?);
+            out.blank();
+            poll(
+                out,
+                &symbol("_poll"),
+                format!("future::poll_future::<{}>", output),
+            );
+            out.blank();
+            let body = [
+                format!("let outcome = Outcome::from_future(unsafe {{ future::complete_future::<{}>(handle as _) }});", output),
+                format!("to_jbytes(&env, &outcome{}.to_bytes())", stream_lower),
+            ];
+            shim(
+                out,
+                true,
+                &symbol("_complete"),
+                &next_params,
+                Some("jbyteArray"),
+                &body,
+            );
+            for action in ["cancel", "free"] {
+                out.blank();
+                let body = [format!(
+                    "unsafe {{ future::{}_future::<{}>(handle as _) }}",
+                    action, output
+                )];
+                shim(
+                    out,
+                    true,
+                    &symbol(&format!("_{}", action)),
+                    &handle_params,
+                    None,
+                    &body,
+                );
+            }
+        }
+        if let Some(item) = self.stream_item(function)? {
+            out.blank();
+            poll(
+                out,
+                &symbol("_stream_poll"),
+                format!("stream::poll_stream::<{}>", item),
+            );
+            out.blank();
+            let body = [
+                format!("let item = Outcome::from_stream(unsafe {{ stream::next_item::<{}>(handle as _) }});", item),
+                "to_jbytes(&env, &item.to_bytes())".to_string(),
+            ];
+            shim(
+                out,
+                true,
+                &symbol("_stream_next"),
+                &next_params,
+                Some("jbyteArray"),
+                &body,
+            );
+            for action in ["cancel", "free"] {
+                out.blank();
+                let body = [format!(
+                    "unsafe {{ stream::{}_stream::<{}>(handle as _) }}",
+                    action, item
+                )];
+                shim(
+                    out,
+                    true,
+                    &symbol(&format!("_stream_{}", action)),
+                    &handle_params,
+                    None,
+                    &body,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+const VOID_PTR: &str = "*const ::std::ffi::c_void";
+
+const MACRO_IMPORTS: &str = "\
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use ::slime_ffi::call::future::BoxFuture;
+use ::slime_ffi::call::stream::BoxStream;
+use ::slime_ffi::call::{future, stream, CallError, CallResult, Fallible, Outcome};
+use ::slime_ffi::types::bytes::{decode, encode, FfiBuffer, FfiSlice};
+use ::slime_ffi::types::handle;";
+
+const MACRO_JNI_IMPORTS: &str = "\
+#[cfg(feature = \"jvm\")]
+use ::slime_ffi::__private::jni::{objects::{JByteArray, JClass, JObject}, sys::*, JNIEnv};
+#[cfg(feature = \"jvm\")]
+use ::slime_ffi::types::bytes::{from_jbytes, to_jbytes};";
+
+/// An exported function, `jni` ones only with the `jvm` feature.
+fn shim(
+    out: &mut CodeWriter,
+    jni: bool,
+    name: &str,
+    params: &[impl AsRef<str>],
+    ret: Option<&str>,
+    body: &[String],
+) {
+    if jni {
+        out.line("#[cfg(feature = \"jvm\")]");
+    }
+    out.line("#[no_mangle]");
+    let (abi, generics) = match jni {
+        true => ("system", "<'local>"),
+        false => ("C", ""),
+    };
+    let params: Vec<&str> = params.iter().map(AsRef::as_ref).collect();
+    let ret = ret.map(|ret| format!(" -> {}", ret)).unwrap_or_default();
+    out.open(format!(
+        "pub unsafe extern \"{}\" fn {}{}({}){} {{",
+        abi,
+        name,
+        generics,
+        params.join(", "),
+        ret
+    ));
+    body.iter().for_each(|line| out.line(line));
+    out.close("}");
+}
+
+fn vtable_static(interface: &str) -> String {
+    format!("{}_VTABLE", shouty_snake_case(interface))
+}
+
+fn variant_types(fields: &EnumVariantFields) -> Box<dyn Iterator<Item = &Type> + '_> {
+    match fields {
+        EnumVariantFields::Unit => Box::new(std::iter::empty()),
+        EnumVariantFields::Unnamed(types) => Box::new(types.iter()),
+        EnumVariantFields::Named(fields) => Box::new(fields.iter().map(|field| &field.ty)),
+    }
+}
+
+/// `Event::Click { x: f0, y: f1 }` binding the payload to `f0`, `f1` and so on.
+fn variant_pattern(name: &str, variant: &str, fields: &EnumVariantFields) -> (String, usize) {
+    let variant = ident(variant);
+    match fields {
+        EnumVariantFields::Unit => (format!("{}::{}", name, variant), 0),
+        EnumVariantFields::Unnamed(types) => {
+            let bindings: Vec<String> = (0..types.len())
+                .map(|index| format!("f{}", index))
+                .collect();
+            (
+                format!("{}::{}({})", name, variant, bindings.join(", ")),
+                types.len(),
+            )
+        }
+        EnumVariantFields::Named(fields) => {
+            let bindings: Vec<String> = fields
+                .iter()
+                .enumerate()
+                .map(|(index, field)| format!("{}: f{}", ident(field.name.as_str()), index))
+                .collect();
+            (
+                format!("{}::{} {{ {} }}", name, variant, bindings.join(", ")),
+                fields.len(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::process::Command;
+
+    use slime_ffi_ir::config::JvmConfig;
+
+    use super::{RustCodegen, RustCodegenOutput, RustPath};
+    use crate::lang::test::{compile, load, temp_dir};
+    use crate::Codegen;
+
+    const APP: &str = r#"
+import "common.slime";
+
+newtype UserId = u64;
+
+model Point {
+    x: i32,
+    y: i32 = 1,
+}
+
+enum Shape {
+    Empty,
+    Circle(f64),
+}
+
+interface Listener {
+    fn on_point(point: Point);
+}
+
+fn add(a: i32, b: i32) -> i32;
+fn watch(listener: Listener);
+"#;
+
+    const COMMON: &str = r#"
+model Shared {
+    id: u32,
+}
+"#;
+
+    fn collect(output: RustCodegenOutput, files: &mut Vec<(String, String)>) {
+        match output {
+            RustCodegenOutput::Source(path, source) => {
+                files.push((path.to_string_lossy().replace('\\', "/"), source))
+            }
+            RustCodegenOutput::Mod(_, children) => {
+                for child in children {
+                    collect(*child, files)
+                }
+            }
+        }
+    }
+
+    /// Generates the Rust side, checking that every file parses.
+    fn generate(jvm: Option<&JvmConfig>) -> Vec<(String, String)> {
+        let ir = load(&[("app.slime", APP), ("common.slime", COMMON)]);
+        let mut codegen = RustCodegen::new(&ir, RustPath::parse("crate::ffi"));
+        if let Some(jvm) = jvm {
+            codegen = codegen.with_jvm(jvm);
+        }
+        let mut files = vec![];
+        collect(codegen.generate().unwrap(), &mut files);
+        for (path, source) in &files {
+            if let Err(error) = syn::parse_file(source) {
+                panic!("{} does not parse: {}\n{}", path, error, source);
+            }
+        }
+        files
+    }
+
+    fn file<'f>(files: &'f [(String, String)], path: &str) -> &'f str {
+        files
+            .iter()
+            .find(|(name, _)| name.ends_with(path))
+            .map(|(_, source)| source.as_str())
+            .unwrap_or_else(|| panic!("no file {}", path))
+    }
+
+    #[test]
+    fn test_generate() {
+        let files = generate(None);
+        let app = file(&files, "app.rs");
+        assert!(app.contains("pub struct UserId(pub u64);"));
+        assert!(app
+            .contains("#[repr(C)]\n#[derive(Debug, Clone, Copy, PartialEq)]\npub struct Point {"));
+        assert!(app.contains("Circle(f64) = 1,"));
+        assert!(app.contains("pub trait Module {"));
+        assert!(app.contains("fn add(a: i32, b: i32) -> i32;"));
+        assert!(!app.contains("#[cfg(feature = \"jvm\")]\n#[no_mangle]"));
+    }
+
+    #[test]
+    fn test_generate_jvm() {
+        let jvm = JvmConfig {
+            package_name: "com.example".into(),
+            ..Default::default()
+        };
+        let files = generate(Some(&jvm));
+        let app = file(&files, "app.rs");
+        assert!(app.contains("Java_com_example_"));
+        assert!(app.contains("unsafe { slime_ffi::types::r#ref::FfiRef::host::<"));
+    }
+
+    #[test]
+    fn test_export_macro_doc() {
+        let files = generate(None);
+        let app = file(&files, "app.rs");
+        assert!(app.contains(
+            "/// Exports the `app` module from the library, `$module` implements its `Module` traits."
+        ));
+        assert!(app.contains("($module:ty) => {"));
+        let common = file(&files, "common.rs");
+        assert!(common.contains(
+            "/// Exports the `common` module from the library, it has no functions to implement."
+        ));
+        assert!(common.contains("macro_rules! export_common {\n    () => {"));
+        assert!(!common.contains("$module"));
+    }
+
+    const FULL: &str = r#"
+newtype UserId = u64;
+
+model Point {
+    x: i32,
+    y: i32 = 1,
+}
+
+enum Mode: u8 { Fast, Slow = 4 }
+enum Event { Click { x: i32, y: i32 }, Key(u32), At(Point) }
+enum IoError { NotFound(String), Denied }
+
+interface Named {
+    name: String { get; }
+}
+
+interface Listener extends Named {
+    fn event(e: Event) throws IoError;
+    fn count(mode: Mode) -> u32;
+}
+
+class Client {
+    constructor(url: String) throws IoError;
+    url: String;
+    fn send(p: Point) -> Optional<Point>;
+    static fn default_client() -> Client;
+}
+
+fn add(a: i32, b: i32) -> i32;
+fn lookup(id: UserId) -> Optional<String> throws IoError;
+async fn fetch(url: String) -> Bytes throws IoError;
+fn events() -> Stream<Event>;
+fn listen(l: Listener, c: Client) -> Client;
+fn pair(t: (i32, String), m: Map<String, List<Point>>) -> List<UserId>;
+"#;
+
+    /// Builds the generated code against the runtime in a crate of its own, implementing
+    /// `Module` with `todo!()`. Skipped where the runtime's dependencies are not available
+    /// offline.
+    #[test]
+    fn test_compile() {
+        let runtime = Path::new(env!("CARGO_MANIFEST_DIR")).join("../slime-ffi");
+        if !runtime.join("Cargo.toml").exists() {
+            eprintln!(
+                "no runtime at {}, skipping the compile check",
+                runtime.display()
+            );
+            return;
+        }
+        let jvm = JvmConfig {
+            package_name: "com.example".into(),
+            ..Default::default()
+        };
+        let ir = load(&[("app.slime", FULL)]);
+        let output = RustCodegen::new(&ir, RustPath::parse("crate::ffi"))
+            .with_jvm(&jvm)
+            .generate()
+            .unwrap();
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        output.write_to(&dir.join("src")).unwrap();
+        let mut files = vec![];
+        collect(output, &mut files);
+        let module = file(&files, "app.rs");
+        let start = module.find("pub trait Module {\n").unwrap();
+        let end = start + module[start..].find("\n}\n").unwrap();
+        let functions: Vec<String> = module[start..end]
+            .lines()
+            .skip(1)
+            .map(|line| format!("{} {{ todo!() }}", line.trim_end_matches(';')))
+            .collect();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            format!(
+                "#![allow(unused)]\n\
+                 pub mod ffi;\n\
+                 use std::collections::HashMap;\n\
+                 use std::sync::Arc;\n\
+                 use slime_ffi::call::future::BoxFuture;\n\
+                 use slime_ffi::call::stream::BoxStream;\n\
+                 use ffi::app::*;\n\
+                 pub struct Lib;\n\
+                 impl Module for Lib {{\n{}\n}}\n\
+                 export_app!(Lib);\n",
+                functions.join("\n")
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"generated\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\nslime-ffi = {{ path = {:?} }}\n\n\
+                 [features]\njvm = [\"slime-ffi/jvm\"]\n\n[workspace]\n",
+                runtime.canonicalize().unwrap()
+            ),
+        )
+        .unwrap();
+
+        // Shared between runs, the runtime is only built once.
+        let target = std::env::temp_dir().join("slime-ffi-codegen-target");
+        let resolved = Command::new("cargo")
+            .args(["generate-lockfile", "--offline"])
+            .current_dir(&dir)
+            .output();
+        if !resolved.is_ok_and(|output| output.status.success()) {
+            eprintln!("the runtime's dependencies are not available, skipping the compile check");
+            return;
+        }
+        compile(
+            "cargo",
+            &[
+                "build",
+                "--offline",
+                "--features",
+                "jvm",
+                "--target-dir",
+                target.to_str().unwrap(),
+            ],
+            &dir,
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
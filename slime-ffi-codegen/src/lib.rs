@@ -1,11 +1,29 @@
 use std::error::Error;
+use std::fmt;
 
 use slime_ffi_ir::Lang;
 
+#[derive(Debug)]
 pub enum CodegenError {
     Custom(Box<dyn Error>),
 }
 
+impl CodegenError {
+    pub fn custom(message: impl Into<String>) -> Self {
+        CodegenError::Custom(message.into().into())
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Custom(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for CodegenError {}
+
 pub trait Codegen {
     const LANG: Lang;
 
@@ -14,4 +32,7 @@ pub trait Codegen {
     fn generate(&self) -> Result<Self::Output, CodegenError>;
 }
 
+pub mod abi;
 pub mod lang;
+pub mod names;
+pub mod writer;
@@ -0,0 +1,69 @@
+//! Case conversions between IDL names and the conventions of the target languages.
+
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            // `fooBar`, `foo2Bar` and the `Client` of `HTTPClient` start a word.
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// `HttpClient` to `http_client`.
+pub fn snake_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `max_retries` to `MAX_RETRIES`.
+pub fn shouty_snake_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+/// `http_client` to `HttpClient`.
+pub fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|word| capitalize(word)).collect()
+}
+
+/// `http_client` to `httpClient`.
+pub fn camel_case(name: &str) -> String {
+    let words = words(name);
+    let mut words = words.iter();
+    let first = words
+        .next()
+        .map(|word| word.to_lowercase())
+        .unwrap_or_default();
+    first + &words.map(|word| capitalize(word)).collect::<String>()
+}
@@ -0,0 +1,60 @@
+/// Builds source text line by line with the indentation of the target language.
+pub struct CodeWriter {
+    out: String,
+    indent: usize,
+    unit: &'static str,
+}
+
+impl CodeWriter {
+    /// `unit` is one level of indentation, e.g. four spaces.
+    pub fn new(unit: &'static str) -> Self {
+        CodeWriter {
+            out: String::new(),
+            indent: 0,
+            unit,
+        }
+    }
+
+    pub fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if !line.is_empty() {
+            self.out.push_str(&self.unit.repeat(self.indent));
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+    }
+
+    /// Writes every line of `text` at the current indentation.
+    pub fn lines(&mut self, text: impl AsRef<str>) {
+        text.as_ref().lines().for_each(|line| self.line(line))
+    }
+
+    pub fn blank(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes `line` and indents the lines after it, e.g. after a `{`.
+    pub fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    /// Dedents and writes `line`, e.g. a `}`.
+    pub fn close(&mut self, line: impl AsRef<str>) {
+        self.indent -= 1;
+        self.line(line);
+    }
+
+    /// Dedents, writes `line` and indents again, e.g. a `} else {`.
+    pub fn reopen(&mut self, line: impl AsRef<str>) {
+        self.indent -= 1;
+        self.line(line);
+        self.indent += 1;
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
@@ -0,0 +1,1027 @@
+use slime_ffi_ir::{
+    ast::{
+        AliasDecl, BuiltinType, ClassDecl, ClassMember, ConstDecl, ConstLit, Decl, DeclKind,
+        DeclRef, EnumDecl, Expr, FieldDecl, FnDecl, Function, Ident, InterfaceDecl,
+        InterfaceMember, KeywordType, Lit, Module, NumLit, PropertyDecl, RefType, Type, TypeKind,
+        TypeWithArgs,
+    },
+    span::Span,
+    visit::{self, VisitMut},
+};
+
+use crate::{
+    diagnostic::Diagnostic,
+    resolve::{display_path, kind_name},
+};
+
+pub(crate) fn display_type(ty: &Type) -> String {
+    let name = match &ty.kind {
+        TypeKind::Keyword(keyword) => keyword.name().to_owned(),
+        TypeKind::RefType(RefType::Ident(name) | RefType::TypeParam(name)) => {
+            name.as_str().to_owned()
+        }
+        TypeKind::RefType(RefType::QualifiedName(name)) => display_path(name),
+        TypeKind::RefType(RefType::Resolved(target)) => display_path(&target.name),
+        TypeKind::Builtin(BuiltinType::Optional(inner)) => {
+            format!("Optional<{}>", display_type(inner))
+        }
+        TypeKind::Builtin(BuiltinType::List(inner)) => format!("List<{}>", display_type(inner)),
+        TypeKind::Builtin(BuiltinType::Map(map)) => format!(
+            "Map<{}, {}>",
+            display_type(&map.key),
+            display_type(&map.value)
+        ),
+        TypeKind::Builtin(BuiltinType::Set(inner)) => format!("Set<{}>", display_type(inner)),
+        TypeKind::Builtin(BuiltinType::Stream(inner)) => {
+            format!("Stream<{}>", display_type(inner))
+        }
+        TypeKind::Builtin(BuiltinType::Tuple(elements)) => format!(
+            "({})",
+            elements
+                .iter()
+                .map(display_type)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        TypeKind::Builtin(BuiltinType::Array(array)) => {
+            format!("Array<{}, {}>", display_type(&array.element), array.len)
+        }
+    };
+    match &ty.type_args {
+        Some(args) => format!(
+            "{}<{}>",
+            name,
+            args.iter().map(display_type).collect::<Vec<_>>().join(", ")
+        ),
+        None => name,
+    }
+}
+
+fn display_lit(lit: &Lit) -> String {
+    match lit {
+        Lit::Str(value) => format!("{:?}", value),
+        Lit::Bool(value) => value.to_string(),
+        Lit::Num(num) => match num {
+            NumLit::I8(value) => value.to_string(),
+            NumLit::I16(value) => value.to_string(),
+            NumLit::I32(value) => value.to_string(),
+            NumLit::I64(value) => value.to_string(),
+            NumLit::U8(value) => value.to_string(),
+            NumLit::U16(value) => value.to_string(),
+            NumLit::U32(value) => value.to_string(),
+            NumLit::U64(value) => value.to_string(),
+            NumLit::F32(value) => value.to_string(),
+            NumLit::F64(value) => value.to_string(),
+        },
+    }
+}
+
+fn lit_kind(lit: &Lit) -> &'static str {
+    match lit {
+        Lit::Str(_) => "string literal",
+        Lit::Bool(_) => "`bool` literal",
+        Lit::Num(NumLit::F32(_) | NumLit::F64(_)) => "float literal",
+        Lit::Num(_) => "integer literal",
+    }
+}
+
+fn int_value(num: &NumLit) -> Option<i128> {
+    Some(match *num {
+        NumLit::I8(value) => value.into(),
+        NumLit::I16(value) => value.into(),
+        NumLit::I32(value) => value.into(),
+        NumLit::I64(value) => value.into(),
+        NumLit::U8(value) => value.into(),
+        NumLit::U16(value) => value.into(),
+        NumLit::U32(value) => value.into(),
+        NumLit::U64(value) => value.into(),
+        NumLit::F32(_) | NumLit::F64(_) => return None,
+    })
+}
+
+fn float_value(num: &NumLit) -> f64 {
+    match *num {
+        NumLit::F32(value) => value.into(),
+        NumLit::F64(value) => value,
+        _ => int_value(num).unwrap() as f64,
+    }
+}
+
+/// Converts a literal to the literal of `ty`, `Err(None)` on a type mismatch and
+/// `Err(Some(range))` when out of range.
+fn typed_lit(ty: KeywordType, lit: &Lit) -> Result<Lit, Option<&'static str>> {
+    let num = match (ty, lit) {
+        (KeywordType::Bool, Lit::Bool(_)) | (KeywordType::String, Lit::Str(_)) => {
+            return Ok(lit.clone())
+        }
+        (_, Lit::Num(num)) => num,
+        _ => return Err(None),
+    };
+    let int = int_value(num);
+    let typed = match ty {
+        KeywordType::Float32 => {
+            let value = float_value(num);
+            if value.is_finite() && !(value as f32).is_finite() {
+                return Err(Some("the range of `f32`"));
+            }
+            NumLit::F32(value as f32)
+        }
+        KeywordType::Float64 => NumLit::F64(float_value(num)),
+        _ => {
+            let int = int.ok_or(None)?;
+            match ty {
+                KeywordType::Int8 => NumLit::I8(int.try_into().map_err(|_| Some("-128..=127"))?),
+                KeywordType::Int16 => {
+                    NumLit::I16(int.try_into().map_err(|_| Some("-32768..=32767"))?)
+                }
+                KeywordType::Int32 => NumLit::I32(
+                    int.try_into()
+                        .map_err(|_| Some("-2147483648..=2147483647"))?,
+                ),
+                KeywordType::Int64 => NumLit::I64(
+                    int.try_into()
+                        .map_err(|_| Some("-9223372036854775808..=9223372036854775807"))?,
+                ),
+                KeywordType::Uint8 => NumLit::U8(int.try_into().map_err(|_| Some("0..=255"))?),
+                KeywordType::Uint16 => NumLit::U16(int.try_into().map_err(|_| Some("0..=65535"))?),
+                KeywordType::Uint32 => {
+                    NumLit::U32(int.try_into().map_err(|_| Some("0..=4294967295"))?)
+                }
+                KeywordType::Uint64 => NumLit::U64(
+                    int.try_into()
+                        .map_err(|_| Some("0..=18446744073709551615"))?,
+                ),
+                _ => return Err(None),
+            }
+        }
+    };
+    Ok(Lit::Num(typed))
+}
+
+/// The literal the parser would produce for `value`.
+fn int_lit(value: i128) -> Option<NumLit> {
+    match i64::try_from(value) {
+        Ok(value) => Some(NumLit::I64(value)),
+        Err(_) => u64::try_from(value).ok().map(NumLit::U64),
+    }
+}
+
+fn is_integer(ty: KeywordType) -> bool {
+    matches!(
+        ty,
+        KeywordType::Int8
+            | KeywordType::Int16
+            | KeywordType::Int32
+            | KeywordType::Int64
+            | KeywordType::Uint8
+            | KeywordType::Uint16
+            | KeywordType::Uint32
+            | KeywordType::Uint64
+    )
+}
+
+fn is_hashable(ty: &Type) -> bool {
+    match ty.kind {
+        TypeKind::Keyword(keyword) => {
+            is_integer(keyword) || matches!(keyword, KeywordType::Bool | KeywordType::String)
+        }
+        _ => false,
+    }
+}
+
+fn check_unique(diagnostics: &mut Vec<Diagnostic>, items: Vec<&Decl>) {
+    let mut seen: Vec<&Ident> = vec![];
+    for item in items {
+        let name = item.name();
+        match seen.iter().find(|prev| **prev == name) {
+            Some(prev) => diagnostics.push(
+                Diagnostic::error(format!(
+                    "the name `{}` is defined multiple times",
+                    name.as_str()
+                ))
+                .with_secondary(prev.span, "first defined here")
+                .with_label(name.span, "redefined here")
+                .with_note("names must be unique within a module or `mod`"),
+            ),
+            None => seen.push(name),
+        }
+        if let Decl::Mod(decl) = item {
+            check_unique(
+                diagnostics,
+                decl.items.iter().map(|item| item.as_ref()).collect(),
+            );
+        }
+    }
+}
+
+/// Checks which need resolved names, run once resolution is done.
+struct Checker<'a, 'd> {
+    /// Snapshot of all modules, to look through aliases.
+    modules: &'a [Module],
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'a, 'd> Checker<'a, 'd> {
+    /// The type `ty` stands for once aliases and newtypes are replaced by their definition.
+    /// Stops at a cyclic alias, which is reported separately.
+    fn underlying<'t>(&self, ty: &'t Type) -> &'t Type
+    where
+        'a: 't,
+    {
+        let mut ty = ty;
+        let mut seen: Vec<&DeclRef> = vec![];
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            if target.kind != DeclKind::Alias || seen.contains(&target) {
+                break;
+            }
+            seen.push(target);
+            match self.lookup(target) {
+                Some(Decl::Alias(decl)) => ty = &decl.ty,
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    fn lookup(&self, target: &DeclRef) -> Option<&'a Decl> {
+        let module = target.name.path.segments().first()?;
+        self.modules
+            .iter()
+            .find(|m| m.name == module.as_str())?
+            .lookup(&target.name)
+    }
+
+    /// Type arguments go to generic classes and interfaces, one per type parameter.
+    fn check_type_args(&mut self, ty: &Type, args: &[Type]) {
+        let (what, params) = match &ty.kind {
+            TypeKind::RefType(RefType::Resolved(target)) => {
+                let params = match self.lookup(target) {
+                    Some(Decl::Class(decl)) => decl.type_params.as_deref(),
+                    Some(Decl::Interface(decl)) => decl.type_params.as_deref(),
+                    _ => None,
+                };
+                (
+                    format!(
+                        "{} `{}`",
+                        kind_name(target.kind),
+                        display_path(&target.name)
+                    ),
+                    params.map_or(0, <[_]>::len),
+                )
+            }
+            TypeKind::RefType(RefType::TypeParam(name)) => {
+                (format!("type parameter `{}`", name.as_str()), 0)
+            }
+            // Unresolved names are reported by name resolution.
+            _ => return,
+        };
+        if params != args.len() {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "{} takes {} type argument(s) but {} were supplied",
+                    what,
+                    params,
+                    args.len()
+                ))
+                .with_label(ty.span, format!("expected {}", params)),
+            );
+        }
+    }
+
+    fn lit_error(
+        &mut self,
+        keyword: KeywordType,
+        lit: &Lit,
+        range: Option<&str>,
+        span: Span,
+        ty_span: Span,
+    ) {
+        let diagnostic = match range {
+            None => Diagnostic::error(format!(
+                "mismatched types: expected `{}`, found {}",
+                keyword.name(),
+                lit_kind(lit)
+            ))
+            .with_label(
+                span,
+                format!("`{}` is not a `{}`", display_lit(lit), keyword.name()),
+            )
+            .with_secondary(ty_span, "expected because of this type"),
+            Some(range) => {
+                Diagnostic::error(format!("literal out of range for `{}`", keyword.name()))
+                    .with_label(
+                        span,
+                        format!(
+                            "`{}` does not fit into `{}`",
+                            display_lit(lit),
+                            keyword.name()
+                        ),
+                    )
+                    .with_secondary(ty_span, "declared type")
+                    .with_note(format!("`{}` values are within {}", keyword.name(), range))
+            }
+        };
+        self.diagnostics.push(diagnostic)
+    }
+
+    /// Converts the default value of `field` to its type, see [`FieldDecl::default`].
+    fn check_default(&mut self, field: &mut FieldDecl) {
+        let Some(default) = &field.default else {
+            return;
+        };
+        let (ty, optional) = match &field.ty.kind {
+            TypeKind::Builtin(BuiltinType::Optional(inner)) => (self.underlying(inner), true),
+            _ => (self.underlying(&field.ty), false),
+        };
+        let mismatch = |found: String| {
+            Diagnostic::error(format!(
+                "mismatched types: expected `{}`, found {}",
+                display_type(&field.ty),
+                found
+            ))
+            .with_label(field.span, "default value of the wrong type")
+            .with_secondary(field.ty.span, "expected because of this type")
+        };
+        match default {
+            Expr::Null if !optional => self.diagnostics.push(
+                mismatch("`null`".to_owned())
+                    .with_note("only `Optional` fields and parameters can default to `null`"),
+            ),
+            Expr::Lit(lit) => match ty.kind {
+                TypeKind::Keyword(keyword) if keyword != KeywordType::Bytes => {
+                    match typed_lit(keyword, lit) {
+                        Ok(lit) => field.default = Some(Expr::Const(ConstLit::Primitive(lit))),
+                        Err(range) => {
+                            let lit = lit.clone();
+                            self.lit_error(keyword, &lit, range, field.span, field.ty.span)
+                        }
+                    }
+                }
+                _ => self.diagnostics.push(mismatch(lit_kind(lit).to_owned())),
+            },
+            Expr::Const(ConstLit::UnitEnumVariant(variant))
+                if self.underlying(&variant.ty) != ty =>
+            {
+                self.diagnostics.push(mismatch(format!(
+                    "variant `{}.{}`",
+                    display_type(&variant.ty),
+                    variant.variant_name.as_str()
+                )))
+            }
+            // Names which failed to resolve have been reported already.
+            _ => {}
+        }
+    }
+
+    /// Reports properties whose generated getter or setter takes the name of a method.
+    fn check_accessors(&mut self, properties: &[&PropertyDecl], methods: &[&FnDecl]) {
+        for property in properties {
+            for accessor in property.getter().into_iter().chain(property.setter()) {
+                if let Some(method) = methods.iter().find(|method| method.name == accessor.name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "method `{}` clashes with an accessor of property `{}`",
+                            method.name.as_str(),
+                            property.name.as_str()
+                        ))
+                        .with_label(method.name.span, "defined here")
+                        .with_secondary(
+                            property.name.span,
+                            format!("generates `{}`", accessor.name.as_str()),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn expect_kind(&mut self, ty: &TypeWithArgs, expected: DeclKind) {
+        let Some(target) = &ty.target else {
+            return;
+        };
+        if target.kind != expected {
+            let expected = match expected {
+                DeclKind::Interface => "an interface",
+                _ => "a class",
+            };
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "expected {}, found {} `{}`",
+                    expected,
+                    kind_name(target.kind),
+                    display_path(&target.name)
+                ))
+                .with_label(ty.ty.span, format!("not {}", expected))
+                .with_secondary(target.name.name.span, "defined here"),
+            );
+        }
+    }
+}
+
+impl<'a, 'd> VisitMut for Checker<'a, 'd> {
+    fn visit_const_decl_mut(&mut self, decl: &mut ConstDecl) {
+        let keyword = match self.underlying(&decl.ty).kind {
+            TypeKind::Keyword(KeywordType::Bytes) | TypeKind::Builtin(_) | TypeKind::RefType(_) => {
+                self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "consts of type `{}` are not supported",
+                        display_type(&decl.ty)
+                    ))
+                    .with_label(decl.ty.span, "unsupported const type")
+                    .with_note("consts must be numbers, `bool` or `String`"),
+                );
+                return;
+            }
+            TypeKind::Keyword(keyword) => keyword,
+        };
+        match typed_lit(keyword, &decl.value) {
+            Ok(value) => decl.value = value,
+            Err(range) => self.lit_error(keyword, &decl.value, range, decl.span, decl.ty.span),
+        }
+    }
+
+    fn visit_alias_decl_mut(&mut self, decl: &mut AliasDecl) {
+        if decl.distinct && !matches!(self.underlying(&decl.ty).kind, TypeKind::Keyword(_)) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "newtype `{}` must wrap a primitive type",
+                    decl.name.as_str()
+                ))
+                .with_label(decl.ty.span, "not a primitive type")
+                .with_note("newtypes are passed as their underlying type, use `type` for an alias"),
+            );
+        }
+        self.visit_type_mut(&mut decl.ty)
+    }
+
+    fn visit_field_mut(&mut self, field: &mut FieldDecl) {
+        self.check_default(field);
+        self.visit_type_mut(&mut field.ty)
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        let mut defaulted: Option<&FieldDecl> = None;
+        for param in &function.params {
+            match (param.default.is_some(), defaulted) {
+                (true, None) => defaulted = Some(param),
+                (false, Some(prev)) => self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "parameter `{}` needs a default value",
+                        param.name.as_str()
+                    ))
+                    .with_label(param.span, "no default value")
+                    .with_secondary(prev.span, "follows this parameter with a default value")
+                    .with_note("parameters with default values must come last"),
+                ),
+                _ => {}
+            }
+        }
+        if let Some(error) = &function.error_type {
+            let is_enum = matches!(
+                &self.underlying(error).kind,
+                TypeKind::RefType(RefType::Resolved(target)) if target.kind == DeclKind::Enum
+            );
+            // Unresolved names have been reported already.
+            if !is_enum
+                && !matches!(
+                    error.kind,
+                    TypeKind::RefType(RefType::Ident(_) | RefType::QualifiedName(_))
+                )
+            {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` cannot be thrown", display_type(error)))
+                        .with_label(error.span, "not an enum")
+                        .with_note("errors are enums, their variants become the exception types"),
+                );
+            }
+        }
+        // The return type is the only place a `Stream` may appear, check its items and walk
+        // the rest of the signature without it.
+        if let Some(TypeKind::Builtin(BuiltinType::Stream(inner))) =
+            function.return_type.as_deref_mut().map(|ty| &mut ty.kind)
+        {
+            self.visit_type_mut(inner);
+            let stream = function.return_type.take();
+            visit::walk_function_mut(self, function);
+            function.return_type = stream;
+            return;
+        }
+        visit::walk_function_mut(self, function)
+    }
+
+    fn visit_enum_decl_mut(&mut self, decl: &mut EnumDecl) {
+        if let Some(repr) = &decl.repr {
+            if !matches!(repr.kind, TypeKind::Keyword(keyword) if is_integer(keyword)) {
+                self.diagnostics.push(
+                    Diagnostic::error(format!("`{}` cannot back an enum", display_type(repr)))
+                        .with_label(repr.span, "not an integer type"),
+                );
+                decl.repr = None;
+            }
+        }
+        let repr = decl.repr_type();
+
+        let mut seen: Vec<(i128, &Ident)> = vec![];
+        let mut next = Some(0i128);
+        for variant in &mut decl.variants {
+            let value = match &variant.discriminant {
+                Some(num) => match int_value(num) {
+                    Some(value) => value,
+                    None => {
+                        self.diagnostics.push(
+                            Diagnostic::error("enum discriminants must be integers")
+                                .with_label(variant.name.span, "float discriminant"),
+                        );
+                        next = None;
+                        continue;
+                    }
+                },
+                None => match next {
+                    Some(value) => value,
+                    // The previous value is broken and has been reported.
+                    None => continue,
+                },
+            };
+            match int_lit(value).map(|num| typed_lit(repr, &Lit::Num(num))) {
+                Some(Ok(Lit::Num(num))) => variant.discriminant = Some(num),
+                _ => {
+                    let diagnostic = if variant.discriminant.is_some() {
+                        Diagnostic::error(format!(
+                            "discriminant `{}` is out of range for `{}`",
+                            value,
+                            repr.name()
+                        ))
+                    } else {
+                        Diagnostic::error(format!("enum discriminant overflowed `{}`", repr.name()))
+                            .with_note(format!(
+                                "the variant would get `{}`, one more than the previous one",
+                                value
+                            ))
+                    };
+                    let mut diagnostic = diagnostic.with_label(variant.name.span, "out of range");
+                    if let Some(repr) = &decl.repr {
+                        diagnostic = diagnostic.with_secondary(repr.span, "backing type");
+                    }
+                    self.diagnostics.push(diagnostic);
+                    next = None;
+                    continue;
+                }
+            }
+            match seen.iter().find(|(prev, _)| *prev == value) {
+                Some((_, prev)) => self.diagnostics.push(
+                    Diagnostic::error(format!(
+                        "discriminant `{}` is assigned more than once",
+                        value
+                    ))
+                    .with_secondary(
+                        prev.span,
+                        format!("first assigned to `{}` here", prev.as_str()),
+                    )
+                    .with_label(variant.name.span, "assigned again here"),
+                ),
+                None => seen.push((value, &variant.name)),
+            }
+            next = Some(value + 1);
+        }
+        visit::walk_enum_decl_mut(self, decl)
+    }
+
+    fn visit_interface_decl_mut(&mut self, decl: &mut InterfaceDecl) {
+        for extend in &decl.extends {
+            self.expect_kind(extend, DeclKind::Interface);
+        }
+        let mut properties = vec![];
+        let mut methods = vec![];
+        for member in &decl.member {
+            match member {
+                InterfaceMember::Method(method) => methods.push(method),
+                InterfaceMember::Property(property) => properties.push(property),
+            }
+        }
+        self.check_accessors(&properties, &methods);
+        visit::walk_interface_decl_mut(self, decl)
+    }
+
+    fn visit_class_decl_mut(&mut self, decl: &mut ClassDecl) {
+        if let Some(extend) = &decl.extend {
+            self.expect_kind(extend, DeclKind::Class);
+        }
+        for ty in decl.impls.iter().flatten() {
+            self.expect_kind(ty, DeclKind::Interface);
+        }
+        let mut properties = vec![];
+        let mut methods = vec![];
+        for member in &decl.member {
+            match member {
+                ClassMember::Method(method) | ClassMember::StaticMethod(method) => {
+                    methods.push(method)
+                }
+                ClassMember::Property(property) => properties.push(property),
+                _ => {}
+            }
+        }
+        self.check_accessors(&properties, &methods);
+        visit::walk_class_decl_mut(self, decl)
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        let (key, what) = match &ty.kind {
+            TypeKind::Builtin(BuiltinType::Map(map)) => (Some(&map.key), ("map key", "map keys")),
+            TypeKind::Builtin(BuiltinType::Set(inner)) => {
+                (Some(inner.as_ref()), ("set element", "set elements"))
+            }
+            _ => (None, ("", "")),
+        };
+        if let TypeKind::Builtin(BuiltinType::Stream(_)) = &ty.kind {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` is not a return type", display_type(ty)))
+                    .with_label(ty.span, "`Stream` used here")
+                    .with_note("`Stream` can only be the return type of a function"),
+            );
+        }
+        if let Some(args) = &ty.type_args {
+            self.check_type_args(ty, args);
+        }
+        if let Some(key) = key.filter(|key| !is_hashable(self.underlying(key))) {
+            self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "`{}` cannot be used as a {}",
+                    display_type(key),
+                    what.0
+                ))
+                .with_label(key.span, "not hashable")
+                .with_note(format!("{} must be integers, `bool` or `String`", what.1)),
+            );
+        }
+        visit::walk_type_mut(self, ty)
+    }
+}
+
+/// Semantic checks over resolved modules. Const values are converted to their declared type.
+pub(crate) fn check_modules(modules: &mut [Module]) -> Vec<Diagnostic> {
+    let snapshot = modules.to_vec();
+    let mut diagnostics = vec![];
+    for module in modules {
+        check_unique(&mut diagnostics, module.decls.iter().collect());
+        Checker {
+            modules: &snapshot,
+            diagnostics: &mut diagnostics,
+        }
+        .visit_module_mut(module);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::ast::{
+        ClassMember, ConstLit, Decl, Expr, KeywordType, Lit, NumLit, TypeKind,
+    };
+
+    use crate::loader::test::{errors, load};
+
+    fn check(src: &str) -> Vec<String> {
+        errors(&[("app.slime", src)])
+    }
+
+    #[test]
+    fn test_const_types() {
+        let graph = load(&[(
+            "app.slime",
+            "const SMALL: u8 = 200;\nconst RATIO: f32 = 2;\nconst NAME: String = \"x\";",
+        )])
+        .unwrap();
+        let values = graph
+            .ir
+            .root()
+            .decls
+            .iter()
+            .map(|decl| match decl {
+                Decl::Const(decl) => decl.value.clone(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                Lit::Num(NumLit::U8(200)),
+                Lit::Num(NumLit::F32(2.0)),
+                Lit::Str("x".to_owned()),
+            ]
+        );
+
+        assert_eq!(
+            check("const SMALL: u8 = 256;\nconst NEG: u32 = -1;"),
+            [
+                "literal out of range for `u8`",
+                "literal out of range for `u32`"
+            ]
+        );
+        assert_eq!(
+            check("const FLAG: bool = 1;"),
+            ["mismatched types: expected `bool`, found integer literal"]
+        );
+        assert_eq!(
+            check("const DATA: Bytes = \"x\";\nconst IDS: List<u32> = 1;"),
+            [
+                "consts of type `Bytes` are not supported",
+                "consts of type `List<u32>` are not supported",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unique_names() {
+        assert_eq!(
+            check("model A {}\nenum A { X }\nmod m { fn f(); fn f(); }"),
+            [
+                "the name `A` is defined multiple times",
+                "the name `f` is defined multiple times",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inheritance_kinds() {
+        assert_eq!(
+            check("model M {}\ninterface I extends M {}\nclass C extends I implements M {}"),
+            [
+                "expected an interface, found model `app.M`",
+                "expected a class, found interface `app.I`",
+                "expected an interface, found model `app.M`",
+            ]
+        );
+        assert_eq!(
+            check("const N: u32 = 1;\nfn f(n: N);"),
+            ["expected a type, found const `app.N`"]
+        );
+    }
+
+    #[test]
+    fn test_type_args() {
+        assert!(check(
+            "interface L<T> {}\nclass C<K, V> {}\nfn f(l: L<u8>, c: List<C<u8, L<String>>>);"
+        )
+        .is_empty());
+        assert_eq!(
+            check(
+                "interface L<T> {}\nmodel M {}\n\
+                 fn f(l: L<u8, u8>, m: M<u8>);\n\
+                 interface I<T> { fn g(t: T<u8>); }"
+            ),
+            [
+                "interface `app.L` takes 1 type argument(s) but 2 were supplied",
+                "model `app.M` takes 0 type argument(s) but 1 were supplied",
+                "type parameter `T` takes 0 type argument(s) but 1 were supplied",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discriminants() {
+        let graph = load(&[(
+            "app.slime",
+            "enum Status: u16 { Ok = 200, Created, NotFound = 404 }
+enum Plain { A, B }",
+        )])
+        .unwrap();
+        let Decl::Enum(status) = &graph.ir.root().decls[0] else {
+            panic!("expected an enum");
+        };
+        assert_eq!(status.repr_type(), KeywordType::Uint16);
+        let values = status
+            .variants
+            .iter()
+            .map(|variant| variant.discriminant.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                Some(NumLit::U16(200)),
+                Some(NumLit::U16(201)),
+                Some(NumLit::U16(404)),
+            ]
+        );
+        let Decl::Enum(plain) = &graph.ir.root().decls[1] else {
+            panic!("expected an enum");
+        };
+        assert_eq!(plain.repr_type(), KeywordType::Int32);
+        assert_eq!(plain.variants[1].discriminant, Some(NumLit::I32(1)));
+
+        assert_eq!(
+            check("enum E: u8 { A = 255, B }"),
+            ["enum discriminant overflowed `u8`"]
+        );
+        assert_eq!(
+            check("enum E: i8 { A = -129 }"),
+            ["discriminant `-129` is out of range for `i8`"]
+        );
+        assert_eq!(
+            check("enum E { A = 1, B = 0, C }"),
+            ["discriminant `1` is assigned more than once"]
+        );
+        assert_eq!(
+            check(
+                "enum E: String { A }
+enum F { A = 1.5 }"
+            ),
+            [
+                "`String` cannot back an enum",
+                "enum discriminants must be integers",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_properties() {
+        let graph = load(&[(
+            "app.slime",
+            "class Player { volume: u8 { get; set; } position: u64 { get; } seek: u64 { set; } }",
+        )])
+        .unwrap();
+        let Decl::Class(player) = &graph.ir.root().decls[0] else {
+            panic!("expected a class");
+        };
+        let accessors = player
+            .member
+            .iter()
+            .map(|member| match member {
+                ClassMember::Property(property) => property
+                    .getter()
+                    .into_iter()
+                    .chain(property.setter())
+                    .map(|accessor| accessor.name.as_str().to_owned())
+                    .collect::<Vec<_>>(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            accessors,
+            [
+                vec!["get_volume", "set_volume"],
+                vec!["get_position"],
+                vec!["set_seek"],
+            ]
+        );
+
+        assert_eq!(
+            check("class C { volume: u8 { get; set; } fn set_volume(value: u8); }"),
+            ["method `set_volume` clashes with an accessor of property `volume`"]
+        );
+        assert_eq!(
+            check("class C { volume: u8 { get; get; } mute: bool { } }"),
+            ["duplicate accessor", "property `mute` has no accessors"]
+        );
+    }
+
+    #[test]
+    fn test_default_values() {
+        let graph = load(&[(
+            "app.slime",
+            r#"
+            const RETRIES: u8 = 3;
+            enum Mode { Fast, Slow }
+            model Options {
+                retries: u8 = RETRIES,
+                mode: Mode = Mode.Slow,
+                name: Optional<String> = null,
+                ratio: f32 = 1,
+            }
+            "#,
+        )])
+        .unwrap();
+        let Decl::Model(options) = &graph.ir.root().decls[2] else {
+            panic!("expected a model");
+        };
+        let defaults = options
+            .fields
+            .iter()
+            .map(|field| field.default.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            defaults[0],
+            Expr::Const(ConstLit::Primitive(Lit::Num(NumLit::U8(3))))
+        );
+        assert!(matches!(
+            &defaults[1],
+            Expr::Const(ConstLit::UnitEnumVariant(variant)) if variant.variant_name.as_str() == "Slow"
+        ));
+        assert_eq!(defaults[2], Expr::Null);
+        assert_eq!(
+            defaults[3],
+            Expr::Const(ConstLit::Primitive(Lit::Num(NumLit::F32(1.0))))
+        );
+
+        assert_eq!(
+            check("model M { id: u64 = null, name: String = 1, }"),
+            [
+                "mismatched types: expected `u64`, found `null`",
+                "mismatched types: expected `String`, found integer literal",
+            ]
+        );
+        assert_eq!(
+            check(
+                "enum A { X }
+enum B { Y }
+model M { a: A = B.Y, }"
+            ),
+            ["mismatched types: expected `app.A`, found variant `app.B.Y`"]
+        );
+        assert_eq!(
+            check("fn f(a: u32 = 1, b: u32);"),
+            ["parameter `b` needs a default value"]
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let graph = load(&[(
+            "app.slime",
+            "newtype UserId = u64;
+type Owner = UserId;
+type Ids = List<Owner>;
+fn f(owner: Owner);",
+        )])
+        .unwrap();
+        let Decl::Method(f) = &graph.ir.root().decls[3] else {
+            panic!("expected a function");
+        };
+        let ty = &f.function.params[0].ty;
+        assert_eq!(
+            graph.ir.underlying(ty).kind,
+            TypeKind::Keyword(KeywordType::Uint64)
+        );
+        let Decl::Alias(user_id) = &graph.ir.root().decls[0] else {
+            panic!("expected an alias");
+        };
+        assert!(user_id.distinct);
+
+        assert_eq!(
+            check(
+                "model M {}
+newtype A = M;
+newtype B = List<u8>;
+newtype C = A;"
+            ),
+            [
+                "newtype `A` must wrap a primitive type",
+                "newtype `B` must wrap a primitive type",
+                "newtype `C` must wrap a primitive type",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hashable_keys() {
+        assert!(
+            check("newtype Id = u64;\nmodel M { ids: Set<Id>, names: Map<String, f64>, }")
+                .is_empty()
+        );
+        assert_eq!(
+            check("model M { a: Set<f64>, b: Map<(u8, u8), i32>, c: Set<Bytes>, }"),
+            [
+                "`f64` cannot be used as a set element",
+                "`(u8, u8)` cannot be used as a map key",
+                "`Bytes` cannot be used as a set element",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_throws() {
+        assert!(check(
+            "enum IoError { NotFound, Denied { path: String } }\ntype Error = IoError;\n\
+             fn load(path: String) -> Bytes throws Error;\n\
+             class File { constructor(path: String) throws IoError; }"
+        )
+        .is_empty());
+        assert_eq!(
+            check("model Failure {}\nfn f() throws Failure;\nfn g() throws String;"),
+            [
+                "`app.Failure` cannot be thrown",
+                "`String` cannot be thrown",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_return_only() {
+        assert!(check(
+            "fn events() -> Stream<String>;\n\
+             class Feed { async fn items(since: u64) -> Stream<List<u8>>; }"
+        )
+        .is_empty());
+        assert_eq!(
+            check(
+                "fn f(input: Stream<u8>);\nfn g() -> Optional<Stream<u8>>;\n\
+                 fn h() -> Stream<Stream<u8>>;\nmodel M { items: Stream<u8>, }"
+            ),
+            [
+                "`Stream<u8>` is not a return type",
+                "`Stream<u8>` is not a return type",
+                "`Stream<u8>` is not a return type",
+                "`Stream<u8>` is not a return type",
+            ]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use slime_ffi_ir::{
+    ast::{
+        BuiltinType, Decl, DeclKind, EnumVariantFields, Ident, Module, RefType, Type, TypeKind,
+        TypeWithArgs,
+    },
+    span::Span,
+};
+
+use crate::{diagnostic::Diagnostic, resolve::display_path};
+
+struct Edge {
+    target: usize,
+    /// Where the reference is written, the field type or the `extends` clause.
+    span: Span,
+    /// The field holding the reference, or the declaration extending another.
+    via: String,
+}
+
+struct Node {
+    name: String,
+    edges: Vec<Edge>,
+    alias: bool,
+}
+
+/// Declarations which must not reference themselves, directly or not.
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<String, usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unvisited,
+    OnStack,
+    Done,
+}
+
+impl Graph {
+    fn node(&mut self, name: String) -> usize {
+        if let Some(idx) = self.index.get(&name) {
+            return *idx;
+        }
+        self.nodes.push(Node {
+            name: name.clone(),
+            edges: vec![],
+            alias: false,
+        });
+        self.index.insert(name, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, target: String, span: Span, via: String) {
+        let target = self.node(target);
+        self.nodes[from].edges.push(Edge { target, span, via });
+    }
+
+    /// Every cycle closed by a back edge of a depth-first search, as the edges along it.
+    fn cycles(&self) -> Vec<Vec<(usize, &Edge)>> {
+        let mut state = vec![State::Unvisited; self.nodes.len()];
+        let mut stack = vec![];
+        let mut cycles = vec![];
+        for idx in 0..self.nodes.len() {
+            if state[idx] == State::Unvisited {
+                self.visit(idx, &mut state, &mut stack, &mut cycles);
+            }
+        }
+        cycles
+    }
+
+    fn visit<'g>(
+        &'g self,
+        idx: usize,
+        state: &mut [State],
+        stack: &mut Vec<(usize, &'g Edge)>,
+        cycles: &mut Vec<Vec<(usize, &'g Edge)>>,
+    ) {
+        state[idx] = State::OnStack;
+        for edge in &self.nodes[idx].edges {
+            match state[edge.target] {
+                State::Unvisited => {
+                    stack.push((idx, edge));
+                    self.visit(edge.target, state, stack, cycles);
+                    stack.pop();
+                }
+                State::OnStack => {
+                    let start = stack
+                        .iter()
+                        .position(|(from, _)| *from == edge.target)
+                        .unwrap_or(stack.len());
+                    let mut cycle = stack[start..].to_vec();
+                    cycle.push((idx, edge));
+                    cycles.push(cycle);
+                }
+                State::Done => {}
+            }
+        }
+        state[idx] = State::Done;
+    }
+
+    fn path(&self, cycle: &[(usize, &Edge)]) -> String {
+        cycle
+            .iter()
+            .map(|(from, _)| format!("`{}`", self.nodes[*from].name))
+            .chain(std::iter::once(format!(
+                "`{}`",
+                self.nodes[cycle[0].0].name
+            )))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// Targets of a type held by value, also inside tuples and arrays. `Optional`, `List`, `Set`
+/// and `Map` are boxed on every side of the FFI boundary and classes or interfaces are passed
+/// by reference, so none of them count. Aliases are held by value as they are replaced by their
+/// definition.
+fn by_value(ty: &Type, out: &mut Vec<(String, Span)>) {
+    match &ty.kind {
+        TypeKind::RefType(RefType::Resolved(target))
+            if matches!(
+                target.kind,
+                DeclKind::Alias | DeclKind::Model | DeclKind::Enum
+            ) =>
+        {
+            out.push((display_path(&target.name), ty.span))
+        }
+        TypeKind::Builtin(BuiltinType::Tuple(elements)) => {
+            for element in elements {
+                by_value(element, out)
+            }
+        }
+        TypeKind::Builtin(BuiltinType::Array(array)) => by_value(&array.element, out),
+        _ => {}
+    }
+}
+
+/// Every alias `ty` mentions, also inside builtin types, which an alias cannot be defined
+/// through either.
+fn aliases(ty: &Type, out: &mut Vec<(String, Span)>) {
+    match &ty.kind {
+        TypeKind::RefType(RefType::Resolved(target)) if target.kind == DeclKind::Alias => {
+            out.push((display_path(&target.name), ty.span))
+        }
+        TypeKind::Builtin(
+            BuiltinType::Optional(inner) | BuiltinType::List(inner) | BuiltinType::Set(inner),
+        ) => aliases(inner, out),
+        TypeKind::Builtin(BuiltinType::Map(map)) => {
+            aliases(&map.key, out);
+            aliases(&map.value, out);
+        }
+        TypeKind::Builtin(BuiltinType::Tuple(elements)) => {
+            for element in elements {
+                aliases(element, out)
+            }
+        }
+        TypeKind::Builtin(BuiltinType::Array(array)) => aliases(&array.element, out),
+        _ => {}
+    }
+}
+
+fn supertype(ty: &TypeWithArgs, kind: DeclKind) -> Option<String> {
+    ty.target
+        .as_ref()
+        .filter(|target| target.kind == kind)
+        .map(|target| display_path(&target.name))
+}
+
+fn collect(decls: &[&Decl], path: &mut Vec<Ident>, values: &mut Graph, supers: &mut Graph) {
+    for decl in decls {
+        let name = || {
+            path.iter()
+                .chain(std::iter::once(decl.name()))
+                .map(|ident| ident.as_str())
+                .collect::<Vec<_>>()
+                .join(".")
+        };
+        match decl {
+            Decl::Mod(decl) => {
+                path.push(decl.name.clone());
+                let items: Vec<&Decl> = decl.items.iter().map(|item| item.as_ref()).collect();
+                collect(&items, path, values, supers);
+                path.pop();
+            }
+            Decl::Alias(alias) => {
+                let from = values.node(name());
+                values.nodes[from].alias = true;
+                let mut targets = vec![];
+                by_value(&alias.ty, &mut targets);
+                aliases(&alias.ty, &mut targets);
+                let mut seen = vec![];
+                for (target, span) in targets {
+                    if seen.contains(&target) {
+                        continue;
+                    }
+                    seen.push(target.clone());
+                    values.edge(from, target, span, alias.name.as_str().to_owned());
+                }
+            }
+            Decl::Model(model) => {
+                let from = values.node(name());
+                for field in &model.fields {
+                    let mut targets = vec![];
+                    by_value(&field.ty, &mut targets);
+                    for (target, span) in targets {
+                        let via = format!("{}.{}", model.name.as_str(), field.name.as_str());
+                        values.edge(from, target, span, via);
+                    }
+                }
+            }
+            Decl::Enum(decl) => {
+                let from = values.node(name());
+                for variant in &decl.variants {
+                    let fields: Vec<(String, &Type)> = match &variant.fields {
+                        EnumVariantFields::Unit => vec![],
+                        EnumVariantFields::Unnamed(types) => types
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, ty)| (idx.to_string(), ty))
+                            .collect(),
+                        EnumVariantFields::Named(fields) => fields
+                            .iter()
+                            .map(|field| (field.name.as_str().to_owned(), &field.ty))
+                            .collect(),
+                    };
+                    for (field, ty) in fields {
+                        let mut targets = vec![];
+                        by_value(ty, &mut targets);
+                        for (target, span) in targets {
+                            let via = format!(
+                                "{}.{}.{}",
+                                decl.name.as_str(),
+                                variant.name.as_str(),
+                                field
+                            );
+                            values.edge(from, target, span, via);
+                        }
+                    }
+                }
+            }
+            Decl::Class(class) => {
+                let from = supers.node(name());
+                if let Some(extend) = &class.extend {
+                    if let Some(target) = supertype(extend, DeclKind::Class) {
+                        let via = class.name.as_str().to_owned();
+                        supers.edge(from, target, extend.ty.span, via);
+                    }
+                }
+            }
+            Decl::Interface(interface) => {
+                let from = supers.node(name());
+                for extend in &interface.extends {
+                    if let Some(target) = supertype(extend, DeclKind::Interface) {
+                        let via = interface.name.as_str().to_owned();
+                        supers.edge(from, target, extend.ty.span, via);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rejects models and enums containing themselves by value, aliases defined through themselves and
+/// classes or interfaces inheriting from themselves, reporting the whole cycle.
+pub(crate) fn check_cycles(modules: &[Module]) -> Vec<Diagnostic> {
+    let mut values = Graph::default();
+    let mut supers = Graph::default();
+    for module in modules {
+        let decls: Vec<&Decl> = module.decls.iter().collect();
+        collect(
+            &decls,
+            &mut vec![Ident::from(&module.name)],
+            &mut values,
+            &mut supers,
+        );
+    }
+
+    let mut diagnostics = vec![];
+    for cycle in values.cycles() {
+        let name = &values.nodes[cycle[0].0].name;
+        let aliases = cycle.iter().all(|(from, _)| values.nodes[*from].alias);
+        let mut diagnostic = if aliases {
+            Diagnostic::error(format!("type alias `{}` is defined through itself", name))
+                .with_note(format!("cycle: {}", values.path(&cycle)))
+                .with_note("use a model or class to define a recursive type")
+        } else {
+            Diagnostic::error(format!("recursive type `{}` has infinite size", name))
+                .with_note(format!("cycle: {}", values.path(&cycle)))
+                .with_note(
+                    "wrap a field in `Optional` or `List`, or use a class, to break the cycle",
+                )
+        };
+        for (idx, (from, edge)) in cycle.iter().enumerate() {
+            let target = &values.nodes[edge.target].name;
+            let message = if values.nodes[*from].alias {
+                format!("`{}` refers to `{}`", edge.via, target)
+            } else {
+                format!("`{}` holds `{}` by value", edge.via, target)
+            };
+            diagnostic = if idx == 0 {
+                diagnostic.with_label(edge.span, message)
+            } else {
+                diagnostic.with_secondary(edge.span, message)
+            };
+        }
+        diagnostics.push(diagnostic);
+    }
+    for cycle in supers.cycles() {
+        let mut diagnostic = Diagnostic::error(format!(
+            "cyclic inheritance of `{}`",
+            supers.nodes[cycle[0].0].name
+        ))
+        .with_note(format!("cycle: {}", supers.path(&cycle)));
+        for (idx, (_, edge)) in cycle.iter().enumerate() {
+            let message = format!(
+                "`{}` extends `{}`",
+                edge.via, supers.nodes[edge.target].name
+            );
+            diagnostic = if idx == 0 {
+                diagnostic.with_label(edge.span, message)
+            } else {
+                diagnostic.with_secondary(edge.span, message)
+            };
+        }
+        diagnostics.push(diagnostic);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        loader::test::{errors, load},
+        ParseError,
+    };
+
+    fn check(src: &str) -> Vec<String> {
+        errors(&[("app.slime", src)])
+    }
+
+    #[test]
+    fn test_recursive_models() {
+        assert_eq!(
+            check("model A { b: B, }\nmodel B { pair: (i32, A), }"),
+            ["recursive type `app.A` has infinite size"]
+        );
+        assert_eq!(
+            check("enum Tree { Leaf, Node { left: Tree, right: Optional<Tree> } }"),
+            ["recursive type `app.Tree` has infinite size"]
+        );
+        // Each field holding the type by value closes a cycle of its own.
+        assert_eq!(check("model Pair { left: Pair, right: Pair, }").len(), 2);
+        assert!(check("model Node { next: Optional<Node>, children: List<Node>, }").is_empty());
+    }
+
+    #[test]
+    fn test_cycle_note() {
+        let Err(ParseError::Semantic(report)) = load(&[(
+            "app.slime",
+            "model A { b: B, }\nmodel B { c: C, }\nmodel C { a: A, }",
+        )]) else {
+            panic!("expected a semantic error");
+        };
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].labels.len(), 3);
+        assert_eq!(
+            report.diagnostics[0].notes[0],
+            "cycle: `app.A` -> `app.B` -> `app.C` -> `app.A`"
+        );
+    }
+
+    #[test]
+    fn test_alias_and_inheritance_cycles() {
+        assert_eq!(
+            check("type A = List<B>;\ntype B = Optional<A>;"),
+            ["type alias `app.A` is defined through itself"]
+        );
+        assert_eq!(
+            check("class A extends B {}\nclass B extends A {}\ninterface I extends I {}"),
+            [
+                "cyclic inheritance of `app.A`",
+                "cyclic inheritance of `app.I`"
+            ]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Write};
+
+use slime_ffi_ir::span::Span;
+
+use crate::source::SourceMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// Primary labels are underlined with `^`, secondary ones with `-`.
+    pub primary: bool,
+}
+
+/// A message attached to one or more labelled source spans.
+///
+/// ```text
+/// error: duplicate field `id`
+///  --> user.slime:3:5
+///   |
+/// 2 |     id: u64,
+///   |     -- first defined here
+/// 3 |     id: String,
+///   |     ^^ redefined here
+/// ```
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(message)
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .or(self.labels.first())
+            .map(|label| label.span)
+    }
+
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(out, "{}: {}", severity, self.message);
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        let primary = self.primary_span();
+        let primary_file = primary.map(|span| span.file);
+        labels.sort_by_key(|label| {
+            (
+                Some(label.span.file) != primary_file,
+                label.span.file,
+                label.span.start,
+            )
+        });
+        let gutter = labels
+            .iter()
+            .map(|label| label.span.start.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(gutter);
+
+        let mut current: Option<(Span, u32)> = None;
+        for label in &labels {
+            let span = label.span;
+            let source = sources.get(span.file);
+            let line = span.start.line;
+            match current {
+                Some((prev, prev_line)) if prev.file == span.file => {
+                    if line > prev_line + 1 {
+                        let _ = writeln!(out, "...");
+                    }
+                }
+                _ => {
+                    let (arrow, at) = match primary {
+                        Some(primary) if current.is_none() && primary.file == span.file => {
+                            ("-->", primary.start)
+                        }
+                        _ if current.is_none() => ("-->", span.start),
+                        _ => (":::", span.start),
+                    };
+                    let _ = writeln!(
+                        out,
+                        "{}{} {}:{}:{}",
+                        pad,
+                        arrow,
+                        source.display_name(),
+                        at.line,
+                        at.column
+                    );
+                    let _ = writeln!(out, "{} |", pad);
+                }
+            }
+            let text = source.line(line);
+            if current.map(|(prev, prev_line)| (prev.file, prev_line)) != Some((span.file, line)) {
+                let _ = writeln!(
+                    out,
+                    "{:>width$} | {}",
+                    line,
+                    text.replace('\t', " "),
+                    width = gutter
+                );
+            }
+            let start = span.start.column as usize;
+            let end = if span.end.line == line {
+                span.end.column as usize
+            } else {
+                text.chars().count() + 1
+            };
+            let marker = if label.primary { "^" } else { "-" };
+            let _ = writeln!(
+                out,
+                "{} | {}{} {}",
+                pad,
+                " ".repeat(start - 1),
+                marker.repeat(end.saturating_sub(start).max(1)),
+                label.message
+            );
+            current = Some((span, line));
+        }
+        if !self.notes.is_empty() && !labels.is_empty() {
+            let _ = writeln!(out, "{} |", pad);
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{} = note: {}", pad, note);
+        }
+        out
+    }
+}
+
+/// Diagnostics together with the sources needed to render them.
+#[derive(Clone)]
+pub struct Report {
+    pub sources: SourceMap,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn new(sources: SourceMap, diagnostics: Vec<Diagnostic>) -> Self {
+        Self {
+            sources,
+            diagnostics,
+        }
+    }
+}
+
+impl std::fmt::Debug for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(&self.diagnostics).finish()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, diagnostic) in self.diagnostics.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            f.write_str(&diagnostic.render(&self.sources))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{IdlParser, ParseError, SourceFile, SourceMap};
+
+    use super::Diagnostic;
+
+    #[test]
+    fn test_render_duplicate_field() {
+        let src = "model User {\n    name: String,\n    id: u64,\n    id: String,\n}\n";
+        let Err(ParseError::Semantic(report)) = IdlParser::parse_str("user.slime", src) else {
+            panic!("expected a semantic error");
+        };
+        assert_eq!(
+            report.to_string(),
+            "error: duplicate field `id`\n \
+             --> user.slime:4:5\n  \
+             |\n\
+             3 |     id: u64,\n  \
+             |     -- first defined here\n\
+             4 |     id: String,\n  \
+             |     ^^ redefined here\n"
+        );
+    }
+
+    #[test]
+    fn test_render_across_files() {
+        let mut sources = SourceMap::default();
+        let a = sources.add(SourceFile::new("a.slime", None, "model A {}\n"));
+        let b = sources.add(SourceFile::new("b.slime", None, "\n\nmodel A {}\n"));
+        let diagnostic = Diagnostic::error("duplicate declaration `A`")
+            .with_label(sources.span(b, 8, 9), "redefined here")
+            .with_secondary(sources.span(a, 6, 7), "first defined here")
+            .with_note("names are shared by all files of a module");
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error: duplicate declaration `A`\n \
+             --> b.slime:3:7\n  \
+             |\n\
+             3 | model A {}\n  \
+             |       ^ redefined here\n \
+             ::: a.slime:1:7\n  \
+             |\n\
+             1 | model A {}\n  \
+             |       - first defined here\n  \
+             |\n  \
+             = note: names are shared by all files of a module\n"
+        );
+    }
+
+    #[test]
+    fn test_position() {
+        let source = SourceFile::new("test", None, "ab\ncdé\nf");
+        let pos = source.position(8);
+        assert_eq!((pos.line, pos.column), (3, 1));
+        let pos = source.position(7);
+        assert_eq!((pos.line, pos.column), (2, 4));
+        assert_eq!(source.line(2), "cdé");
+    }
+}
@@ -0,0 +1,141 @@
+use slime_ffi_ir::{
+    ast::{BuiltinType, DeclRef, FnDecl, InterfaceDecl, InterfaceMember, Module, TypeKind},
+    visit::{self, VisitMut},
+};
+
+use crate::{diagnostic::Diagnostic, resolve::display_path};
+
+/// Every interface without type parameters can be implemented by host objects, which Rust
+/// calls through one vtable slot per method.
+struct HostInterfaces<'d> {
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'d> HostInterfaces<'d> {
+    fn check_method(&mut self, decl: &InterfaceDecl, from: Option<&DeclRef>, method: &FnDecl) {
+        let generic = method
+            .type_params
+            .as_ref()
+            .is_some_and(|params| !params.is_empty());
+        let stream = matches!(
+            method.return_type.as_deref().map(|ty| &ty.kind),
+            Some(TypeKind::Builtin(BuiltinType::Stream(_)))
+        );
+        let (kind, note) = if generic {
+            (
+                "generic",
+                "a host object implements one function per method, give the interface the type \
+                 parameter instead",
+            )
+        } else if method.is_async {
+            (
+                "async",
+                "Rust calls host objects synchronously, declare the method without `async`",
+            )
+        } else if stream {
+            (
+                "streaming",
+                "Rust calls host objects synchronously, return a `List` instead",
+            )
+        } else {
+            return;
+        };
+        let mut diagnostic = Diagnostic::error(format!(
+            "{} method `{}` in interface `{}`, which the host can implement",
+            kind,
+            method.name.as_str(),
+            decl.name.as_str()
+        ));
+        diagnostic = match from {
+            None => diagnostic.with_label(method.name.span, format!("{} method", kind)),
+            Some(from) => diagnostic
+                .with_label(decl.name.span, format!("inherits a {} method", kind))
+                .with_secondary(
+                    method.name.span,
+                    format!(
+                        "`{}.{}` declared here",
+                        display_path(&from.name),
+                        method.name.as_str()
+                    ),
+                ),
+        };
+        self.diagnostics.push(diagnostic.with_note(note));
+    }
+}
+
+impl<'d> VisitMut for HostInterfaces<'d> {
+    fn visit_interface_decl_mut(&mut self, decl: &mut InterfaceDecl) {
+        if decl
+            .type_params
+            .as_ref()
+            .is_some_and(|params| !params.is_empty())
+        {
+            return;
+        }
+        let members = decl
+            .inherited
+            .iter()
+            .map(|inherited| (Some(&inherited.from), &inherited.member))
+            .chain(decl.member.iter().map(|member| (None, member)));
+        for (from, member) in members {
+            if let InterfaceMember::Method(method) = member {
+                self.check_method(decl, from, method);
+            }
+        }
+        visit::walk_interface_decl_mut(self, decl)
+    }
+}
+
+/// Rejects methods of interfaces the host can implement which a host object cannot provide:
+/// generic ones, and async or streaming ones, which would block the calling thread.
+/// Runs once inherited members are filled in.
+pub(crate) fn check_host_interfaces(modules: &mut [Module]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for module in modules {
+        HostInterfaces {
+            diagnostics: &mut diagnostics,
+        }
+        .visit_module_mut(module);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use crate::loader::test::errors;
+
+    #[test]
+    fn test_async_and_streams() {
+        assert_eq!(
+            errors(&[(
+                "app.slime",
+                "interface Feed { async fn next() -> u8; fn items() -> Stream<u8>; }\n\
+                 interface Source<T> { fn all() -> Stream<T>; }\n\
+                 interface Ints extends Source<i32> {}\n\
+                 class Client implements Source<u8> { async fn send(); fn all() -> Stream<u8>; }",
+            )]),
+            [
+                "async method `next` in interface `Feed`, which the host can implement",
+                "streaming method `items` in interface `Feed`, which the host can implement",
+                "streaming method `all` in interface `Ints`, which the host can implement",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generic_methods() {
+        assert_eq!(
+            errors(&[(
+                "app.slime",
+                "interface Source<T> { fn map<U>(value: U) -> T; }\n\
+                 interface Ints extends Source<i32> {}\n\
+                 interface Each { fn each<T>(t: T); }\n\
+                 interface Wrapped<W> extends Source<W> {}",
+            )]),
+            [
+                "generic method `map` in interface `Ints`, which the host can implement",
+                "generic method `each` in interface `Each`, which the host can implement",
+            ]
+        );
+    }
+}
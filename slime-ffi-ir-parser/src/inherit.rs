@@ -0,0 +1,351 @@
+use slime_ffi_ir::{
+    ast::{
+        Decl, DeclKind, DeclRef, Function, Ident, InheritedMember, InterfaceDecl, InterfaceMember,
+        Module, QualifiedName, RefType, Type, TypeKind, TypeWithArgs,
+    },
+    span::Span,
+    visit::{self, VisitMut},
+};
+
+use crate::{diagnostic::Diagnostic, resolve::display_path};
+
+fn lookup<'m>(modules: &'m [Module], name: &QualifiedName) -> Option<&'m InterfaceDecl> {
+    let first = name.path.segments().first()?;
+    let decl = modules
+        .iter()
+        .find(|module| module.name == first.as_str())?
+        .lookup(name)?;
+    match decl {
+        Decl::Interface(decl) => Some(decl),
+        _ => None,
+    }
+}
+
+/// Replaces type parameters by type arguments, leaving those shadowed by a method's own
+/// type parameters alone.
+struct Substitute<'s> {
+    subst: &'s [(Ident, Type)],
+    shadowed: Vec<Ident>,
+}
+
+impl<'s> VisitMut for Substitute<'s> {
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        let len = self.shadowed.len();
+        self.shadowed.extend(
+            function
+                .type_params
+                .iter()
+                .flatten()
+                .map(|param| param.name.clone()),
+        );
+        visit::walk_function_mut(self, function);
+        self.shadowed.truncate(len);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let TypeKind::RefType(RefType::TypeParam(name)) = &ty.kind {
+            if !self.shadowed.contains(name) {
+                if let Some((_, arg)) = self.subst.iter().find(|(param, _)| param == name) {
+                    ty.kind = arg.kind.clone();
+                    ty.type_args = arg.type_args.clone();
+                }
+            }
+            return;
+        }
+        visit::walk_type_mut(self, ty)
+    }
+}
+
+fn substitute(member: &InterfaceMember, subst: &[(Ident, Type)]) -> InterfaceMember {
+    let mut member = member.clone();
+    let mut visitor = Substitute {
+        subst,
+        shadowed: vec![],
+    };
+    match &mut member {
+        InterfaceMember::Method(method) => visitor.visit_fn_decl_mut(method),
+        InterfaceMember::Property(property) => visitor.visit_property_mut(property),
+    }
+    member
+}
+
+fn same_signature(a: &InterfaceMember, b: &InterfaceMember) -> bool {
+    match (a, b) {
+        (InterfaceMember::Method(a), InterfaceMember::Method(b)) => {
+            a.params.len() == b.params.len()
+                && a.params.iter().zip(&b.params).all(|(a, b)| a.ty == b.ty)
+                && a.return_type == b.return_type
+                && a.error_type == b.error_type
+                && a.is_async == b.is_async
+                && a.type_params.as_ref().map_or(0, Vec::len)
+                    == b.type_params.as_ref().map_or(0, Vec::len)
+        }
+        (InterfaceMember::Property(a), InterfaceMember::Property(b)) => {
+            a.ty == b.ty && a.has_getter() == b.has_getter() && a.has_setter() == b.has_setter()
+        }
+        _ => false,
+    }
+}
+
+struct Inheritance<'a, 'd> {
+    modules: &'a [Module],
+    diagnostics: &'d mut Vec<Diagnostic>,
+}
+
+impl<'a, 'd> Inheritance<'a, 'd> {
+    /// Collects the members of everything `extends` reaches, each with the span of the
+    /// `extends` clause of the interface being processed it comes through.
+    fn collect(
+        &mut self,
+        extends: &[TypeWithArgs],
+        subst: &[(Ident, Type)],
+        via: Option<Span>,
+        visited: &mut Vec<String>,
+        out: &mut Vec<(InheritedMember, Span)>,
+    ) {
+        for extend in extends {
+            let Some(target) = extend
+                .target
+                .as_ref()
+                .filter(|target| target.kind == DeclKind::Interface)
+            else {
+                continue;
+            };
+            let path = display_path(&target.name);
+            if visited.contains(&path) {
+                continue;
+            }
+            visited.push(path.clone());
+            let Some(base) = lookup(self.modules, &target.name) else {
+                continue;
+            };
+
+            let params = base.type_params.as_deref().unwrap_or_default();
+            let args: Vec<Type> = extend
+                .type_args
+                .iter()
+                .flatten()
+                .map(|arg| {
+                    let mut arg = arg.as_ref().clone();
+                    Substitute {
+                        subst,
+                        shadowed: vec![],
+                    }
+                    .visit_type_mut(&mut arg);
+                    arg
+                })
+                .collect();
+            if params.len() != args.len() {
+                if via.is_none() {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!(
+                            "interface `{}` takes {} type argument(s) but {} were supplied",
+                            path,
+                            params.len(),
+                            args.len()
+                        ))
+                        .with_label(extend.ty.span, format!("expected {}", params.len()))
+                        .with_secondary(base.name.span, "declared here"),
+                    );
+                }
+                continue;
+            }
+            let subst: Vec<(Ident, Type)> = params
+                .iter()
+                .map(|param| param.name.clone())
+                .zip(args)
+                .collect();
+
+            let span = via.unwrap_or(extend.ty.span);
+            for member in &base.member {
+                out.push((
+                    InheritedMember {
+                        from: target.clone(),
+                        member: substitute(member, &subst),
+                    },
+                    span,
+                ));
+            }
+            self.collect(&base.extends, &subst, Some(span), visited, out);
+        }
+    }
+
+    fn conflict(
+        &mut self,
+        interface: &Ident,
+        name: &Ident,
+        first: (Option<&DeclRef>, &InterfaceMember),
+        second: (&DeclRef, &InterfaceMember),
+        via: Span,
+    ) {
+        let describe = |from: &DeclRef| format!("`{}.{}`", display_path(&from.name), name.as_str());
+        let mut diagnostic = Diagnostic::error(format!(
+            "conflicting signatures for `{}` in interface `{}`",
+            name.as_str(),
+            interface.as_str()
+        ));
+        diagnostic = match first.0 {
+            None => {
+                diagnostic.with_label(first.1.name().span, "conflicts with an inherited member")
+            }
+            Some(from) => diagnostic
+                .with_label(via, "inherits conflicting members")
+                .with_secondary(
+                    first.1.name().span,
+                    format!("{} declared here", describe(from)),
+                ),
+        };
+        self.diagnostics.push(diagnostic.with_secondary(
+            second.1.name().span,
+            format!("{} declared here", describe(second.0)),
+        ));
+    }
+}
+
+impl<'a, 'd> VisitMut for Inheritance<'a, 'd> {
+    fn visit_interface_decl_mut(&mut self, decl: &mut InterfaceDecl) {
+        let mut found = vec![];
+        self.collect(&decl.extends, &[], None, &mut vec![], &mut found);
+
+        let mut inherited: Vec<InheritedMember> = vec![];
+        for (member, via) in found {
+            let name = member.member.name().clone();
+            if let Some(own) = decl.member.iter().find(|own| own.name() == &name) {
+                if !same_signature(own, &member.member) {
+                    self.conflict(
+                        &decl.name,
+                        &name,
+                        (None, own),
+                        (&member.from, &member.member),
+                        via,
+                    );
+                }
+                continue;
+            }
+            if let Some(prev) = inherited.iter().find(|prev| prev.member.name() == &name) {
+                if !same_signature(&prev.member, &member.member) {
+                    let prev = prev.clone();
+                    self.conflict(
+                        &decl.name,
+                        &name,
+                        (Some(&prev.from), &prev.member),
+                        (&member.from, &member.member),
+                        via,
+                    );
+                }
+                continue;
+            }
+            inherited.push(member);
+        }
+        decl.inherited = inherited;
+    }
+}
+
+/// Copies inherited members into every interface and reports conflicting signatures.
+pub(crate) fn inherit_members(modules: &mut [Module]) -> Vec<Diagnostic> {
+    let snapshot = modules.to_vec();
+    let mut diagnostics = vec![];
+    for module in modules {
+        Inheritance {
+            modules: &snapshot,
+            diagnostics: &mut diagnostics,
+        }
+        .visit_module_mut(module);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::ast::{BuiltinType, Decl, InterfaceDecl, KeywordType, TypeKind};
+
+    use crate::loader::test::{errors, load};
+
+    fn interfaces(src: &str) -> Vec<InterfaceDecl> {
+        let graph = load(&[("app.slime", src)]).unwrap();
+        graph
+            .ir
+            .root()
+            .decls
+            .iter()
+            .filter_map(|decl| match decl {
+                Decl::Interface(decl) => Some(decl.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn vtable(interface: &InterfaceDecl) -> Vec<String> {
+        interface
+            .vtable()
+            .iter()
+            .map(|method| method.name.as_str().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_substitute_type_args() {
+        let decls = interfaces(
+            r#"
+            interface Source<T> {
+                fn next() -> Optional<T>;
+                fn map<T>(value: T) -> T;
+            }
+            // Generic, as the host could not implement the generic `map` otherwise.
+            interface Ints<U> extends Source<i32> {
+                fn reset();
+                count: u32 { get; set; }
+            }
+            "#,
+        );
+        let ints = &decls[1];
+        assert_eq!(
+            vtable(ints),
+            ["next", "map", "reset", "get_count", "set_count"]
+        );
+        let next = ints.vtable()[0].function.return_type.clone().unwrap();
+        assert!(matches!(
+            next.kind,
+            TypeKind::Builtin(BuiltinType::Optional(inner))
+                if inner.kind == TypeKind::Keyword(KeywordType::Int32)
+        ));
+        // `map` declares its own `T`, which the type argument must not replace.
+        let map = ints.vtable()[1].function.return_type.clone().unwrap();
+        assert!(!matches!(map.kind, TypeKind::Keyword(KeywordType::Int32)));
+    }
+
+    #[test]
+    fn test_diamond() {
+        let decls = interfaces(
+            r#"
+            interface Base { fn close(); }
+            interface Reader extends Base { fn read() -> Bytes; }
+            interface Writer extends Base { fn write(data: Bytes); }
+            interface Stream extends Reader, Writer { fn close(); }
+            "#,
+        );
+        assert_eq!(vtable(&decls[3]), ["read", "write", "close"]);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        assert_eq!(
+            errors(&[(
+                "app.slime",
+                "interface A { fn get() -> i32; }\ninterface B { fn get() -> String; }\n\
+                 interface C extends A, B {}\ninterface D extends A { fn get() -> u8; }",
+            )]),
+            [
+                "conflicting signatures for `get` in interface `C`",
+                "conflicting signatures for `get` in interface `D`",
+            ]
+        );
+        assert_eq!(
+            errors(&[(
+                "app.slime",
+                "interface A<T> { fn get() -> T; }\ninterface B extends A {}",
+            )]),
+            ["interface `app.A` takes 1 type argument(s) but 0 were supplied"]
+        );
+    }
+}
@@ -0,0 +1,473 @@
+use slime_ffi_ir::span::{FileId, Span};
+
+use crate::{diagnostic::Diagnostic, source::SourceFile};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Ident(String),
+    Int(u64),
+    Float(f64),
+    Str(String),
+
+    /* Keywords */
+    Mod,
+    Const,
+    Type,
+    Newtype,
+    Model,
+    Enum,
+    Fn,
+    Async,
+    Interface,
+    Class,
+    Static,
+    Constructor,
+    Extends,
+    Implements,
+    Throws,
+    True,
+    False,
+    Null,
+    Import,
+    Use,
+    As,
+
+    /* Punctuations */
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Lt,
+    Gt,
+    Comma,
+    Colon,
+    Semi,
+    Eq,
+    Arrow,
+    Dot,
+    Minus,
+
+    Eof,
+}
+
+impl TokenKind {
+    pub fn describe(&self) -> String {
+        match self {
+            TokenKind::Ident(name) => format!("identifier `{}`", name),
+            TokenKind::Int(value) => format!("integer `{}`", value),
+            TokenKind::Float(value) => format!("float `{}`", value),
+            TokenKind::Str(_) => "string literal".to_owned(),
+            TokenKind::Eof => "end of file".to_owned(),
+            other => format!("`{}`", other.text()),
+        }
+    }
+
+    fn text(&self) -> &'static str {
+        match self {
+            TokenKind::Mod => "mod",
+            TokenKind::Const => "const",
+            TokenKind::Type => "type",
+            TokenKind::Newtype => "newtype",
+            TokenKind::Model => "model",
+            TokenKind::Enum => "enum",
+            TokenKind::Fn => "fn",
+            TokenKind::Async => "async",
+            TokenKind::Interface => "interface",
+            TokenKind::Class => "class",
+            TokenKind::Static => "static",
+            TokenKind::Constructor => "constructor",
+            TokenKind::Extends => "extends",
+            TokenKind::Implements => "implements",
+            TokenKind::Throws => "throws",
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Null => "null",
+            TokenKind::Import => "import",
+            TokenKind::Use => "use",
+            TokenKind::As => "as",
+            TokenKind::LBrace => "{",
+            TokenKind::RBrace => "}",
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::Lt => "<",
+            TokenKind::Gt => ">",
+            TokenKind::Comma => ",",
+            TokenKind::Colon => ":",
+            TokenKind::Semi => ";",
+            TokenKind::Eq => "=",
+            TokenKind::Arrow => "->",
+            TokenKind::Dot => ".",
+            TokenKind::Minus => "-",
+            _ => "",
+        }
+    }
+}
+
+fn keyword(ident: &str) -> Option<TokenKind> {
+    Some(match ident {
+        "mod" => TokenKind::Mod,
+        "const" => TokenKind::Const,
+        "type" => TokenKind::Type,
+        "newtype" => TokenKind::Newtype,
+        "model" => TokenKind::Model,
+        "enum" => TokenKind::Enum,
+        "fn" => TokenKind::Fn,
+        "async" => TokenKind::Async,
+        "interface" => TokenKind::Interface,
+        "class" => TokenKind::Class,
+        "static" => TokenKind::Static,
+        "constructor" => TokenKind::Constructor,
+        "extends" => TokenKind::Extends,
+        "implements" => TokenKind::Implements,
+        "throws" => TokenKind::Throws,
+        "true" => TokenKind::True,
+        "false" => TokenKind::False,
+        "null" => TokenKind::Null,
+        "import" => TokenKind::Import,
+        "use" => TokenKind::Use,
+        "as" => TokenKind::As,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte offset of the first character in the source.
+    pub start: usize,
+    /// Byte offset just past the last character in the source.
+    pub end: usize,
+}
+
+pub struct Lexer<'a> {
+    file: FileId,
+    source: &'a SourceFile,
+    src: &'a str,
+    pos: usize,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(file: FileId, source: &'a SourceFile) -> Self {
+        Self {
+            file,
+            source,
+            src: &source.text,
+            pos: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Records an error, lexing carries on with the best guess for the token.
+    fn error(&mut self, start: usize, end: usize, message: impl Into<String>, label: &str) {
+        let span = Span::new(
+            self.file,
+            self.source.position(start),
+            self.source.position(end),
+        );
+        self.errors
+            .push(Diagnostic::error(message).with_label(span, label));
+    }
+
+    /// Splits the whole source into tokens, always ending with `Eof`.
+    pub fn tokenize(mut self) -> (Vec<Token>, Vec<Diagnostic>) {
+        let mut tokens = vec![];
+        loop {
+            let token = self.next_token();
+            let eof = token.kind == TokenKind::Eof;
+            tokens.push(token);
+            if eof {
+                return (tokens, self.errors);
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match (self.peek(), self.peek_nth(1)) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('/'), Some('/')) => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    let start = self.pos;
+                    self.pos += 2;
+                    loop {
+                        match (self.peek(), self.peek_nth(1)) {
+                            (Some('*'), Some('/')) => {
+                                self.pos += 2;
+                                break;
+                            }
+                            (Some(_), _) => {
+                                self.bump();
+                            }
+                            (None, _) => {
+                                self.error(
+                                    start,
+                                    start + 2,
+                                    "unterminated block comment",
+                                    "comment starts here",
+                                );
+                                return;
+                            }
+                        }
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Token {
+        self.skip_trivia();
+        let start = self.pos;
+        let c = match self.bump() {
+            Some(c) => c,
+            None => {
+                return Token {
+                    kind: TokenKind::Eof,
+                    start,
+                    end: start,
+                }
+            }
+        };
+        let kind = match c {
+            '{' => TokenKind::LBrace,
+            '}' => TokenKind::RBrace,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '<' => TokenKind::Lt,
+            '>' => TokenKind::Gt,
+            ',' => TokenKind::Comma,
+            ':' => TokenKind::Colon,
+            ';' => TokenKind::Semi,
+            '=' => TokenKind::Eq,
+            '.' => TokenKind::Dot,
+            '-' if self.peek() == Some('>') => {
+                self.bump();
+                TokenKind::Arrow
+            }
+            '-' => TokenKind::Minus,
+            '"' => self.string(start),
+            c if c.is_ascii_digit() => self.number(start),
+            c if c == '_' || c.is_alphabetic() => {
+                while matches!(self.peek(), Some(c) if c == '_' || c.is_alphanumeric()) {
+                    self.bump();
+                }
+                let text = &self.src[start..self.pos];
+                keyword(text).unwrap_or_else(|| TokenKind::Ident(text.to_owned()))
+            }
+            c => {
+                self.error(
+                    start,
+                    self.pos,
+                    format!("unexpected character `{}`", c),
+                    "not valid in an IDL file",
+                );
+                return self.next_token();
+            }
+        };
+        Token {
+            kind,
+            start,
+            end: self.pos,
+        }
+    }
+
+    fn string(&mut self, start: usize) -> TokenKind {
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return TokenKind::Str(value),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some('\\') => '\\',
+                        Some('"') => '"',
+                        Some(c) => {
+                            self.error(
+                                self.pos - c.len_utf8() - 1,
+                                self.pos,
+                                format!("unknown escape sequence `\\{}`", c),
+                                "unknown escape",
+                            );
+                            c
+                        }
+                        None => break,
+                    };
+                    value.push(escaped);
+                }
+                Some(c) => value.push(c),
+                None => break,
+            }
+        }
+        self.error(
+            start,
+            self.pos,
+            "unterminated string literal",
+            "string starts here",
+        );
+        TokenKind::Str(value)
+    }
+
+    fn number(&mut self, start: usize) -> TokenKind {
+        let radix = if self.src[start..].starts_with("0x") {
+            self.pos += 1;
+            16
+        } else {
+            10
+        };
+        let mut is_float = false;
+        loop {
+            match self.peek() {
+                Some(c) if c == '_' || c.is_digit(radix) => {
+                    self.bump();
+                }
+                Some('.')
+                    if radix == 10
+                        && !is_float
+                        && matches!(self.peek_nth(1), Some(c) if c.is_ascii_digit()) =>
+                {
+                    is_float = true;
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+        // `1e40`, `2.5E-3`: an exponent needs digits, so `1e` stays `1` followed by `e`.
+        let sign = matches!(self.peek_nth(1), Some('+' | '-')) as usize;
+        if radix == 10
+            && matches!(self.peek(), Some('e' | 'E'))
+            && matches!(self.peek_nth(1 + sign), Some(c) if c.is_ascii_digit())
+        {
+            is_float = true;
+            for _ in 0..=sign {
+                self.bump();
+            }
+            while matches!(self.peek(), Some(c) if c == '_' || c.is_ascii_digit()) {
+                self.bump();
+            }
+        }
+        let text: String = self.src[start..self.pos]
+            .chars()
+            .filter(|c| *c != '_')
+            .collect();
+        let parsed = if is_float {
+            text.parse::<f64>().ok().map(TokenKind::Float)
+        } else if radix == 16 {
+            u64::from_str_radix(&text[2..], 16).ok().map(TokenKind::Int)
+        } else {
+            text.parse::<u64>().ok().map(TokenKind::Int)
+        };
+        parsed.unwrap_or_else(|| {
+            self.error(
+                start,
+                self.pos,
+                format!("invalid number literal `{}`", &self.src[start..self.pos]),
+                "does not fit in 64 bits or has no digits",
+            );
+            TokenKind::Int(0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::span::FileId;
+
+    use super::{Lexer, TokenKind};
+    use crate::source::SourceFile;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        let source = SourceFile::new("test", None, src);
+        let (tokens, errors) = Lexer::new(FileId::default(), &source).tokenize();
+        assert!(errors.is_empty(), "{:?}", errors);
+        tokens.into_iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            kinds("fn get(id: u64) -> Optional<String>; // trailing"),
+            [
+                TokenKind::Fn,
+                TokenKind::Ident("get".to_owned()),
+                TokenKind::LParen,
+                TokenKind::Ident("id".to_owned()),
+                TokenKind::Colon,
+                TokenKind::Ident("u64".to_owned()),
+                TokenKind::RParen,
+                TokenKind::Arrow,
+                TokenKind::Ident("Optional".to_owned()),
+                TokenKind::Lt,
+                TokenKind::Ident("String".to_owned()),
+                TokenKind::Gt,
+                TokenKind::Semi,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(
+            kinds(r#"1_000 0xff 2.5 -3 "a\"b\n" /* skipped */ true"#),
+            [
+                TokenKind::Int(1000),
+                TokenKind::Int(255),
+                TokenKind::Float(2.5),
+                TokenKind::Minus,
+                TokenKind::Int(3),
+                TokenKind::Str("a\"b\n".to_owned()),
+                TokenKind::True,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exponents() {
+        assert_eq!(
+            kinds("1e40 2.5E-3 1_0e+2 1e"),
+            [
+                TokenKind::Float(1e40),
+                TokenKind::Float(2.5e-3),
+                TokenKind::Float(10e2),
+                TokenKind::Int(1),
+                TokenKind::Ident("e".to_owned()),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = SourceFile::new("test", None, "model # \"open");
+        let (tokens, errors) = Lexer::new(FileId::default(), &source).tokenize();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "unexpected character `#`");
+        assert_eq!(errors[1].message, "unterminated string literal");
+        assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+    }
+}
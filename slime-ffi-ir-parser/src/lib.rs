@@ -1,9 +1,39 @@
-use std::path::Path;
+use std::{fmt::Display, path::Path};
 
 use slime_ffi_ir::ast::Module;
 
+mod check;
+mod cycles;
+mod diagnostic;
+mod host;
+mod inherit;
+mod lexer;
+mod loader;
+mod parser;
+mod resolve;
+mod source;
+
+pub use diagnostic::*;
+pub use loader::{Loader, ModuleGraph};
+pub use parser::{IdlParser, ParseOutput};
+pub use source::*;
+
+#[derive(Debug)]
 pub enum ParseError {
     IO(std::io::Error),
+    /// The input is not valid IDL syntax.
+    Syntax(Report),
+    /// The input parses but is not a valid declaration, e.g. duplicated names.
+    Semantic(Report),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::IO(e) => write!(f, "error: {}", e),
+            ParseError::Syntax(report) | ParseError::Semantic(report) => report.fmt(f),
+        }
+    }
 }
 
 pub trait Parser {
@@ -0,0 +1,315 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use slime_ffi_ir::{ast::Module, resolved::ResolvedIr, span::Span};
+
+use crate::{
+    check::check_modules,
+    cycles::check_cycles,
+    diagnostic::{Diagnostic, Report},
+    host::check_host_interfaces,
+    inherit::inherit_members,
+    parser::IdlParser,
+    resolve::resolve_names,
+    source::{SourceFile, SourceMap},
+    ParseError,
+};
+
+/// Every module reachable from a root IDL file through `import`s, after semantic analysis.
+pub struct ModuleGraph {
+    pub sources: SourceMap,
+    pub ir: ResolvedIr,
+    /// Indices into `ir.modules` of the modules each module imports.
+    pub imports: Vec<Vec<usize>>,
+}
+
+/// Loads an IDL file and, recursively, the files it imports, then resolves and checks them.
+///
+/// Each file becomes a [`Module`] named after its file stem. Import paths are relative to the
+/// importing file and import cycles are rejected.
+#[derive(Default)]
+pub struct Loader {
+    sources: SourceMap,
+    modules: Vec<Module>,
+    imports: Vec<Vec<usize>>,
+    paths: Vec<PathBuf>,
+    by_path: HashMap<PathBuf, usize>,
+    diagnostics: Vec<Diagnostic>,
+    recovered: bool,
+}
+
+impl Loader {
+    pub fn load(root: &Path) -> Result<ModuleGraph, ParseError> {
+        let mut loader = Loader::default();
+        let canonical = root.canonicalize().map_err(ParseError::IO)?;
+        let text = std::fs::read_to_string(&canonical).map_err(ParseError::IO)?;
+        let root_idx = loader.add(canonical, root, text);
+        loader.load_imports(root_idx, &mut vec![(root_idx, Span::default())]);
+
+        let mut diagnostics = loader.diagnostics;
+        diagnostics.extend(resolve_names(&mut loader.modules, &loader.imports));
+        diagnostics.extend(check_modules(&mut loader.modules));
+        diagnostics.extend(check_cycles(&loader.modules));
+        diagnostics.extend(inherit_members(&mut loader.modules));
+        diagnostics.extend(check_host_interfaces(&mut loader.modules));
+        if loader.recovered {
+            return Err(ParseError::Syntax(Report::new(loader.sources, diagnostics)));
+        }
+        if !diagnostics.is_empty() {
+            return Err(ParseError::Semantic(Report::new(
+                loader.sources,
+                diagnostics,
+            )));
+        }
+        Ok(ModuleGraph {
+            sources: loader.sources,
+            ir: ResolvedIr {
+                modules: loader.modules,
+                root: root_idx,
+            },
+            imports: loader.imports,
+        })
+    }
+
+    fn add(&mut self, canonical: PathBuf, path: &Path, text: String) -> usize {
+        let name = module_name(path);
+        let file = self
+            .sources
+            .add(SourceFile::new(name, Some(path.to_owned()), text));
+        let output = IdlParser::parse_partial(&self.sources, file);
+        self.recovered |= output.recovered;
+        self.diagnostics.extend(output.diagnostics);
+
+        let idx = self.modules.len();
+        self.modules.push(output.module);
+        self.imports.push(vec![]);
+        self.paths.push(canonical.clone());
+        self.by_path.insert(canonical, idx);
+        idx
+    }
+
+    /// Depth-first walk over imports. `stack` holds the modules being loaded, each with the
+    /// span of the import which led to it.
+    fn load_imports(&mut self, idx: usize, stack: &mut Vec<(usize, Span)>) {
+        let dir = self.paths[idx]
+            .parent()
+            .map(Path::to_owned)
+            .unwrap_or_default();
+        let display_dir = self.sources.get(self.modules[idx].file).path.clone();
+        for import in self.modules[idx].imports.clone() {
+            let path = display_dir
+                .as_deref()
+                .and_then(Path::parent)
+                .map(|parent| parent.join(&import.path))
+                .unwrap_or_else(|| PathBuf::from(&import.path));
+            let canonical = match dir.join(&import.path).canonicalize() {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("cannot import `{}`: {}", import.path, e))
+                            .with_label(import.span, "imported here"),
+                    );
+                    continue;
+                }
+            };
+
+            let target = match self.by_path.get(&canonical) {
+                Some(&target) => {
+                    if let Some(pos) = stack.iter().position(|(module, _)| *module == target) {
+                        self.report_cycle(&stack[pos..], import.span);
+                        continue;
+                    }
+                    target
+                }
+                None => {
+                    let name = module_name(&path);
+                    if let Some(other) = self.modules.iter().find(|module| module.name == name) {
+                        let other = self.sources.get(other.file).display_name();
+                        self.diagnostics.push(
+                            Diagnostic::error(format!(
+                                "module `{}` is already loaded from `{}`",
+                                name, other
+                            ))
+                            .with_label(import.span, "imported here")
+                            .with_note("module names come from file names and must be unique"),
+                        );
+                        continue;
+                    }
+                    let text = match std::fs::read_to_string(&canonical) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            self.diagnostics.push(
+                                Diagnostic::error(format!(
+                                    "cannot import `{}`: {}",
+                                    import.path, e
+                                ))
+                                .with_label(import.span, "imported here"),
+                            );
+                            continue;
+                        }
+                    };
+                    let target = self.add(canonical, &path, text);
+                    stack.push((target, import.span));
+                    self.load_imports(target, stack);
+                    stack.pop();
+                    target
+                }
+            };
+            if !self.imports[idx].contains(&target) {
+                self.imports[idx].push(target);
+            }
+        }
+    }
+
+    /// `cycle` starts with the module imported again, `closing` is the import doing so.
+    fn report_cycle(&mut self, cycle: &[(usize, Span)], closing: Span) {
+        let names: Vec<&str> = cycle
+            .iter()
+            .chain(std::iter::once(&cycle[0]))
+            .map(|(module, _)| self.modules[*module].name.as_str())
+            .collect();
+        let mut diagnostic = Diagnostic::error(format!("import cycle: {}", names.join(" -> ")))
+            .with_label(
+                closing,
+                format!("`{}` imports `{}` again", names[names.len() - 2], names[0]),
+            );
+        for (idx, (module, span)) in cycle.iter().enumerate().skip(1) {
+            diagnostic = diagnostic.with_secondary(
+                *span,
+                format!(
+                    "`{}` imports `{}`",
+                    self.modules[cycle[idx - 1].0].name,
+                    self.modules[*module].name
+                ),
+            );
+        }
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use slime_ffi_ir::ast::{Decl, RefType, TypeKind};
+
+    use super::{Loader, ModuleGraph};
+    use crate::ParseError;
+
+    /// Writes `files` into a fresh directory and loads the first one.
+    pub(crate) fn load(files: &[(&str, &str)]) -> Result<ModuleGraph, ParseError> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir: PathBuf = std::env::temp_dir().join(format!(
+            "slime-ffi-ir-parser-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        for (name, text) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        let graph = Loader::load(&dir.join(files[0].0));
+        let _ = std::fs::remove_dir_all(&dir);
+        graph
+    }
+
+    /// Messages of the diagnostics `files` fail to load with.
+    pub(crate) fn errors(files: &[(&str, &str)]) -> Vec<String> {
+        match load(files) {
+            Ok(_) => vec![],
+            Err(ParseError::Syntax(report)) | Err(ParseError::Semantic(report)) => report
+                .diagnostics
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect(),
+            Err(ParseError::IO(e)) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_import_and_use() {
+        let graph = load(&[
+            (
+                "app.slime",
+                r#"
+                import "common/net.slime";
+                use net.http.Request as Req;
+                fn send(request: Req, retry: net.Retry);
+                "#,
+            ),
+            (
+                "common/net.slime",
+                r#"
+                model Retry { times: u32, }
+                mod http {
+                    model Request { url: String, }
+                }
+                "#,
+            ),
+        ])
+        .unwrap();
+        assert_eq!(graph.ir.root().name, "app");
+        assert_eq!(graph.imports, [vec![1], vec![]]);
+
+        let Some(Decl::Method(send)) = graph.ir.root().decls.get(1) else {
+            panic!("expected a function");
+        };
+        let targets = send
+            .function
+            .params
+            .iter()
+            .map(|param| match &param.ty.kind {
+                TypeKind::RefType(RefType::Resolved(target)) => graph.ir.get(target).unwrap(),
+                ty => panic!("unresolved {:?}", ty),
+            })
+            .map(|decl| decl.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(targets, ["Request", "Retry"]);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        assert_eq!(
+            errors(&[
+                ("a.slime", "import \"b.slime\";"),
+                ("b.slime", "import \"a.slime\";"),
+            ]),
+            ["import cycle: a -> b -> a"]
+        );
+        assert_eq!(
+            errors(&[("a.slime", "import \"missing.slime\";")])[0]
+                .split(':')
+                .next(),
+            Some("cannot import `missing.slime`")
+        );
+        assert_eq!(
+            errors(&[
+                ("a.slime", "import \"b.slime\";\nfn f(request: Request);"),
+                ("b.slime", "model Request {}"),
+            ]),
+            ["cannot find `Request` in this scope"]
+        );
+        assert_eq!(
+            errors(&[
+                (
+                    "a.slime",
+                    "import \"b.slime\";\nfn f(request: b.http.Request);"
+                ),
+                ("b.slime", "model Request {}"),
+            ]),
+            ["cannot find `mod` `http` in `b.http.Request`"]
+        );
+    }
+}
@@ -0,0 +1,1378 @@
+use std::path::Path;
+
+use slime_ffi_ir::{
+    ast::{
+        Accessor, AliasDecl, ArrayType, BuiltinType, ClassDecl, ClassMember, ConstDecl, CtorDecl,
+        Decl, EnumDecl, EnumVariant, EnumVariantFields, Expr, FieldDecl, FnDecl, Function, Ident,
+        ImportDecl, InterfaceDecl, InterfaceMember, KeywordType, Lit, MapType, ModDecl, ModelDecl,
+        Module, NumLit, Pat, PropertyDecl, QualifiedName, RefType, Type, TypeKind, TypeParam,
+        TypeWithArgs, UseDecl,
+    },
+    span::{FileId, Span},
+};
+
+use crate::{
+    check::display_type,
+    diagnostic::{Diagnostic, Report},
+    lexer::{Lexer, Token, TokenKind},
+    source::{SourceFile, SourceMap},
+    ParseError, Parser,
+};
+
+/// Parser for the textual slime IDL.
+///
+/// ```text
+/// import "common.slime";
+///
+/// mod net {
+///     use common.Timestamp;
+///
+///     const TIMEOUT: u32 = 30;
+///
+///     type Headers = Map<String, String>;
+///     type Digest = Array<u8, 32>;
+///     newtype RequestId = u64;
+///
+///     model Request { url: String, method: Method = Method.Get, body: Optional<Bytes> = null }
+///
+///     enum Method: u8 { Get = 1, Post = 2 }
+///
+///     enum Event { Click { x: i32, y: i32 }, Key(u32), Close }
+///
+///     async fn fetch(request: Request, timeout: u32 = TIMEOUT) -> Bytes throws NetError;
+///     fn events() -> Stream<Event>;
+///     fn subscribe(listener: Listener<Event>);
+///
+///     interface Listener<T> extends Base<T> {
+///         fn on_event(event: T);
+///         enabled: bool { get; set; }
+///     }
+///
+///     class Client extends Base implements Closeable {
+///         constructor(base_url: String);
+///         base_url: String;
+///         timeout: u32 { get; set; }
+///         fn send(request: Request) -> Optional<Bytes>;
+///         static fn shared() -> Client;
+///         const VERSION: u8 = 1;
+///     }
+/// }
+/// ```
+///
+/// Errors do not stop the parser: it skips to the next member or declaration and keeps
+/// going, so one pass reports every mistake and still yields a partial [`Module`].
+pub struct IdlParser;
+
+/// A module parsed with error recovery.
+pub struct ParseOutput {
+    /// Every declaration which could be parsed, declarations with errors are partial or missing.
+    pub module: Module,
+    /// Syntax and semantic errors, in source order.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Whether the parser had to skip input because of syntax errors.
+    pub recovered: bool,
+}
+
+impl IdlParser {
+    pub fn parse_str(name: &str, src: &str) -> Result<Module, ParseError> {
+        let mut sources = SourceMap::default();
+        let file = sources.add(SourceFile::new(name, None, src));
+        Self::parse_source(&sources, file)
+    }
+
+    /// Parses a file already registered in `sources`, so that several files can share spans.
+    pub fn parse_source(sources: &SourceMap, file: FileId) -> Result<Module, ParseError> {
+        let output = Self::parse_partial(sources, file);
+        let report = Report::new(sources.clone(), output.diagnostics);
+        if output.recovered {
+            Err(ParseError::Syntax(report))
+        } else if !report.diagnostics.is_empty() {
+            Err(ParseError::Semantic(report))
+        } else {
+            Ok(output.module)
+        }
+    }
+
+    /// Parses with error recovery, never failing.
+    pub fn parse_partial(sources: &SourceMap, file: FileId) -> ParseOutput {
+        let source = sources.get(file);
+        let (tokens, errors) = Lexer::new(file, source).tokenize();
+        let mut parser = TokenParser {
+            file,
+            source,
+            tokens,
+            pos: 0,
+            prev_end: 0,
+            errors,
+            semantic: vec![],
+            imports: vec![],
+        };
+        let decls = parser.parse_items(true);
+        let recovered = !parser.errors.is_empty();
+        let mut diagnostics = parser.errors;
+        diagnostics.extend(parser.semantic);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.primary_span().map(|span| span.start));
+        ParseOutput {
+            module: Module {
+                name: source.name.clone(),
+                config: (),
+                decls,
+                imports: parser.imports,
+                file,
+            },
+            diagnostics,
+            recovered,
+        }
+    }
+}
+
+impl Parser for IdlParser {
+    fn parse(idl_path: &Path) -> Result<Module, ParseError> {
+        let src = std::fs::read_to_string(idl_path).map_err(ParseError::IO)?;
+        let name = idl_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut sources = SourceMap::default();
+        let file = sources.add(SourceFile::new(name, Some(idl_path.to_owned()), src));
+        Self::parse_source(&sources, file)
+    }
+}
+
+struct TokenParser<'a> {
+    file: FileId,
+    source: &'a SourceFile,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// End offset of the last consumed token.
+    prev_end: usize,
+    /// Syntax errors the parser recovered from.
+    errors: Vec<Diagnostic>,
+    /// Errors which do not prevent building the syntax tree, e.g. duplicate names.
+    semantic: Vec<Diagnostic>,
+    imports: Vec<ImportDecl>,
+}
+
+type PResult<T> = Result<T, Diagnostic>;
+
+impl<'a> TokenParser<'a> {
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.pos].kind
+    }
+
+    fn bump(&mut self) -> &Token {
+        let token = &self.tokens[self.pos];
+        self.prev_end = token.end;
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn start(&self) -> usize {
+        self.tokens[self.pos].start
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(
+            self.file,
+            self.source.position(start),
+            self.source.position(end),
+        )
+    }
+
+    /// Span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: usize) -> Span {
+        self.span(start, self.prev_end.max(start))
+    }
+
+    fn check_unique<'i>(&mut self, what: &str, names: impl IntoIterator<Item = &'i Ident>) {
+        let mut seen: Vec<&Ident> = vec![];
+        for name in names {
+            match seen.iter().find(|prev| **prev == name) {
+                Some(prev) => self.semantic.push(
+                    Diagnostic::error(format!("duplicate {} `{}`", what, name.as_str()))
+                        .with_secondary(prev.span, "first defined here")
+                        .with_label(name.span, "redefined here"),
+                ),
+                None => seen.push(name),
+            }
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek() == kind {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T>(&self, expected: &str) -> PResult<T> {
+        let token = &self.tokens[self.pos];
+        Err(Diagnostic::error(format!(
+            "expected {}, found {}",
+            expected,
+            token.kind.describe()
+        ))
+        .with_label(
+            self.span(token.start, token.end),
+            format!("expected {}", expected),
+        ))
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> PResult<()> {
+        if self.eat(kind) {
+            Ok(())
+        } else {
+            self.error(&kind.describe())
+        }
+    }
+
+    fn ident(&mut self) -> PResult<Ident> {
+        match self.peek() {
+            TokenKind::Ident(name) => {
+                let name = name.clone();
+                let token = self.bump();
+                let span = (token.start, token.end);
+                Ok(Ident::new(name, self.span(span.0, span.1)))
+            }
+            _ => self.error("identifier"),
+        }
+    }
+
+    /// Skips tokens after an error until something that can start over at item level:
+    /// a declaration keyword, or just past a `;` or the `}` closing a skipped block.
+    /// Blocks opened since `from` count as skipped.
+    fn synchronize(&mut self, from: usize, stop: impl Fn(&TokenKind) -> bool) {
+        if self.pos == from {
+            self.bump();
+        }
+        let mut depth = self.tokens[from..self.pos]
+            .iter()
+            .fold(0usize, |depth, token| match token.kind {
+                TokenKind::LBrace => depth + 1,
+                TokenKind::RBrace => depth.saturating_sub(1),
+                _ => depth,
+            });
+        loop {
+            match self.peek() {
+                TokenKind::Eof => return,
+                kind if depth == 0 && stop(kind) => return,
+                TokenKind::RBrace if depth == 0 => return,
+                TokenKind::Semi if depth == 0 => {
+                    self.bump();
+                    return;
+                }
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.bump();
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            self.bump();
+        }
+    }
+
+    /// Consumes the `}` of a block opened at `open`, reporting it when missing.
+    fn close_block(&mut self, open: Span) {
+        if !self.eat(&TokenKind::RBrace) {
+            let token = &self.tokens[self.pos];
+            let found = self.span(token.start, token.end);
+            let diagnostic =
+                Diagnostic::error(format!("expected `}}`, found {}", token.kind.describe()))
+                    .with_label(found, "expected `}`")
+                    .with_secondary(open, "unclosed block starts here");
+            self.errors.push(diagnostic);
+        }
+    }
+
+    fn open_block(&mut self) -> PResult<Span> {
+        let start = self.start();
+        self.expect(&TokenKind::LBrace)?;
+        Ok(self.span_from(start))
+    }
+
+    /// Parses declarations until the end of file, or the closing brace of a `mod`.
+    fn parse_items(&mut self, top_level: bool) -> Vec<Decl> {
+        let mut items = vec![];
+        loop {
+            match self.peek() {
+                TokenKind::Eof => return items,
+                TokenKind::RBrace if !top_level => return items,
+                TokenKind::RBrace => {
+                    let token = self.bump();
+                    let span = (token.start, token.end);
+                    self.errors.push(
+                        Diagnostic::error("unexpected `}`")
+                            .with_label(self.span(span.0, span.1), "no block to close"),
+                    );
+                }
+                TokenKind::Import => {
+                    let from = self.pos;
+                    match self.parse_import() {
+                        Ok(import) if top_level => self.imports.push(import),
+                        Ok(import) => self.errors.push(
+                            Diagnostic::error(
+                                "`import` is only allowed at the top level of a file",
+                            )
+                            .with_label(import.span, "inside a `mod`"),
+                        ),
+                        Err(e) => {
+                            self.errors.push(e);
+                            self.synchronize(from, is_item_start);
+                        }
+                    }
+                }
+                _ => {
+                    let from = self.pos;
+                    match self.parse_decl() {
+                        Ok(decl) => items.push(decl),
+                        Err(e) => {
+                            self.errors.push(e);
+                            self.synchronize(from, is_item_start);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses `{ item, item, }` and recovers from errors at the next `,`.
+    fn parse_block_list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        let open = self.open_block()?;
+        let mut items = vec![];
+        loop {
+            match self.peek() {
+                TokenKind::RBrace => break,
+                kind if kind == &TokenKind::Eof || is_item_start(kind) => break,
+                _ => {}
+            }
+            let from = self.pos;
+            let result = item(self).and_then(|item| {
+                if self.eat(&TokenKind::Comma) || self.peek() == &TokenKind::RBrace {
+                    Ok(item)
+                } else {
+                    self.error("`,` or `}`")
+                }
+            });
+            match result {
+                Ok(item) => items.push(item),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize(from, |kind| {
+                        kind == &TokenKind::Comma || is_item_start(kind)
+                    });
+                    self.eat(&TokenKind::Comma);
+                }
+            }
+        }
+        self.close_block(open);
+        Ok(items)
+    }
+
+    fn parse_decl(&mut self) -> PResult<Decl> {
+        match self.peek() {
+            TokenKind::Mod => self.parse_mod().map(Decl::Mod),
+            TokenKind::Const => self.parse_const().map(Decl::Const),
+            TokenKind::Type | TokenKind::Newtype => self.parse_alias().map(Decl::Alias),
+            TokenKind::Model => self.parse_model().map(Decl::Model),
+            TokenKind::Enum => self.parse_enum().map(Decl::Enum),
+            TokenKind::Fn | TokenKind::Async => self.parse_fn().map(Decl::Method),
+            TokenKind::Interface => self.parse_interface().map(Decl::Interface),
+            TokenKind::Class => self.parse_class().map(Decl::Class),
+            TokenKind::Use => self.parse_use().map(Decl::Use),
+            _ => self.error("declaration"),
+        }
+    }
+
+    fn parse_import(&mut self) -> PResult<ImportDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Import)?;
+        let path = match self.peek() {
+            TokenKind::Str(path) => path.clone(),
+            _ => return self.error("file path"),
+        };
+        self.bump();
+        self.expect(&TokenKind::Semi)?;
+        Ok(ImportDecl {
+            path,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_use(&mut self) -> PResult<UseDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Use)?;
+        let mut segments = vec![self.ident()?];
+        while self.eat(&TokenKind::Dot) {
+            segments.push(self.ident()?);
+        }
+        let name = segments.pop().unwrap();
+        let alias = if self.eat(&TokenKind::As) {
+            Some(self.ident()?)
+        } else {
+            None
+        };
+        self.expect(&TokenKind::Semi)?;
+        Ok(UseDecl {
+            path: QualifiedName {
+                path: Pat::new(segments),
+                name,
+            },
+            alias,
+            target: None,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_mod(&mut self) -> PResult<ModDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Mod)?;
+        let name = self.ident()?;
+        let open = self.open_block()?;
+        let items = self.parse_items(false).into_iter().map(Box::new).collect();
+        self.close_block(open);
+        Ok(ModDecl {
+            name,
+            items,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_alias(&mut self) -> PResult<AliasDecl> {
+        let start = self.start();
+        let distinct = self.eat(&TokenKind::Newtype);
+        if !distinct {
+            self.expect(&TokenKind::Type)?;
+        }
+        let name = self.ident()?;
+        self.expect(&TokenKind::Eq)?;
+        let ty = self.parse_type()?;
+        self.expect(&TokenKind::Semi)?;
+        Ok(AliasDecl {
+            name,
+            ty,
+            distinct,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_const(&mut self) -> PResult<ConstDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Const)?;
+        let name = self.ident()?;
+        self.expect(&TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+        self.expect(&TokenKind::Eq)?;
+        let value = self.parse_lit()?;
+        self.expect(&TokenKind::Semi)?;
+        Ok(ConstDecl {
+            name,
+            ty,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_model(&mut self) -> PResult<ModelDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Model)?;
+        let name = self.ident()?;
+        let fields = self.parse_block_list(Self::parse_field)?;
+        self.check_unique("field", fields.iter().map(|field| &field.name));
+        Ok(ModelDecl {
+            name,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_enum(&mut self) -> PResult<EnumDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Enum)?;
+        let name = self.ident()?;
+        let repr = if self.eat(&TokenKind::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let variants = self.parse_block_list(Self::parse_variant)?;
+        self.check_unique("variant", variants.iter().map(|variant| &variant.name));
+        Ok(EnumDecl {
+            name,
+            repr,
+            variants,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_variant(&mut self) -> PResult<EnumVariant> {
+        let name = self.ident()?;
+        let fields = match self.peek() {
+            TokenKind::LParen => EnumVariantFields::Unnamed(self.parse_delimited(
+                TokenKind::LParen,
+                TokenKind::RParen,
+                Self::parse_type,
+            )?),
+            TokenKind::LBrace => {
+                let fields =
+                    self.parse_delimited(TokenKind::LBrace, TokenKind::RBrace, Self::parse_field)?;
+                self.check_unique("field", fields.iter().map(|field| &field.name));
+                EnumVariantFields::Named(fields)
+            }
+            _ => EnumVariantFields::Unit,
+        };
+        let discriminant = if self.eat(&TokenKind::Eq) {
+            let start = self.start();
+            match self.parse_lit()? {
+                Lit::Num(num) => Some(num),
+                _ => {
+                    return Err(Diagnostic::error("expected an integer discriminant")
+                        .with_label(self.span_from(start), "not an integer"))
+                }
+            }
+        } else {
+            None
+        };
+        Ok(EnumVariant {
+            name,
+            fields,
+            discriminant,
+        })
+    }
+
+    fn parse_fn(&mut self) -> PResult<FnDecl> {
+        let start = self.start();
+        let is_async = self.eat(&TokenKind::Async);
+        self.expect(&TokenKind::Fn)?;
+        let name = self.ident()?;
+        let type_params = self.parse_type_params()?;
+        let function = self.parse_signature(type_params, true)?;
+        Ok(FnDecl {
+            name,
+            function: Box::new(function),
+            is_async,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `(params) -> ret throws err;` following a function name.
+    fn parse_signature(
+        &mut self,
+        type_params: Option<Vec<TypeParam>>,
+        allow_return: bool,
+    ) -> PResult<Function> {
+        let params =
+            self.parse_delimited(TokenKind::LParen, TokenKind::RParen, Self::parse_field)?;
+        self.check_unique("parameter", params.iter().map(|param| &param.name));
+        let return_type = if allow_return && self.eat(&TokenKind::Arrow) {
+            Some(Box::new(self.parse_type()?))
+        } else {
+            None
+        };
+        let error_type = if self.eat(&TokenKind::Throws) {
+            Some(Box::new(self.parse_type()?))
+        } else {
+            None
+        };
+        self.expect(&TokenKind::Semi)?;
+        Ok(Function {
+            params,
+            type_params,
+            return_type,
+            error_type,
+        })
+    }
+
+    fn parse_interface(&mut self) -> PResult<InterfaceDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Interface)?;
+        let name = self.ident()?;
+        let type_params = self.parse_type_params()?;
+        let mut extends = vec![];
+        if self.eat(&TokenKind::Extends) {
+            loop {
+                extends.push(self.parse_type_with_args()?);
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+        let member = self.parse_members(Self::parse_interface_member)?;
+        self.check_unique("member", member.iter().map(InterfaceMember::name));
+        Ok(InterfaceDecl {
+            name,
+            type_params,
+            extends,
+            member,
+            inherited: vec![],
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_interface_member(&mut self) -> PResult<InterfaceMember> {
+        match self.peek() {
+            TokenKind::Fn | TokenKind::Async => self.parse_fn().map(InterfaceMember::Method),
+            TokenKind::Ident(_) => {
+                let field = self.parse_field()?;
+                if self.peek() == &TokenKind::Semi {
+                    return Err(Diagnostic::error("interfaces cannot have fields")
+                        .with_label(field.span, "field")
+                        .with_note(format!(
+                            "declare a property instead: `{}: {} {{ get; set; }}`",
+                            field.name.as_str(),
+                            display_type(&field.ty)
+                        )));
+                }
+                self.parse_property(field).map(InterfaceMember::Property)
+            }
+            _ => self.error("interface member"),
+        }
+    }
+
+    /// Parses the `{ get; set; }` following the name and type of a property.
+    fn parse_property(&mut self, field: FieldDecl) -> PResult<PropertyDecl> {
+        let start = field.span.start.offset;
+        if field.default.is_some() {
+            self.semantic.push(
+                Diagnostic::error("properties cannot have default values")
+                    .with_label(field.span, "has a default value")
+                    .with_note("the getter provides the value"),
+            );
+        }
+        let open = self.start();
+        self.expect(&TokenKind::LBrace)?;
+        let mut accessors = vec![];
+        while self.peek() != &TokenKind::RBrace {
+            let accessor_start = self.start();
+            let accessor = match self.peek() {
+                TokenKind::Ident(name) if name == "get" => Accessor::Getter,
+                TokenKind::Ident(name) if name == "set" => Accessor::Setter,
+                _ => return self.error("`get` or `set`"),
+            };
+            self.bump();
+            if accessors.contains(&accessor) {
+                self.semantic.push(
+                    Diagnostic::error("duplicate accessor")
+                        .with_label(self.span_from(accessor_start), "already declared"),
+                );
+            } else {
+                accessors.push(accessor);
+            }
+            self.expect(&TokenKind::Semi)?;
+        }
+        self.bump();
+        if accessors.is_empty() {
+            self.semantic.push(
+                Diagnostic::error(format!(
+                    "property `{}` has no accessors",
+                    field.name.as_str()
+                ))
+                .with_label(self.span_from(open), "expected `get;` or `set;`"),
+            );
+        }
+        Ok(PropertyDecl {
+            name: field.name,
+            ty: field.ty,
+            accessors,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_class(&mut self) -> PResult<ClassDecl> {
+        let start = self.start();
+        self.expect(&TokenKind::Class)?;
+        let name = self.ident()?;
+        let type_params = self.parse_type_params()?;
+        let extend = if self.eat(&TokenKind::Extends) {
+            Some(Box::new(self.parse_type_with_args()?))
+        } else {
+            None
+        };
+        let impls = if self.eat(&TokenKind::Implements) {
+            let mut impls = vec![];
+            loop {
+                impls.push(Box::new(self.parse_type_with_args()?));
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            Some(impls)
+        } else {
+            None
+        };
+        let member = self.parse_members(Self::parse_class_member)?;
+        self.check_unique(
+            "member",
+            member.iter().filter_map(|member| match member {
+                ClassMember::Field(FieldDecl { name, .. })
+                | ClassMember::Method(FnDecl { name, .. })
+                | ClassMember::StaticMethod(FnDecl { name, .. })
+                | ClassMember::Const(ConstDecl { name, .. })
+                | ClassMember::Property(PropertyDecl { name, .. }) => Some(name),
+                ClassMember::Constructor(_) => None,
+            }),
+        );
+        Ok(ClassDecl {
+            name,
+            extend,
+            impls,
+            member,
+            type_params,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses a `{ .. }` body of class or interface members, recovering at each member.
+    fn parse_members<T>(
+        &mut self,
+        mut parse: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        let open = self.open_block()?;
+        let mut members = vec![];
+        loop {
+            match self.peek() {
+                TokenKind::RBrace | TokenKind::Eof => break,
+                kind if is_item_start(kind) && !is_member_start(kind) => break,
+                _ => {}
+            }
+            let from = self.pos;
+            match parse(self) {
+                Ok(member) => members.push(member),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize(from, |kind| is_member_start(kind) || is_item_start(kind));
+                }
+            }
+        }
+        self.close_block(open);
+        Ok(members)
+    }
+
+    fn parse_class_member(&mut self) -> PResult<ClassMember> {
+        let start = self.start();
+        match self.peek() {
+            TokenKind::Constructor => {
+                self.bump();
+                let function = self.parse_signature(None, false)?;
+                Ok(ClassMember::Constructor(CtorDecl {
+                    function: Box::new(function),
+                    span: self.span_from(start),
+                }))
+            }
+            TokenKind::Static => {
+                self.bump();
+                let mut method = self.parse_fn()?;
+                method.span = self.span_from(start);
+                Ok(ClassMember::StaticMethod(method))
+            }
+            TokenKind::Fn | TokenKind::Async => self.parse_fn().map(ClassMember::Method),
+            TokenKind::Const => self.parse_const().map(ClassMember::Const),
+            TokenKind::Ident(_) => {
+                let field = self.parse_field()?;
+                if self.peek() == &TokenKind::LBrace {
+                    return self.parse_property(field).map(ClassMember::Property);
+                }
+                self.expect(&TokenKind::Semi)?;
+                Ok(ClassMember::Field(field))
+            }
+            _ => self.error("class member"),
+        }
+    }
+
+    fn parse_field(&mut self) -> PResult<FieldDecl> {
+        let start = self.start();
+        let name = self.ident()?;
+        self.expect(&TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+        let default = if self.eat(&TokenKind::Eq) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(FieldDecl {
+            name,
+            ty,
+            default,
+            span: self.span_from(start),
+        })
+    }
+
+    /// A default value: a literal, `null`, or the name of a const or enum variant.
+    fn parse_expr(&mut self) -> PResult<Expr> {
+        match self.peek() {
+            TokenKind::Null => {
+                self.bump();
+                Ok(Expr::Null)
+            }
+            TokenKind::Ident(_) => {
+                let mut segments = vec![self.ident()?];
+                while self.eat(&TokenKind::Dot) {
+                    segments.push(self.ident()?);
+                }
+                let name = segments.pop().unwrap();
+                Ok(if segments.is_empty() {
+                    Expr::Ident(name)
+                } else {
+                    Expr::Path(QualifiedName {
+                        path: Pat::new(segments),
+                        name,
+                    })
+                })
+            }
+            _ => self.parse_lit().map(Expr::Lit),
+        }
+    }
+
+    fn parse_type_params(&mut self) -> PResult<Option<Vec<TypeParam>>> {
+        if self.peek() != &TokenKind::Lt {
+            return Ok(None);
+        }
+        let params = self.parse_delimited(TokenKind::Lt, TokenKind::Gt, |p| {
+            let name = p.ident()?;
+            let constraint = if p.eat(&TokenKind::Colon) {
+                Some(Box::new(p.parse_type()?))
+            } else {
+                None
+            };
+            Ok(TypeParam { name, constraint })
+        })?;
+        self.check_unique("type parameter", params.iter().map(|param| &param.name));
+        Ok(Some(params))
+    }
+
+    fn parse_type_with_args(&mut self) -> PResult<TypeWithArgs> {
+        let ty = Box::new(self.ident()?);
+        let type_args = if self.peek() == &TokenKind::Lt {
+            let args = self.parse_delimited(TokenKind::Lt, TokenKind::Gt, |p| {
+                p.parse_type().map(Box::new)
+            })?;
+            Some(args)
+        } else {
+            None
+        };
+        Ok(TypeWithArgs {
+            ty,
+            type_args,
+            target: None,
+        })
+    }
+
+    fn parse_type(&mut self) -> PResult<Type> {
+        let start = self.start();
+        if self.peek() == &TokenKind::LParen {
+            let elements =
+                self.parse_delimited(TokenKind::LParen, TokenKind::RParen, Self::parse_type)?;
+            if elements.len() < 2 {
+                return Err(
+                    Diagnostic::error("tuples need at least two elements").with_label(
+                        self.span_from(start),
+                        format!("{} element(s)", elements.len()),
+                    ),
+                );
+            }
+            return Ok(Type::new(
+                TypeKind::Builtin(BuiltinType::Tuple(elements)),
+                self.span_from(start),
+            ));
+        }
+        let name = self.ident()?;
+        let kind = self.parse_type_kind(name)?;
+        let type_args = match kind {
+            TypeKind::RefType(_) if self.peek() == &TokenKind::Lt => {
+                Some(self.parse_delimited(TokenKind::Lt, TokenKind::Gt, Self::parse_type)?)
+            }
+            _ => None,
+        };
+        Ok(Type {
+            type_args,
+            ..Type::new(kind, self.span_from(start))
+        })
+    }
+
+    fn parse_type_kind(&mut self, name: Ident) -> PResult<TypeKind> {
+        if let Some(keyword) = keyword_type(name.as_str()) {
+            return Ok(TypeKind::Keyword(keyword));
+        }
+        match name.as_str() {
+            "Optional" | "List" | "Set" | "Stream" if self.peek() == &TokenKind::Lt => {
+                let mut args = self.parse_type_args(&name, 1)?;
+                let inner = Box::new(args.remove(0));
+                Ok(TypeKind::Builtin(match name.as_str() {
+                    "Optional" => BuiltinType::Optional(inner),
+                    "List" => BuiltinType::List(inner),
+                    "Set" => BuiltinType::Set(inner),
+                    _ => BuiltinType::Stream(inner),
+                }))
+            }
+            "Array" if self.peek() == &TokenKind::Lt => {
+                self.bump();
+                let element = self.parse_type()?;
+                self.expect(&TokenKind::Comma)?;
+                let len = match self.peek() {
+                    TokenKind::Int(len) => *len,
+                    _ => return self.error("array length"),
+                };
+                let token = self.bump();
+                let (start, end) = (token.start, token.end);
+                let span = self.span(start, end);
+                let len = match u32::try_from(len) {
+                    Ok(len) if len > 0 => len,
+                    _ => {
+                        return Err(Diagnostic::error("invalid array length")
+                            .with_label(span, "not within 1..=4294967295"))
+                    }
+                };
+                self.expect(&TokenKind::Gt)?;
+                Ok(TypeKind::Builtin(BuiltinType::Array(Box::new(ArrayType {
+                    element,
+                    len,
+                }))))
+            }
+            "Map" if self.peek() == &TokenKind::Lt => {
+                let mut args = self.parse_type_args(&name, 2)?.into_iter();
+                let key = args.next().unwrap();
+                let value = args.next().unwrap();
+                Ok(TypeKind::Builtin(BuiltinType::Map(Box::new(MapType {
+                    key,
+                    value,
+                }))))
+            }
+            _ => {
+                let mut segments = vec![name];
+                while self.peek() == &TokenKind::Dot {
+                    self.bump();
+                    segments.push(self.ident()?);
+                }
+                let name = segments.pop().unwrap();
+                Ok(TypeKind::RefType(if segments.is_empty() {
+                    RefType::Ident(name)
+                } else {
+                    RefType::QualifiedName(QualifiedName {
+                        path: Pat::new(segments),
+                        name,
+                    })
+                }))
+            }
+        }
+    }
+
+    fn parse_type_args(&mut self, ty: &Ident, count: usize) -> PResult<Vec<Type>> {
+        let start = self.start();
+        let args = self.parse_delimited(TokenKind::Lt, TokenKind::Gt, Self::parse_type)?;
+        if args.len() != count {
+            return Err(Diagnostic::error(format!(
+                "`{}` takes {} type argument(s) but {} were supplied",
+                ty.as_str(),
+                count,
+                args.len()
+            ))
+            .with_label(self.span_from(start), format!("expected {}", count))
+            .with_secondary(ty.span, "for this type"));
+        }
+        Ok(args)
+    }
+
+    fn parse_lit(&mut self) -> PResult<Lit> {
+        let start = self.start();
+        let negative = self.eat(&TokenKind::Minus);
+        let lit = match self.peek().clone() {
+            TokenKind::Int(value) if negative => {
+                if value > i64::MAX as u64 + 1 {
+                    let token = &self.tokens[self.pos];
+                    return Err(Diagnostic::error("integer literal is out of range")
+                        .with_label(self.span(start, token.end), "smaller than `i64::MIN`"));
+                }
+                Lit::Num(NumLit::I64((value as i64).wrapping_neg()))
+            }
+            TokenKind::Int(value) => Lit::Num(match i64::try_from(value) {
+                Ok(value) => NumLit::I64(value),
+                Err(_) => NumLit::U64(value),
+            }),
+            TokenKind::Float(value) => Lit::Num(NumLit::F64(if negative { -value } else { value })),
+            TokenKind::Str(value) if !negative => Lit::Str(value),
+            TokenKind::True if !negative => Lit::Bool(true),
+            TokenKind::False if !negative => Lit::Bool(false),
+            _ if negative => return self.error("number literal"),
+            _ => return self.error("literal"),
+        };
+        self.bump();
+        Ok(lit)
+    }
+
+    /// Parses `open item (, item)* ,? close`.
+    fn parse_delimited<T>(
+        &mut self,
+        open: TokenKind,
+        close: TokenKind,
+        mut item: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        self.expect(&open)?;
+        let mut items = vec![];
+        while self.peek() != &close {
+            items.push(item(self)?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&close)?;
+        Ok(items)
+    }
+}
+
+fn is_item_start(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Mod
+            | TokenKind::Const
+            | TokenKind::Type
+            | TokenKind::Newtype
+            | TokenKind::Model
+            | TokenKind::Enum
+            | TokenKind::Fn
+            | TokenKind::Async
+            | TokenKind::Interface
+            | TokenKind::Class
+            | TokenKind::Use
+            | TokenKind::Import
+    )
+}
+
+fn is_member_start(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Fn
+            | TokenKind::Async
+            | TokenKind::Static
+            | TokenKind::Constructor
+            | TokenKind::Const
+    )
+}
+
+fn keyword_type(name: &str) -> Option<KeywordType> {
+    Some(match name {
+        "i8" => KeywordType::Int8,
+        "i16" => KeywordType::Int16,
+        "i32" => KeywordType::Int32,
+        "i64" => KeywordType::Int64,
+        "u8" => KeywordType::Uint8,
+        "u16" => KeywordType::Uint16,
+        "u32" => KeywordType::Uint32,
+        "u64" => KeywordType::Uint64,
+        "f32" => KeywordType::Float32,
+        "f64" => KeywordType::Float64,
+        "bool" => KeywordType::Bool,
+        "String" => KeywordType::String,
+        "Bytes" => KeywordType::Bytes,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use slime_ffi_ir::ast::{
+        BuiltinType, ClassMember, Decl, EnumVariantFields, KeywordType, Lit, NumLit, RefType,
+        TypeKind,
+    };
+
+    use super::IdlParser;
+    use crate::{SourceFile, SourceMap};
+
+    #[test]
+    fn test_parse_decls() {
+        let module = IdlParser::parse_str(
+            "app",
+            r#"
+            const MAX: u32 = 16;
+            model Point {
+                x: i32,
+                y: i32,
+            }
+            enum Mode { Fast, Slow }
+            fn distance(a: Point, b: Point) -> f64;
+            interface Listener {
+                fn on_event(name: String);
+            }
+            class Store<T> {
+                constructor(path: String);
+                fn get(key: String) -> Optional<T>;
+                static fn open(path: String) -> Store;
+            }
+            mod nested {
+                model Inner {
+                    tags: List<String>,
+                    values: Map<String, Bytes>,
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        let names = module
+            .decls
+            .iter()
+            .map(|decl| decl.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["MAX", "Point", "Mode", "distance", "Listener", "Store", "nested"]
+        );
+
+        let Decl::Const(max) = &module.decls[0] else {
+            panic!("expected a const");
+        };
+        assert_eq!(max.ty.kind, TypeKind::Keyword(KeywordType::Uint32));
+        assert_eq!(max.value, Lit::Num(NumLit::I64(16)));
+
+        let Decl::Model(point) = &module.decls[1] else {
+            panic!("expected a model");
+        };
+        assert_eq!(point.fields.len(), 2);
+        assert_eq!(point.fields[1].name.as_str(), "y");
+
+        let Decl::Method(distance) = &module.decls[3] else {
+            panic!("expected a function");
+        };
+        assert_eq!(distance.function.params.len(), 2);
+        assert!(matches!(
+            &distance.function.params[0].ty.kind,
+            TypeKind::RefType(RefType::Ident(name)) if name.as_str() == "Point"
+        ));
+        assert_eq!(
+            distance.function.return_type.as_ref().unwrap().kind,
+            TypeKind::Keyword(KeywordType::Float64)
+        );
+
+        let Decl::Class(store) = &module.decls[5] else {
+            panic!("expected a class");
+        };
+        assert_eq!(store.type_params.as_ref().unwrap().len(), 1);
+        assert!(matches!(store.member[0], ClassMember::Constructor(_)));
+        assert!(matches!(store.member[1], ClassMember::Method(_)));
+        assert!(matches!(store.member[2], ClassMember::StaticMethod(_)));
+
+        let Decl::Mod(nested) = &module.decls[6] else {
+            panic!("expected a mod");
+        };
+        let Decl::Model(inner) = nested.items[0].as_ref() else {
+            panic!("expected a model");
+        };
+        assert!(matches!(
+            inner.fields[0].ty.kind,
+            TypeKind::Builtin(BuiltinType::List(_))
+        ));
+        assert!(matches!(
+            inner.fields[1].ty.kind,
+            TypeKind::Builtin(BuiltinType::Map(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_syntax_error() {
+        let error = IdlParser::parse_str("app", "model Point { x: i32 y: i32 }").unwrap_err();
+        assert!(error.to_string().contains("expected"), "{}", error);
+    }
+
+    #[test]
+    fn test_recover_all_errors() {
+        let mut sources = SourceMap::default();
+        let file = sources.add(SourceFile::new(
+            "app",
+            None,
+            r#"
+            model Point {
+                x: i32
+                y: i32,
+            }
+            fn broken(a: ) -> i32;
+            enum Mode { Fast, Slow }
+            const LIMIT u32 = 4;
+            fn ok() -> Mode;
+            "#,
+        ));
+        let output = IdlParser::parse_partial(&sources, file);
+        assert!(output.recovered);
+        let messages = output
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "expected `,` or `}`, found identifier `y`",
+                "expected identifier, found `)`",
+                "expected `:`, found identifier `u32`",
+            ]
+        );
+        let names = output
+            .module
+            .decls
+            .iter()
+            .map(|decl| decl.name().as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"Mode"), "{:?}", names);
+        assert!(names.contains(&"ok"), "{:?}", names);
+    }
+
+    #[test]
+    fn test_recover_unclosed_block() {
+        let mut sources = SourceMap::default();
+        let file = sources.add(SourceFile::new("app", None, "mod net {\n    model A {}\n"));
+        let output = IdlParser::parse_partial(&sources, file);
+        assert_eq!(output.diagnostics.len(), 1);
+        assert_eq!(
+            output.diagnostics[0].message,
+            "expected `}`, found end of file"
+        );
+        assert_eq!(output.diagnostics[0].labels.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_enum_payloads() {
+        let module = IdlParser::parse_str(
+            "app",
+            "enum Shape { Empty, Circle(f64), Rect { width: f64, height: f64 }, Pair(i32, i32), }",
+        )
+        .unwrap();
+        let Decl::Enum(shape) = &module.decls[0] else {
+            panic!("expected an enum");
+        };
+        let fields = shape
+            .variants
+            .iter()
+            .map(|variant| match &variant.fields {
+                EnumVariantFields::Unit => 0,
+                EnumVariantFields::Unnamed(types) => types.len(),
+                EnumVariantFields::Named(fields) => fields.len(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fields, [0, 1, 2, 2]);
+        assert!(matches!(
+            shape.variants[2].fields,
+            EnumVariantFields::Named(_)
+        ));
+
+        let error = IdlParser::parse_str("app", "enum E { A { x: i32, x: i32 } }").unwrap_err();
+        assert!(
+            error.to_string().contains("duplicate field `x`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_parse_tuples_and_arrays() {
+        let module =
+            IdlParser::parse_str("app", "type Entry = (String, Array<u8, 32>, Set<u64>);").unwrap();
+        let Decl::Alias(entry) = &module.decls[0] else {
+            panic!("expected an alias");
+        };
+        let TypeKind::Builtin(BuiltinType::Tuple(elements)) = &entry.ty.kind else {
+            panic!("expected a tuple");
+        };
+        assert_eq!(elements.len(), 3);
+        assert!(matches!(
+            &elements[1].kind,
+            TypeKind::Builtin(BuiltinType::Array(array))
+                if array.len == 32 && array.element.kind == TypeKind::Keyword(KeywordType::Uint8)
+        ));
+        assert!(matches!(
+            elements[2].kind,
+            TypeKind::Builtin(BuiltinType::Set(_))
+        ));
+
+        for (src, message) in [
+            ("type T = (i32);", "tuples need at least two elements"),
+            ("type T = Array<u8, 0>;", "invalid array length"),
+            ("type T = Array<u8, 4294967296>;", "invalid array length"),
+            (
+                "type T = Map<String>;",
+                "`Map` takes 2 type argument(s) but 1 were supplied",
+            ),
+        ] {
+            let error = IdlParser::parse_str("app", src).unwrap_err();
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_parse_type_args() {
+        let module = IdlParser::parse_str(
+            "app",
+            "interface L<T> {}\nfn f(x: L<u8>, y: Optional<app.L<List<L<String>>>>);",
+        )
+        .unwrap();
+        let Decl::Method(f) = &module.decls[1] else {
+            panic!("expected a function");
+        };
+        let x = &f.function.params[0].ty;
+        assert!(matches!(&x.kind, TypeKind::RefType(RefType::Ident(name)) if name.as_str() == "L"));
+        assert_eq!(
+            x.type_args.as_deref().unwrap()[0].kind,
+            TypeKind::Keyword(KeywordType::Uint8)
+        );
+        let TypeKind::Builtin(BuiltinType::Optional(y)) = &f.function.params[1].ty.kind else {
+            panic!("expected an optional");
+        };
+        assert!(matches!(
+            y.kind,
+            TypeKind::RefType(RefType::QualifiedName(_))
+        ));
+        assert_eq!(y.type_args.as_ref().map(Vec::len), Some(1));
+    }
+
+    #[test]
+    fn test_field_equality() {
+        let fields = ["x: u32 = 1", "x: u32 = 2", "x: u32 = 1", "x: u32"].map(|field| {
+            let module = IdlParser::parse_str("app", &format!("model A {{ {} }}", field)).unwrap();
+            let Decl::Model(model) = &module.decls[0] else {
+                panic!("expected a model");
+            };
+            model.fields[0].clone()
+        });
+        assert_eq!(fields[0], fields[2]);
+        assert_ne!(fields[0], fields[1]);
+        assert_ne!(fields[0], fields[3]);
+    }
+
+    #[test]
+    fn test_parse_async() {
+        let module = IdlParser::parse_str(
+            "app",
+            "async fn fetch(url: String) -> Bytes;\nfn now() -> u64;\n\
+             interface Store { async fn load(key: String) -> Optional<Bytes>; }\n\
+             class Client { async fn send(body: Bytes); static async fn connect() -> Client; }",
+        )
+        .unwrap();
+        let Decl::Method(fetch) = &module.decls[0] else {
+            panic!("expected a function");
+        };
+        assert!(fetch.is_async);
+        let Decl::Method(now) = &module.decls[1] else {
+            panic!("expected a function");
+        };
+        assert!(!now.is_async);
+        let Decl::Interface(store) = &module.decls[2] else {
+            panic!("expected an interface");
+        };
+        assert!(store.member[0].functions()[0].is_async);
+        let Decl::Class(client) = &module.decls[3] else {
+            panic!("expected a class");
+        };
+        assert!(matches!(&client.member[0], ClassMember::Method(send) if send.is_async));
+        assert!(matches!(
+            &client.member[1],
+            ClassMember::StaticMethod(connect) if connect.is_async
+        ));
+    }
+}
@@ -0,0 +1,518 @@
+use slime_ffi_ir::{
+    ast::{
+        ClassDecl, ConstEnumVariant, ConstLit, Decl, DeclKind, DeclRef, EnumVariantFields, Expr,
+        FieldDecl, Function, Ident, InterfaceDecl, ModDecl, Module, Pat, QualifiedName, RefType,
+        Type, TypeKind, TypeParam, TypeWithArgs, UseDecl,
+    },
+    span::Span,
+    visit::{self, VisitMut},
+};
+
+use crate::diagnostic::Diagnostic;
+
+/// A place declarations can be looked up in: a module, or a `mod` nested in it.
+#[derive(Clone)]
+struct Namespace {
+    module: usize,
+    mods: Vec<Ident>,
+}
+
+/// Read-only view of all loaded modules used to look names up.
+pub(crate) struct Scopes<'a> {
+    pub modules: &'a [Module],
+    /// Indices of the modules each module imports.
+    pub imports: &'a [Vec<usize>],
+}
+
+pub(crate) fn display_path(name: &QualifiedName) -> String {
+    name.path
+        .segments()
+        .iter()
+        .chain(std::iter::once(&name.name))
+        .map(|ident| ident.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn kind_name(kind: DeclKind) -> &'static str {
+    match kind {
+        DeclKind::Mod => "mod",
+        DeclKind::Const => "const",
+        DeclKind::Alias => "type",
+        DeclKind::Model => "model",
+        DeclKind::Enum => "enum",
+        DeclKind::Method => "fn",
+        DeclKind::Interface => "interface",
+        DeclKind::Class => "class",
+    }
+}
+
+impl<'a> Scopes<'a> {
+    fn module_index(&self, name: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.name == name)
+    }
+
+    fn items(&self, ns: &Namespace) -> Vec<&'a Decl> {
+        self.modules[ns.module]
+            .items_in(&ns.mods)
+            .unwrap_or_default()
+    }
+
+    fn namespace_of(&self, target: &QualifiedName) -> Option<Namespace> {
+        let (module, mods) = target.path.segments().split_first()?;
+        let mut mods = mods.to_vec();
+        mods.push(target.name.clone());
+        Some(Namespace {
+            module: self.module_index(module.as_str())?,
+            mods,
+        })
+    }
+
+    /// Namespaces named `first` as seen from `scope`, searching from the innermost `mod`
+    /// outwards and stopping at the first level with a match. At the top level of a file the
+    /// imported modules and the module itself are visible too.
+    fn namespaces(
+        &self,
+        module: usize,
+        scope: &[Ident],
+        first: &Ident,
+        through_uses: bool,
+    ) -> Vec<(Namespace, Span)> {
+        for depth in (0..=scope.len()).rev() {
+            let here = Namespace {
+                module,
+                mods: scope[..depth].to_vec(),
+            };
+            let mut found = vec![];
+            for item in self.items(&here) {
+                match item {
+                    Decl::Mod(decl) if &decl.name == first => {
+                        let mut mods = here.mods.clone();
+                        mods.push(first.clone());
+                        found.push((Namespace { module, mods }, decl.name.span));
+                    }
+                    Decl::Use(decl) if through_uses && item.name() == first => {
+                        if let Some(target) = &decl.target {
+                            if target.kind == DeclKind::Mod {
+                                if let Some(ns) = self.namespace_of(&target.name) {
+                                    found.push((ns, decl.span));
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if depth == 0 {
+                let visible = self.imports[module].iter().copied().chain([module]);
+                for idx in visible {
+                    if self.modules[idx].name == first.as_str() {
+                        found.push((
+                            Namespace {
+                                module: idx,
+                                mods: vec![],
+                            },
+                            Span::default(),
+                        ));
+                    }
+                }
+            }
+            if !found.is_empty() {
+                return found;
+            }
+        }
+        vec![]
+    }
+
+    fn decl_ref(&self, ns: &Namespace, decl: &Decl) -> DeclRef {
+        let mut path = vec![Ident::from(&self.modules[ns.module].name)];
+        path.extend(ns.mods.iter().cloned());
+        DeclRef {
+            kind: decl.kind().unwrap(),
+            name: QualifiedName {
+                path: Pat::new(path),
+                name: decl.name().clone(),
+            },
+        }
+    }
+
+    /// Resolves a single identifier written in `scope` of `module`, searching from the
+    /// innermost `mod` outwards. Returns `Ok(None)` for a `use` which failed to resolve, as
+    /// that has been reported already.
+    pub fn resolve_ident(
+        &self,
+        module: usize,
+        scope: &[Ident],
+        name: &Ident,
+    ) -> Result<Option<DeclRef>, Diagnostic> {
+        for depth in (0..=scope.len()).rev() {
+            let ns = Namespace {
+                module,
+                mods: scope[..depth].to_vec(),
+            };
+            let found = self.items(&ns).into_iter().find(|item| item.name() == name);
+            match found {
+                Some(Decl::Use(decl)) => return Ok(decl.target.clone()),
+                Some(decl) => return Ok(Some(self.decl_ref(&ns, decl))),
+                None => {}
+            }
+        }
+
+        let mut diagnostic =
+            Diagnostic::error(format!("cannot find `{}` in this scope", name.as_str()))
+                .with_label(name.span, "not found in this scope");
+        for idx in &self.imports[module] {
+            let other = &self.modules[*idx];
+            if other
+                .decls
+                .iter()
+                .any(|decl| decl.kind().is_some() && decl.name() == name)
+            {
+                diagnostic = diagnostic.with_note(format!(
+                    "`{}.{}` exists, refer to it by that path or add `use {0}.{1};`",
+                    other.name,
+                    name.as_str()
+                ));
+            }
+        }
+        Err(diagnostic)
+    }
+
+    /// Resolves a path with at least two segments written in `scope` of `module`.
+    pub fn resolve_path(
+        &self,
+        module: usize,
+        scope: &[Ident],
+        name: &QualifiedName,
+        through_uses: bool,
+    ) -> Result<DeclRef, Diagnostic> {
+        let written = display_path(name);
+        let (first, rest) = match name.path.segments().split_first() {
+            Some(split) => split,
+            None => {
+                return Err(Diagnostic::error(format!(
+                    "expected a qualified path, found `{}`",
+                    written
+                ))
+                .with_label(name.name.span, "needs a module prefix"))
+            }
+        };
+
+        let mut candidates = self.namespaces(module, scope, first, through_uses);
+        let mut ns = match candidates.len() {
+            0 => {
+                return Err(Diagnostic::error(format!(
+                    "cannot find module or `mod` `{}`",
+                    first.as_str()
+                ))
+                .with_label(first.span, "not found in this scope")
+                .with_note("modules of other files must be imported with `import \"<file>\";`"))
+            }
+            1 => candidates.remove(0).0,
+            _ => {
+                let mut diagnostic =
+                    Diagnostic::error(format!("`{}` is ambiguous", first.as_str()))
+                        .with_label(first.span, "ambiguous name");
+                for (_, span) in &candidates {
+                    if *span != Span::default() {
+                        diagnostic = diagnostic.with_secondary(*span, "could refer to this");
+                    }
+                }
+                let modules = candidates
+                    .iter()
+                    .filter(|(_, span)| *span == Span::default())
+                    .count();
+                if modules > 0 {
+                    diagnostic = diagnostic
+                        .with_note(format!("`{}` is also the name of a module", first.as_str()));
+                }
+                return Err(diagnostic);
+            }
+        };
+
+        for segment in rest {
+            let mods: Vec<&ModDecl> = self
+                .items(&ns)
+                .into_iter()
+                .filter_map(|item| match item {
+                    Decl::Mod(decl) if &decl.name == segment => Some(decl),
+                    _ => None,
+                })
+                .collect();
+            if mods.len() != 1 {
+                return Err(Diagnostic::error(format!(
+                    "cannot find `mod` `{}` in `{}`",
+                    segment.as_str(),
+                    written
+                ))
+                .with_label(segment.span, "not found"));
+            }
+            ns.mods.push(segment.clone());
+        }
+
+        // Duplicate names are reported by the checker, the first one wins here.
+        let found = self
+            .items(&ns)
+            .into_iter()
+            .find(|item| item.kind().is_some() && item.name() == &name.name);
+        match found {
+            Some(decl) => Ok(self.decl_ref(&ns, decl)),
+            None => Err(Diagnostic::error(format!(
+                "cannot find `{}` in `{}`",
+                name.name.as_str(),
+                name.path
+                    .segments()
+                    .iter()
+                    .map(|ident| ident.as_str())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            ))
+            .with_label(name.name.span, "not found")),
+        }
+    }
+}
+
+/// Tracks the `mod` nesting while walking a module.
+struct ScopedResolver<'s, 'a> {
+    scopes: &'s Scopes<'a>,
+    module: usize,
+    scope: Vec<Ident>,
+    /// Type parameters in scope, innermost last.
+    type_params: Vec<Ident>,
+    diagnostics: &'s mut Vec<Diagnostic>,
+    resolve_uses: bool,
+}
+
+impl<'s, 'a> ScopedResolver<'s, 'a> {
+    fn with_type_params(&mut self, params: Option<&Vec<TypeParam>>, walk: impl FnOnce(&mut Self)) {
+        let len = self.type_params.len();
+        self.type_params
+            .extend(params.into_iter().flatten().map(|param| param.name.clone()));
+        walk(self);
+        self.type_params.truncate(len);
+    }
+
+    /// Binds `ty` to `target` if it names a type.
+    fn bind(&mut self, ty: &mut Type, target: DeclRef) {
+        match target.kind {
+            DeclKind::Alias
+            | DeclKind::Model
+            | DeclKind::Enum
+            | DeclKind::Interface
+            | DeclKind::Class => ty.kind = TypeKind::RefType(RefType::Resolved(target)),
+            kind => self.diagnostics.push(
+                Diagnostic::error(format!(
+                    "expected a type, found {} `{}`",
+                    kind_name(kind),
+                    display_path(&target.name)
+                ))
+                .with_label(ty.span, "not a type")
+                .with_secondary(target.name.name.span, "defined here"),
+            ),
+        }
+    }
+
+    fn find(&self, target: &DeclRef) -> Option<&'a Decl> {
+        let module = target.name.path.segments().first()?;
+        self.scopes.modules[self.scopes.module_index(module.as_str())?].lookup(&target.name)
+    }
+
+    /// Resolves a name used as a value to the literal of a const or a unit enum variant.
+    fn resolve_expr(&mut self, expr: &Expr) -> Result<Option<Expr>, Diagnostic> {
+        let (target, span) = match expr {
+            Expr::Ident(name) => match self.scopes.resolve_ident(self.module, &self.scope, name)? {
+                Some(target) => (target, name.span),
+                None => return Ok(None),
+            },
+            Expr::Path(name) => {
+                if let Some(found) = self.resolve_variant(name)? {
+                    return Ok(Some(found));
+                }
+                let target = self
+                    .scopes
+                    .resolve_path(self.module, &self.scope, name, true)?;
+                (target, name.name.span)
+            }
+            _ => return Ok(None),
+        };
+        match self.find(&target) {
+            Some(Decl::Const(decl)) => Ok(Some(Expr::Lit(decl.value.clone()))),
+            _ => Err(Diagnostic::error(format!(
+                "expected a value, found {} `{}`",
+                kind_name(target.kind),
+                display_path(&target.name)
+            ))
+            .with_label(span, "not a value")
+            .with_secondary(target.name.name.span, "defined here")
+            .with_note("default values are literals, consts or unit enum variants")),
+        }
+    }
+
+    /// Resolves `Enum.Variant`, or returns `None` if the prefix does not name an enum.
+    fn resolve_variant(&mut self, name: &QualifiedName) -> Result<Option<Expr>, Diagnostic> {
+        let Some((last, path)) = name.path.segments().split_last() else {
+            return Ok(None);
+        };
+        let prefix = match path {
+            [] => self.scopes.resolve_ident(self.module, &self.scope, last),
+            _ => self
+                .scopes
+                .resolve_path(
+                    self.module,
+                    &self.scope,
+                    &QualifiedName {
+                        path: Pat::new(path.to_vec()),
+                        name: last.clone(),
+                    },
+                    true,
+                )
+                .map(Some),
+        };
+        let Some(target) = prefix.ok().flatten() else {
+            return Ok(None);
+        };
+        let Some(Decl::Enum(decl)) = self.find(&target) else {
+            return Ok(None);
+        };
+        let variant = decl
+            .variants
+            .iter()
+            .find(|variant| variant.name == name.name);
+        match variant {
+            Some(variant) if matches!(variant.fields, EnumVariantFields::Unit) => {
+                let span = path.first().unwrap_or(last).span.to(last.span);
+                Ok(Some(Expr::Const(ConstLit::UnitEnumVariant(
+                    ConstEnumVariant {
+                        ty: Type::new(TypeKind::RefType(RefType::Resolved(target)), span),
+                        variant_name: name.name.clone(),
+                    },
+                ))))
+            }
+            Some(variant) => Err(Diagnostic::error(format!(
+                "variant `{}.{}` has fields and cannot be used as a value",
+                decl.name.as_str(),
+                name.name.as_str()
+            ))
+            .with_label(name.name.span, "not a unit variant")
+            .with_secondary(variant.name.span, "defined here")),
+            None => Err(Diagnostic::error(format!(
+                "no variant `{}` in enum `{}`",
+                name.name.as_str(),
+                display_path(&target.name)
+            ))
+            .with_label(name.name.span, "variant not found")
+            .with_secondary(decl.name.span, "enum defined here")),
+        }
+    }
+}
+
+impl<'s, 'a> VisitMut for ScopedResolver<'s, 'a> {
+    fn visit_mod_decl_mut(&mut self, decl: &mut ModDecl) {
+        self.scope.push(decl.name.clone());
+        visit::walk_mod_decl_mut(self, decl);
+        self.scope.pop();
+    }
+
+    fn visit_use_decl_mut(&mut self, decl: &mut UseDecl) {
+        if !self.resolve_uses {
+            return;
+        }
+        match self
+            .scopes
+            .resolve_path(self.module, &self.scope, &decl.path, false)
+        {
+            Ok(target) => decl.target = Some(target),
+            Err(e) => self.diagnostics.push(e),
+        }
+    }
+
+    fn visit_class_decl_mut(&mut self, decl: &mut ClassDecl) {
+        let params = decl.type_params.clone();
+        self.with_type_params(params.as_ref(), |v| visit::walk_class_decl_mut(v, decl))
+    }
+
+    fn visit_interface_decl_mut(&mut self, decl: &mut InterfaceDecl) {
+        let params = decl.type_params.clone();
+        self.with_type_params(params.as_ref(), |v| visit::walk_interface_decl_mut(v, decl))
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        let params = function.type_params.clone();
+        self.with_type_params(params.as_ref(), |v| visit::walk_function_mut(v, function))
+    }
+
+    fn visit_field_mut(&mut self, field: &mut FieldDecl) {
+        if !self.resolve_uses {
+            if let Some(default) = &field.default {
+                match self.resolve_expr(default) {
+                    Ok(Some(resolved)) => field.default = Some(resolved),
+                    Ok(None) => {}
+                    Err(e) => self.diagnostics.push(e),
+                }
+            }
+        }
+        self.visit_type_mut(&mut field.ty)
+    }
+
+    fn visit_type_with_args_mut(&mut self, ty: &mut TypeWithArgs) {
+        if !self.resolve_uses {
+            match self.scopes.resolve_ident(self.module, &self.scope, &ty.ty) {
+                Ok(target) => ty.target = target,
+                Err(e) => self.diagnostics.push(e),
+            }
+        }
+        for arg in ty.type_args.iter_mut().flatten() {
+            self.visit_type_mut(arg)
+        }
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if self.resolve_uses {
+            return;
+        }
+        let resolved = match &ty.kind {
+            TypeKind::RefType(RefType::Ident(name)) if self.type_params.contains(name) => {
+                ty.kind = TypeKind::RefType(RefType::TypeParam(name.clone()));
+                Ok(None)
+            }
+            TypeKind::RefType(RefType::Ident(name)) => {
+                self.scopes.resolve_ident(self.module, &self.scope, name)
+            }
+            TypeKind::RefType(RefType::QualifiedName(name)) => self
+                .scopes
+                .resolve_path(self.module, &self.scope, name, true)
+                .map(Some),
+            _ => Ok(None),
+        };
+        match resolved {
+            Ok(Some(target)) => self.bind(ty, target),
+            Ok(None) => {}
+            Err(e) => self.diagnostics.push(e),
+        }
+        visit::walk_type_mut(self, ty)
+    }
+}
+
+/// Resolves `use` declarations and then every type name, in place.
+pub(crate) fn resolve_names(modules: &mut [Module], imports: &[Vec<usize>]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    for resolve_uses in [true, false] {
+        let snapshot = modules.to_vec();
+        let scopes = Scopes {
+            modules: &snapshot,
+            imports,
+        };
+        for (idx, module) in modules.iter_mut().enumerate() {
+            ScopedResolver {
+                scopes: &scopes,
+                module: idx,
+                scope: vec![],
+                type_params: vec![],
+                diagnostics: &mut diagnostics,
+                resolve_uses,
+            }
+            .visit_module_mut(module);
+        }
+    }
+    diagnostics
+}
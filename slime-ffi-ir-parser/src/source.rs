@@ -0,0 +1,77 @@
+use std::{path::PathBuf, sync::Arc};
+
+use slime_ffi_ir::span::{FileId, Pos, Span};
+
+pub struct SourceFile {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: impl Into<String>, path: Option<PathBuf>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            name: name.into(),
+            path,
+            text,
+            line_starts,
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Pos {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let column = self.text[self.line_starts[line]..offset].chars().count();
+        Pos {
+            offset,
+            line: line as u32 + 1,
+            column: column as u32 + 1,
+        }
+    }
+
+    /// Text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: u32) -> &str {
+        let idx = line as usize - 1;
+        let start = self.line_starts[idx];
+        let end = self
+            .line_starts
+            .get(idx + 1)
+            .copied()
+            .unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// Name shown in diagnostics, the path when the file was loaded from disk.
+    pub fn display_name(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// All source files of a parse session. Cheap to clone, files are shared.
+#[derive(Clone, Default)]
+pub struct SourceMap {
+    files: Vec<Arc<SourceFile>>,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, file: SourceFile) -> FileId {
+        self.files.push(Arc::new(file));
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn get(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn span(&self, file: FileId, start: usize, end: usize) -> Span {
+        let source = self.get(file);
+        Span::new(file, source.position(start), source.position(end))
+    }
+}
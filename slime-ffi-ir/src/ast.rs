@@ -1,12 +1,22 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct Ident(String);
+use crate::span::{FileId, Span};
+
+/// An identifier. Equality, ordering and hashing only consider the name, never the span.
+#[derive(Debug, Clone)]
+pub struct Ident {
+    name: String,
+    pub span: Span,
+}
 
 impl<T: ToString> From<T> for Ident {
     fn from(value: T) -> Self {
-        Self(value.to_string())
+        Self {
+            name: value.to_string(),
+            span: Span::default(),
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeywordType {
     Int8,
     Int16,
@@ -23,116 +33,270 @@ pub enum KeywordType {
     Bytes,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum RefType {
     QualifiedName(QualifiedName),
     Ident(Ident),
+    /// Filled in by name resolution in place of the written name.
+    Resolved(DeclRef),
+    /// A type parameter of the enclosing function, class or interface, bound by name resolution.
+    TypeParam(Ident),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeclKind {
+    Mod,
+    Const,
+    Alias,
+    Model,
+    Enum,
+    Method,
+    Interface,
+    Class,
+}
+
+/// A resolved reference to a declaration.
+///
+/// `name.path` starts with the module (file) name followed by the enclosing `mod`s,
+/// and `name.name` is the declaring identifier, so its span points at the definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclRef {
+    pub kind: DeclKind,
+    pub name: QualifiedName,
 }
 
 // Qualified Path
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct Pat(Vec<Ident>);
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct QualifiedName {
     pub path: Pat,
     pub name: Ident,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct MapType {
     pub key: Type,
     pub value: Type,
 }
 
+/// `Array<T, N>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayType {
+    pub element: Type,
+    pub len: u32,
+}
+
+/// On the wire `Optional` is a presence flag followed by the value if present. `List`, `Set` and
+/// `Map` are their length followed by the elements, map entries as key then value. `Tuple` and
+/// `Array` are their elements in order without a length, as it is part of the type.
+#[derive(Debug, Clone, PartialEq)]
 pub enum BuiltinType {
     Optional(Box<Type>),
     List(Box<Type>),
     Map(Box<MapType>),
+    /// Elements are hashable like `Map` keys.
+    Set(Box<Type>),
+    /// `(i32, String)`, with at least two elements.
+    Tuple(Vec<Type>),
+    Array(Box<ArrayType>),
+    /// Only the return type of a function. The result is a handle the host pulls items from
+    /// one at a time, each encoded as `T`.
+    Stream(Box<Type>),
 }
 
-pub enum Type {
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
     Keyword(KeywordType),
     RefType(RefType),
     Builtin(BuiltinType),
 }
 
+/// A type expression, compared structurally regardless of its span.
+#[derive(Debug, Clone)]
+pub struct Type {
+    pub kind: TypeKind,
+    /// `Listener<u8>`, the type arguments of a generic class or interface.
+    pub type_args: Option<Vec<Type>>,
+    pub span: Span,
+}
+
+/// A named and typed slot: model field, class field or function parameter.
+#[derive(Debug, Clone)]
 pub struct FieldDecl {
     pub name: Ident,
     pub ty: Type,
+    /// `timeout: u32 = 30`. Semantic analysis leaves either [`Expr::Const`] or [`Expr::Null`].
+    pub default: Option<Expr>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone)]
 pub struct ModelDecl {
     pub name: Ident,
     pub fields: Vec<FieldDecl>,
+    pub span: Span,
 }
 
+/// Payload of an enum variant.
+///
+/// On the wire an enum value is the discriminant of its variant, encoded as the enum's
+/// [`repr`](EnumDecl::repr_type) type, followed by the payload fields in declaration order,
+/// each encoded as its own type. Unit variants carry no payload, so an enum with only unit
+/// variants is passed as the bare discriminant.
+#[derive(Debug, Clone, PartialEq)]
 pub enum EnumVariantFields {
     Unit,
     Unnamed(Vec<Type>),
     Named(Vec<FieldDecl>),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct EnumVariant {
     pub name: Ident,
+    pub fields: EnumVariantFields,
+    /// `Variant = 7`. Semantic analysis fills in the implicit ones, counting up from the
+    /// previous variant like Rust does, as the literal of the enum's repr type.
+    pub discriminant: Option<NumLit>,
 }
 
+#[derive(Debug, Clone)]
 pub struct EnumDecl {
     pub name: Ident,
+    /// Backing integer type, `enum Status: u16 { .. }`.
+    pub repr: Option<Type>,
     pub variants: Vec<EnumVariant>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypeParam {
     pub name: Ident,
     pub constraint: Option<Box<Type>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub params: Vec<FieldDecl>,
     pub type_params: Option<Vec<TypeParam>>,
     pub return_type: Option<Box<Type>>,
+    /// The enum of a `throws` clause. A thrown variant is returned with the runtime's
+    /// `CallStatus::Error` and the enum as payload, the JVM bindings raise one exception
+    /// subclass per variant.
     pub error_type: Option<Box<Type>>,
 }
 
+#[derive(Debug, Clone)]
 pub struct FnDecl {
     pub name: Ident,
     pub function: Box<Function>,
+    /// `async fn`, a Rust future polled by foreign code through the runtime.
+    pub is_async: bool,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypeWithArgs {
     pub ty: Box<Ident>,
     pub type_args: Option<Vec<Box<Type>>>,
+    /// Filled in by name resolution.
+    pub target: Option<DeclRef>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accessor {
+    Getter,
+    Setter,
+}
+
+/// `name: String { get; set; }`, accessed through generated getter and setter functions.
+#[derive(Debug, Clone)]
+pub struct PropertyDecl {
+    pub name: Ident,
+    pub ty: Type,
+    pub accessors: Vec<Accessor>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum InterfaceMember {
+    Method(FnDecl),
+    Property(PropertyDecl),
+}
+
+/// A member an interface gets from one it extends, directly or not.
+#[derive(Debug, Clone)]
+pub struct InheritedMember {
+    /// The interface declaring the member.
+    pub from: DeclRef,
+    /// The member with the type parameters of `from` replaced by the type arguments
+    /// given along the `extends` chain.
+    pub member: InterfaceMember,
+}
+
+#[derive(Debug, Clone)]
 pub struct InterfaceDecl {
     pub name: Ident,
     pub type_params: Option<Vec<TypeParam>>,
     pub extends: Vec<TypeWithArgs>,
+    pub member: Vec<InterfaceMember>,
+    /// Filled in by semantic analysis, without the members this interface redeclares.
+    pub inherited: Vec<InheritedMember>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone)]
 pub struct ConstDecl {
     pub name: Ident,
     pub ty: Type,
     pub value: Lit,
+    pub span: Span,
+}
+
+/// `type UserId = u64;` is another name for its type. `newtype UserId = u64;` is `distinct`: a
+/// separate type in generated code which is passed as the underlying type on the wire.
+#[derive(Debug, Clone)]
+pub struct AliasDecl {
+    pub name: Ident,
+    pub ty: Type,
+    pub distinct: bool,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConstEnumVariant {
     pub ty: Type,
     pub variant_name: Ident,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum ConstLit {
     Primitive(Lit),
     UnitEnumVariant(ConstEnumVariant),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Ident(Ident),
+    /// `Mode.Fast` or `net.TIMEOUT`.
+    Path(QualifiedName),
     Lit(Lit),
+    /// `null`, the default of an `Optional`.
+    Null,
+    /// Filled in by semantic analysis: the value converted to the declared type, with names of
+    /// consts replaced by their values.
+    Const(ConstLit),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Lit {
     Str(String),
     Num(NumLit),
     Bool(bool),
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum NumLit {
     I8(i8),
     I16(i16),
@@ -146,57 +310,385 @@ pub enum NumLit {
     F64(f64),
 }
 
+#[derive(Debug, Clone)]
 pub struct CtorDecl {
     pub function: Box<Function>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct MethodDecl {
     pub name: Ident,
     pub function: Box<Function>,
 }
 
+#[derive(Debug, Clone)]
 pub enum ClassMember {
     Field(FieldDecl),
+    /// Read-only, write-only or read-write depending on its accessors.
+    Property(PropertyDecl),
     Method(FnDecl),
     StaticMethod(FnDecl),
     Constructor(CtorDecl),
     Const(ConstDecl),
 }
 
+#[derive(Debug, Clone)]
 pub struct ClassDecl {
     pub name: Ident,
     pub extend: Option<Box<TypeWithArgs>>,
     pub impls: Option<Vec<Box<TypeWithArgs>>>,
     pub member: Vec<ClassMember>,
     pub type_params: Option<Vec<TypeParam>>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone)]
 pub struct ModDecl {
     pub name: Ident,
     pub items: Vec<Box<Decl>>,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone)]
 pub enum Decl {
     Mod(ModDecl),
     Const(ConstDecl),
+    Alias(AliasDecl),
     Model(ModelDecl),
     Enum(EnumDecl),
     Method(FnDecl),
     Interface(InterfaceDecl),
     Class(ClassDecl),
+    Use(UseDecl),
 }
 
+/// `import "common.slime";`, makes the module of another IDL file visible by its name.
+#[derive(Debug, Clone)]
+pub struct ImportDecl {
+    /// Path of the imported file, relative to the importing one.
+    pub path: String,
+    pub span: Span,
+}
+
+/// `use common.net.Request as NetRequest;`, binds a declaration to a local name.
+#[derive(Debug, Clone)]
+pub struct UseDecl {
+    pub path: QualifiedName,
+    pub alias: Option<Ident>,
+    /// Filled in by name resolution.
+    pub target: Option<DeclRef>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
 pub struct Module {
     pub name: String,
     pub config: (),
     pub decls: Vec<Decl>,
+    pub imports: Vec<ImportDecl>,
+    pub file: FileId,
 }
 
 #[doc(hidden)]
 mod __impls {
-    use std::ops::Deref;
+    use std::{
+        cmp::Ordering,
+        hash::{Hash, Hasher},
+        ops::Deref,
+    };
+
+    use crate::span::Span;
+
+    use super::{
+        Accessor, ClassMember, CtorDecl, Decl, DeclKind, EnumDecl, EnumVariantFields, FieldDecl,
+        FnDecl, Function, Ident, InterfaceDecl, InterfaceMember, KeywordType, Module, Pat,
+        PropertyDecl, QualifiedName, Type, TypeKind,
+    };
+
+    impl Ident {
+        pub fn new(name: impl Into<String>, span: Span) -> Self {
+            Self {
+                name: name.into(),
+                span,
+            }
+        }
+
+        pub fn as_str(&self) -> &str {
+            self.name.as_str()
+        }
+    }
+
+    impl PartialEq for Ident {
+        fn eq(&self, other: &Self) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl Eq for Ident {}
+
+    impl Hash for Ident {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.name.hash(state)
+        }
+    }
+
+    impl PartialOrd for Ident {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Ident {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.name.cmp(&other.name)
+        }
+    }
+
+    impl KeywordType {
+        /// The name the type is written with in the IDL.
+        pub fn name(&self) -> &'static str {
+            match self {
+                KeywordType::Int8 => "i8",
+                KeywordType::Int16 => "i16",
+                KeywordType::Int32 => "i32",
+                KeywordType::Int64 => "i64",
+                KeywordType::Uint8 => "u8",
+                KeywordType::Uint16 => "u16",
+                KeywordType::Uint32 => "u32",
+                KeywordType::Uint64 => "u64",
+                KeywordType::Float32 => "f32",
+                KeywordType::Float64 => "f64",
+                KeywordType::Bool => "bool",
+                KeywordType::String => "String",
+                KeywordType::Bytes => "Bytes",
+            }
+        }
+    }
+
+    impl Type {
+        pub fn new(kind: TypeKind, span: Span) -> Self {
+            Self {
+                kind,
+                type_args: None,
+                span,
+            }
+        }
+    }
 
-    use super::{CtorDecl, FnDecl, Function};
+    impl From<TypeKind> for Type {
+        fn from(kind: TypeKind) -> Self {
+            Self::new(kind, Span::default())
+        }
+    }
+
+    impl PartialEq for Type {
+        fn eq(&self, other: &Self) -> bool {
+            self.kind == other.kind && self.type_args == other.type_args
+        }
+    }
+
+    impl PartialEq for FieldDecl {
+        fn eq(&self, other: &Self) -> bool {
+            self.name == other.name && self.ty == other.ty && self.default == other.default
+        }
+    }
+
+    impl EnumDecl {
+        /// The backing integer type, `i32` unless specified, matching a JVM `int`.
+        pub fn repr_type(&self) -> KeywordType {
+            match self.repr.as_ref().map(|ty| &ty.kind) {
+                Some(TypeKind::Keyword(keyword)) => *keyword,
+                _ => KeywordType::Int32,
+            }
+        }
+
+        /// Whether no variant has a payload, so values are passed as the bare tag.
+        pub fn is_primitive(&self) -> bool {
+            self.variants
+                .iter()
+                .all(|variant| variant.fields == EnumVariantFields::Unit)
+        }
+    }
+
+    impl Decl {
+        pub fn name(&self) -> &Ident {
+            match self {
+                Decl::Mod(decl) => &decl.name,
+                Decl::Const(decl) => &decl.name,
+                Decl::Alias(decl) => &decl.name,
+                Decl::Model(decl) => &decl.name,
+                Decl::Enum(decl) => &decl.name,
+                Decl::Method(decl) => &decl.name,
+                Decl::Interface(decl) => &decl.name,
+                Decl::Class(decl) => &decl.name,
+                Decl::Use(decl) => decl.alias.as_ref().unwrap_or(&decl.path.name),
+            }
+        }
+
+        pub fn kind(&self) -> Option<DeclKind> {
+            Some(match self {
+                Decl::Mod(_) => DeclKind::Mod,
+                Decl::Const(_) => DeclKind::Const,
+                Decl::Alias(_) => DeclKind::Alias,
+                Decl::Model(_) => DeclKind::Model,
+                Decl::Enum(_) => DeclKind::Enum,
+                Decl::Method(_) => DeclKind::Method,
+                Decl::Interface(_) => DeclKind::Interface,
+                Decl::Class(_) => DeclKind::Class,
+                Decl::Use(_) => return None,
+            })
+        }
+
+        pub fn span(&self) -> Span {
+            match self {
+                Decl::Mod(decl) => decl.span,
+                Decl::Const(decl) => decl.span,
+                Decl::Alias(decl) => decl.span,
+                Decl::Model(decl) => decl.span,
+                Decl::Enum(decl) => decl.span,
+                Decl::Method(decl) => decl.span,
+                Decl::Interface(decl) => decl.span,
+                Decl::Class(decl) => decl.span,
+                Decl::Use(decl) => decl.span,
+            }
+        }
+    }
+
+    impl Module {
+        /// Items of the `mod` nested at `mods`, or of the module itself when `mods` is empty.
+        pub fn items_in(&self, mods: &[Ident]) -> Option<Vec<&Decl>> {
+            let mut items: Vec<&Decl> = self.decls.iter().collect();
+            for name in mods {
+                items = items.into_iter().find_map(|decl| match decl {
+                    Decl::Mod(decl) if &decl.name == name => {
+                        Some(decl.items.iter().map(|item| item.as_ref()).collect())
+                    }
+                    _ => None,
+                })?;
+            }
+            Some(items)
+        }
+
+        /// Looks up a declaration by a name from [`DeclRef`], whose path starts with this module.
+        pub fn lookup(&self, name: &QualifiedName) -> Option<&Decl> {
+            let (module, mods) = name.path.segments().split_first()?;
+            if module.as_str() != self.name {
+                return None;
+            }
+            self.items_in(mods)?
+                .into_iter()
+                .find(|decl| decl.kind().is_some() && decl.name() == &name.name)
+        }
+    }
+
+    impl ClassMember {
+        pub fn span(&self) -> Span {
+            match self {
+                ClassMember::Field(field) => field.span,
+                ClassMember::Property(property) => property.span,
+                ClassMember::Method(method) | ClassMember::StaticMethod(method) => method.span,
+                ClassMember::Constructor(ctor) => ctor.span,
+                ClassMember::Const(decl) => decl.span,
+            }
+        }
+    }
+
+    impl InterfaceMember {
+        pub fn name(&self) -> &Ident {
+            match self {
+                InterfaceMember::Method(method) => &method.name,
+                InterfaceMember::Property(property) => &property.name,
+            }
+        }
+
+        pub fn span(&self) -> Span {
+            match self {
+                InterfaceMember::Method(method) => method.span,
+                InterfaceMember::Property(property) => property.span,
+            }
+        }
+
+        /// The method itself, or the getter then the setter of a property.
+        pub fn functions(&self) -> Vec<FnDecl> {
+            match self {
+                InterfaceMember::Method(method) => vec![method.clone()],
+                InterfaceMember::Property(property) => property
+                    .getter()
+                    .into_iter()
+                    .chain(property.setter())
+                    .collect(),
+            }
+        }
+    }
+
+    impl InterfaceDecl {
+        /// Functions of a host implementation in vtable slot order, inherited members first.
+        /// Only complete once semantic analysis has filled in `inherited`.
+        pub fn vtable(&self) -> Vec<FnDecl> {
+            self.inherited
+                .iter()
+                .map(|inherited| &inherited.member)
+                .chain(&self.member)
+                .flat_map(InterfaceMember::functions)
+                .collect()
+        }
+    }
+
+    impl PropertyDecl {
+        pub fn has_getter(&self) -> bool {
+            self.accessors.contains(&Accessor::Getter)
+        }
+
+        pub fn has_setter(&self) -> bool {
+            self.accessors.contains(&Accessor::Setter)
+        }
+
+        /// `fn get_<name>() -> T;`, the FFI function backing the getter.
+        pub fn getter(&self) -> Option<FnDecl> {
+            self.has_getter().then(|| FnDecl {
+                name: Ident::new(format!("get_{}", self.name.name), self.name.span),
+                function: Box::new(Function {
+                    params: vec![],
+                    type_params: None,
+                    return_type: Some(Box::new(self.ty.clone())),
+                    error_type: None,
+                }),
+                is_async: false,
+                span: self.span,
+            })
+        }
+
+        /// `fn set_<name>(value: T);`, the FFI function backing the setter.
+        pub fn setter(&self) -> Option<FnDecl> {
+            self.has_setter().then(|| FnDecl {
+                name: Ident::new(format!("set_{}", self.name.name), self.name.span),
+                function: Box::new(Function {
+                    params: vec![FieldDecl {
+                        name: Ident::new("value", self.ty.span),
+                        ty: self.ty.clone(),
+                        default: None,
+                        span: self.ty.span,
+                    }],
+                    type_params: None,
+                    return_type: None,
+                    error_type: None,
+                }),
+                is_async: false,
+                span: self.span,
+            })
+        }
+    }
+
+    impl Pat {
+        pub fn new(segments: Vec<Ident>) -> Self {
+            Self(segments)
+        }
+
+        pub fn segments(&self) -> &[Ident] {
+            &self.0
+        }
+    }
 
     impl Deref for FnDecl {
         type Target = Function;
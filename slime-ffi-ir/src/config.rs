@@ -1,20 +1,107 @@
+use crate::ast::DeclRef;
+
 pub enum LangConfig {
     Rust,
     Jvm(JvmConfig),
     Kotlin(KotlinConfig),
+    Java(JavaConfig),
+    C(CConfig),
+    Cpp(CppConfig),
+    Swift(SwiftConfig),
+    Node(NodeConfig),
+    Web(WebConfig),
+}
+
+/// Binds an IDL type to an existing host-language type, e.g. a `newtype Timestamp = i64;` of
+/// epoch millis to `java.time.Instant`. The wire format stays the one of the IDL type, generated
+/// bindings convert with `lift` and `lower` where values cross the boundary.
+pub struct TypeMapping {
+    /// `Timestamp`, or `common.Timestamp` to tell apart types of the same name.
+    pub idl_type: String,
+    /// Fully qualified host type, `java.time.Instant`.
+    pub host_type: String,
+    /// Expression turning the wire value `{}` into a host value, `Instant.ofEpochMilli({})`.
+    pub lift: String,
+    /// Expression turning the host value `{}` into a wire value, `{}.toEpochMilli()`.
+    pub lower: String,
+}
+
+impl TypeMapping {
+    /// Whether `idl_type` names `decl`, by its declared name or a suffix of its full path.
+    pub fn matches(&self, decl: &DeclRef) -> bool {
+        let mut path: Vec<&str> = decl
+            .name
+            .path
+            .segments()
+            .iter()
+            .map(|ident| ident.as_str())
+            .collect();
+        path.push(decl.name.name.as_str());
+        let wanted: Vec<&str> = self.idl_type.split('.').collect();
+        path.ends_with(&wanted)
+    }
+
+    pub fn lift(&self, value: &str) -> String {
+        self.lift.replace("{}", value)
+    }
+
+    pub fn lower(&self, value: &str) -> String {
+        self.lower.replace("{}", value)
+    }
 }
 
+#[derive(Default)]
 pub struct JvmConfig {
     pub package_name: String,
+    pub type_mappings: Vec<TypeMapping>,
 }
 
+#[derive(Default)]
 pub enum KotlinUnsignedStrategy {
+    #[default]
     Signed,
     KotlinSigned,
 }
 
+#[derive(Default)]
 pub struct KotlinConfig {
     pub unsigned_strategy: KotlinUnsignedStrategy,
+    pub type_mappings: Vec<TypeMapping>,
+}
+
+/// Whether the exceptions of IDL errors are checked ones, or extend `RuntimeException`.
+#[derive(Default)]
+pub enum JavaExceptionStyle {
+    #[default]
+    Checked,
+    Unchecked,
+}
+
+#[derive(Default)]
+pub struct JavaConfig {
+    pub exception_style: JavaExceptionStyle,
+    pub type_mappings: Vec<TypeMapping>,
+}
+
+pub struct CConfig {
+    /// The name the library is linked by, `app` for `libapp.so`, also naming the header.
+    pub library_name: String,
+    /// The version of the pkg-config file.
+    pub version: String,
+}
+
+/// How C++ bindings report the error a function `throws`.
+pub enum CppErrorStyle {
+    /// Throw it as `slime::Exception<E>`.
+    Exceptions,
+    /// Return `slime::Expected<T, E>`, the `std::expected` of C++23.
+    Expected,
+}
+
+pub struct CppConfig {
+    /// The namespace the namespaces of modules are nested in, `acme::desktop`, or empty.
+    pub namespace: String,
+    pub error_style: CppErrorStyle,
 }
 
 pub struct SwiftConfig {
@@ -42,3 +129,45 @@ pub enum WebDialet {
 pub struct WebConfig {
     pub dialect: WebDialet,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::ast::{DeclKind, DeclRef, Ident, Pat, QualifiedName};
+
+    use super::TypeMapping;
+
+    fn mapping(idl_type: &str) -> TypeMapping {
+        TypeMapping {
+            idl_type: idl_type.to_owned(),
+            host_type: "java.time.Instant".to_owned(),
+            lift: "java.time.Instant.ofEpochMilli({})".to_owned(),
+            lower: "{}.toEpochMilli()".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let decl = DeclRef {
+            kind: DeclKind::Alias,
+            name: QualifiedName {
+                path: Pat::new(vec![Ident::from("common"), Ident::from("time")]),
+                name: Ident::from("Timestamp"),
+            },
+        };
+        assert!(mapping("Timestamp").matches(&decl));
+        assert!(mapping("time.Timestamp").matches(&decl));
+        assert!(mapping("common.time.Timestamp").matches(&decl));
+        assert!(!mapping("other.Timestamp").matches(&decl));
+        assert!(!mapping("Stamp").matches(&decl));
+    }
+
+    #[test]
+    fn test_lift_lower() {
+        let mapping = mapping("Timestamp");
+        assert_eq!(
+            mapping.lift("reader.i64()"),
+            "java.time.Instant.ofEpochMilli(reader.i64())"
+        );
+        assert_eq!(mapping.lower("at"), "at.toEpochMilli()");
+    }
+}
@@ -1,10 +1,15 @@
 pub mod ast;
 pub mod config;
+pub mod resolved;
+pub mod span;
+pub mod visit;
 
 pub enum Lang {
     Rust,
     Kotlin,
     Java,
+    C,
+    Cpp,
     Swift,
     ObjC,
     NodeJS,
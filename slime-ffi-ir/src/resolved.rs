@@ -0,0 +1,57 @@
+//! Output of semantic analysis, the input of the code generators.
+
+use crate::ast::{Decl, DeclKind, DeclRef, Module, QualifiedName, RefType, Type, TypeKind};
+
+/// Modules that passed semantic analysis, so backends can rely on them without checking again:
+///
+/// - every type reference is [`RefType::Resolved`](crate::ast::RefType::Resolved) to an alias,
+///   model, enum, class or interface, or is a [`RefType::TypeParam`](crate::ast::RefType::TypeParam)
+///   in scope; `extends` and `implements` have their `target` filled in,
+/// - names are unique within each module and `mod`,
+/// - const values are stored as the [`NumLit`](crate::ast::NumLit) variant of their declared type
+///   and fit into it,
+/// - default values are [`Expr::Const`](crate::ast::Expr::Const) of the field type, or
+///   [`Expr::Null`](crate::ast::Expr::Null) for `Optional` fields, and parameters with defaults
+///   come last,
+/// - `Map` keys and `Set` elements are integers, `bool` or `String`,
+/// - aliases are not cyclic and newtypes wrap a primitive type,
+/// - `throws` types are enums, possibly through an alias,
+/// - `Stream` only appears as the outermost return type of a function.
+#[derive(Debug, Clone)]
+pub struct ResolvedIr {
+    pub modules: Vec<Module>,
+    /// Index of the module the IR was loaded from.
+    pub root: usize,
+}
+
+impl ResolvedIr {
+    pub fn root(&self) -> &Module {
+        &self.modules[self.root]
+    }
+
+    pub fn module(&self, name: &str) -> Option<&Module> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    /// Looks up a declaration by the name of a [`DeclRef`].
+    pub fn lookup(&self, name: &QualifiedName) -> Option<&Decl> {
+        let module = name.path.segments().first()?;
+        self.module(module.as_str())?.lookup(name)
+    }
+
+    pub fn get(&self, decl: &DeclRef) -> Option<&Decl> {
+        self.lookup(&decl.name)
+    }
+
+    /// The type passed on the wire for `ty`, with aliases and newtypes replaced by their
+    /// definition.
+    pub fn underlying<'a>(&'a self, mut ty: &'a Type) -> &'a Type {
+        while let TypeKind::RefType(RefType::Resolved(target)) = &ty.kind {
+            match self.get(target) {
+                Some(Decl::Alias(decl)) if target.kind == DeclKind::Alias => ty = &decl.ty,
+                _ => break,
+            }
+        }
+        ty
+    }
+}
@@ -0,0 +1,209 @@
+//! Mutable traversal of the syntax tree, in the spirit of `syn::visit_mut`.
+//!
+//! Override the `visit_*` methods of interest and call the matching `walk_*` function
+//! to keep descending.
+
+use crate::ast::{
+    AliasDecl, BuiltinType, ClassDecl, ClassMember, ConstDecl, Decl, EnumDecl, EnumVariantFields,
+    FieldDecl, FnDecl, Function, InterfaceDecl, InterfaceMember, ModDecl, ModelDecl, Module,
+    PropertyDecl, Type, TypeKind, TypeParam, TypeWithArgs, UseDecl,
+};
+
+pub trait VisitMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module)
+    }
+
+    fn visit_decl_mut(&mut self, decl: &mut Decl) {
+        walk_decl_mut(self, decl)
+    }
+
+    fn visit_mod_decl_mut(&mut self, decl: &mut ModDecl) {
+        walk_mod_decl_mut(self, decl)
+    }
+
+    fn visit_const_decl_mut(&mut self, decl: &mut ConstDecl) {
+        self.visit_type_mut(&mut decl.ty)
+    }
+
+    fn visit_alias_decl_mut(&mut self, decl: &mut AliasDecl) {
+        self.visit_type_mut(&mut decl.ty)
+    }
+
+    fn visit_model_decl_mut(&mut self, decl: &mut ModelDecl) {
+        walk_model_decl_mut(self, decl)
+    }
+
+    fn visit_enum_decl_mut(&mut self, decl: &mut EnumDecl) {
+        walk_enum_decl_mut(self, decl)
+    }
+
+    fn visit_fn_decl_mut(&mut self, decl: &mut FnDecl) {
+        self.visit_function_mut(&mut decl.function)
+    }
+
+    fn visit_interface_decl_mut(&mut self, decl: &mut InterfaceDecl) {
+        walk_interface_decl_mut(self, decl)
+    }
+
+    fn visit_class_decl_mut(&mut self, decl: &mut ClassDecl) {
+        walk_class_decl_mut(self, decl)
+    }
+
+    fn visit_use_decl_mut(&mut self, _decl: &mut UseDecl) {}
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function)
+    }
+
+    fn visit_field_mut(&mut self, field: &mut FieldDecl) {
+        self.visit_type_mut(&mut field.ty)
+    }
+
+    fn visit_property_mut(&mut self, property: &mut PropertyDecl) {
+        self.visit_type_mut(&mut property.ty)
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut TypeParam) {
+        if let Some(constraint) = &mut param.constraint {
+            self.visit_type_mut(constraint)
+        }
+    }
+
+    fn visit_type_with_args_mut(&mut self, ty: &mut TypeWithArgs) {
+        for arg in ty.type_args.iter_mut().flatten() {
+            self.visit_type_mut(arg)
+        }
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        walk_type_mut(self, ty)
+    }
+}
+
+pub fn walk_module_mut<V: VisitMut + ?Sized>(v: &mut V, module: &mut Module) {
+    for decl in &mut module.decls {
+        v.visit_decl_mut(decl)
+    }
+}
+
+pub fn walk_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut Decl) {
+    match decl {
+        Decl::Mod(decl) => v.visit_mod_decl_mut(decl),
+        Decl::Const(decl) => v.visit_const_decl_mut(decl),
+        Decl::Alias(decl) => v.visit_alias_decl_mut(decl),
+        Decl::Model(decl) => v.visit_model_decl_mut(decl),
+        Decl::Enum(decl) => v.visit_enum_decl_mut(decl),
+        Decl::Method(decl) => v.visit_fn_decl_mut(decl),
+        Decl::Interface(decl) => v.visit_interface_decl_mut(decl),
+        Decl::Class(decl) => v.visit_class_decl_mut(decl),
+        Decl::Use(decl) => v.visit_use_decl_mut(decl),
+    }
+}
+
+pub fn walk_mod_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut ModDecl) {
+    for item in &mut decl.items {
+        v.visit_decl_mut(item)
+    }
+}
+
+pub fn walk_model_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut ModelDecl) {
+    for field in &mut decl.fields {
+        v.visit_field_mut(field)
+    }
+}
+
+pub fn walk_enum_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut EnumDecl) {
+    for variant in &mut decl.variants {
+        match &mut variant.fields {
+            EnumVariantFields::Unit => {}
+            EnumVariantFields::Unnamed(types) => {
+                for ty in types {
+                    v.visit_type_mut(ty)
+                }
+            }
+            EnumVariantFields::Named(fields) => {
+                for field in fields {
+                    v.visit_field_mut(field)
+                }
+            }
+        }
+    }
+}
+
+pub fn walk_interface_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut InterfaceDecl) {
+    for param in decl.type_params.iter_mut().flatten() {
+        v.visit_type_param_mut(param)
+    }
+    for extend in &mut decl.extends {
+        v.visit_type_with_args_mut(extend)
+    }
+    for member in &mut decl.member {
+        match member {
+            InterfaceMember::Method(method) => v.visit_fn_decl_mut(method),
+            InterfaceMember::Property(property) => v.visit_property_mut(property),
+        }
+    }
+}
+
+pub fn walk_class_decl_mut<V: VisitMut + ?Sized>(v: &mut V, decl: &mut ClassDecl) {
+    for param in decl.type_params.iter_mut().flatten() {
+        v.visit_type_param_mut(param)
+    }
+    if let Some(extend) = &mut decl.extend {
+        v.visit_type_with_args_mut(extend)
+    }
+    for ty in decl.impls.iter_mut().flatten() {
+        v.visit_type_with_args_mut(ty)
+    }
+    for member in &mut decl.member {
+        match member {
+            ClassMember::Field(field) => v.visit_field_mut(field),
+            ClassMember::Property(property) => v.visit_property_mut(property),
+            ClassMember::Method(method) | ClassMember::StaticMethod(method) => {
+                v.visit_fn_decl_mut(method)
+            }
+            ClassMember::Constructor(ctor) => v.visit_function_mut(&mut ctor.function),
+            ClassMember::Const(decl) => v.visit_const_decl_mut(decl),
+        }
+    }
+}
+
+pub fn walk_function_mut<V: VisitMut + ?Sized>(v: &mut V, function: &mut Function) {
+    for param in function.type_params.iter_mut().flatten() {
+        v.visit_type_param_mut(param)
+    }
+    for param in &mut function.params {
+        v.visit_field_mut(param)
+    }
+    if let Some(ty) = &mut function.return_type {
+        v.visit_type_mut(ty)
+    }
+    if let Some(ty) = &mut function.error_type {
+        v.visit_type_mut(ty)
+    }
+}
+
+pub fn walk_type_mut<V: VisitMut + ?Sized>(v: &mut V, ty: &mut Type) {
+    if let TypeKind::Builtin(builtin) = &mut ty.kind {
+        match builtin {
+            BuiltinType::Optional(inner)
+            | BuiltinType::List(inner)
+            | BuiltinType::Set(inner)
+            | BuiltinType::Stream(inner) => v.visit_type_mut(inner),
+            BuiltinType::Map(map) => {
+                v.visit_type_mut(&mut map.key);
+                v.visit_type_mut(&mut map.value);
+            }
+            BuiltinType::Tuple(elements) => {
+                for element in elements {
+                    v.visit_type_mut(element)
+                }
+            }
+            BuiltinType::Array(array) => v.visit_type_mut(&mut array.element),
+        }
+    }
+    for arg in ty.type_args.iter_mut().flatten() {
+        v.visit_type_mut(arg)
+    }
+}
@@ -14,6 +14,7 @@ pub const ENTRY: Symbol = Symbol("ffi_entry");
 pub const RENAME: Symbol = Symbol("rename");
 pub const IGNORE: Symbol = Symbol("ignore");
 pub const DOC: Symbol = Symbol("doc");
+pub const REPR: Symbol = Symbol("repr");
 
 pub const ENTRY_JVM: Symbol = Symbol("jvm");
 pub const ETNRY_COMMON: Symbol = Symbol("common");
@@ -1,5 +1,7 @@
-use crate::types::{Field, Type};
+use crate::symbol::REPR;
+use crate::types::{Field, PrimitiveType, Type};
 use serde::{Deserialize, Serialize};
+use syn::{Error, Expr, ExprLit, ExprUnary, Lit, UnOp};
 
 pub enum EnumVariantType {
     Primitive,
@@ -10,18 +12,105 @@ pub enum EnumVariantType {
 pub struct EnumVariant {
     pub name: String,
     pub variant_type: EnumVariantType,
+    /// Explicit `= value`, shared with the IDL so both sides agree on the numbers.
+    pub discriminant: Option<i64>,
 }
 
 pub struct EnumItem {
     pub name: String,
     pub variants: Vec<EnumVariant>,
+    /// From `#[repr(..)]`, `Int32` when absent as for an IDL enum without a backing type.
+    pub repr: PrimitiveType,
 }
 
 impl EnumItem {
+    pub fn parse_ast(item: &syn::ItemEnum) -> syn::Result<EnumItem> {
+        let mut repr = PrimitiveType::Int32;
+        for attr in item.attrs.iter().filter(|attr| *attr.path() == REPR) {
+            // `C` and the like leave the default, the integer type decides the discriminants.
+            attr.parse_nested_meta(|meta| {
+                if let Some(ty) = meta.path.get_ident().and_then(int_type) {
+                    repr = ty;
+                }
+                Ok(())
+            })?;
+        }
+        let variants = item
+            .variants
+            .iter()
+            .map(|variant| {
+                if !matches!(variant.fields, syn::Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "only unit variants are supported",
+                    ));
+                }
+                let discriminant = match &variant.discriminant {
+                    Some((_, expr)) => Some(parse_discriminant(expr)?),
+                    None => None,
+                };
+                Ok(EnumVariant {
+                    name: variant.ident.to_string(),
+                    variant_type: EnumVariantType::Primitive,
+                    discriminant,
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+        Ok(EnumItem {
+            name: item.ident.to_string(),
+            variants,
+            repr,
+        })
+    }
+
     pub fn is_primitive(&self) -> bool {
         self.variants
             .iter()
             .all(|v| matches!(v.variant_type, EnumVariantType::Primitive))
     }
+
+    /// The value of every variant, one more than the previous one where it is implicit as in Rust.
+    pub fn discriminants(&self) -> Vec<i64> {
+        let mut next = 0;
+        self.variants
+            .iter()
+            .map(|v| {
+                let value = v.discriminant.unwrap_or(next);
+                next = value.wrapping_add(1);
+                value
+            })
+            .collect()
+    }
+}
+
+fn int_type(ident: &syn::Ident) -> Option<PrimitiveType> {
+    Some(match ident.to_string().as_str() {
+        "i8" => PrimitiveType::Int8,
+        "i16" => PrimitiveType::Int16,
+        "i32" => PrimitiveType::Int32,
+        "i64" => PrimitiveType::Int64,
+        "u8" => PrimitiveType::UInt8,
+        "u16" => PrimitiveType::UInt16,
+        "u32" => PrimitiveType::UInt32,
+        "u64" => PrimitiveType::UInt64,
+        _ => return None,
+    })
 }
 
+/// `= 3` or `= -3`, the IDL takes no other expressions either.
+fn parse_discriminant(expr: &Expr) -> syn::Result<i64> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_discriminant(expr).map(|value| -value),
+        _ => Err(Error::new_spanned(
+            expr,
+            "discriminant must be an integer literal",
+        )),
+    }
+}
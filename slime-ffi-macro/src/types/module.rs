@@ -9,7 +9,7 @@ use crate::symbol::{
     DISPATCHER_ENABLE, ENTRY, ENTRY_JVM, ETNRY_COMMON, LIBRARY_NAME, MODULEMAP_NAME, PACKAGE_NAME,
 };
 
-use super::{constants::ConstantItem, name::Name, Item, ItemAttr};
+use super::{constants::ConstantItem, name::Name, EnumItem, Item, ItemAttr};
 
 /// An exported module, which can and only can have one in a crate.
 pub struct Module {
@@ -41,6 +41,7 @@ struct ModuleVisitor<'a> {
     pub attrs: Vec<Attr>,
     pub decls: Vec<&'a Ident>,
     pub constants: Vec<ConstantItem>,
+    pub enums: Vec<EnumItem>,
     pub structs: Vec<&'a ItemStruct>,
 }
 
@@ -74,6 +75,7 @@ impl<'a> ModuleVisitor<'a> {
     fn visit_item(&mut self, item: &'a syn::Item) -> syn::Result<()> {
         match item {
             syn::Item::Const(const_value) => self.visit_item_const(const_value),
+            syn::Item::Enum(enum_item) => self.visit_item_enum(enum_item),
             _ => Err(Error::new_spanned(item, format!("Unsupported item"))),
         }
     }
@@ -87,6 +89,11 @@ impl<'a> ModuleVisitor<'a> {
         self.constants.push(ConstantItem::parse_ast(item)?);
         Ok(())
     }
+
+    fn visit_item_enum(&mut self, item: &'a syn::ItemEnum) -> syn::Result<()> {
+        self.enums.push(EnumItem::parse_ast(item)?);
+        Ok(())
+    }
 }
 
 impl<'a> From<ModuleVisitor<'a>> for Module {
@@ -105,6 +112,7 @@ impl<'a> ModuleVisitor<'a> {
             attrs: vec![],
             structs: vec![],
             constants: vec![],
+            enums: vec![],
             decls: vec![],
         }
     }
@@ -53,7 +53,21 @@ fn generate_enum_converter(enum_type: &EnumItem) -> TokenStream {
     let jvm_ty = generate_enum_type(enum_type);
     let ty: syn::Type = syn::parse_str(&enum_type.name).unwrap();
     let smt = if enum_type.is_primitive() {
-        quote! { <#ty>::try_from(value).map_err(|e| jni::errors::Error::JavaException) }
+        let repr = generate_primitive_type(&enum_type.repr);
+        let variants = enum_type
+            .variants
+            .iter()
+            .map(|v| Ident::new(&v.name, Span::call_site()));
+        let values = enum_type
+            .discriminants()
+            .into_iter()
+            .map(proc_macro2::Literal::i64_unsuffixed);
+        quote! {
+            match <#repr>::try_from(value).map_err(|_| jni::errors::Error::JavaException)? {
+                #(#values => Ok(#ty::#variants),)*
+                _ => Err(jni::errors::Error::JavaException),
+            }
+        }
     } else {
         todo!()
     };
@@ -123,7 +137,13 @@ fn generate_primitive_type(ty: &PrimitiveType) -> syn::Type {
 
 fn generate_enum_type(enum_type: &EnumItem) -> syn::Type {
     if enum_type.is_primitive() {
-        syn::parse_quote!(jni::sys::jint)
+        match enum_type.repr {
+            // Discriminants which may not fit into a `jint`
+            PrimitiveType::UInt32 | PrimitiveType::Int64 | PrimitiveType::UInt64 => {
+                syn::parse_quote!(jni::sys::jlong)
+            }
+            _ => syn::parse_quote!(jni::sys::jint),
+        }
     } else {
         syn::parse_quote!(jni::objects::JObject<'a>)
     }
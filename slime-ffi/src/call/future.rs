@@ -0,0 +1,459 @@
+//! Rust futures polled from foreign code.
+//!
+//! An `async fn` is exported as a function returning a handle from [`new_future`]. The foreign
+//! side calls [`poll_future`] with a continuation, which is invoked once with
+//! [`FUTURE_READY`] when the output is available, or with [`FUTURE_MAYBE_READY`] when the
+//! future was woken and should be polled again. Once ready, [`complete_future`] takes the
+//! output and [`free_future`] releases the handle. Nothing blocks a foreign thread: Kotlin
+//! resumes a `suspend` continuation, Swift a `CheckedContinuation` and JS resolves a `Promise`.
+//!
+//! The handle doubles as the cancel handle: [`cancel_future`] drops the future right away,
+//! when the coroutine, `Task` or `AbortSignal` on the foreign side is cancelled.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use super::CallStatus;
+
+/// The future completed, call [`complete_future`].
+pub const FUTURE_READY: i8 = 0;
+/// The future was woken, call [`poll_future`] again.
+pub const FUTURE_MAYBE_READY: i8 = 1;
+
+/// Invoked with the `data` passed to [`poll_future`] and [`FUTURE_READY`] or
+/// [`FUTURE_MAYBE_READY`]. It may run on any thread and must not poll from within the call.
+pub type Continuation = extern "C" fn(data: u64, status: i8);
+
+enum Scheduler {
+    Empty,
+    Waiting(Continuation, u64),
+    Woken,
+}
+
+/// Hands wakeups of a pending poll to the foreign continuation.
+pub(super) struct Wakeup(Mutex<Scheduler>);
+
+impl Wakeup {
+    pub(super) fn new() -> Arc<Self> {
+        Arc::new(Wakeup(Mutex::new(Scheduler::Empty)))
+    }
+
+    /// Stores the continuation of a pending poll, `false` if a wake came in while polling and
+    /// the caller must call it at once instead. Called before the poller unlocks its slot, so
+    /// a cancel waiting for that lock finds the continuation to finish.
+    pub(super) fn wait(&self, continuation: Continuation, data: u64) -> bool {
+        let mut state = self.0.lock().unwrap();
+        match *state {
+            Scheduler::Woken => {
+                *state = Scheduler::Empty;
+                false
+            }
+            _ => {
+                *state = Scheduler::Waiting(continuation, data);
+                true
+            }
+        }
+    }
+
+    /// Calls a stored continuation with [`FUTURE_READY`], after cancelling.
+    pub(super) fn finish(&self) {
+        let mut state = self.0.lock().unwrap();
+        if let Scheduler::Waiting(continuation, data) =
+            std::mem::replace(&mut *state, Scheduler::Empty)
+        {
+            drop(state);
+            continuation(data, FUTURE_READY)
+        }
+    }
+}
+
+impl Wake for Wakeup {
+    fn wake(self: Arc<Self>) {
+        let mut state = self.0.lock().unwrap();
+        match std::mem::replace(&mut *state, Scheduler::Empty) {
+            Scheduler::Waiting(continuation, data) => {
+                drop(state);
+                continuation(data, FUTURE_MAYBE_READY)
+            }
+            _ => *state = Scheduler::Woken,
+        }
+    }
+}
+
+/// What generated traits return for an `async fn`.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// How a future ended, see [`complete_future`].
+pub enum FutureOutput<T> {
+    Ready(T),
+    Panicked(Box<dyn Any + Send>),
+    Cancelled,
+}
+
+impl<T> FutureOutput<T> {
+    /// The status reported to foreign code, `Error` is up to the value of a fallible call.
+    pub fn status(&self) -> CallStatus {
+        match self {
+            FutureOutput::Ready(_) => CallStatus::Success,
+            FutureOutput::Panicked(_) => CallStatus::Panic,
+            FutureOutput::Cancelled => CallStatus::Cancelled,
+        }
+    }
+}
+
+struct RustFuture<T> {
+    future: Mutex<Option<BoxFuture<T>>>,
+    output: Mutex<Option<FutureOutput<T>>>,
+    wakeup: Arc<Wakeup>,
+}
+
+/// Boxes `future` behind a handle for foreign code.
+pub fn new_future<F, T>(future: F) -> *const c_void
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let future: Arc<RustFuture<T>> = Arc::new(RustFuture {
+        future: Mutex::new(Some(Box::pin(future))),
+        output: Mutex::new(None),
+        wakeup: Wakeup::new(),
+    });
+    Arc::into_raw(future) as _
+}
+
+/// Polls the future once. A panic completes it, [`complete_future`] returns the payload.
+///
+/// # Safety
+///
+/// `handle` must come from [`new_future`] with the same `T` and not have been freed.
+pub unsafe fn poll_future<T: Send + 'static>(
+    handle: *const c_void,
+    continuation: Continuation,
+    data: u64,
+) {
+    let this = &*(handle as *const RustFuture<T>);
+    let mut slot = this.future.lock().unwrap();
+    let Some(future) = slot.as_mut() else {
+        drop(slot);
+        return continuation(data, FUTURE_READY);
+    };
+    let waker = Waker::from(this.wakeup.clone());
+    let polled = catch_unwind(AssertUnwindSafe(|| {
+        future.as_mut().poll(&mut Context::from_waker(&waker))
+    }));
+    let output = match polled {
+        Ok(Poll::Pending) => {
+            let waiting = this.wakeup.wait(continuation, data);
+            drop(slot);
+            if !waiting {
+                continuation(data, FUTURE_MAYBE_READY)
+            }
+            return;
+        }
+        Ok(Poll::Ready(value)) => FutureOutput::Ready(value),
+        Err(payload) => FutureOutput::Panicked(payload),
+    };
+    *slot = None;
+    drop(slot);
+    *this.output.lock().unwrap() = Some(output);
+    continuation(data, FUTURE_READY)
+}
+
+/// Takes the output of a future polled to [`FUTURE_READY`] or cancelled, `None` if it is not
+/// ready or was taken already.
+///
+/// # Safety
+///
+/// Same as [`poll_future`].
+pub unsafe fn complete_future<T: Send + 'static>(handle: *const c_void) -> Option<FutureOutput<T>> {
+    let this = &*(handle as *const RustFuture<T>);
+    let output = this.output.lock().unwrap().take();
+    output
+}
+
+/// Drops the future and its resources before returning, waiting for a poll in progress on
+/// another thread. A pending continuation is called with [`FUTURE_READY`] and
+/// [`complete_future`] then returns [`FutureOutput::Cancelled`]. Does nothing if the future
+/// has completed already.
+///
+/// # Safety
+///
+/// Same as [`poll_future`].
+pub unsafe fn cancel_future<T: Send + 'static>(handle: *const c_void) {
+    let this = &*(handle as *const RustFuture<T>);
+    let mut slot = this.future.lock().unwrap();
+    let Some(future) = slot.take() else {
+        return;
+    };
+    drop(slot);
+    drop(future);
+    *this.output.lock().unwrap() = Some(FutureOutput::Cancelled);
+    this.wakeup.finish()
+}
+
+/// Drops the future, finished or not.
+///
+/// # Safety
+///
+/// Same as [`poll_future`], the handle must not be used afterwards.
+pub unsafe fn free_future<T: Send + 'static>(handle: *const c_void) {
+    drop(Arc::from_raw(handle as *const RustFuture<T>))
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    use super::{
+        cancel_future, complete_future, free_future, new_future, poll_future, FutureOutput,
+        FUTURE_MAYBE_READY, FUTURE_READY,
+    };
+
+    type Log = Mutex<Vec<i8>>;
+
+    extern "C" fn record(data: u64, status: i8) {
+        let log = unsafe { &*(data as *const Log) };
+        log.lock().unwrap().push(status)
+    }
+
+    /// Pending until `open` is called, which wakes the last poll.
+    #[derive(Default)]
+    struct Gate(Mutex<(bool, Option<Waker>)>);
+
+    impl Gate {
+        fn open(&self) {
+            let mut state = self.0.lock().unwrap();
+            state.0 = true;
+            if let Some(waker) = state.1.take() {
+                drop(state);
+                waker.wake()
+            }
+        }
+    }
+
+    struct Wait(Arc<Gate>);
+
+    impl Future for Wait {
+        type Output = u32;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32> {
+            let mut state = self.0 .0.lock().unwrap();
+            if state.0 {
+                return Poll::Ready(7);
+            }
+            state.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Counts its drops.
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    unsafe fn poll<T: Send + 'static>(handle: *const std::ffi::c_void, log: &Log) {
+        poll_future::<T>(handle, record, log as *const Log as u64)
+    }
+
+    #[test]
+    fn test_ready() {
+        let log = Log::default();
+        let handle = new_future(async { 7u32 });
+        unsafe {
+            poll::<u32>(handle, &log);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<u32>(handle),
+                Some(FutureOutput::Ready(7))
+            ));
+            assert!(complete_future::<u32>(handle).is_none());
+            free_future::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_panic() {
+        let log = Log::default();
+        let handle = new_future(async { panic!("boom") });
+        unsafe {
+            poll::<()>(handle, &log);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<()>(handle),
+                Some(FutureOutput::Panicked(_))
+            ));
+            free_future::<()>(handle);
+        }
+    }
+
+    #[test]
+    fn test_wake_from_thread() {
+        let log = Log::default();
+        let gate = Arc::new(Gate::default());
+        let handle = new_future(Wait(gate.clone()));
+        unsafe {
+            poll::<u32>(handle, &log);
+            assert!(log.lock().unwrap().is_empty());
+            assert!(complete_future::<u32>(handle).is_none());
+
+            thread::spawn(move || gate.open()).join().unwrap();
+            assert_eq!(*log.lock().unwrap(), [FUTURE_MAYBE_READY]);
+
+            poll::<u32>(handle, &log);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_MAYBE_READY, FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<u32>(handle),
+                Some(FutureOutput::Ready(7))
+            ));
+            free_future::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_wake_while_polling() {
+        // Wakes itself before returning `Pending`, the continuation is called at once.
+        let log = Log::default();
+        let mut woken = false;
+        let handle = new_future(std::future::poll_fn(move |cx| {
+            if woken {
+                return Poll::Ready(1u8);
+            }
+            woken = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }));
+        unsafe {
+            poll::<u8>(handle, &log);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_MAYBE_READY]);
+            poll::<u8>(handle, &log);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_MAYBE_READY, FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<u8>(handle),
+                Some(FutureOutput::Ready(1))
+            ));
+            free_future::<u8>(handle);
+        }
+    }
+
+    #[test]
+    fn test_cancel_before_ready() {
+        let log = Log::default();
+        let gate = Arc::new(Gate::default());
+        let handle = new_future(Wait(gate.clone()));
+        unsafe {
+            poll::<u32>(handle, &log);
+            cancel_future::<u32>(handle);
+            // The future is dropped right away and the pending continuation finished.
+            assert_eq!(Arc::strong_count(&gate), 1);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<u32>(handle),
+                Some(FutureOutput::Cancelled)
+            ));
+
+            // Polling and cancelling again are no-ops that report ready.
+            poll::<u32>(handle, &log);
+            cancel_future::<u32>(handle);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY, FUTURE_READY]);
+            assert!(complete_future::<u32>(handle).is_none());
+            free_future::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_cancel_while_polling() {
+        // However a cancel on another thread interleaves with a pending poll, the continuation
+        // is finished exactly once.
+        for _ in 0..200 {
+            let log = Log::default();
+            let handle = new_future(Wait(Arc::new(Gate::default())));
+            let address = handle as usize;
+            let cancel =
+                thread::spawn(move || unsafe { cancel_future::<u32>(address as *const _) });
+            unsafe {
+                poll::<u32>(handle, &log);
+                cancel.join().unwrap();
+                assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+                assert!(matches!(
+                    complete_future::<u32>(handle),
+                    Some(FutureOutput::Cancelled)
+                ));
+                free_future::<u32>(handle);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel_after_ready() {
+        let log = Log::default();
+        let handle = new_future(async { 7u32 });
+        unsafe {
+            poll::<u32>(handle, &log);
+            cancel_future::<u32>(handle);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+            assert!(matches!(
+                complete_future::<u32>(handle),
+                Some(FutureOutput::Ready(7))
+            ));
+            free_future::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_drop_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        // An output that is never taken is dropped with the handle.
+        let log = Log::default();
+        let counted = Counted(drops.clone());
+        let handle = new_future(async move { counted });
+        unsafe {
+            poll::<Counted>(handle, &log);
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+            free_future::<Counted>(handle);
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        // A taken output belongs to the caller, freeing does not drop it again.
+        let counted = Counted(drops.clone());
+        let handle = new_future(async move { counted });
+        unsafe {
+            poll::<Counted>(handle, &log);
+            let output = complete_future::<Counted>(handle);
+            free_future::<Counted>(handle);
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+            drop(output);
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        // A cancelled future is dropped by `cancel_future`, not again by `free_future`.
+        let counted = Counted(drops.clone());
+        let gate = Arc::new(Gate::default());
+        let wait = Wait(gate);
+        let handle = new_future(async move {
+            let _counted = counted;
+            wait.await
+        });
+        unsafe {
+            poll::<u32>(handle, &log);
+            cancel_future::<u32>(handle);
+            assert_eq!(drops.load(Ordering::SeqCst), 3);
+            free_future::<u32>(handle);
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+    }
+}
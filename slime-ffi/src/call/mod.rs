@@ -1,6 +1,18 @@
-use std::mem::MaybeUninit;
+pub mod future;
+pub mod stream;
+
+use std::any::Any;
+use std::fmt;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use num_enum::{TryFromPrimitive, IntoPrimitive};
 
+use crate::types::bytes::{FfiBuffer, Wire, WireError};
+use crate::types::handle::HandleError;
+
+use self::future::FutureOutput;
+use self::stream::StreamItem;
+
 #[derive(Debug, Eq, PartialEq, TryFromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum CallStatus {
@@ -8,6 +20,8 @@ pub enum CallStatus {
     Success = 1,
     Error = 2,
     Panic = 3,
+    /// The foreign side cancelled an async call before it completed.
+    Cancelled = 4,
 }
 
 #[repr(C)]
@@ -47,4 +61,272 @@ impl<T> CallResult<T> {
             data: MaybeUninit::new(info.into()),
         }
     }
-}
\ No newline at end of file
+
+    pub fn cancelled() -> CallResult<T> {
+        CallResult {
+            code: CallStatus::Cancelled.into(),
+            data: MaybeUninit::zeroed(),
+        }
+    }
+}
+
+/// Payload of a [`CallResult`] for functions with a `throws` clause: the return value on
+/// [`CallStatus::Success`], the lowered error enum on [`CallStatus::Error`] and the panic
+/// message on [`CallStatus::Panic`]. It is zeroed on [`CallStatus::Cancelled`], read none of
+/// the fields then.
+#[repr(C)]
+pub union Fallible<T, E, P> {
+    pub value: ManuallyDrop<T>,
+    pub error: ManuallyDrop<E>,
+    pub panic: ManuallyDrop<P>,
+}
+
+impl<T, E, P> CallResult<Fallible<T, E, P>> {
+    pub fn from_result<R: Into<E>>(result: Result<T, R>) -> Self {
+        match result {
+            Ok(value) => CallResult {
+                code: CallStatus::Success.into(),
+                data: MaybeUninit::new(Fallible { value: ManuallyDrop::new(value) }),
+            },
+            Err(error) => CallResult {
+                code: CallStatus::Error.into(),
+                data: MaybeUninit::new(Fallible { error: ManuallyDrop::new(error.into()) }),
+            },
+        }
+    }
+
+    pub fn fallible_panic<R: Into<P>>(info: R) -> Self {
+        CallResult {
+            code: CallStatus::Panic.into(),
+            data: MaybeUninit::new(Fallible { panic: ManuallyDrop::new(info.into()) }),
+        }
+    }
+}
+
+/// Why a call failed without panicking.
+///
+/// The payload of [`CallStatus::Error`] is a `u8` kind, `0` followed by the thrown enum or `1`
+/// followed by the message as `String`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    /// The encoded enum of a `throws` clause.
+    Thrown(Vec<u8>),
+    /// An error of the runtime, e.g. a stale handle or malformed arguments.
+    Message(String),
+}
+
+impl CallError {
+    pub fn thrown<E: Wire>(error: E) -> Self {
+        let mut bytes = Vec::new();
+        error.write(&mut bytes);
+        CallError::Thrown(bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            CallError::Thrown(error) => {
+                out.push(0);
+                out.extend_from_slice(error)
+            }
+            CallError::Message(message) => {
+                out.push(1);
+                message.write(out)
+            }
+        }
+    }
+
+    /// Reads the payload, a thrown enum takes up the rest of `input`.
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        match u8::read(input)? {
+            0 => Ok(CallError::Thrown(std::mem::take(input).to_vec())),
+            1 => String::read(input).map(CallError::Message),
+            kind => Err(WireError::Discriminant {
+                r#enum: "CallError",
+                value: kind as i128,
+            }),
+        }
+    }
+
+    /// The thrown error, or the error of the runtime that is not one.
+    pub fn into_thrown<E: Wire>(self) -> Result<E, CallError> {
+        match self {
+            CallError::Thrown(error) => Ok(crate::types::bytes::decode(&error)?),
+            error => Err(error),
+        }
+    }
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Thrown(_) => f.write_str("an error was thrown"),
+            CallError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+impl From<WireError> for CallError {
+    fn from(error: WireError) -> Self {
+        CallError::Message(error.to_string())
+    }
+}
+
+impl From<HandleError> for CallError {
+    fn from(error: HandleError) -> Self {
+        CallError::Message(error.to_string())
+    }
+}
+
+impl From<CallError> for FfiBuffer {
+    fn from(error: CallError) -> Self {
+        FfiBuffer::from_vec(error.to_bytes())
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "panicked".to_string(),
+        },
+    }
+}
+
+/// How a call through the FFI ended, whatever way it was made.
+pub enum Outcome<T> {
+    Value(T),
+    Error(CallError),
+    Panic(String),
+    Cancelled,
+}
+
+impl<T> Outcome<T> {
+    /// Runs the body of an exported function, catching panics.
+    pub fn catch(body: impl FnOnce() -> Result<T, CallError>) -> Self {
+        match catch_unwind(AssertUnwindSafe(body)) {
+            Ok(Ok(value)) => Outcome::Value(value),
+            Ok(Err(error)) => Outcome::Error(error),
+            Err(payload) => Outcome::Panic(panic_message(&*payload)),
+        }
+    }
+
+    /// The outcome of an async call from [`complete_future`](future::complete_future).
+    pub fn from_future(output: Option<FutureOutput<Result<T, CallError>>>) -> Self {
+        match output {
+            Some(FutureOutput::Ready(Ok(value))) => Outcome::Value(value),
+            Some(FutureOutput::Ready(Err(error))) => Outcome::Error(error),
+            Some(FutureOutput::Panicked(payload)) => Outcome::Panic(panic_message(&*payload)),
+            Some(FutureOutput::Cancelled) => Outcome::Cancelled,
+            None => Outcome::Error(CallError::Message("the future is not ready".to_string())),
+        }
+    }
+
+    pub fn map<R>(self, f: impl FnOnce(T) -> R) -> Outcome<R> {
+        match self {
+            Outcome::Value(value) => Outcome::Value(f(value)),
+            Outcome::Error(error) => Outcome::Error(error),
+            Outcome::Panic(message) => Outcome::Panic(message),
+            Outcome::Cancelled => Outcome::Cancelled,
+        }
+    }
+
+    /// The C return value, the error and the panic message are [`FfiBuffer`]s, the latter of
+    /// UTF-8 without length.
+    pub fn into_result<R>(self, lower: impl FnOnce(T) -> R) -> CallResult<Fallible<R, FfiBuffer, FfiBuffer>> {
+        match self {
+            Outcome::Value(value) => match catch_unwind(AssertUnwindSafe(|| lower(value))) {
+                Ok(value) => CallResult::from_result(Ok::<_, CallError>(value)),
+                Err(payload) => CallResult::fallible_panic(FfiBuffer::from_vec(panic_message(&*payload).into_bytes())),
+            },
+            Outcome::Error(error) => CallResult::from_result(Err(error)),
+            Outcome::Panic(message) => {
+                CallResult::fallible_panic(FfiBuffer::from_vec(message.into_bytes()))
+            }
+            Outcome::Cancelled => CallResult::cancelled(),
+        }
+    }
+
+    /// The result of a call to a host object, the outer error if the host failed other than
+    /// by throwing `E`.
+    pub fn into_host_result<E: Wire>(self) -> Result<Result<T, E>, CallError> {
+        match self {
+            Outcome::Value(value) => Ok(Ok(value)),
+            Outcome::Error(error) => error.into_thrown().map(Err),
+            Outcome::Panic(message) => Err(CallError::Message(format!(
+                "host callback failed: {}",
+                message
+            ))),
+            Outcome::Cancelled => Err(CallError::Message(
+                "host callback was cancelled".to_string(),
+            )),
+        }
+    }
+}
+
+impl<T> Outcome<Option<T>> {
+    /// The outcome of a poll from [`next_item`](stream::next_item), `None` after the last item.
+    pub fn from_stream(item: Option<StreamItem<T>>) -> Self {
+        match item {
+            Some(StreamItem::Item(value)) => Outcome::Value(Some(value)),
+            Some(StreamItem::End) => Outcome::Value(None),
+            Some(StreamItem::Panicked(payload)) => Outcome::Panic(panic_message(&*payload)),
+            Some(StreamItem::Cancelled) => Outcome::Cancelled,
+            None => Outcome::Error(CallError::Message("no item is ready".to_string())),
+        }
+    }
+}
+
+/// As one buffer where a [`CallResult`] does not fit, for JNI and host callbacks: the
+/// [`CallStatus`] followed by the value, the [`CallError`], the panic message as `String` or
+/// nothing for `Cancelled`.
+impl<T: Wire> Outcome<T> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Outcome::Value(value) => {
+                out.push(CallStatus::Success.into());
+                value.write(&mut out)
+            }
+            Outcome::Error(error) => {
+                out.push(CallStatus::Error.into());
+                error.write(&mut out)
+            }
+            Outcome::Panic(message) => {
+                out.push(CallStatus::Panic.into());
+                message.write(&mut out)
+            }
+            Outcome::Cancelled => out.push(CallStatus::Cancelled.into()),
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self, WireError> {
+        let input = &mut bytes;
+        let status = u8::read(input)?;
+        let outcome = match CallStatus::try_from(status) {
+            Ok(CallStatus::Success) => Outcome::Value(T::read(input)?),
+            Ok(CallStatus::Error) => Outcome::Error(CallError::read(input)?),
+            Ok(CallStatus::Panic) => Outcome::Panic(String::read(input)?),
+            Ok(CallStatus::Cancelled) => Outcome::Cancelled,
+            _ => {
+                return Err(WireError::Discriminant {
+                    r#enum: "CallStatus",
+                    value: status as i128,
+                })
+            }
+        };
+        if !input.is_empty() {
+            return Err(WireError::Trailing);
+        }
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,375 @@
+//! Rust streams consumed from foreign code, one item per request.
+//!
+//! A function returning `Stream<T>` hands out a handle from [`new_stream`]. The foreign side
+//! calls [`poll_stream`] whenever it wants the next item, with the same continuation protocol
+//! as [`poll_future`](super::future::poll_future), and takes the item with [`next_item`].
+//! The stream is only polled on request, so a slow Kotlin `Flow` collector, Swift
+//! `AsyncSequence` loop or JS async iterator holds the producer back.
+//!
+//! The stream is dropped when it ends, on [`cancel_stream`] once the consumer stops early, or
+//! at the latest by [`free_stream`], so an abandoned subscription cannot outlive its handle.
+
+use std::any::Any;
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use super::future::{Continuation, Wakeup, FUTURE_MAYBE_READY, FUTURE_READY};
+use super::CallStatus;
+
+/// A source of values polled by foreign code, the shape of `futures::Stream`.
+pub trait PollNext: Send {
+    type Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>;
+}
+
+/// What generated traits return for a `Stream<T>`.
+pub type BoxStream<T> = Pin<Box<dyn PollNext<Item = T>>>;
+
+impl<S: PollNext + ?Sized> PollNext for Pin<Box<S>> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        self.get_mut().as_mut().poll_next(cx)
+    }
+}
+
+/// Serves a blocking iterator as a stream, one `next` per poll.
+pub struct IterStream<I>(pub I);
+
+impl<I: Iterator + Send + Unpin> PollNext for IterStream<I> {
+    type Item = I::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+        Poll::Ready(self.0.next())
+    }
+}
+
+/// Serves a `futures::Stream` through [`PollNext`].
+#[cfg(feature = "futures")]
+pub struct AsyncStream<S>(pub S);
+
+#[cfg(feature = "futures")]
+impl<S: futures_core::Stream + Send + Unpin> PollNext for AsyncStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
+
+/// What a poll produced, see [`next_item`].
+pub enum StreamItem<T> {
+    Item(T),
+    End,
+    Panicked(Box<dyn Any + Send>),
+    Cancelled,
+}
+
+impl<T> StreamItem<T> {
+    /// The status reported to foreign code, the end of the stream is a success without value.
+    pub fn status(&self) -> CallStatus {
+        match self {
+            StreamItem::Item(_) | StreamItem::End => CallStatus::Success,
+            StreamItem::Panicked(_) => CallStatus::Panic,
+            StreamItem::Cancelled => CallStatus::Cancelled,
+        }
+    }
+}
+
+struct RustStream<T> {
+    stream: Mutex<Option<BoxStream<T>>>,
+    item: Mutex<Option<StreamItem<T>>>,
+    wakeup: Arc<Wakeup>,
+}
+
+/// Boxes `stream` behind a handle for foreign code.
+pub fn new_stream<S, T>(stream: S) -> *const c_void
+where
+    S: PollNext<Item = T> + 'static,
+    T: Send + 'static,
+{
+    let stream: Arc<RustStream<T>> = Arc::new(RustStream {
+        stream: Mutex::new(Some(Box::pin(stream))),
+        item: Mutex::new(None),
+        wakeup: Wakeup::new(),
+    });
+    Arc::into_raw(stream) as _
+}
+
+/// Polls for the next item. The continuation is called with
+/// [`FUTURE_READY`](super::future::FUTURE_READY) once [`next_item`] has one, which is
+/// [`StreamItem::End`] after the last item.
+///
+/// # Safety
+///
+/// `handle` must come from [`new_stream`] with the same `T` and not have been freed.
+pub unsafe fn poll_stream<T: Send + 'static>(
+    handle: *const c_void,
+    continuation: Continuation,
+    data: u64,
+) {
+    let this = &*(handle as *const RustStream<T>);
+    let mut slot = this.stream.lock().unwrap();
+    let Some(stream) = slot.as_mut() else {
+        drop(slot);
+        return continuation(data, FUTURE_READY);
+    };
+    let waker = Waker::from(this.wakeup.clone());
+    let polled = catch_unwind(AssertUnwindSafe(|| {
+        stream.as_mut().poll_next(&mut Context::from_waker(&waker))
+    }));
+    let item = match polled {
+        Ok(Poll::Pending) => {
+            let waiting = this.wakeup.wait(continuation, data);
+            drop(slot);
+            if !waiting {
+                continuation(data, FUTURE_MAYBE_READY)
+            }
+            return;
+        }
+        Ok(Poll::Ready(Some(item))) => StreamItem::Item(item),
+        Ok(Poll::Ready(None)) => StreamItem::End,
+        Err(payload) => StreamItem::Panicked(payload),
+    };
+    if !matches!(item, StreamItem::Item(_)) {
+        *slot = None;
+    }
+    drop(slot);
+    *this.item.lock().unwrap() = Some(item);
+    continuation(data, FUTURE_READY)
+}
+
+/// Takes the item of the last poll, `None` if it is pending or was taken already.
+///
+/// # Safety
+///
+/// Same as [`poll_stream`].
+pub unsafe fn next_item<T: Send + 'static>(handle: *const c_void) -> Option<StreamItem<T>> {
+    let this = &*(handle as *const RustStream<T>);
+    let item = this.item.lock().unwrap().take();
+    item
+}
+
+/// Drops the stream before returning, like
+/// [`cancel_future`](super::future::cancel_future). A pending poll completes with
+/// [`StreamItem::Cancelled`].
+///
+/// # Safety
+///
+/// Same as [`poll_stream`].
+pub unsafe fn cancel_stream<T: Send + 'static>(handle: *const c_void) {
+    let this = &*(handle as *const RustStream<T>);
+    let mut slot = this.stream.lock().unwrap();
+    let Some(stream) = slot.take() else {
+        return;
+    };
+    drop(slot);
+    drop(stream);
+    *this.item.lock().unwrap() = Some(StreamItem::Cancelled);
+    this.wakeup.finish()
+}
+
+/// Drops the stream, finished or not.
+///
+/// # Safety
+///
+/// Same as [`poll_stream`], the handle must not be used afterwards.
+pub unsafe fn free_stream<T: Send + 'static>(handle: *const c_void) {
+    drop(Arc::from_raw(handle as *const RustStream<T>))
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::c_void;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    use super::{
+        cancel_stream, free_stream, new_stream, next_item, poll_stream, IterStream, PollNext,
+        StreamItem,
+    };
+    use crate::call::future::{FUTURE_MAYBE_READY, FUTURE_READY};
+
+    type Log = Mutex<Vec<i8>>;
+
+    extern "C" fn record(data: u64, status: i8) {
+        let log = unsafe { &*(data as *const Log) };
+        log.lock().unwrap().push(status)
+    }
+
+    unsafe fn poll<T: Send + 'static>(handle: *const c_void, log: &Log) {
+        poll_stream::<T>(handle, record, log as *const Log as u64)
+    }
+
+    /// Pending until `open` is called, then yields `7` once and ends.
+    #[derive(Default)]
+    struct Gate(Mutex<(bool, Option<Waker>)>);
+
+    impl Gate {
+        fn open(&self) {
+            let mut state = self.0.lock().unwrap();
+            state.0 = true;
+            if let Some(waker) = state.1.take() {
+                drop(state);
+                waker.wake()
+            }
+        }
+    }
+
+    struct Once(Arc<Gate>, bool);
+
+    impl PollNext for Once {
+        type Item = u32;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<u32>> {
+            if self.1 {
+                return Poll::Ready(None);
+            }
+            let mut state = self.0 .0.lock().unwrap();
+            if !state.0 {
+                state.1 = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            drop(state);
+            self.1 = true;
+            Poll::Ready(Some(7))
+        }
+    }
+
+    struct Boom;
+
+    impl PollNext for Boom {
+        type Item = u32;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<u32>> {
+            panic!("boom")
+        }
+    }
+
+    /// Counts its drops.
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_items() {
+        let log = Log::default();
+        let handle = new_stream(IterStream(vec![1u32, 2].into_iter()));
+        unsafe {
+            for expected in [1, 2] {
+                poll::<u32>(handle, &log);
+                assert!(
+                    matches!(next_item::<u32>(handle), Some(StreamItem::Item(item)) if item == expected)
+                );
+                assert!(next_item::<u32>(handle).is_none());
+            }
+            poll::<u32>(handle, &log);
+            assert!(matches!(next_item::<u32>(handle), Some(StreamItem::End)));
+            // The stream is gone after its end, polling reports ready without an item.
+            poll::<u32>(handle, &log);
+            assert!(next_item::<u32>(handle).is_none());
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY; 4]);
+            free_stream::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_wake_from_thread() {
+        let log = Log::default();
+        let gate = Arc::new(Gate::default());
+        let handle = new_stream(Once(gate.clone(), false));
+        unsafe {
+            poll::<u32>(handle, &log);
+            assert!(log.lock().unwrap().is_empty());
+            assert!(next_item::<u32>(handle).is_none());
+
+            thread::spawn(move || gate.open()).join().unwrap();
+            assert_eq!(*log.lock().unwrap(), [FUTURE_MAYBE_READY]);
+
+            poll::<u32>(handle, &log);
+            assert!(matches!(
+                next_item::<u32>(handle),
+                Some(StreamItem::Item(7))
+            ));
+            poll::<u32>(handle, &log);
+            assert!(matches!(next_item::<u32>(handle), Some(StreamItem::End)));
+            assert_eq!(
+                *log.lock().unwrap(),
+                [FUTURE_MAYBE_READY, FUTURE_READY, FUTURE_READY]
+            );
+            free_stream::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_panic() {
+        let log = Log::default();
+        let handle = new_stream(Boom);
+        unsafe {
+            poll::<u32>(handle, &log);
+            assert!(matches!(
+                next_item::<u32>(handle),
+                Some(StreamItem::Panicked(_))
+            ));
+            poll::<u32>(handle, &log);
+            assert!(next_item::<u32>(handle).is_none());
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY; 2]);
+            free_stream::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let log = Log::default();
+        let gate = Arc::new(Gate::default());
+        let handle = new_stream(Once(gate.clone(), false));
+        unsafe {
+            poll::<u32>(handle, &log);
+            cancel_stream::<u32>(handle);
+            // The stream is dropped right away and the pending poll completed.
+            assert_eq!(Arc::strong_count(&gate), 1);
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY]);
+            assert!(matches!(
+                next_item::<u32>(handle),
+                Some(StreamItem::Cancelled)
+            ));
+
+            // Polling and cancelling again report ready without an item.
+            poll::<u32>(handle, &log);
+            cancel_stream::<u32>(handle);
+            assert!(next_item::<u32>(handle).is_none());
+            assert_eq!(*log.lock().unwrap(), [FUTURE_READY; 2]);
+            free_stream::<u32>(handle);
+        }
+    }
+
+    #[test]
+    fn test_free() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let log = Log::default();
+        let items = vec![Counted(drops.clone()), Counted(drops.clone())];
+        let handle = new_stream(IterStream(items.into_iter()));
+        unsafe {
+            poll::<Counted>(handle, &log);
+            let first = next_item::<Counted>(handle);
+            poll::<Counted>(handle, &log);
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+            // Frees the untaken second item, the taken one is left to the caller.
+            free_stream::<Counted>(handle);
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+            drop(first);
+        }
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+    }
+}
@@ -43,7 +43,7 @@ where
     R: FromWith<T, RT>,
 {
     fn into_with(self, rt: RT) -> R {
-        R::from_with(value, rt)
+        R::from_with(self, rt)
     }
 }
 
@@ -54,7 +54,7 @@ where
     type Error = E;
 
     fn try_into_with(self, rt: RT) -> Result<R, Self::Error> {
-        R::try_from_with(value, rt)
+        R::try_from_with(self, rt)
     }
 }
 
@@ -4,14 +4,21 @@ use std::ffi::c_void;
 use std::sync::{Arc, RwLock};
 use jni::{JavaVM, JNIEnv, NativeMethod};
 use jni::objects::{GlobalRef, JFieldID, JMethodID, JObject, JStaticFieldID, JStaticMethodID};
-use jni::sys::{jint, jlong, JNI_VERSION_1_8};
+use jni::signature::{Primitive, ReturnType};
+use jni::sys::{jint, jlong, jvalue, JNI_VERSION_1_8};
 use once_cell::sync::OnceCell;
+use crate::call::future::Continuation;
 use crate::runtime::{FrontendRuntime, Runtime};
 
 type Error = jni::errors::Error;
 
-const CALLBACK_NAME: &'static str = "com.slime.ffi.Callback";
+/// Java interface every host implementation of an IDL interface extends.
+pub const CALLBACK_NAME: &str = "com.slime.ffi.Callback";
+/// Java class whose `static void resume(long data, byte status)` receives the continuations of
+/// async calls, see [`continuation`].
+pub const FUTURES_NAME: &str = "com.slime.ffi.Futures";
 static JAVA_VM: OnceCell<JavaVM> = OnceCell::new();
+static FUTURES: OnceCell<(JavaVM, GlobalRef, JStaticMethodID)> = OnceCell::new();
 
 struct FieldMeta {
     pub field_id: JFieldID,
@@ -116,4 +123,32 @@ pub fn on_jvm_load(vm: JavaVM) {
     if JAVA_VM.set(vm).is_err() {
         eprintln!("Double initialization for JavaVM!");
     }
-}
\ No newline at end of file
+}
+
+/// The continuation to poll futures and streams with from the JVM, forwarding to
+/// [`FUTURES_NAME`]. Resolves the class on first use, so call it from a Java thread where the
+/// class loader of the app is current.
+pub fn continuation(env: &mut JNIEnv) -> Result<Continuation, Error> {
+    FUTURES.get_or_try_init(|| {
+        let class = env.find_class(FUTURES_NAME.replace('.', "/"))?;
+        let resume = env.get_static_method_id(&class, "resume", "(JB)V")?;
+        Ok::<_, Error>((env.get_java_vm()?, env.new_global_ref(class)?, resume))
+    })?;
+    Ok(resume)
+}
+
+extern "C" fn resume(data: u64, status: i8) {
+    let (vm, class, resume) = FUTURES.get().unwrap();
+    let Ok(mut env) = vm.attach_current_thread_permanently() else {
+        return;
+    };
+    let _ = unsafe {
+        env.call_static_method_unchecked(
+            <&jni::objects::JClass>::from(class.as_obj()),
+            *resume,
+            ReturnType::Primitive(Primitive::Void),
+            &[jvalue { j: data as jlong }, jvalue { b: status }],
+        )
+    };
+}
+
@@ -0,0 +1,377 @@
+//! Buffers crossing the C ABI and the wire format of the values in them.
+//!
+//! Values without a fixed C layout, like strings, collections and payload enums, are passed
+//! encoded as bytes. Integers and floats are little-endian, `bool` is one byte, `String` and
+//! `Bytes` are a `u32` length followed by the bytes, and the builtin types are encoded as
+//! documented on `BuiltinType` of the IR. Models are their fields in declaration order, objects
+//! are their `u64` handle.
+//!
+//! The host passes arguments as borrowed [`FfiSlice`]s, Rust returns owned [`FfiBuffer`]s which
+//! the host gives back to [`slime_buffer_free`].
+
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use super::handle::{self, HandleError, Object};
+
+/// Bytes owned by Rust, handed to the host.
+#[repr(C)]
+pub struct FfiBuffer {
+    pub data: *mut u8,
+    pub len: u64,
+    pub capacity: u64,
+}
+
+impl FfiBuffer {
+    pub fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = std::mem::ManuallyDrop::new(bytes);
+        FfiBuffer {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len() as u64,
+            capacity: bytes.capacity() as u64,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The buffer must come from [`FfiBuffer::from_vec`] or [`slime_buffer_alloc`] and is
+    /// consumed.
+    pub unsafe fn into_vec(self) -> Vec<u8> {
+        Vec::from_raw_parts(self.data, self.len as usize, self.capacity as usize)
+    }
+}
+
+/// Bytes borrowed from the host for the duration of a call.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FfiSlice {
+    pub data: *const u8,
+    pub len: u64,
+}
+
+impl FfiSlice {
+    /// # Safety
+    ///
+    /// `data` must point to `len` readable bytes, or be null with `len` 0, for `'a`.
+    pub unsafe fn as_slice<'a>(&self) -> &'a [u8] {
+        if self.data.is_null() {
+            &[]
+        } else {
+            std::slice::from_raw_parts(self.data, self.len as usize)
+        }
+    }
+}
+
+/// Allocates a zeroed buffer the host fills and hands to Rust, e.g. the result of a callback.
+#[no_mangle]
+pub extern "C" fn slime_buffer_alloc(len: u64) -> FfiBuffer {
+    FfiBuffer::from_vec(vec![0; len as usize])
+}
+
+/// Frees a buffer returned by Rust.
+///
+/// # Safety
+///
+/// `buffer` must come from Rust and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn slime_buffer_free(buffer: FfiBuffer) {
+    drop(buffer.into_vec())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    /// The input ended in the middle of a value.
+    Eof,
+    /// Bytes left over after the value.
+    Trailing,
+    /// A string was not UTF-8.
+    Utf8,
+    /// No variant of `enum` has the discriminant.
+    Discriminant { r#enum: &'static str, value: i128 },
+    Handle(HandleError),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Eof => f.write_str("unexpected end of input"),
+            WireError::Trailing => f.write_str("unexpected bytes after the value"),
+            WireError::Utf8 => f.write_str("string is not UTF-8"),
+            WireError::Discriminant { r#enum, value } => {
+                write!(f, "`{}` has no variant `{}`", r#enum, value)
+            }
+            WireError::Handle(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<HandleError> for WireError {
+    fn from(error: HandleError) -> Self {
+        WireError::Handle(error)
+    }
+}
+
+/// A value in the wire format, implemented by generated code for models and enums.
+pub trait Wire: Sized {
+    fn write(&self, out: &mut Vec<u8>);
+
+    /// Reads a value from the front of `input` and advances it.
+    fn read(input: &mut &[u8]) -> Result<Self, WireError>;
+}
+
+pub fn encode<T: Wire>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.write(&mut out);
+    out
+}
+
+/// Reads a value taking up all of `bytes`.
+pub fn decode<T: Wire>(mut bytes: &[u8]) -> Result<T, WireError> {
+    let value = T::read(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(WireError::Trailing);
+    }
+    Ok(value)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], WireError> {
+    if input.len() < len {
+        return Err(WireError::Eof);
+    }
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+fn write_len(len: usize, out: &mut Vec<u8>) {
+    (len as u32).write(out)
+}
+
+fn read_len(input: &mut &[u8]) -> Result<usize, WireError> {
+    u32::read(input).map(|len| len as usize)
+}
+
+macro_rules! wire_number {
+    ($($ty:ty),*) => {
+        $(
+            impl Wire for $ty {
+                fn write(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes())
+                }
+
+                fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+                    let bytes = take(input, std::mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+wire_number!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl Wire for bool {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8)
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(take(input, 1)?[0] != 0)
+    }
+}
+
+/// The value of a function without a return type.
+impl Wire for () {
+    fn write(&self, _out: &mut Vec<u8>) {}
+
+    fn read(_input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(())
+    }
+}
+
+/// The error of a function without a `throws` clause.
+impl Wire for Infallible {
+    fn write(&self, _out: &mut Vec<u8>) {
+        match *self {}
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        Err(WireError::Discriminant {
+            r#enum: "Infallible",
+            value: u8::read(input)? as i128,
+        })
+    }
+}
+
+impl Wire for String {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes())
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = read_len(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| WireError::Utf8)
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.is_some().write(out);
+        if let Some(value) = self {
+            value.write(out)
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        Ok(match bool::read(input)? {
+            true => Some(T::read(input)?),
+            false => None,
+        })
+    }
+}
+
+/// Breaks up types containing themselves, encoded as the value.
+impl<T: Wire> Wire for Box<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.as_ref().write(out)
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        T::read(input).map(Box::new)
+    }
+}
+
+/// `List<T>`, and `Bytes` as `Vec<u8>`, which is encoded the same way.
+impl<T: Wire> Wire for Vec<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        self.iter().for_each(|value| value.write(out))
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = read_len(input)?;
+        // Every value takes at least one byte, so this bounds the allocation by the input.
+        let mut values = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            values.push(T::read(input)?)
+        }
+        Ok(values)
+    }
+}
+
+impl<T: Wire + Eq + Hash> Wire for HashSet<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        self.iter().for_each(|value| value.write(out))
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = read_len(input)?;
+        (0..len).map(|_| T::read(input)).collect()
+    }
+}
+
+impl<K: Wire + Eq + Hash, V: Wire> Wire for HashMap<K, V> {
+    fn write(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        for (key, value) in self {
+            key.write(out);
+            value.write(out)
+        }
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let len = read_len(input)?;
+        (0..len)
+            .map(|_| Ok((K::read(input)?, V::read(input)?)))
+            .collect()
+    }
+}
+
+impl<T: Wire, const N: usize> Wire for [T; N] {
+    fn write(&self, out: &mut Vec<u8>) {
+        self.iter().for_each(|value| value.write(out))
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let mut values = Vec::with_capacity(N);
+        for _ in 0..N {
+            values.push(T::read(input)?)
+        }
+        Ok(values.try_into().ok().unwrap())
+    }
+}
+
+macro_rules! wire_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Wire),*> Wire for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn write(&self, out: &mut Vec<u8>) {
+                let ($($name,)*) = self;
+                $($name.write(out);)*
+            }
+
+            fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+                Ok(($($name::read(input)?,)*))
+            }
+        }
+    };
+}
+
+wire_tuple!(A, B);
+wire_tuple!(A, B, C);
+wire_tuple!(A, B, C, D);
+wire_tuple!(A, B, C, D, E);
+wire_tuple!(A, B, C, D, E, F);
+wire_tuple!(A, B, C, D, E, F, G);
+wire_tuple!(A, B, C, D, E, F, G, H);
+wire_tuple!(A, B, C, D, E, F, G, H, I);
+wire_tuple!(A, B, C, D, E, F, G, H, I, J);
+wire_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+wire_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Objects are written as a new handle of the host and read with [`Object::from_handle`].
+impl<T: Object + ?Sized> Wire for Arc<T> {
+    fn write(&self, out: &mut Vec<u8>) {
+        handle::export(Arc::new(self.clone())).write(out)
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, WireError> {
+        let handle = u64::read(input)?;
+        // Raw handles are trusted like every other pointer the host passes.
+        Ok(unsafe { T::from_handle(handle) }?)
+    }
+}
+
+#[cfg(feature = "jvm")]
+pub use jvm::*;
+
+#[cfg(feature = "jvm")]
+mod jvm {
+    use jni::objects::JByteArray;
+    use jni::sys::jbyteArray;
+    use jni::JNIEnv;
+
+    use crate::call::CallError;
+
+    /// The bytes of a `byte[]` argument of a JNI function.
+    pub fn from_jbytes(env: &JNIEnv, array: &JByteArray) -> Result<Vec<u8>, CallError> {
+        env.convert_byte_array(array)
+            .map_err(|error| CallError::Message(error.to_string()))
+    }
+
+    /// A `byte[]` to return from a JNI function, null with an exception thrown if it cannot
+    /// be allocated.
+    pub fn to_jbytes(env: &JNIEnv, bytes: &[u8]) -> jbyteArray {
+        env.byte_array_from_slice(bytes)
+            .map(|array| array.into_raw())
+            .unwrap_or(std::ptr::null_mut())
+    }
+}
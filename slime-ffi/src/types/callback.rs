@@ -0,0 +1,292 @@
+//! IDL interfaces implemented by the host and called from Rust.
+//!
+//! The host registers a vtable once per interface, with the entry points in the slot order of
+//! `InterfaceDecl::vtable`: C passes a table of function pointers, the JVM resolves the method
+//! IDs of the interface, which extends `com.slime.ffi.Callback`. Every host
+//! object passed into Rust then becomes an [`FfiRef`] holding the host handle and that vtable.
+//! Generated code wraps it in a type implementing the Rust trait, so Rust sees a
+//! `Box<dyn Trait>`, and the host handle is released when that box is dropped.
+//!
+//! Every method takes its arguments encoded in the wire format and returns an
+//! [`Outcome`] record, see [`call_host`].
+
+use std::ffi::c_void;
+use std::ptr::NonNull;
+
+use super::bytes::{FfiBuffer, FfiSlice, Wire};
+use super::r#ref::{DtorFn, FfiAny, FfiCVTable, FfiRef, FfiRefVTable};
+use crate::call::{CallError, Outcome};
+
+/// A method of a C host object: the object handle and the encoded arguments in, the
+/// [`Outcome`] record out, allocated with
+/// [`slime_buffer_alloc`](super::bytes::slime_buffer_alloc).
+pub type HostFn = unsafe extern "C" fn(FfiAny, FfiSlice) -> FfiBuffer;
+
+/// Registers the vtable of a C interface implementation, `methods` holds `len` function
+/// pointers in slot order. `dtor` is called with the object handle when Rust drops it.
+///
+/// # Safety
+///
+/// `methods` must point to `len` non-null function pointers. The vtable lives as long as
+/// the process, it is meant to be registered once per interface.
+#[no_mangle]
+pub unsafe extern "C" fn slime_callback_vtable(
+    dtor: Option<DtorFn>,
+    methods: *const NonNull<()>,
+    len: u32,
+) -> *const c_void {
+    let methods = if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(methods, len as usize)
+    };
+    let vtable = FfiRefVTable::C(FfiCVTable {
+        runtime: (),
+        ctor: None,
+        dtor,
+        methods: methods
+            .iter()
+            .copied()
+            .zip(0..)
+            .map(|(method, slot)| (slot, method))
+            .collect(),
+    });
+    Box::into_raw(Box::new(vtable)) as _
+}
+
+/// Wraps a host object implementing the interface `T` stands for.
+///
+/// # Safety
+///
+/// `vtable` must come from [`slime_callback_vtable`] or [`register_jvm`] for the same
+/// interface, and the object must meet the requirements of [`FfiRef::host`].
+pub unsafe fn from_host<T: 'static>(handle: NonNull<()>, vtable: *const c_void) -> FfiRef {
+    FfiRef::host::<T>(handle, &*(vtable as *const FfiRefVTable))
+}
+
+/// Calls slot `slot` of a host object with the encoded arguments. A JVM method has the
+/// signature `([B)[B`. The outer error is a failure other than throwing `E`: an unknown slot,
+/// a malformed result, or a host that panicked or raised an exception.
+///
+/// # Safety
+///
+/// `host` must wrap an object of the interface the slot numbers are for.
+pub unsafe fn call_host<T: Wire, E: Wire>(
+    host: &FfiRef,
+    slot: u32,
+    args: Vec<u8>,
+) -> Result<Result<T, E>, CallError> {
+    let method = host
+        .method(slot)
+        .ok_or_else(|| CallError::Message(format!("host object has no method {}", slot)))?;
+    let outcome = match host.vtable() {
+        FfiRefVTable::C(_) => {
+            let method: HostFn = std::mem::transmute(method.as_ptr());
+            let args = FfiSlice {
+                data: args.as_ptr(),
+                len: args.len() as u64,
+            };
+            method(host.handle(), args).into_vec()
+        }
+        #[cfg(feature = "jvm")]
+        FfiRefVTable::Java(vtable) => {
+            let handle = host
+                .handle()
+                .ok_or_else(|| CallError::Message("host object is null".to_string()))?;
+            call_jvm(vtable, handle, method, &args)
+        }
+        FfiRefVTable::Rust(_) => {
+            return Err(CallError::Message("not a host object".to_string()));
+        }
+    };
+    Outcome::from_bytes(&outcome)?.into_host_result()
+}
+
+#[cfg(feature = "jvm")]
+pub use jvm::*;
+
+#[cfg(feature = "jvm")]
+mod jvm {
+    use std::ptr::NonNull;
+
+    use jni::objects::{JByteArray, JClass, JMethodID, JObject, JString, JValueOwned};
+    use jni::signature::{Primitive, ReturnType};
+    use jni::sys::{jlong, jobject, jvalue};
+    use jni::JNIEnv;
+
+    use crate::call::{CallError, Outcome};
+    use crate::runtime::jvm::CALLBACK_NAME;
+    use crate::types::r#ref::{FfiRefVTable, JavaRuntimeVTable, VTable};
+
+    type Error = jni::errors::Error;
+
+    /// Resolves the methods of a JVM interface, given as `(name, signature)` in slot order.
+    /// `ctor` and `dtor` are `retain()` and `release()` of [`CALLBACK_NAME`], the latter is
+    /// called before Rust deletes the global ref of a dropped object.
+    pub fn register_jvm(
+        env: &mut JNIEnv,
+        interface: &JClass,
+        methods: &[(&str, &str)],
+    ) -> Result<&'static FfiRefVTable, Error> {
+        let callback = env.find_class(CALLBACK_NAME.replace('.', "/"))?;
+        if !env.is_assignable_from(interface, &callback)? {
+            return Err(Error::WrongJValueType("interface", CALLBACK_NAME));
+        }
+        let ctor = env.get_method_id(&callback, "retain", "()V")?;
+        let dtor = env.get_method_id(&callback, "release", "()V")?;
+        let methods = methods
+            .iter()
+            .zip(0..)
+            .map(|((name, sig), slot)| {
+                let id = env.get_method_id(interface, name, sig)?;
+                Ok((slot, NonNull::new(id.into_raw() as *mut ()).unwrap()))
+            })
+            .collect::<Result<_, Error>>()?;
+        let vtable = FfiRefVTable::Java(VTable {
+            runtime: env.get_java_vm()?,
+            ctor,
+            dtor,
+            methods,
+        });
+        Ok(Box::leak(Box::new(vtable)))
+    }
+
+    /// Calls `release()` on the object, the global ref held as handle, and deletes the ref.
+    pub(crate) unsafe fn release_jvm(vtable: &JavaRuntimeVTable, handle: NonNull<()>) {
+        let Ok(mut env) = vtable.runtime.attach_current_thread_permanently() else {
+            return;
+        };
+        let object = JObject::from_raw(handle.as_ptr() as jobject);
+        let _ = env.call_method_unchecked(
+            &object,
+            vtable.dtor,
+            ReturnType::Primitive(Primitive::Void),
+            &[],
+        );
+        let raw = env.get_raw();
+        if let Some(delete) = (**raw).DeleteGlobalRef {
+            delete(raw, object.into_raw())
+        }
+    }
+
+    /// Creates the global ref a JVM host object is passed to Rust as, released by
+    /// [`release_jvm`].
+    pub fn retain_jvm(env: &mut JNIEnv, object: &JObject) -> jlong {
+        let raw = env.get_raw();
+        unsafe {
+            match (**raw).NewGlobalRef {
+                Some(new) => new(raw, object.as_raw()) as jlong,
+                None => 0,
+            }
+        }
+    }
+
+    /// Clears the pending exception, described by its `toString()`.
+    fn take_exception(env: &mut JNIEnv) -> Result<String, Error> {
+        let exception = env.exception_occurred()?;
+        env.exception_clear()?;
+        let message = env
+            .call_method(&exception, "toString", "()Ljava/lang/String;", &[])
+            .and_then(JValueOwned::l);
+        if env.exception_check()? {
+            env.exception_clear()?;
+            return Ok("host callback threw an exception".to_string());
+        }
+        let message = JString::from(message?);
+        let message: String = env.get_string(&message)?.into();
+        Ok(format!("host callback threw {}", message))
+    }
+
+    pub(super) unsafe fn call_jvm(
+        vtable: &JavaRuntimeVTable,
+        handle: NonNull<()>,
+        method: NonNull<()>,
+        args: &[u8],
+    ) -> Vec<u8> {
+        let result = vtable
+            .runtime
+            .attach_current_thread_permanently()
+            .and_then(|mut env| {
+                // The thread may stay attached for good, so free the local refs right away.
+                env.with_local_frame(4, |env| {
+                    let object = JObject::from_raw(handle.as_ptr() as jobject);
+                    let method = JMethodID::from_raw(method.as_ptr() as _);
+                    let args = env.byte_array_from_slice(args)?;
+                    let result = env.call_method_unchecked(
+                        &object,
+                        method,
+                        ReturnType::Array,
+                        &[jvalue { l: args.as_raw() }],
+                    );
+                    if env.exception_check()? {
+                        let message = take_exception(env)?;
+                        return Ok(Outcome::<()>::Error(CallError::Message(message)).to_bytes());
+                    }
+                    let result = JByteArray::from(result.and_then(JValueOwned::l)?);
+                    env.convert_byte_array(&result)
+                })
+            });
+        result.unwrap_or_else(|error| {
+            Outcome::<()>::Error(CallError::Message(error.to_string())).to_bytes()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr::NonNull;
+
+    use super::{call_host, from_host, slime_callback_vtable, HostFn};
+    use crate::call::{CallError, Outcome};
+    use crate::types::bytes::{FfiBuffer, FfiSlice};
+    use crate::types::r#ref::FfiAny;
+
+    /// Answers by the first argument byte: a value, a thrown `u8`, a panic or garbage.
+    unsafe extern "C" fn method(_: FfiAny, args: FfiSlice) -> FfiBuffer {
+        FfiBuffer::from_vec(match args.as_slice()[0] {
+            0 => Outcome::Value(7u32).to_bytes(),
+            1 => Outcome::<u32>::Error(CallError::thrown(3u8)).to_bytes(),
+            2 => Outcome::<u32>::Panic("boom".to_string()).to_bytes(),
+            _ => vec![9],
+        })
+    }
+
+    fn call(mode: u8) -> Result<Result<u32, u8>, CallError> {
+        unsafe {
+            let methods = [NonNull::new(method as HostFn as *mut ()).unwrap()];
+            let vtable = slime_callback_vtable(None, methods.as_ptr(), 1);
+            let host = from_host::<u32>(NonNull::dangling(), vtable);
+            call_host(&host, 0, vec![mode])
+        }
+    }
+
+    #[test]
+    fn test_call_host() {
+        assert_eq!(call(0), Ok(Ok(7)));
+        assert_eq!(call(1), Ok(Err(3)));
+        assert_eq!(
+            call(2),
+            Err(CallError::Message("host callback failed: boom".to_string()))
+        );
+        assert_eq!(
+            call(3),
+            Err(CallError::Message(
+                "`CallStatus` has no variant `9`".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unknown_slot() {
+        unsafe {
+            let vtable = slime_callback_vtable(None, std::ptr::null(), 0);
+            let host = from_host::<u32>(NonNull::dangling(), vtable);
+            assert_eq!(
+                call_host::<u32, u8>(&host, 0, vec![]),
+                Err(CallError::Message(
+                    "host object has no method 0".to_string()
+                ))
+            );
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Handles of Rust objects given to the host.
+//!
+//! In safe mode a handle indexes a table instead of pointing at the object: its low 32 bits
+//! are the slot, the next 24 bits the generation of the slot and the top 8 bits a tag of the
+//! type. A handle used after it was released, or as the wrong type, resolves to a
+//! [`HandleError`] which the call reports as `CallStatus::Error`, where a raw pointer would
+//! corrupt memory. Raw mode passes `Arc` pointers and checks nothing.
+//!
+//! Safe mode is on in debug builds, the `safe_handles` and `raw_handles` features force
+//! either mode regardless of the profile.
+
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// Whether handles go through [`HANDLES`] in this build.
+pub const SAFE_HANDLES: bool = cfg!(any(
+    feature = "safe_handles",
+    all(debug_assertions, not(feature = "raw_handles"))
+));
+
+const INDEX_BITS: u32 = 32;
+const GENERATION_BITS: u32 = 24;
+const GENERATION_MASK: u64 = (1 << GENERATION_BITS) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+    /// Never handed out, e.g. `0` or a random value.
+    Invalid,
+    /// Released already, the slot is empty or holds a newer object.
+    Stale,
+    /// Handed out for another type.
+    WrongType,
+}
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HandleError::Invalid => "invalid handle",
+            HandleError::Stale => "use of a released object",
+            HandleError::WrongType => "handle of another type",
+        })
+    }
+}
+
+impl std::error::Error for HandleError {}
+
+type AnyObject = Arc<dyn Any + Send + Sync>;
+
+struct Slot {
+    generation: u64,
+    object: Option<AnyObject>,
+}
+
+#[derive(Default)]
+struct Slots {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+/// Generational table of the objects the host holds handles to.
+pub struct HandleMap {
+    inner: Mutex<Slots>,
+}
+
+/// The table behind every handle in safe mode.
+pub static HANDLES: HandleMap = HandleMap::new();
+
+fn tag<T: 'static>() -> u64 {
+    let mut hasher = DefaultHasher::new();
+    TypeId::of::<T>().hash(&mut hasher);
+    hasher.finish() >> 56
+}
+
+impl HandleMap {
+    pub const fn new() -> Self {
+        HandleMap {
+            inner: Mutex::new(Slots {
+                slots: Vec::new(),
+                free: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn insert<T: Send + Sync + 'static>(&self, object: Arc<T>) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        let index = match inner.free.pop() {
+            Some(index) => index,
+            None => {
+                inner.slots.push(Slot {
+                    generation: 0,
+                    object: None,
+                });
+                (inner.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut inner.slots[index as usize];
+        // Generation 0 is never handed out, so the handle of slot 0 is never 0.
+        slot.generation = (slot.generation % GENERATION_MASK) + 1;
+        slot.object = Some(object);
+        tag::<T>() << (INDEX_BITS + GENERATION_BITS)
+            | slot.generation << INDEX_BITS
+            | index as u64
+    }
+
+    fn slot<T: 'static>(inner: &mut Slots, handle: u64) -> Result<&mut Slot, HandleError> {
+        let index = (handle & u32::MAX as u64) as usize;
+        let generation = (handle >> INDEX_BITS) & GENERATION_MASK;
+        let slot = inner
+            .slots
+            .get_mut(index)
+            .filter(|_| generation != 0)
+            .ok_or(HandleError::Invalid)?;
+        if slot.generation != generation || slot.object.is_none() {
+            return Err(HandleError::Stale);
+        }
+        if handle >> (INDEX_BITS + GENERATION_BITS) != tag::<T>() {
+            return Err(HandleError::WrongType);
+        }
+        Ok(slot)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self, handle: u64) -> Result<Arc<T>, HandleError> {
+        let mut inner = self.inner.lock().unwrap();
+        let object = Self::slot::<T>(&mut inner, handle)?.object.clone().unwrap();
+        object.downcast().map_err(|_| HandleError::WrongType)
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&self, handle: u64) -> Result<Arc<T>, HandleError> {
+        let mut inner = self.inner.lock().unwrap();
+        let slot = Self::slot::<T>(&mut inner, handle)?;
+        if !slot.object.as_ref().unwrap().is::<T>() {
+            return Err(HandleError::WrongType);
+        }
+        let object = slot.object.take().unwrap();
+        inner.free.push((handle & u32::MAX as u64) as u32);
+        Ok(object.downcast().unwrap())
+    }
+}
+
+impl Default for HandleMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hands `object` to the host.
+pub fn export<T: Send + Sync + 'static>(object: Arc<T>) -> u64 {
+    if SAFE_HANDLES {
+        HANDLES.insert(object)
+    } else {
+        Arc::into_raw(object) as u64
+    }
+}
+
+/// The object behind a handle from [`export`].
+///
+/// # Safety
+///
+/// In raw mode `handle` must be live and of type `T`, safe mode checks both.
+pub unsafe fn resolve<T: Send + Sync + 'static>(handle: u64) -> Result<Arc<T>, HandleError> {
+    if SAFE_HANDLES {
+        HANDLES.get(handle)
+    } else if handle == 0 {
+        Err(HandleError::Invalid)
+    } else {
+        Arc::increment_strong_count(handle as *const T);
+        Ok(Arc::from_raw(handle as *const T))
+    }
+}
+
+/// Gives up the host's reference, the object is dropped once Rust holds none either.
+///
+/// # Safety
+///
+/// Same as [`resolve`].
+pub unsafe fn release<T: Send + Sync + 'static>(handle: u64) -> Result<(), HandleError> {
+    if SAFE_HANDLES {
+        HANDLES.remove::<T>(handle).map(drop)
+    } else if handle == 0 {
+        Err(HandleError::Invalid)
+    } else {
+        drop(Arc::from_raw(handle as *const T));
+        Ok(())
+    }
+}
+
+/// The trait object of an IDL class or interface, e.g. `dyn Client`, whose values cross the
+/// boundary as handles.
+pub trait Object: Send + Sync + 'static {
+    /// The object for a handle the host passed in, a Rust object from [`export`] by default.
+    /// Interfaces the host implements wrap the host object instead.
+    ///
+    /// # Safety
+    ///
+    /// See [`resolve`].
+    unsafe fn from_handle(handle: u64) -> Result<Arc<Self>, HandleError> {
+        resolve::<Arc<Self>>(handle).map(|object| Arc::clone(&*object))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{HandleError, HandleMap};
+
+    #[test]
+    fn test_insert_get_remove() {
+        let map = HandleMap::default();
+        let handle = map.insert(Arc::new(7u32));
+        assert_ne!(handle, 0);
+        assert_eq!(*map.get::<u32>(handle).unwrap(), 7);
+        assert_eq!(*map.remove::<u32>(handle).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_stale_and_invalid() {
+        let map = HandleMap::default();
+        let first = map.insert(Arc::new(1u32));
+        map.remove::<u32>(first).unwrap();
+        assert_eq!(map.get::<u32>(first).unwrap_err(), HandleError::Stale);
+        assert_eq!(map.remove::<u32>(first).unwrap_err(), HandleError::Stale);
+
+        // The slot is reused with a new generation, the old handle stays stale.
+        let second = map.insert(Arc::new(2u32));
+        assert_eq!(second & u32::MAX as u64, first & u32::MAX as u64);
+        assert_ne!(second, first);
+        assert_eq!(map.get::<u32>(first).unwrap_err(), HandleError::Stale);
+        assert_eq!(*map.get::<u32>(second).unwrap(), 2);
+
+        assert_eq!(map.get::<u32>(0).unwrap_err(), HandleError::Invalid);
+        assert_eq!(map.get::<u32>(1 << 32 | 99).unwrap_err(), HandleError::Invalid);
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let map = HandleMap::default();
+        let handle = map.insert(Arc::new(String::from("x")));
+        assert_eq!(map.get::<u32>(handle).unwrap_err(), HandleError::WrongType);
+        assert_eq!(map.remove::<u32>(handle).unwrap_err(), HandleError::WrongType);
+        assert_eq!(*map.get::<String>(handle).unwrap(), "x");
+    }
+}
@@ -1,2 +1,4 @@
 pub mod bytes;
-pub mod r#ref;
\ No newline at end of file
+pub mod callback;
+pub mod handle;
+pub mod r#ref;
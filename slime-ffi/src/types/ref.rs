@@ -1,22 +1,34 @@
-use std::{any::TypeId, marker::PhantomData, ptr::NonNull};
+use std::{any::TypeId, marker::PhantomData, ptr::NonNull, sync::Arc};
 
-struct VTable<RT, M, N> {
+use super::handle::{self, HandleError};
+
+pub struct VTable<RT, M, N> {
     pub(crate) runtime: RT,
     pub(crate) ctor: M,
     pub(crate) dtor: N,
+    /// Entry points by slot, the order of `InterfaceDecl::vtable` for host callbacks.
     pub(crate) methods: Vec<(u32, NonNull<()>)>,
 }
 
+impl<RT, M, N> VTable<RT, M, N> {
+    pub fn method(&self, slot: u32) -> Option<NonNull<()>> {
+        self.methods
+            .iter()
+            .find(|(id, _)| *id == slot)
+            .map(|(_, method)| *method)
+    }
+}
+
 mod common {
     use std::ptr::NonNull;
 
     use super::VTable;
 
     pub type FfiAny = Option<NonNull<()>>;
-    pub type CtorFn = unsafe fn(FfiAny) -> FfiAny;
-    pub type DtorFn = unsafe fn(FfiAny);
+    pub type CtorFn = unsafe extern "C" fn(FfiAny) -> FfiAny;
+    pub type DtorFn = unsafe extern "C" fn(FfiAny);
 
-    pub type FfiCVTable = VTable<(), CtorFn, DtorFn>;
+    pub type FfiCVTable = VTable<(), Option<CtorFn>, Option<DtorFn>>;
 }
 
 pub use common::*;
@@ -33,10 +45,12 @@ mod jvm {
 #[cfg(feature = "jvm")]
 pub use jvm::*;
 
-enum FfiRefVTable {
+pub enum FfiRefVTable {
     C(FfiCVTable),
     #[cfg(feature = "jvm")]
     Java(JavaRuntimeVTable),
+    /// A Rust object behind a handle of [`handle::export`], released with the function.
+    Rust(unsafe fn(u64) -> Result<(), HandleError>),
 }
 
 impl Default for FfiRefVTable {
@@ -50,6 +64,14 @@ impl Default for FfiRefVTable {
     }
 }
 
+/// The vtable of `FfiRef::none`, there is no object to call or release.
+static EMPTY_VTABLE: FfiRefVTable = FfiRefVTable::C(FfiCVTable {
+    runtime: (),
+    ctor: None,
+    dtor: None,
+    methods: Vec::new(),
+});
+
 #[repr(C)]
 pub struct FfiRef {
     inner_ref: Option<NonNull<()>>,
@@ -57,20 +79,64 @@ pub struct FfiRef {
     vtable: NonNull<FfiRefVTable>,
 }
 
+// Host objects are thread-safe, a requirement of `FfiRef::host`. Vtables are not changed after
+// registration.
+unsafe impl Send for FfiRef {}
+unsafe impl Sync for FfiRef {}
+unsafe impl Send for FfiRefVTable {}
+unsafe impl Sync for FfiRefVTable {}
+
 pub trait SlimeType {}
 
 fn ref_ty<T: 'static>() -> NonNull<TypeId> {
     NonNull::new(Box::into_raw(Box::new(TypeId::of::<T>()))).unwrap()
 }
 
+impl FfiRefVTable {
+    pub fn method(&self, slot: u32) -> Option<NonNull<()>> {
+        match self {
+            FfiRefVTable::C(vtable) => vtable.method(slot),
+            #[cfg(feature = "jvm")]
+            FfiRefVTable::Java(vtable) => vtable.method(slot),
+            FfiRefVTable::Rust(_) => None,
+        }
+    }
+}
+
 impl FfiRef {
+    /// Wraps an object owned by the host, `vtable` is registered once per interface.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be an object of the interface `vtable` was registered for, valid until
+    /// the vtable's destructor is called with it. The result is `Send` and `Sync`, so the host
+    /// object must be thread-safe: its methods may be called from any thread Rust runs on,
+    /// concurrently, and it may be released from another thread than the one passing it in.
+    pub unsafe fn host<T: 'static>(handle: NonNull<()>, vtable: &'static FfiRefVTable) -> FfiRef {
+        FfiRef {
+            inner_ref: Some(handle),
+            vtable: NonNull::from(vtable),
+            type_id: ref_ty::<T>(),
+        }
+    }
+
+    pub fn handle(&self) -> Option<NonNull<()>> {
+        self.inner_ref
+    }
+
+    pub fn method(&self, slot: u32) -> Option<NonNull<()>> {
+        unsafe { self.vtable.as_ref().method(slot) }
+    }
+
+    pub(crate) fn vtable(&self) -> &FfiRefVTable {
+        unsafe { self.vtable.as_ref() }
+    }
+
     pub fn none<T: 'static>() -> FfiRef {
-        unsafe {
-            FfiRef {
-                inner_ref: None,
-                vtable: NonNull::new_unchecked(&EMPTY_VTABLE as *const _ as _),
-                type_id: ref_ty::<T>(),
-            }
+        FfiRef {
+            inner_ref: None,
+            vtable: NonNull::from(&EMPTY_VTABLE),
+            type_id: ref_ty::<T>(),
         }
     }
 }
@@ -81,17 +147,106 @@ pub struct Ref<T> {
 }
 
 impl<T: 'static> Ref<T> {
-    pub fn from_ffi(value: FfiRef) -> Result<Self, ()> {
+    pub fn from_ffi(value: FfiRef) -> Result<Self, HandleError> {
         unsafe {
             if *value.type_id.as_ref() == TypeId::of::<T>() {
                 Ok(Ref::<T> {
                     inner: value,
-                    _phantom: PhantomData::default(),
+                    _phantom: PhantomData,
                 })
             } else {
-                // TODO: implement a error type
-                Err(())
+                Err(HandleError::WrongType)
             }
         }
     }
 }
+
+impl<T: Send + Sync + 'static> Ref<T> {
+    /// Hands `object` out through [`handle::export`], the handle is released on drop. In safe
+    /// mode a handle the host got from [`Ref::handle`] and used after that is caught.
+    pub fn new(object: Arc<T>) -> Self {
+        let handle = handle::export(object);
+        Ref {
+            inner: FfiRef {
+                inner_ref: NonNull::new(handle as *mut ()),
+                type_id: ref_ty::<T>(),
+                vtable: NonNull::from(const { &FfiRefVTable::Rust(handle::release::<T>) }),
+            },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The handle the host passes back for the object.
+    pub fn handle(&self) -> u64 {
+        self.inner
+            .handle()
+            .map_or(0, |handle| handle.as_ptr() as u64)
+    }
+
+    /// The object of a `Ref` from [`Ref::new`], resolved through the handle table.
+    pub fn get(&self) -> Result<Arc<T>, HandleError> {
+        match self.inner.vtable() {
+            // The handle is of type `T`, checked by `from_ffi`, and live until `self` is dropped.
+            FfiRefVTable::Rust(_) => unsafe { handle::resolve(self.handle()) },
+            _ => Err(HandleError::WrongType),
+        }
+    }
+}
+
+impl Drop for FfiRef {
+    /// Releases the referenced object through the vtable, e.g. the global ref of a JVM
+    /// callback, once Rust drops its last handle.
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(self.type_id.as_ptr()));
+            let Some(inner) = self.inner_ref.take() else {
+                return;
+            };
+            match self.vtable.as_ref() {
+                FfiRefVTable::C(vtable) => {
+                    if let Some(dtor) = vtable.dtor {
+                        dtor(Some(inner))
+                    }
+                }
+                #[cfg(feature = "jvm")]
+                FfiRefVTable::Java(vtable) => crate::types::callback::release_jvm(vtable, inner),
+                FfiRefVTable::Rust(release) => {
+                    let _ = release(inner.as_ptr() as u64);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{FfiRef, Ref};
+    use crate::types::handle::{self, HandleError, SAFE_HANDLES};
+
+    #[test]
+    fn test_rust_object() {
+        let object = Arc::new(7u32);
+        let value = Ref::new(object.clone());
+        assert!(Arc::ptr_eq(&value.get().unwrap(), &object));
+        let handle = value.handle();
+        drop(value);
+        assert_eq!(Arc::strong_count(&object), 1);
+        if SAFE_HANDLES {
+            let stale = unsafe { handle::resolve::<u32>(handle) };
+            assert_eq!(stale.unwrap_err(), HandleError::Stale);
+        }
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let value = Ref::new(Arc::new(7u32));
+        assert_eq!(
+            Ref::<String>::from_ffi(value.inner).err(),
+            Some(HandleError::WrongType)
+        );
+        let none = Ref::<u32>::from_ffi(FfiRef::none::<u32>()).unwrap();
+        assert_eq!(none.get().unwrap_err(), HandleError::WrongType);
+    }
+}